@@ -0,0 +1,105 @@
+/**
+ * Vercel API Route for the Room Directory
+ * Lists open rooms, leaving out rooms whose age or region restrictions exclude the viewer
+ *
+ * Query: ?age=<viewer age>&region=<ISO 3166-1 alpha-2>
+ * Without an age, age-restricted rooms are left out; without a region, region-restricted ones are.
+ */
+
+import { createHash } from 'crypto';
+import { Connection, PublicKey } from '@solana/web3.js';
+
+const REGION_MODES = ['unrestricted', 'allow', 'deny'];
+
+const discriminator = (name) =>
+  createHash('sha256').update(`account:${name}`).digest().subarray(0, 8);
+
+function decodeRoom(data) {
+  let offset = 8;
+  const creator = new PublicKey(data.subarray(offset, offset + 32)).toBase58();
+  offset += 32;
+  const roomId = data.readBigUInt64LE(offset).toString();
+  offset += 8;
+  const nameLen = data.readUInt32LE(offset);
+  const name = data.subarray(offset + 4, offset + 4 + nameLen).toString('utf8');
+  offset += 4 + nameLen;
+  const createdAt = Number(data.readBigInt64LE(offset));
+  const expiresAt = Number(data.readBigInt64LE(offset + 8));
+  const messageCount = Number(data.readBigUInt64LE(offset + 16));
+  offset += 24;
+  const minAge = data.readUInt8(offset);
+  const regionMode = REGION_MODES[data.readUInt8(offset + 1)];
+  const regionCount = data.readUInt32LE(offset + 2);
+  offset += 6;
+  const regions = [];
+  for (let i = 0; i < regionCount; i++) {
+    regions.push(data.subarray(offset + i * 2, offset + i * 2 + 2).toString('ascii'));
+  }
+
+  return { creator, roomId, name, createdAt, expiresAt, messageCount, minAge, regionMode, regions };
+}
+
+// Restrictions are advisory on-chain; the directory is where they are enforced for discovery
+function admits(room, { age, region }) {
+  if (room.minAge > 0 && (age === null || age < room.minAge)) {
+    return false;
+  }
+  switch (room.regionMode) {
+    case 'allow':
+      return region !== null && room.regions.includes(region);
+    case 'deny':
+      return region !== null && !room.regions.includes(region);
+    default:
+      return true;
+  }
+}
+
+export default async function handler(req, res) {
+  // Enable CORS
+  res.setHeader('Access-Control-Allow-Origin', '*');
+  res.setHeader('Access-Control-Allow-Methods', 'GET, OPTIONS');
+  res.setHeader('Access-Control-Allow-Headers', 'Content-Type');
+
+  if (req.method === 'OPTIONS') {
+    res.status(200).end();
+    return;
+  }
+
+  if (req.method !== 'GET') {
+    return res.status(405).json({ error: 'Method not allowed' });
+  }
+
+  try {
+    const age = req.query.age === undefined ? null : Number.parseInt(req.query.age, 10);
+    if (age !== null && (!Number.isInteger(age) || age < 0)) {
+      return res.status(400).json({ error: 'Invalid age' });
+    }
+    const region = req.query.region === undefined ? null : String(req.query.region).toUpperCase();
+    if (region !== null && !/^[A-Z]{2}$/.test(region)) {
+      return res.status(400).json({ error: 'Invalid region' });
+    }
+
+    const connection = new Connection(process.env.SOLANA_RPC_URL || 'https://api.mainnet-beta.solana.com');
+    const programId = new PublicKey(process.env.MESSAGING_PROGRAM_ID);
+    const accounts = await connection.getProgramAccounts(programId, {
+      filters: [{ memcmp: { offset: 0, bytes: discriminator('Room').toString('base64'), encoding: 'base64' } }],
+    });
+
+    const now = Math.floor(Date.now() / 1000);
+    const rooms = accounts
+      .map(({ pubkey, account }) => ({ address: pubkey.toBase58(), ...decodeRoom(account.data) }))
+      .filter((room) => room.expiresAt > now && admits(room, { age, region }))
+      .sort((a, b) => b.messageCount - a.messageCount);
+
+    // Viewer-specific results; keep the CDN from sharing them across viewers
+    res.setHeader('Cache-Control', 'private, max-age=60');
+    res.json({ rooms });
+
+  } catch (error) {
+    console.error('❌ Directory listing failed:', error);
+    res.status(500).json({
+      error: 'Failed to list rooms',
+      details: error.message
+    });
+  }
+}
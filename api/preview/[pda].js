@@ -0,0 +1,235 @@
+/**
+ * Vercel API Route for OpenGraph Message Previews
+ * Renders an on-chain message account as an OpenGraph card descriptor so links unfurl
+ */
+
+import { createDecipheriv, createHash } from 'crypto';
+import { Connection, PublicKey } from '@solana/web3.js';
+
+const DESCRIPTION_LIMIT = 200;
+// CryptoJS AES output ("Salted__" in base64) marks client-side encrypted content
+const ENCRYPTED_PREFIX = 'U2FsdGVkX1';
+const ROOM_MESSAGE_STATUS = ['published', 'pending', 'hidden'];
+
+const discriminator = (name) =>
+  createHash('sha256').update(`account:${name}`).digest().subarray(0, 8);
+
+class Reader {
+  constructor(data) {
+    this.data = data;
+    this.offset = 8;
+  }
+
+  pubkey() {
+    const key = new PublicKey(this.data.subarray(this.offset, this.offset + 32));
+    this.offset += 32;
+    return key.toBase58();
+  }
+
+  bytes(len) {
+    this.offset += len;
+  }
+
+  u64() {
+    const value = this.data.readBigUInt64LE(this.offset);
+    this.offset += 8;
+    return value;
+  }
+
+  i64() {
+    const value = this.data.readBigInt64LE(this.offset);
+    this.offset += 8;
+    return Number(value);
+  }
+
+  string() {
+    const len = this.data.readUInt32LE(this.offset);
+    const value = this.data.subarray(this.offset + 4, this.offset + 4 + len).toString('utf8');
+    this.offset += 4 + len;
+    return value;
+  }
+}
+
+// Key and IV from a CryptoJS passphrase, as OpenSSL's EVP_BytesToKey with MD5
+function deriveKeyAndIv(passphrase, salt) {
+  let derived = Buffer.alloc(0);
+  let block = Buffer.alloc(0);
+  while (derived.length < 48) {
+    block = createHash('md5').update(Buffer.concat([block, passphrase, salt])).digest();
+    derived = Buffer.concat([derived, block]);
+  }
+  return { key: derived.subarray(0, 32), iv: derived.subarray(32, 48) };
+}
+
+// Decrypts content with a key published through `disclose_message`; null if it doesn't fit
+function decryptDisclosed(content, passphrase) {
+  try {
+    const raw = Buffer.from(content, 'base64');
+    const { key, iv } = deriveKeyAndIv(passphrase, raw.subarray(8, 16));
+    const decipher = createDecipheriv('aes-256-cbc', key, iv);
+    return Buffer.concat([decipher.update(raw.subarray(16)), decipher.final()]).toString('utf8');
+  } catch {
+    return null;
+  }
+}
+
+// A disclosure by either participant makes the message readable
+async function findDisclosedKey(connection, programId, message, participants) {
+  const addresses = participants.map((participant) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from('disclosure'), message.toBuffer(), new PublicKey(participant).toBuffer()],
+      programId,
+    )[0],
+  );
+  const accounts = await connection.getMultipleAccountsInfo(addresses);
+  const disclosure = accounts.find((account) => account && account.owner.equals(programId));
+  if (!disclosure) {
+    return null;
+  }
+  const reader = new Reader(disclosure.data);
+  reader.pubkey(); // message
+  reader.pubkey(); // discloser
+  const len = disclosure.data.readUInt32LE(reader.offset);
+  return disclosure.data.subarray(reader.offset + 4, reader.offset + 4 + len);
+}
+
+const shortKey = (key) => `${key.slice(0, 4)}…${key.slice(-4)}`;
+
+const visibleContent = (content) =>
+  content.startsWith(ENCRYPTED_PREFIX)
+    ? { text: '🔒 Encrypted message', encrypted: true }
+    : { text: content.length > DESCRIPTION_LIMIT ? `${content.slice(0, DESCRIPTION_LIMIT - 1)}…` : content, encrypted: false };
+
+// Direct messages: parties are shown, content only when it isn't encrypted or was disclosed
+async function previewMessage(reader, { connection, programId, address }) {
+  const sender = reader.pubkey();
+  const recipient = reader.pubkey();
+  const raw = reader.string();
+  const timestamp = reader.i64();
+
+  let content = visibleContent(raw);
+  if (content.encrypted) {
+    const disclosedKey = await findDisclosedKey(connection, programId, address, [sender, recipient]);
+    const plaintext = disclosedKey && decryptDisclosed(raw, disclosedKey);
+    if (plaintext !== null) {
+      content = { ...visibleContent(plaintext), disclosed: true };
+    }
+  }
+
+  return {
+    title: `Message from ${shortKey(sender)} to ${shortKey(recipient)}`,
+    description: content.text,
+    timestamp,
+    encrypted: content.encrypted,
+    disclosed: content.disclosed === true,
+    image: { template: 'message', params: { sender, recipient, encrypted: content.encrypted } },
+  };
+}
+
+function previewRoomMessage(reader) {
+  reader.pubkey(); // room
+  const author = reader.pubkey();
+  reader.u64(); // index
+  const content = visibleContent(reader.string());
+  const timestamp = reader.i64();
+  const expiresAt = reader.i64();
+  reader.bytes(1); // imported
+  // Posts still in a moderated room's queue or hidden by a community vote are not shown
+  const status = ROOM_MESSAGE_STATUS[reader.data.readUInt8(reader.offset)];
+
+  return {
+    title: `Room message from ${shortKey(author)}`,
+    description: status === 'published' ? content.text : status === 'pending' ? 'Awaiting approval' : 'Hidden by the community',
+    timestamp,
+    encrypted: content.encrypted,
+    image: { template: 'room', params: { author, expiresAt } },
+  };
+}
+
+// AMA questions are public by design; the asker has no on-chain identity to show
+function previewAmaQuestion(reader) {
+  reader.pubkey(); // ama_inbox
+  reader.u64(); // index
+  reader.bytes(32); // commitment
+  const question = reader.string();
+  const timestamp = reader.i64();
+  const answer = reader.string();
+
+  return {
+    title: 'Anonymous question',
+    description: answer ? `Q: ${question}\nA: ${answer}`.slice(0, DESCRIPTION_LIMIT) : question.slice(0, DESCRIPTION_LIMIT),
+    timestamp,
+    encrypted: false,
+    image: { template: 'ama', params: { answered: answer.length > 0 } },
+  };
+}
+
+const PREVIEWS = [
+  [discriminator('Message'), previewMessage],
+  [discriminator('RoomMessage'), previewRoomMessage],
+  [discriminator('AmaQuestion'), previewAmaQuestion],
+];
+
+export default async function handler(req, res) {
+  // Enable CORS
+  res.setHeader('Access-Control-Allow-Origin', '*');
+  res.setHeader('Access-Control-Allow-Methods', 'GET, OPTIONS');
+  res.setHeader('Access-Control-Allow-Headers', 'Content-Type');
+
+  if (req.method === 'OPTIONS') {
+    res.status(200).end();
+    return;
+  }
+
+  if (req.method !== 'GET') {
+    return res.status(405).json({ error: 'Method not allowed' });
+  }
+
+  try {
+    const { pda } = req.query;
+
+    let address;
+    try {
+      address = new PublicKey(pda);
+    } catch {
+      return res.status(400).json({ error: 'Invalid message address' });
+    }
+
+    const connection = new Connection(process.env.SOLANA_RPC_URL || 'https://api.mainnet-beta.solana.com');
+    const programId = new PublicKey(process.env.MESSAGING_PROGRAM_ID);
+    const account = await connection.getAccountInfo(address);
+
+    if (!account || !account.owner.equals(programId)) {
+      return res.status(404).json({ error: 'Message not found' });
+    }
+
+    const entry = PREVIEWS.find(([disc]) => account.data.subarray(0, 8).equals(disc));
+    if (!entry) {
+      return res.status(404).json({ error: 'Account is not a previewable message' });
+    }
+
+    const preview = await entry[1](new Reader(account.data), { connection, programId, address });
+    const url = `${process.env.PUBLIC_APP_URL || ''}/m/${address.toBase58()}`;
+
+    // Card contents only change if the account is closed, so let the CDN keep them briefly
+    res.setHeader('Cache-Control', 's-maxage=300, stale-while-revalidate=600');
+    res.json({
+      'og:type': 'article',
+      'og:site_name': 'GMChat',
+      'og:title': preview.title,
+      'og:description': preview.description,
+      'og:url': url,
+      'article:published_time': new Date(preview.timestamp * 1000).toISOString(),
+      encrypted: preview.encrypted,
+      disclosed: preview.disclosed === true,
+      image: { ...preview.image, width: 1200, height: 630 },
+    });
+
+  } catch (error) {
+    console.error('❌ Preview rendering failed:', error);
+    res.status(500).json({
+      error: 'Failed to render preview',
+      details: error.message
+    });
+  }
+}
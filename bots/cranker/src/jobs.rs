@@ -0,0 +1,181 @@
+//! Finds crankable accounts in a `getProgramAccounts` dump and turns them into instructions.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_messaging::{
+    accounts, instruction, DeliveryState, Invoice, InvoiceStatus, MessageDelivery, Reminder, Room, RoomMessage,
+    Snooze, ID as PROGRAM_ID,
+};
+
+/// A permissionless maintenance call that is due.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrankJob {
+    CloseRoom { room: Pubkey, creator: Pubkey },
+    CloseRoomMessage { message: Pubkey, room_creator: Pubkey },
+    CollectInvoicePeriod { invoice: Pubkey, payer_token_account: Pubkey, issuer_token_account: Pubkey },
+    DeliverQueued { delivery: Pubkey },
+    FireReminder { reminder: Pubkey, owner: Pubkey },
+    ExpireSnooze { snooze: Pubkey, owner: Pubkey },
+}
+
+impl CrankJob {
+    /// Inspects one program account and returns the job it needs at `now`, if any.
+    pub fn for_account(address: Pubkey, data: &[u8], now: i64) -> Option<Self> {
+        let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+        let mut data = data;
+
+        if discriminator == Room::DISCRIMINATOR {
+            let room = Room::try_deserialize(&mut data).ok()?;
+            (now >= room.expires_at).then_some(CrankJob::CloseRoom { room: address, creator: room.creator })
+        } else if discriminator == RoomMessage::DISCRIMINATOR {
+            let message = RoomMessage::try_deserialize(&mut data).ok()?;
+            (now >= message.expires_at)
+                .then_some(CrankJob::CloseRoomMessage { message: address, room_creator: message.room_creator })
+        } else if discriminator == Invoice::DISCRIMINATOR {
+            let invoice = Invoice::try_deserialize(&mut data).ok()?;
+            (invoice.status == InvoiceStatus::Active && now >= invoice.due_ts).then_some(
+                CrankJob::CollectInvoicePeriod {
+                    invoice: address,
+                    payer_token_account: invoice.payer_token_account,
+                    issuer_token_account: get_associated_token_address(&invoice.issuer, &invoice.mint),
+                },
+            )
+        } else if discriminator == MessageDelivery::DISCRIMINATOR {
+            let delivery = MessageDelivery::try_deserialize(&mut data).ok()?;
+            (delivery.state == DeliveryState::Queued && now >= delivery.deliver_at)
+                .then_some(CrankJob::DeliverQueued { delivery: address })
+        } else if discriminator == Reminder::DISCRIMINATOR {
+            let reminder = Reminder::try_deserialize(&mut data).ok()?;
+            (now >= reminder.remind_at).then_some(CrankJob::FireReminder { reminder: address, owner: reminder.owner })
+        } else if discriminator == Snooze::DISCRIMINATOR {
+            let snooze = Snooze::try_deserialize(&mut data).ok()?;
+            (now >= snooze.until).then_some(CrankJob::ExpireSnooze { snooze: address, owner: snooze.owner })
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrankJob::CloseRoom { .. } => "close_room",
+            CrankJob::CloseRoomMessage { .. } => "close_room_message",
+            CrankJob::CollectInvoicePeriod { .. } => "collect_invoice_period",
+            CrankJob::DeliverQueued { .. } => "deliver_queued",
+            CrankJob::FireReminder { .. } => "fire_reminder",
+            CrankJob::ExpireSnooze { .. } => "expire_snooze",
+        }
+    }
+
+    /// Builds the crank instruction; `cranker` signs and collects the crank bounty.
+    pub fn instruction(&self, cranker: Pubkey) -> Instruction {
+        let config = Pubkey::find_program_address(&[b"config"], &PROGRAM_ID).0;
+        let treasury = Pubkey::find_program_address(&[b"treasury"], &PROGRAM_ID).0;
+        let (accounts, data) = match *self {
+            CrankJob::CloseRoom { room, creator } => (
+                accounts::CloseRoom { room, creator, config, cranker }.to_account_metas(None),
+                instruction::CloseRoom {}.data(),
+            ),
+            CrankJob::CloseRoomMessage { message, room_creator } => (
+                accounts::CloseRoomMessage { message, room_creator, config, cranker }.to_account_metas(None),
+                instruction::CloseRoomMessage {}.data(),
+            ),
+            CrankJob::CollectInvoicePeriod { invoice, payer_token_account, issuer_token_account } => (
+                accounts::CollectInvoicePeriod {
+                    invoice,
+                    payer_token_account,
+                    issuer_token_account,
+                    config,
+                    treasury,
+                    cranker,
+                    token_program: anchor_spl::token::ID,
+                }
+                .to_account_metas(None),
+                instruction::CollectInvoicePeriod {}.data(),
+            ),
+            CrankJob::DeliverQueued { delivery } => (
+                accounts::DeliverQueued { delivery, config, treasury, cranker }.to_account_metas(None),
+                instruction::DeliverQueued {}.data(),
+            ),
+            CrankJob::FireReminder { reminder, owner } => (
+                accounts::FireReminder { reminder, owner, config, cranker }.to_account_metas(None),
+                instruction::FireReminder {}.data(),
+            ),
+            CrankJob::ExpireSnooze { snooze, owner } => (
+                accounts::ExpireSnooze { snooze, owner, config, cranker }.to_account_metas(None),
+                instruction::ExpireSnooze {}.data(),
+            ),
+        };
+
+        Instruction { program_id: PROGRAM_ID, accounts, data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AccountSerialize;
+    use solana_messaging::{RoomMessageStatus, Snooze};
+
+    use super::*;
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn a_reminder_is_due_at_its_time() {
+        let owner = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let reminder = Reminder { owner, message: Pubkey::new_unique(), remind_at: 100, created_at: 0, bump: 255 };
+        let data = serialize(&reminder);
+
+        assert_eq!(CrankJob::for_account(address, &data, 99), None);
+        let due = CrankJob::for_account(address, &data, 100);
+        assert_eq!(due, Some(CrankJob::FireReminder { reminder: address, owner }));
+    }
+
+    #[test]
+    fn an_expired_room_message_refunds_the_room_creator() {
+        let address = Pubkey::new_unique();
+        let message = RoomMessage {
+            room: Pubkey::new_unique(),
+            author: Pubkey::new_unique(),
+            room_creator: Pubkey::new_unique(),
+            index: 0,
+            content: "gm".to_string(),
+            timestamp: 0,
+            expires_at: 50,
+            imported: false,
+            status: RoomMessageStatus::Published,
+            bump: 255,
+        };
+
+        let job = CrankJob::for_account(address, &serialize(&message), 50).unwrap();
+        assert_eq!(job, CrankJob::CloseRoomMessage { message: address, room_creator: message.room_creator });
+
+        let cranker = Pubkey::new_unique();
+        let instruction = job.instruction(cranker);
+        assert_eq!(instruction.accounts[1].pubkey, message.room_creator);
+        assert!(instruction.accounts[1].is_writable);
+        assert_eq!(instruction.accounts[3].pubkey, cranker);
+        assert!(instruction.accounts[3].is_signer);
+    }
+
+    #[test]
+    fn a_snooze_expires_at_its_end() {
+        let owner = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let data = serialize(&Snooze { owner, peer: Pubkey::new_unique(), until: 10, bump: 255 });
+
+        assert_eq!(CrankJob::for_account(address, &data, 10), Some(CrankJob::ExpireSnooze { snooze: address, owner }));
+    }
+
+    #[test]
+    fn other_accounts_need_no_crank() {
+        assert_eq!(CrankJob::for_account(Pubkey::new_unique(), &[0xff; 64], i64::MAX), None);
+        assert_eq!(CrankJob::for_account(Pubkey::new_unique(), &[], i64::MAX), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Crank service for the permissionless maintenance instructions.
+//!
+//! Scans a `getProgramAccounts` dump of the messaging program (base64 encoding) for ended rooms,
+//! expired room messages, recurring invoice periods that have fallen due, queued messages whose
+//! delivery window has opened, due reminders and lapsed snoozes, and prints one JSON line per
+//! transaction to submit. Each transaction starts with compute budget instructions for the
+//! configured priority fee and is signed by the configured cranker wallet, which collects the crank
+//! bounty set in the program config. Transactions are grouped into waves of `concurrency` that the
+//! submitter may send in parallel.
+//!
+//! With a `[jito]` table in the config, transactions are instead grouped into Jito bundles of up
+//! to `bundle_size`, the last transaction of each bundle carrying the tip transfer. Waves then
+//! count bundles rather than transactions.
+//!
+//! Usage: gmchat-cranker <config.toml> <program-accounts.json> [now-unix-ts]
+
+mod jobs;
+
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, process};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::system_instruction;
+use serde::{Deserialize, Serialize};
+
+use crate::jobs::CrankJob;
+
+/// `ComputeBudget111111111111111111111111111111`
+const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231,
+    188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+]);
+/// `ComputeBudgetInstruction::SetComputeUnitLimit` discriminant
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+/// `ComputeBudgetInstruction::SetComputeUnitPrice` discriminant
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+/// Transactions accepted per Jito bundle
+const MAX_BUNDLE_SIZE: usize = 5;
+
+#[derive(Deserialize)]
+struct Config {
+    /// Cranker wallet that signs, pays fees and collects crank bounties
+    cranker: String,
+    /// Priority fee in micro-lamports per compute unit
+    priority_fee_micro_lamports: u64,
+    /// Compute unit limit requested per transaction
+    compute_unit_limit: u32,
+    /// Crank instructions packed into one transaction
+    max_instructions_per_tx: usize,
+    /// Transactions (or bundles) the submitter may have in flight at once
+    concurrency: usize,
+    jito: Option<JitoConfig>,
+}
+
+#[derive(Deserialize)]
+struct JitoConfig {
+    /// One of the Jito tip payment accounts
+    tip_account: String,
+    tip_lamports: u64,
+    /// Transactions per bundle, at most five
+    bundle_size: usize,
+}
+
+/// One entry of a `getProgramAccounts` response
+#[derive(Deserialize)]
+struct ProgramAccount {
+    pubkey: String,
+    account: AccountData,
+}
+
+#[derive(Deserialize)]
+struct AccountData {
+    /// `[base64 data, "base64"]`
+    data: (String, String),
+}
+
+#[derive(Serialize)]
+struct PlannedTransaction {
+    wave: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundle: Option<usize>,
+    jobs: Vec<&'static str>,
+    instructions: Vec<SerializedInstruction>,
+}
+
+#[derive(Serialize)]
+struct SerializedInstruction {
+    program_id: String,
+    accounts: Vec<SerializedAccountMeta>,
+    /// Base64 instruction data
+    data: String,
+}
+
+#[derive(Serialize)]
+struct SerializedAccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl From<&Instruction> for SerializedInstruction {
+    fn from(ix: &Instruction) -> Self {
+        Self {
+            program_id: ix.program_id.to_string(),
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| SerializedAccountMeta {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: base64::encode(&ix.data),
+        }
+    }
+}
+
+fn compute_budget_instructions(config: &Config) -> [Instruction; 2] {
+    let mut limit = vec![SET_COMPUTE_UNIT_LIMIT];
+    limit.extend_from_slice(&config.compute_unit_limit.to_le_bytes());
+    let mut price = vec![SET_COMPUTE_UNIT_PRICE];
+    price.extend_from_slice(&config.priority_fee_micro_lamports.to_le_bytes());
+
+    [
+        Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: vec![], data: limit },
+        Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: vec![], data: price },
+    ]
+}
+
+fn load_jobs(path: &str, now: i64) -> Result<Vec<CrankJob>, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let accounts: Vec<ProgramAccount> = serde_json::from_str(&raw).map_err(|e| format!("{path}: {e}"))?;
+
+    let mut jobs = Vec::new();
+    for entry in accounts {
+        let address = Pubkey::from_str(&entry.pubkey).map_err(|e| format!("{}: {e}", entry.pubkey))?;
+        let data = base64::decode(&entry.account.data.0).map_err(|e| format!("{}: {e}", entry.pubkey))?;
+        jobs.extend(CrankJob::for_account(address, &data, now));
+    }
+    Ok(jobs)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (config_path, accounts_path, now) = match args {
+        [config, accounts] => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_secs() as i64;
+            (config, accounts, now)
+        }
+        [config, accounts, now] => (config, accounts, now.parse::<i64>().map_err(|e| format!("{now}: {e}"))?),
+        _ => return Err("usage: gmchat-cranker <config.toml> <program-accounts.json> [now-unix-ts]".into()),
+    };
+
+    let raw = fs::read_to_string(config_path).map_err(|e| format!("{config_path}: {e}"))?;
+    let config: Config = toml::from_str(&raw).map_err(|e| format!("{config_path}: {e}"))?;
+    if config.max_instructions_per_tx == 0 || config.concurrency == 0 {
+        return Err(format!("{config_path}: max_instructions_per_tx and concurrency must be positive"));
+    }
+
+    let cranker = Pubkey::from_str(&config.cranker).map_err(|e| format!("{config_path}: cranker: {e}"))?;
+    let tip = match &config.jito {
+        Some(jito) => {
+            if !(1..=MAX_BUNDLE_SIZE).contains(&jito.bundle_size) {
+                return Err(format!("{config_path}: jito.bundle_size must be between 1 and {MAX_BUNDLE_SIZE}"));
+            }
+            let tip_account =
+                Pubkey::from_str(&jito.tip_account).map_err(|e| format!("{config_path}: jito.tip_account: {e}"))?;
+            Some((system_instruction::transfer(&cranker, &tip_account, jito.tip_lamports), jito.bundle_size))
+        }
+        None => None,
+    };
+
+    let jobs = load_jobs(accounts_path, now)?;
+    let budget = compute_budget_instructions(&config);
+    let batches: Vec<&[CrankJob]> = jobs.chunks(config.max_instructions_per_tx).collect();
+
+    for (index, batch) in batches.iter().enumerate() {
+        let mut instructions: Vec<Instruction> =
+            budget.iter().cloned().chain(batch.iter().map(|job| job.instruction(cranker))).collect();
+
+        let (wave, bundle) = match &tip {
+            Some((tip_instruction, bundle_size)) => {
+                let bundle = index / bundle_size;
+                // Tip in the bundle's last transaction so it is only paid if the whole bundle lands
+                if index % bundle_size == bundle_size - 1 || index == batches.len() - 1 {
+                    instructions.push(tip_instruction.clone());
+                }
+                (bundle / config.concurrency, Some(bundle))
+            }
+            None => (index / config.concurrency, None),
+        };
+
+        let planned = PlannedTransaction {
+            wave,
+            bundle,
+            jobs: batch.iter().map(CrankJob::name).collect(),
+            instructions: instructions.iter().map(SerializedInstruction::from).collect(),
+        };
+        println!("{}", serde_json::to_string(&planned).map_err(|e| e.to_string())?);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,99 @@
+//! NFT sale / bid notification bot.
+//!
+//! Reads a marketplace indexer activity export (Magic Eden `activities` shape) and prints one
+//! JSON line per `post_market_notice` call for the collections in the config. The output is
+//! submitted by the bot key registered on each collection feed.
+//!
+//! Usage: gmchat-nft-notifier <config.toml> <activities.json>
+
+use std::collections::HashMap;
+use std::{env, fs, process};
+
+use serde::{Deserialize, Serialize};
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Deserialize)]
+struct Config {
+    collections: Vec<WatchedCollection>,
+}
+
+#[derive(Deserialize)]
+struct WatchedCollection {
+    /// Indexer collection symbol
+    symbol: String,
+    /// Collection mint the on-chain feed is keyed by
+    collection_mint: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Activity {
+    #[serde(rename = "type")]
+    kind: String,
+    collection: Option<String>,
+    token_mint: Option<String>,
+    price: Option<f64>,
+    buyer: Option<String>,
+    seller: Option<String>,
+}
+
+/// Arguments for `post_market_notice`, mirroring `MarketEventKind` in the program
+#[derive(Serialize)]
+struct MarketNotice<'a> {
+    collection_mint: &'a str,
+    kind: &'static str,
+    nft_mint: String,
+    price: u64,
+    counterparty: String,
+}
+
+fn to_notice<'a>(activity: Activity, collections: &HashMap<&str, &'a str>) -> Option<MarketNotice<'a>> {
+    let collection_mint = *collections.get(activity.collection.as_deref()?)?;
+    let (kind, counterparty) = match activity.kind.as_str() {
+        "list" => ("Listing", activity.seller),
+        "buyNow" => ("Sale", activity.buyer),
+        "bid" => ("Bid", activity.buyer),
+        _ => return None,
+    };
+
+    Some(MarketNotice {
+        collection_mint,
+        kind,
+        nft_mint: activity.token_mint?,
+        price: (activity.price? * LAMPORTS_PER_SOL).round() as u64,
+        counterparty: counterparty.unwrap_or_default(),
+    })
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let [config_path, activities_path] = args else {
+        return Err("usage: gmchat-nft-notifier <config.toml> <activities.json>".into());
+    };
+
+    let raw = fs::read_to_string(config_path).map_err(|e| format!("{config_path}: {e}"))?;
+    let config: Config = toml::from_str(&raw).map_err(|e| format!("{config_path}: {e}"))?;
+    let collections: HashMap<&str, &str> = config
+        .collections
+        .iter()
+        .map(|c| (c.symbol.as_str(), c.collection_mint.as_str()))
+        .collect();
+
+    let raw = fs::read_to_string(activities_path).map_err(|e| format!("{activities_path}: {e}"))?;
+    let activities: Vec<Activity> =
+        serde_json::from_str(&raw).map_err(|e| format!("{activities_path}: {e}"))?;
+
+    for notice in activities.into_iter().filter_map(|a| to_notice(a, &collections)) {
+        println!("{}", serde_json::to_string(&notice).map_err(|e| e.to_string())?);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,206 @@
+//! Validator/staking alerts bot.
+//!
+//! Compares two `getVoteAccounts` snapshots for the configured validators and prints one
+//! JSON line per direct message (`{"recipient": ..., "content": ...}`) for every subscribed
+//! delegator. The output is meant to be piped into the relayed-send path that submits the
+//! messages on the bot's behalf.
+//!
+//! Usage: gmchat-validator-alerts <config.toml> <previous.json> <current.json>
+
+use std::collections::HashMap;
+use std::{env, fs, process};
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum content length accepted by `send_message`
+const MAX_CONTENT_LEN: usize = 500;
+
+#[derive(Deserialize)]
+struct Config {
+    validators: Vec<WatchedValidator>,
+}
+
+#[derive(Deserialize)]
+struct WatchedValidator {
+    vote_account: String,
+    name: String,
+    subscribers: Vec<String>,
+}
+
+/// `getVoteAccounts` RPC result
+#[derive(Deserialize)]
+struct VoteAccounts {
+    current: Vec<VoteAccount>,
+    delinquent: Vec<VoteAccount>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct VoteAccount {
+    vote_pubkey: String,
+    commission: u8,
+    /// `[epoch, credits, previous_credits]` entries
+    epoch_credits: Vec<[u64; 3]>,
+}
+
+#[derive(Serialize)]
+struct DirectMessage<'a> {
+    recipient: &'a str,
+    content: String,
+}
+
+struct Snapshot {
+    accounts: HashMap<String, VoteAccount>,
+    delinquent: HashMap<String, VoteAccount>,
+}
+
+impl Snapshot {
+    fn load(path: &str) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        let parsed: VoteAccounts = serde_json::from_str(&raw).map_err(|e| format!("{path}: {e}"))?;
+        let index = |list: &[VoteAccount]| {
+            list.iter()
+                .map(|a| (a.vote_pubkey.clone(), a.clone()))
+                .collect::<HashMap<_, _>>()
+        };
+        let delinquent = index(&parsed.delinquent);
+        let mut accounts = index(&parsed.current);
+        accounts.extend(delinquent.clone());
+        Ok(Self { accounts, delinquent })
+    }
+
+    fn is_delinquent(&self, vote_account: &str) -> bool {
+        self.delinquent.contains_key(vote_account)
+    }
+}
+
+/// Alerts for a single validator between two snapshots.
+fn alerts_for(validator: &WatchedValidator, previous: &Snapshot, current: &Snapshot) -> Vec<String> {
+    let key = validator.vote_account.as_str();
+    let (Some(before), Some(after)) = (previous.accounts.get(key), current.accounts.get(key)) else {
+        return Vec::new();
+    };
+
+    let mut alerts = Vec::new();
+
+    // Delinquency transitions
+    match (previous.is_delinquent(key), current.is_delinquent(key)) {
+        (false, true) => alerts.push(format!("{} is delinquent and not voting", validator.name)),
+        (true, false) => alerts.push(format!("{} has recovered and is voting again", validator.name)),
+        _ => {}
+    }
+
+    // Commission changes
+    if before.commission != after.commission {
+        alerts.push(format!(
+            "{} changed commission from {}% to {}%",
+            validator.name, before.commission, after.commission
+        ));
+    }
+
+    // Credits earned in the epoch that just completed
+    let last_epoch = |a: &VoteAccount| a.epoch_credits.last().copied();
+    if let (Some(prev), Some(curr)) = (last_epoch(before), last_epoch(after)) {
+        if curr[0] > prev[0] {
+            let earned = prev[1].saturating_sub(prev[2]);
+            alerts.push(format!(
+                "{} earned {} vote credits in epoch {}",
+                validator.name, earned, prev[0]
+            ));
+        }
+    }
+
+    alerts
+}
+
+/// Cuts `content` to at most `max` bytes without splitting a UTF-8 sequence.
+fn truncate_on_char_boundary(mut content: String, max: usize) -> String {
+    if content.len() > max {
+        let cut = (0..=max).rev().find(|&i| content.is_char_boundary(i)).unwrap_or(0);
+        content.truncate(cut);
+    }
+    content
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let [config_path, previous_path, current_path] = args else {
+        return Err("usage: gmchat-validator-alerts <config.toml> <previous.json> <current.json>".into());
+    };
+
+    let raw = fs::read_to_string(config_path).map_err(|e| format!("{config_path}: {e}"))?;
+    let config: Config = toml::from_str(&raw).map_err(|e| format!("{config_path}: {e}"))?;
+    let previous = Snapshot::load(previous_path)?;
+    let current = Snapshot::load(current_path)?;
+
+    for validator in &config.validators {
+        for alert in alerts_for(validator, &previous, &current) {
+            let content = truncate_on_char_boundary(alert, MAX_CONTENT_LEN);
+            for recipient in &validator.subscribers {
+                let message = DirectMessage { recipient, content: content.clone() };
+                println!("{}", serde_json::to_string(&message).map_err(|e| e.to_string())?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(commission: u8, epoch_credits: Vec<[u64; 3]>, delinquent: bool) -> Snapshot {
+        let account = VoteAccount { vote_pubkey: "vote".to_string(), commission, epoch_credits };
+        let accounts = HashMap::from([("vote".to_string(), account.clone())]);
+        let delinquent = if delinquent { accounts.clone() } else { HashMap::new() };
+        Snapshot { accounts, delinquent }
+    }
+
+    fn validator() -> WatchedValidator {
+        WatchedValidator { vote_account: "vote".to_string(), name: "Validatör".to_string(), subscribers: Vec::new() }
+    }
+
+    #[test]
+    fn truncates_on_a_char_boundary() {
+        // 'ö' is two bytes, so byte 2 falls inside it
+        assert_eq!(truncate_on_char_boundary("aöb".to_string(), 2), "a");
+        assert_eq!(truncate_on_char_boundary("aöb".to_string(), 3), "aö");
+        assert_eq!(truncate_on_char_boundary("aöb".to_string(), 10), "aöb");
+        assert_eq!(truncate_on_char_boundary("ö".to_string(), 1), "");
+    }
+
+    #[test]
+    fn reports_delinquency_transitions() {
+        let voting = snapshot(5, Vec::new(), false);
+        let delinquent = snapshot(5, Vec::new(), true);
+
+        assert_eq!(alerts_for(&validator(), &voting, &delinquent), ["Validatör is delinquent and not voting"]);
+        assert_eq!(alerts_for(&validator(), &delinquent, &voting), ["Validatör has recovered and is voting again"]);
+        assert!(alerts_for(&validator(), &voting, &voting).is_empty());
+    }
+
+    #[test]
+    fn reports_commission_changes_and_completed_epochs() {
+        let before = snapshot(5, vec![[10, 1_500, 1_000]], false);
+        let after = snapshot(8, vec![[10, 1_900, 1_000], [11, 1_950, 1_900]], false);
+
+        assert_eq!(
+            alerts_for(&validator(), &before, &after),
+            ["Validatör changed commission from 5% to 8%", "Validatör earned 500 vote credits in epoch 10"]
+        );
+    }
+
+    #[test]
+    fn an_unknown_vote_account_raises_nothing() {
+        let empty = Snapshot { accounts: HashMap::new(), delinquent: HashMap::new() };
+        assert!(alerts_for(&validator(), &empty, &snapshot(5, Vec::new(), true)).is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Decodes the messaging program's events out of transaction logs.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use serde_json::{json, Value};
+use solana_messaging::{
+    AmaAnswered, InvoicePaid, MessageDelivered, MessageQueued, MessageUnlocked, PollResolved, ReminderDue,
+    RoomMessageApproved, RoomMessageRejected, TaskStatusChanged, TicketStatusChanged, TradeStatusChanged,
+    ID as PROGRAM_ID,
+};
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// An event the relayer can deliver, with its fields as JSON for filtering and the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEvent {
+    pub kind: &'static str,
+    pub fields: Value,
+}
+
+/// Decodes the events emitted by the messaging program itself. `Program data:` lines logged
+/// while another program is executing are ignored, so a CPI caller can't forge events.
+pub fn decode_logs(logs: &[String]) -> Vec<DecodedEvent> {
+    let program_id = PROGRAM_ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        if let Some(rest) = line.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => stack.push(program),
+                (Some(program), Some("success" | "failed:")) if stack.last() == Some(&program) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+
+        let Some(encoded) = line.strip_prefix(PROGRAM_DATA) else { continue };
+        if stack.last() != Some(&program_id.as_str()) {
+            continue;
+        }
+        if let Some(event) = base64::decode(encoded).ok().and_then(|data| decode_event(&data)) {
+            events.push(event);
+        }
+    }
+
+    events
+}
+
+fn decode_event(data: &[u8]) -> Option<DecodedEvent> {
+    let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+    let mut body = &data[8..];
+
+    let (kind, fields) = if discriminator == InvoicePaid::DISCRIMINATOR {
+        let event = InvoicePaid::deserialize(&mut body).ok()?;
+        (
+            "invoice_paid",
+            json!({
+                "invoice": key(&event.invoice),
+                "receipt": key(&event.receipt),
+                "payer": key(&event.payer),
+                "amount": event.amount,
+                "period": event.period,
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == TicketStatusChanged::DISCRIMINATOR {
+        let event = TicketStatusChanged::deserialize(&mut body).ok()?;
+        (
+            "ticket_status_changed",
+            json!({
+                "ticket": key(&event.ticket),
+                "actor": key(&event.actor),
+                "status": format!("{:?}", event.status),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == PollResolved::DISCRIMINATOR {
+        let event = PollResolved::deserialize(&mut body).ok()?;
+        (
+            "poll_resolved",
+            json!({
+                "poll": key(&event.poll),
+                "resolver": key(&event.resolver),
+                "winning_option": event.winning_option,
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == TaskStatusChanged::DISCRIMINATOR {
+        let event = TaskStatusChanged::deserialize(&mut body).ok()?;
+        (
+            "task_status_changed",
+            json!({
+                "task": key(&event.task),
+                "actor": key(&event.actor),
+                "status": format!("{:?}", event.status),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == TradeStatusChanged::DISCRIMINATOR {
+        let event = TradeStatusChanged::deserialize(&mut body).ok()?;
+        (
+            "trade_status_changed",
+            json!({
+                "trade": key(&event.trade),
+                "actor": key(&event.actor),
+                "status": format!("{:?}", event.status),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == MessageUnlocked::DISCRIMINATOR {
+        let event = MessageUnlocked::deserialize(&mut body).ok()?;
+        (
+            "message_unlocked",
+            json!({
+                "message": key(&event.message),
+                "unlocked_by": key(&event.unlocked_by),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == AmaAnswered::DISCRIMINATOR {
+        let event = AmaAnswered::deserialize(&mut body).ok()?;
+        (
+            "ama_answered",
+            json!({
+                "ama_inbox": key(&event.ama_inbox),
+                "question": key(&event.question),
+                "index": event.index,
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == MessageQueued::DISCRIMINATOR {
+        let event = MessageQueued::deserialize(&mut body).ok()?;
+        (
+            "message_queued",
+            json!({
+                "message": key(&event.message),
+                "recipient": key(&event.recipient),
+                "deliver_at": event.deliver_at,
+            }),
+        )
+    } else if discriminator == MessageDelivered::DISCRIMINATOR {
+        let event = MessageDelivered::deserialize(&mut body).ok()?;
+        (
+            "message_delivered",
+            json!({
+                "message": key(&event.message),
+                "recipient": key(&event.recipient),
+                "delivered_at": event.delivered_at,
+            }),
+        )
+    } else if discriminator == ReminderDue::DISCRIMINATOR {
+        let event = ReminderDue::deserialize(&mut body).ok()?;
+        (
+            "reminder_due",
+            json!({
+                "owner": key(&event.owner),
+                "message": key(&event.message),
+                "remind_at": event.remind_at,
+                "fired_at": event.fired_at,
+            }),
+        )
+    } else if discriminator == RoomMessageApproved::DISCRIMINATOR {
+        let event = RoomMessageApproved::deserialize(&mut body).ok()?;
+        (
+            "room_message_approved",
+            json!({
+                "room": key(&event.room),
+                "message": key(&event.message),
+                "author": key(&event.author),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else if discriminator == RoomMessageRejected::DISCRIMINATOR {
+        let event = RoomMessageRejected::deserialize(&mut body).ok()?;
+        (
+            "room_message_rejected",
+            json!({
+                "room": key(&event.room),
+                "message": key(&event.message),
+                "author": key(&event.author),
+                "timestamp": event.timestamp,
+            }),
+        )
+    } else {
+        return None;
+    };
+
+    Some(DecodedEvent { kind, fields })
+}
+
+fn key(pubkey: &Pubkey) -> String {
+    pubkey.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::Event;
+
+    use super::*;
+
+    fn program_data(event: &impl Event) -> String {
+        format!("{PROGRAM_DATA}{}", base64::encode(event.data()))
+    }
+
+    fn resolved() -> PollResolved {
+        PollResolved { poll: Pubkey::new_unique(), resolver: Pubkey::new_unique(), winning_option: 2, timestamp: 7 }
+    }
+
+    #[test]
+    fn decodes_events_the_program_emits() {
+        let event = resolved();
+        let logs = vec![
+            format!("Program {PROGRAM_ID} invoke [1]"),
+            program_data(&event),
+            format!("Program {PROGRAM_ID} success"),
+        ];
+
+        let decoded = decode_logs(&logs);
+
+        assert_eq!(
+            decoded,
+            [DecodedEvent {
+                kind: "poll_resolved",
+                fields: json!({
+                    "poll": key(&event.poll),
+                    "resolver": key(&event.resolver),
+                    "winning_option": 2,
+                    "timestamp": 7,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_data_logged_by_another_program() {
+        let other = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {other} invoke [1]"),
+            program_data(&resolved()),
+            format!("Program {PROGRAM_ID} invoke [2]"),
+            format!("Program {PROGRAM_ID} success"),
+            // Back in the caller after the CPI returned
+            program_data(&resolved()),
+            format!("Program {other} success"),
+        ];
+
+        assert!(decode_logs(&logs).is_empty());
+    }
+
+    #[test]
+    fn skips_undecodable_data() {
+        let logs = vec![
+            format!("Program {PROGRAM_ID} invoke [1]"),
+            format!("{PROGRAM_DATA}not base64!"),
+            format!("{PROGRAM_DATA}{}", base64::encode([0xff; 16])),
+            format!("Program {PROGRAM_ID} success"),
+        ];
+
+        assert!(decode_logs(&logs).is_empty());
+    }
+}
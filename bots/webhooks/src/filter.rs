@@ -0,0 +1,317 @@
+//! Filter expressions evaluated against an event's fields.
+//!
+//! ```text
+//! expr    = and ("||" and)*
+//! and     = term ("&&" term)*
+//! term    = "(" expr ")" | field op literal
+//! op      = "==" | "!=" | "<" | "<=" | ">" | ">="
+//! literal = integer | "string" | true | false
+//! ```
+//!
+//! e.g. `status == "Open"` or `amount >= 1000000 && payer != "11111111111111111111111111111111"`.
+//! A comparison against a missing field, or between mismatched types, is false.
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Compare { field: String, op: Op, literal: Literal },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Literal {
+    Int(i128),
+    Str(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Literal(Literal),
+    Op(Op),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let filter = parser.expr()?;
+        if parser.position != tokens.len() {
+            return Err(format!("unexpected token {:?}", tokens[parser.position]));
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, fields: &Value) -> bool {
+        match self {
+            Filter::Or(terms) => terms.iter().any(|term| term.matches(fields)),
+            Filter::And(terms) => terms.iter().all(|term| term.matches(fields)),
+            Filter::Compare { field, op, literal } => {
+                let ordering = match (fields.get(field), literal) {
+                    (Some(Value::Number(number)), Literal::Int(expected)) => {
+                        let actual = number.as_i64().map(i128::from).or_else(|| number.as_u64().map(i128::from));
+                        actual.map(|actual| actual.cmp(expected))
+                    }
+                    (Some(Value::String(actual)), Literal::Str(expected)) => Some(actual.as_str().cmp(expected.as_str())),
+                    (Some(Value::Bool(actual)), Literal::Bool(expected)) => Some(actual.cmp(expected)),
+                    _ => None,
+                };
+                ordering.is_some_and(|ordering| op.holds(ordering))
+            }
+        }
+    }
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn expr(&mut self) -> Result<Filter, String> {
+        let mut terms = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Filter::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut terms = vec![self.term()?];
+        while self.eat(&Token::And) {
+            terms.push(self.term()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Filter::And(terms) })
+    }
+
+    fn term(&mut self) -> Result<Filter, String> {
+        if self.eat(&Token::Open) {
+            let inner = self.expr()?;
+            if !self.eat(&Token::Close) {
+                return Err("missing closing parenthesis".to_string());
+            }
+            return Ok(inner);
+        }
+
+        match (self.next(), self.next(), self.next()) {
+            (Some(Token::Ident(field)), Some(Token::Op(op)), Some(Token::Literal(literal))) => {
+                Ok(Filter::Compare { field: field.clone(), op: *op, literal: literal.clone() })
+            }
+            _ => Err("expected `field op literal`".to_string()),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let token = match c {
+            '(' => {
+                chars.next();
+                Token::Open
+            }
+            ')' => {
+                chars.next();
+                Token::Close
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                Token::Literal(Literal::Str(value))
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next_if_eq(&c).is_none() {
+                    return Err(format!("expected `{c}{c}`"));
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let op = match (c, chars.next_if_eq(&'=').is_some()) {
+                    ('=', true) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', true) => Op::Le,
+                    ('>', true) => Op::Ge,
+                    ('<', false) => Op::Lt,
+                    ('>', false) => Op::Gt,
+                    _ => return Err(format!("unexpected operator `{c}`")),
+                };
+                Token::Op(op)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '-' || *c == '_') {
+                    number.push(c);
+                }
+                let value = number.replace('_', "").parse().map_err(|_| format!("invalid number `{number}`"))?;
+                Token::Literal(Literal::Int(value))
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                match ident.as_str() {
+                    "true" => Token::Literal(Literal::Bool(true)),
+                    "false" => Token::Literal(Literal::Bool(false)),
+                    _ => Token::Ident(ident),
+                }
+            }
+            c => return Err(format!("unexpected character `{c}`")),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn matches(source: &str, fields: Value) -> bool {
+        Filter::parse(source).unwrap().matches(&fields)
+    }
+
+    #[test]
+    fn compares_strings() {
+        assert!(matches(r#"status == "Open""#, json!({ "status": "Open" })));
+        assert!(!matches(r#"status == "Open""#, json!({ "status": "Closed" })));
+        assert!(matches(r#"status != "Open""#, json!({ "status": "Closed" })));
+    }
+
+    #[test]
+    fn compares_integers_in_every_direction() {
+        let fields = json!({ "amount": 1_000 });
+        assert!(matches("amount == 1000", fields.clone()));
+        assert!(matches("amount >= 1_000", fields.clone()));
+        assert!(matches("amount <= 1000", fields.clone()));
+        assert!(matches("amount > 999", fields.clone()));
+        assert!(matches("amount < 1001", fields.clone()));
+        assert!(!matches("amount > 1000", fields.clone()));
+        assert!(!matches("amount < 1000", fields));
+    }
+
+    #[test]
+    fn compares_integers_beyond_i64() {
+        assert!(matches("amount > 9223372036854775807", json!({ "amount": u64::MAX })));
+        assert!(matches("delta < -5", json!({ "delta": -6 })));
+    }
+
+    #[test]
+    fn compares_booleans() {
+        assert!(matches("paid == true", json!({ "paid": true })));
+        assert!(!matches("paid == true", json!({ "paid": false })));
+    }
+
+    #[test]
+    fn missing_fields_and_mismatched_types_never_match() {
+        assert!(!matches("amount == 5", json!({})));
+        assert!(!matches("amount != 5", json!({})));
+        assert!(!matches(r#"amount == "5""#, json!({ "amount": 5 })));
+        assert!(!matches("paid != 1", json!({ "paid": true })));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let filter = Filter::parse("a == 1 || b == 2 && c == 3").unwrap();
+        assert!(filter.matches(&json!({ "a": 1 })));
+        assert!(!filter.matches(&json!({ "b": 2 })));
+        assert!(filter.matches(&json!({ "b": 2, "c": 3 })));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let filter = Filter::parse("(a == 1 || b == 2) && c == 3").unwrap();
+        assert!(!filter.matches(&json!({ "a": 1 })));
+        assert!(filter.matches(&json!({ "a": 1, "c": 3 })));
+        assert!(filter.matches(&json!({ "b": 2, "c": 3 })));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for source in [
+            "",
+            "status ==",
+            "status = 1",
+            "a == 1 &",
+            "a == 1 | b == 2",
+            r#"status == "Open"#,
+            "(a == 1",
+            "a == 1)",
+            "a == 1 b == 2",
+            "a == 1-2",
+            "a == @",
+        ] {
+            assert!(Filter::parse(source).is_err(), "{source:?} should not parse");
+        }
+    }
+}
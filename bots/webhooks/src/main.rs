@@ -0,0 +1,189 @@
+//! Webhook relayer for bots and merchants.
+//!
+//! Reads log notifications for the messaging program from stdin, one JSON object per line as
+//! delivered by `logsSubscribe` (`{"signature", "err", "logs"}`, plus an optional `"slot"`),
+//! decodes the program's events and matches them against the subscriptions in the config.
+//! Each subscription names the event kinds it wants and an optional filter expression over the
+//! event's fields (see `filter`), so a bot receives e.g. paid invoices or opened tickets rather
+//! than all inbox traffic.
+//!
+//! Every match is printed as a JSON line with the target URL, the request body and an
+//! HMAC-SHA256 of the body under the subscription secret, for the delivery worker to POST with
+//! an `X-GMChat-Signature` header.
+//!
+//! ```toml
+//! [[subscription]]
+//! name = "support-desk"
+//! url = "https://example.com/hooks/gmchat"
+//! secret = "..."
+//! kinds = ["ticket_status_changed"]
+//! filter = 'status == "Open"'
+//! ```
+//!
+//! Usage: gmchat-webhooks <config.toml> < logs.jsonl
+
+mod events;
+mod filter;
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead};
+use std::{env, fs, process};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::events::{decode_logs, DecodedEvent};
+use crate::filter::Filter;
+
+/// Event kinds a subscription may name
+const KINDS: [&str; 12] = [
+    "invoice_paid",
+    "ticket_status_changed",
+    "poll_resolved",
+    "task_status_changed",
+    "trade_status_changed",
+    "message_unlocked",
+    "ama_answered",
+    "message_queued",
+    "message_delivered",
+    "reminder_due",
+    "room_message_approved",
+    "room_message_rejected",
+];
+
+#[derive(Deserialize)]
+struct Config {
+    subscription: Vec<SubscriptionConfig>,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionConfig {
+    name: String,
+    url: String,
+    /// Shared secret for the body signature
+    secret: String,
+    kinds: Vec<String>,
+    filter: Option<String>,
+}
+
+struct Subscription {
+    config: SubscriptionConfig,
+    filter: Option<Filter>,
+}
+
+impl Subscription {
+    fn wants(&self, event: &DecodedEvent) -> bool {
+        self.config.kinds.iter().any(|kind| kind == event.kind)
+            && self.filter.as_ref().is_none_or(|filter| filter.matches(&event.fields))
+    }
+}
+
+/// A `logsSubscribe` notification value
+#[derive(Deserialize)]
+struct LogNotification {
+    signature: String,
+    #[serde(default)]
+    slot: Option<u64>,
+    err: Option<Value>,
+    logs: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Delivery<'a> {
+    subscription: &'a str,
+    url: &'a str,
+    /// Value for the `X-GMChat-Signature` header
+    signature: String,
+    body: String,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: {} <config.toml> < logs.jsonl", args[0]);
+        process::exit(2);
+    }
+
+    if let Err(err) = run(&args[1]) {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn run(config_path: &str) -> Result<(), String> {
+    let subscriptions = load_subscriptions(config_path)?;
+
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // One bad line from the log source must not stop deliveries for everything after it
+        let notification: LogNotification = match serde_json::from_str(&line) {
+            Ok(notification) => notification,
+            Err(err) => {
+                eprintln!("skipping invalid log notification: {err}");
+                continue;
+            }
+        };
+        // Events from failed transactions never happened
+        if notification.err.is_some() {
+            continue;
+        }
+
+        for event in decode_logs(&notification.logs) {
+            let body = json!({
+                "kind": event.kind,
+                "transaction": notification.signature,
+                "slot": notification.slot,
+                "event": event.fields,
+            })
+            .to_string();
+
+            for subscription in subscriptions.iter().filter(|subscription| subscription.wants(&event)) {
+                let delivery = Delivery {
+                    subscription: &subscription.config.name,
+                    url: &subscription.config.url,
+                    signature: sign(&subscription.config.secret, &body),
+                    body: body.clone(),
+                };
+                println!("{}", serde_json::to_string(&delivery).map_err(|e| e.to_string())?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn load_subscriptions(path: &str) -> Result<Vec<Subscription>, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("reading {path}: {e}"))?;
+    let config: Config = toml::from_str(&raw).map_err(|e| format!("parsing {path}: {e}"))?;
+
+    config
+        .subscription
+        .into_iter()
+        .map(|config| {
+            if let Some(kind) = config.kinds.iter().find(|kind| !KINDS.contains(&kind.as_str())) {
+                return Err(format!("subscription {}: unknown event kind {kind}", config.name));
+            }
+            let filter = config
+                .filter
+                .as_deref()
+                .map(Filter::parse)
+                .transpose()
+                .map_err(|e| format!("subscription {}: invalid filter: {e}", config.name))?;
+            Ok(Subscription { config, filter })
+        })
+        .collect()
+}
+
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    mac.finalize().into_bytes().iter().fold(String::from("sha256="), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
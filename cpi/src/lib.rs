@@ -0,0 +1,43 @@
+//! Stable CPI surface for programs that want to drop notifications into a user's inbox,
+//! e.g. a lending protocol warning a borrower whose health factor is degrading.
+//!
+//! The integrating program registers a notifier once with a PDA it controls as the authority,
+//! then signs `notify` calls with that PDA.
+
+use anchor_lang::prelude::*;
+
+pub use solana_messaging::cpi::accounts::{Notify, RegisterNotifier};
+pub use solana_messaging::program::SolanaMessaging;
+pub use solana_messaging::{NotificationSeverity, ID as PROGRAM_ID};
+
+/// Registers `authority` as a notifier under a display name.
+pub fn register_notifier<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, RegisterNotifier<'info>>,
+    name: String,
+) -> Result<()> {
+    solana_messaging::cpi::register_notifier(ctx, name)
+}
+
+/// Sends a notification to `recipient` from a registered notifier.
+pub fn notify<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, Notify<'info>>,
+    recipient: Pubkey,
+    severity: NotificationSeverity,
+    content: String,
+) -> Result<()> {
+    solana_messaging::cpi::notify(ctx, recipient, severity, content)
+}
+
+/// Notifier account for `authority`.
+pub fn notifier_address(authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"notifier", authority.as_ref()], &PROGRAM_ID).0
+}
+
+/// Notification account created by the `index`-th `notify` call of `notifier`.
+pub fn notification_address(notifier: &Pubkey, index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"notification", notifier.as_ref(), &index.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use gmchat_cpi::{NotificationSeverity, SolanaMessaging};
+
+declare_id!("LendNotif1111111111111111111111111111111111");
+
+/// Health factor (in basis points) below which borrowers are warned
+const WARNING_HEALTH_BPS: u64 = 11_000;
+/// Health factor (in basis points) below which the warning is critical
+const CRITICAL_HEALTH_BPS: u64 = 10_200;
+
+#[program]
+pub mod lending_notifier {
+    use super::*;
+
+    pub fn register(ctx: Context<Register>) -> Result<()> {
+        let seeds: &[&[u8]] = &[b"notifier_authority", &[ctx.bumps.notifier_authority]];
+
+        gmchat_cpi::register_notifier(
+            CpiContext::new_with_signer(
+                ctx.accounts.messaging_program.to_account_info(),
+                gmchat_cpi::RegisterNotifier {
+                    notifier: ctx.accounts.notifier.to_account_info(),
+                    authority: ctx.accounts.notifier_authority.to_account_info(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[seeds],
+            ),
+            "Example Lending".to_string(),
+        )
+    }
+
+    /// Warns the position's borrower if its health is low. The values come from the position
+    /// account, so callers can't forge a warning for a healthy borrower.
+    pub fn check_health(ctx: Context<CheckHealth>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        require!(position.debt_value > 0, LendingError::NoDebt);
+
+        // Health factor in basis points: collateral / debt
+        let health_bps = (position.collateral_value as u128 * 10_000 / position.debt_value as u128) as u64;
+        if health_bps >= WARNING_HEALTH_BPS {
+            return Ok(());
+        }
+
+        let severity = if health_bps < CRITICAL_HEALTH_BPS {
+            NotificationSeverity::Critical
+        } else {
+            NotificationSeverity::Warning
+        };
+        let content = format!(
+            "Your position health is {}.{:02}; add collateral or repay to avoid liquidation",
+            health_bps / 10_000,
+            health_bps % 10_000 / 100
+        );
+
+        let seeds: &[&[u8]] = &[b"notifier_authority", &[ctx.bumps.notifier_authority]];
+        gmchat_cpi::notify(
+            CpiContext::new_with_signer(
+                ctx.accounts.messaging_program.to_account_info(),
+                gmchat_cpi::Notify {
+                    notifier: ctx.accounts.notifier.to_account_info(),
+                    notification: ctx.accounts.notification.to_account_info(),
+                    authority: ctx.accounts.notifier_authority.to_account_info(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[seeds],
+            ),
+            position.borrower,
+            severity,
+            content,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct Register<'info> {
+    /// CHECK: Created by the messaging program
+    #[account(mut)]
+    pub notifier: UncheckedAccount<'info>,
+
+    /// CHECK: PDA signer used as the notifier authority
+    #[account(seeds = [b"notifier_authority"], bump)]
+    pub notifier_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub messaging_program: Program<'info, SolanaMessaging>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CheckHealth<'info> {
+    #[account(seeds = [b"position", position.borrower.as_ref()], bump = position.bump)]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Validated by the messaging program
+    #[account(mut)]
+    pub notifier: UncheckedAccount<'info>,
+
+    /// CHECK: Created by the messaging program
+    #[account(mut)]
+    pub notification: UncheckedAccount<'info>,
+
+    /// CHECK: PDA signer used as the notifier authority
+    #[account(seeds = [b"notifier_authority"], bump)]
+    pub notifier_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub messaging_program: Program<'info, SolanaMessaging>,
+    pub system_program: Program<'info, System>,
+}
+
+/// A borrower's position, kept up to date by the lending instructions this example leaves out.
+#[account]
+pub struct Position {
+    pub borrower: Pubkey,
+    pub collateral_value: u64,
+    pub debt_value: u64,
+    pub bump: u8,
+}
+
+#[error_code]
+pub enum LendingError {
+    #[msg("Position has no debt")]
+    NoDebt,
+}
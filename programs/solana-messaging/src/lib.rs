@@ -1,5 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use anchor_spl::token::{self, Approve, CloseAccount, Revoke, Token, TokenAccount, Mint, MintTo, SetAuthority, Transfer};
 use mpl_token_metadata::{
     instruction as mpl_instruction,
     state as mpl_state,
@@ -7,6 +14,61 @@ use mpl_token_metadata::{
 
 declare_id!("YourProgramIdHere"); // Replace with your actual program ID
 
+/// Pyth oracle program (FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH) that owns the price
+/// accounts read by price-based features
+pub const PYTH_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    220, 229, 235, 225, 228, 156, 59, 159, 17, 76, 181, 84, 76, 80, 169, 158,
+    192, 214, 146, 214, 63, 86, 121, 90, 224, 41, 172, 131, 217, 234, 139, 226,
+]);
+
+/// SPL Governance program (GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw) whose governances may
+/// post proposal notifications
+pub const SPL_GOVERNANCE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    234, 228, 53, 189, 238, 117, 183, 52, 205, 89, 62, 207, 154, 48, 75, 128,
+    36, 186, 40, 152, 103, 183, 105, 177, 249, 60, 167, 187, 184, 142, 70, 254,
+]);
+
+/// Compute Budget program (ComputeBudget111111111111111111111111111111), whose limit and price
+/// requests may precede a paid instruction
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231,
+    188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+]);
+
+/// Jupiter aggregator v6 (JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4) that routes swap-and-tip
+/// payments
+pub const JUPITER_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    4, 121, 213, 91, 242, 49, 192, 110, 238, 116, 197, 110, 206, 104, 21, 7,
+    253, 177, 178, 222, 163, 244, 142, 81, 2, 177, 205, 162, 86, 188, 19, 143,
+]);
+
+/// Metaplex Bubblegum (BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY), which mints the
+/// compressed patron receipts
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    152, 139, 128, 235, 121, 53, 40, 105, 178, 36, 116, 95, 89, 221, 191, 138,
+    38, 88, 202, 19, 220, 104, 129, 33, 38, 53, 28, 174, 7, 193, 165, 165,
+]);
+
+/// SPL Noop (noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV), Bubblegum's log wrapper
+pub const SPL_NOOP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    11, 188, 15, 192, 187, 71, 202, 47, 116, 196, 17, 46, 148, 171, 19, 207,
+    163, 198, 52, 229, 220, 23, 234, 203, 3, 205, 26, 35, 205, 126, 120, 124,
+]);
+
+/// SPL Account Compression (cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK), which owns the
+/// patron receipt tree
+pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    9, 42, 19, 238, 149, 196, 28, 186, 8, 166, 127, 90, 198, 126, 141, 247,
+    225, 218, 17, 98, 94, 29, 100, 19, 127, 143, 79, 35, 131, 3, 127, 20,
+]);
+
+/// Metaplex Token Metadata (metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s), whose metadata names
+/// a collection's update authority
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    11, 112, 101, 177, 227, 209, 124, 69, 56, 157, 82, 127, 107, 4, 195, 205,
+    88, 184, 108, 115, 26, 160, 253, 181, 73, 182, 209, 188, 3, 248, 41, 70,
+]);
+
 #[program]
 pub mod solana_messaging {
     use super::*;
@@ -15,14 +77,43 @@ pub mod solana_messaging {
         ctx: Context<SendMessage>,
         message_content: String,
         recipient: Pubkey,
+        priority: MessagePriority,
+        client_message_id: Option<[u8; 16]>,
     ) -> Result<()> {
         let message = &mut ctx.accounts.message;
         let sender = &ctx.accounts.sender;
         let clock = Clock::get()?;
 
-        // Validate message content
-        require!(message_content.len() <= 500, ErrorCode::MessageTooLong);
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+
+        // Validate message content; pass holders may send longer messages
+        let holds_pass = holds_live_pass(
+            ctx.accounts.sender_pass.as_deref(),
+            ctx.accounts.sender_pass_record.as_deref(),
+            clock.unix_timestamp,
+        );
+        let max_content_len = if holds_pass { Message::MAX_PASS_CONTENT_LEN } else { Message::MAX_CONTENT_LEN };
+        require!(message_content.len() <= max_content_len, ErrorCode::MessageTooLong);
         require!(!message_content.is_empty(), ErrorCode::EmptyMessage);
+        require!(client_message_id != Some([0; 16]), ErrorCode::InvalidClientMessageId);
+
+        // Recipients may require proof that the sender is not a fresh burner wallet, unless the
+        // sender holds one of the recipient's DM links
+        if let Some(dm_link) = ctx.accounts.dm_link.as_mut() {
+            require_keys_eq!(dm_link.owner, recipient, ErrorCode::InvalidDmLink);
+            require!(dm_link.expires_at == 0 || clock.unix_timestamp < dm_link.expires_at, ErrorCode::DmLinkExpired);
+            require!(dm_link.uses < dm_link.max_uses, ErrorCode::DmLinkExhausted);
+
+            dm_link.uses += 1;
+        } else {
+            check_sybil_gate(
+                &ctx.accounts.recipient_gate,
+                &sender.key(),
+                ctx.accounts.sender_stake_account.as_deref(),
+                ctx.accounts.sender_attestation.as_deref(),
+                &clock,
+            )?;
+        }
 
         // Initialize message account
         message.sender = sender.key();
@@ -30,7 +121,195 @@ pub mod solana_messaging {
         message.content = message_content;
         message.timestamp = clock.unix_timestamp;
         message.nft_mint = ctx.accounts.nft_mint.key();
+        message.priority = priority;
+        message.client_message_id = client_message_id.unwrap_or_default();
+        message.app = Pubkey::default();
+        message.namespace = ctx.accounts.namespace.as_ref().map_or(Pubkey::default(), |namespace| namespace.key());
         message.bump = ctx.bumps.message;
+        fail_point(ctx.remaining_accounts, ChaosPoint::AfterMessageInit)?;
+
+        // Members send within their own namespace; crossing into another needs both policies
+        let sender_namespace = NamespaceMember::namespace_of(&ctx.accounts.sender_membership)?;
+        require_keys_eq!(message.namespace, sender_namespace, ErrorCode::WrongNamespace);
+        let recipient_namespace = NamespaceMember::namespace_of(&ctx.accounts.recipient_membership)?;
+        if sender_namespace != recipient_namespace {
+            if let Some(namespace) = ctx.accounts.namespace.as_ref() {
+                require!(namespace.federates_with(recipient_namespace), ErrorCode::NotFederated);
+            }
+            if recipient_namespace != Pubkey::default() {
+                let theirs = ctx.accounts.recipient_namespace.as_ref().ok_or(ErrorCode::NotFederated)?;
+                require_keys_eq!(theirs.key(), recipient_namespace, ErrorCode::WrongNamespace);
+                require!(theirs.federates_with(sender_namespace), ErrorCode::NotFederated);
+            }
+        }
+
+        // Urgent is bounded by the recipient's priority policy
+        let mut urgent_fee_paid = 0;
+        if priority == MessagePriority::Urgent {
+            let policy = PriorityPolicy::load(&ctx.accounts.recipient_priority_policy)?;
+            // Either side can open the settings, so only the recipient's own confirmation of the
+            // safety number makes the sender a contact
+            let is_contact = ctx.accounts.contact_settings.as_ref().is_some_and(|settings| {
+                settings.participants.contains(&sender.key())
+                    && settings.side_of(&recipient).is_ok_and(|side| settings.confirmed[side])
+            });
+            let (urgent_fee, urgent_from_contacts) = match policy {
+                Some(policy) => (policy.urgent_fee_lamports, policy.urgent_from_contacts),
+                None => (0, true),
+            };
+
+            if !(is_contact && urgent_from_contacts) {
+                require!(urgent_fee > 0, ErrorCode::UrgentNotAllowed);
+                urgent_fee_paid = urgent_fee;
+                let recipient_wallet = ctx.accounts.recipient_wallet.as_ref().ok_or(ErrorCode::UrgentNotAllowed)?;
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: sender.to_account_info(),
+                            to: recipient_wallet.to_account_info(),
+                        },
+                    ),
+                    urgent_fee,
+                )?;
+            }
+        }
+
+        // Protocol fee, at the best discount the sender's pass or activity earns
+        let (fee, app_share_bps) = match FeeSchedule::load(&ctx.accounts.fee_schedule)? {
+            Some(schedule) => {
+                let messages_sent = ctx.accounts.sender_stats.as_ref().map_or(0, |stats| stats.messages_sent);
+                (schedule.fee_for(messages_sent, holds_pass), schedule.app_share_bps)
+            }
+            None => (0, 0),
+        };
+        if fee > 0 {
+            let treasury = ctx.accounts.treasury.as_ref().ok_or(ErrorCode::TreasuryRequired)?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: sender.to_account_info(),
+                        to: treasury.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+        }
+
+        // Copies of recently flagged spam cost a deposit, refunded unless the moderator confirms it
+        let spam_deposit = match SpamFilter::load(&ctx.accounts.spam_filter)? {
+            Some(filter) if filter.contains(&SpamFilter::content_hash(&message.content), clock.unix_timestamp) => {
+                filter.deposit_lamports
+            }
+            _ => 0,
+        };
+        match ctx.accounts.spam_deposit.as_mut() {
+            Some(deposit) => {
+                deposit.message = message.key();
+                deposit.sender = sender.key();
+                deposit.recipient = recipient;
+                deposit.amount_lamports = spam_deposit;
+                deposit.held_until = clock.unix_timestamp + SpamDeposit::HOLD_SECS;
+                deposit.bump = ctx.bumps.spam_deposit;
+                if spam_deposit > 0 {
+                    system_program::transfer(
+                        CpiContext::new(
+                            ctx.accounts.system_program.to_account_info(),
+                            system_program::Transfer {
+                                from: sender.to_account_info(),
+                                to: deposit.to_account_info(),
+                            },
+                        ),
+                        spam_deposit,
+                    )?;
+                }
+            }
+            None => require!(spam_deposit == 0, ErrorCode::SpamDepositRequired),
+        }
+
+        // Whoever built the transaction must not have slipped a drain of the sender in ahead of the payment
+        if urgent_fee_paid > 0 || fee > 0 || spam_deposit > 0 {
+            require_no_preceding_drain(&ctx.accounts.instructions, &sender.key())?;
+        }
+
+        // Attribute the send to the front-end that sent it. Its share of the fee is only credited
+        // when the app co-signs, so a sender can't name their own app to take back part of the fee.
+        if let Some(app) = ctx.accounts.app.as_mut() {
+            let signed_by_app = ctx.accounts.app_authority.as_ref().is_some_and(|signer| signer.key() == app.authority);
+            app.record_send(fee, if signed_by_app { app_share_bps } else { 0 });
+            message.app = app.key();
+        }
+
+        // Outside the recipient's delivery window the message is queued until the window opens
+        let deliver_at = match DeliveryWindow::load(&ctx.accounts.recipient_window)? {
+            Some(window) => window.next_open(clock.unix_timestamp),
+            None => clock.unix_timestamp,
+        };
+        let queued = deliver_at > clock.unix_timestamp;
+        match ctx.accounts.delivery.as_mut() {
+            Some(delivery) => {
+                delivery.message = message.key();
+                delivery.recipient = recipient;
+                delivery.state = if queued { DeliveryState::Queued } else { DeliveryState::Delivered };
+                delivery.deliver_at = deliver_at;
+                delivery.delivered_at = if queued { 0 } else { clock.unix_timestamp };
+                delivery.bump = ctx.bumps.delivery;
+            }
+            None => require!(!queued, ErrorCode::DeliveryRecordRequired),
+        }
+        if queued {
+            emit!(MessageQueued { message: message.key(), recipient, deliver_at });
+        }
+
+        if let Some(stats) = ctx.accounts.epoch_stats.as_mut() {
+            stats.record_message(&sender.key(), clock.epoch)?;
+        }
+        if let Some(stats) = ctx.accounts.sender_stats.as_mut() {
+            stats.record_activity(clock.unix_timestamp);
+        }
+
+        // Replying to a message from the recipient counts as answering it
+        if let Some(reply_to) = &ctx.accounts.reply_to {
+            require!(
+                reply_to.sender == recipient && reply_to.recipient == sender.key(),
+                ErrorCode::InvalidReplyTarget
+            );
+            record_reputation(
+                ctx.accounts.sender_reputation.as_mut(),
+                Some(&ctx.accounts.config),
+                ReputationEvent::MessageAnswered,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        // Everything the send cost the sender, except the transaction fee. The accounts it
+        // created hold exactly their rent, apart from the deposit on top of the spam deposit's.
+        let mut rent_lamports = message.to_account_info().lamports()
+            + ctx.accounts.nft_mint.to_account_info().lamports()
+            + ctx.accounts.recipient_token_account.to_account_info().lamports()
+            + ctx.accounts.sender_token_account.to_account_info().lamports();
+        if let Some(delivery) = ctx.accounts.delivery.as_ref() {
+            rent_lamports += delivery.to_account_info().lamports();
+        }
+        if let Some(deposit) = ctx.accounts.spam_deposit.as_ref() {
+            rent_lamports += deposit.to_account_info().lamports() - spam_deposit;
+        }
+        message.cost = MessageCost {
+            rent_lamports,
+            protocol_fee_lamports: fee,
+            urgent_fee_lamports: urgent_fee_paid,
+            deposit_lamports: spam_deposit,
+        };
+
+        emit!(MessageSent {
+            message: message.key(),
+            sender: sender.key(),
+            recipient,
+            client_message_id: message.client_message_id,
+            cost: message.cost,
+            timestamp: clock.unix_timestamp,
+        });
 
         // Create NFT metadata
         let metadata_account = &ctx.accounts.metadata_account;
@@ -54,95 +333,14840 @@ pub mod solana_messaging {
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(message_content: String, recipient: Pubkey)]
-pub struct SendMessage<'info> {
-    #[account(
-        init,
-        payer = sender,
-        space = Message::LEN,
-        seeds = [b"message", sender.key().as_ref(), recipient.as_ref()],
-        bump
-    )]
-    pub message: Account<'info, Message>,
+    pub fn send_event(
+        ctx: Context<SendEvent>,
+        event_id: u64,
+        recipient: Pubkey,
+        title: String,
+        location_uri: String,
+        start_ts: i64,
+        end_ts: i64,
+        capacity: u32,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let organizer = &ctx.accounts.organizer;
+        let clock = Clock::get()?;
 
-    #[account(
-        init,
-        payer = sender,
-        mint::decimals = 0,
-        mint::authority = sender,
-    )]
-    pub nft_mint: Account<'info, Mint>,
+        // Validate event details
+        require!(!title.is_empty(), ErrorCode::EmptyEventTitle);
+        require!(title.len() <= EventMessage::MAX_TITLE_LEN, ErrorCode::EventTitleTooLong);
+        require!(location_uri.len() <= EventMessage::MAX_LOCATION_URI_LEN, ErrorCode::LocationUriTooLong);
+        require!(end_ts > start_ts, ErrorCode::InvalidEventTimes);
+        require!(end_ts > clock.unix_timestamp, ErrorCode::InvalidEventTimes);
+        require!(capacity > 0, ErrorCode::InvalidEventCapacity);
 
-    #[account(
-        init,
-        payer = sender,
-        associated_token::mint = nft_mint,
-        associated_token::authority = recipient,
-    )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
+        // Initialize event account
+        event.organizer = organizer.key();
+        event.recipient = recipient;
+        event.event_id = event_id;
+        event.title = title;
+        event.location_uri = location_uri;
+        event.start_ts = start_ts;
+        event.end_ts = end_ts;
+        event.capacity = capacity;
+        event.attendee_count = 0;
+        event.timestamp = clock.unix_timestamp;
+        event.bump = ctx.bumps.event;
 
-    #[account(
-        init,
-        payer = sender,
-        associated_token::mint = nft_mint,
-        associated_token::authority = sender,
-    )]
-    pub sender_token_account: Account<'info, TokenAccount>,
+        Ok(())
+    }
 
-    /// CHECK: This account is validated in the instruction
-    #[account(mut)]
-    pub metadata_account: UncheckedAccount<'info>,
+    pub fn rsvp(ctx: Context<Rsvp>) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let attendee = &mut ctx.accounts.attendee;
+        let clock = Clock::get()?;
 
-    #[account(mut)]
-    pub sender: Signer<'info>,
+        // RSVPs close once the event is over or full
+        require!(clock.unix_timestamp < event.end_ts, ErrorCode::EventEnded);
+        require!(event.attendee_count < event.capacity, ErrorCode::EventFull);
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
-}
+        // Record the attendee
+        attendee.event = event.key();
+        attendee.attendee = ctx.accounts.user.key();
+        attendee.timestamp = clock.unix_timestamp;
+        attendee.bump = ctx.bumps.attendee;
 
-#[derive(Accounts)]
-pub struct ReceiveMessage<'info> {
-    #[account(
-        mut,
-        seeds = [b"message", message.sender.as_ref(), message.recipient.as_ref()],
-        bump = message.bump,
-    )]
-    pub message: Account<'info, Message>,
+        event.attendee_count = event.attendee_count.checked_add(1).ok_or(ErrorCode::EventFull)?;
 
-    #[account(mut)]
-    pub recipient: Signer<'info>,
-}
+        Ok(())
+    }
 
-#[account]
-pub struct Message {
-    pub sender: Pubkey,
-    pub recipient: Pubkey,
-    pub content: String,
-    pub timestamp: i64,
-    pub nft_mint: Pubkey,
-    pub bump: u8,
-}
+    pub fn cancel_rsvp(ctx: Context<CancelRsvp>) -> Result<()> {
+        let event = &mut ctx.accounts.event;
 
-impl Message {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // sender
-        32 + // recipient
-        4 + 500 + // content (String with max 500 chars)
-        8 + // timestamp
-        32 + // nft_mint
-        1; // bump
-}
+        // The attendee account is closed by the constraint, freeing a seat
+        event.attendee_count = event.attendee_count.saturating_sub(1);
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Message content is too long")]
-    MessageTooLong,
-    #[msg("Message content cannot be empty")]
-    EmptyMessage,
-    #[msg("Unauthorized recipient")]
-    UnauthorizedRecipient,
+        Ok(())
+    }
+
+    pub fn send_task(
+        ctx: Context<SendTask>,
+        task_id: u64,
+        assignee: Pubkey,
+        description: String,
+        due_ts: i64,
+    ) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let creator = &ctx.accounts.creator;
+        let clock = Clock::get()?;
+
+        // Validate task details
+        require!(!description.is_empty(), ErrorCode::EmptyTaskDescription);
+        require!(description.len() <= TaskMessage::MAX_DESCRIPTION_LEN, ErrorCode::TaskDescriptionTooLong);
+        require!(due_ts > clock.unix_timestamp, ErrorCode::InvalidDueDate);
+
+        // Initialize task account
+        task.creator = creator.key();
+        task.assignee = assignee;
+        task.task_id = task_id;
+        task.description = description;
+        task.due_ts = due_ts;
+        task.status = TaskStatus::Open;
+        task.completed_at = 0;
+        task.timestamp = clock.unix_timestamp;
+        task.bump = ctx.bumps.task;
+
+        emit!(TaskStatusChanged {
+            task: task.key(),
+            actor: creator.key(),
+            status: TaskStatus::Open,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn complete_task(ctx: Context<CompleteTask>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let assignee = &ctx.accounts.assignee;
+        let clock = Clock::get()?;
+
+        // Only the assignee can complete an open task
+        require!(task.assignee == assignee.key(), ErrorCode::UnauthorizedAssignee);
+        require!(task.status == TaskStatus::Open, ErrorCode::TaskNotOpen);
+
+        task.status = TaskStatus::Completed;
+        task.completed_at = clock.unix_timestamp;
+
+        emit!(TaskStatusChanged {
+            task: task.key(),
+            actor: assignee.key(),
+            status: TaskStatus::Completed,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn send_trade_proposal(
+        ctx: Context<SendTradeProposal>,
+        trade_id: u64,
+        recipient: Pubkey,
+        offer_amount: u64,
+        ask_amount: u64,
+    ) -> Result<()> {
+        let trade = &mut ctx.accounts.trade;
+        let proposer = &ctx.accounts.proposer;
+        let clock = Clock::get()?;
+
+        // Validate trade terms
+        require!(offer_amount > 0, ErrorCode::InvalidTradeAmount);
+        require!(recipient != proposer.key(), ErrorCode::InvalidTradeParty);
+
+        // Move the offered tokens into escrow
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.proposer_offer_account.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: proposer.to_account_info(),
+                },
+            ),
+            offer_amount,
+        )?;
+
+        // Initialize trade account
+        trade.proposer = proposer.key();
+        trade.recipient = recipient;
+        trade.trade_id = trade_id;
+        trade.offer_mint = ctx.accounts.offer_mint.key();
+        trade.offer_amount = offer_amount;
+        trade.ask_mint = ctx.accounts.ask_mint.key();
+        trade.ask_amount = ask_amount;
+        trade.status = TradeStatus::Pending;
+        trade.timestamp = clock.unix_timestamp;
+        trade.bump = ctx.bumps.trade;
+        trade.vault_bump = ctx.bumps.escrow_vault;
+
+        emit!(TradeStatusChanged {
+            trade: trade.key(),
+            actor: proposer.key(),
+            status: TradeStatus::Pending,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_trade(ctx: Context<AcceptTrade>) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        let recipient = &ctx.accounts.recipient;
+        let clock = Clock::get()?;
+
+        require!(trade.recipient == recipient.key(), ErrorCode::InvalidTradeParty);
+        require!(trade.status == TradeStatus::Pending, ErrorCode::TradeNotPending);
+
+        // Recipient pays the asked side directly to the proposer
+        if trade.ask_amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.recipient_ask_account.to_account_info(),
+                        to: ctx.accounts.proposer_ask_account.to_account_info(),
+                        authority: recipient.to_account_info(),
+                    },
+                ),
+                trade.ask_amount,
+            )?;
+        }
+
+        // Escrow releases the offered side to the recipient and is closed
+        let trade_id = trade.trade_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"trade", trade.proposer.as_ref(), &trade_id, &[trade.bump]];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.recipient_offer_account,
+            &ctx.accounts.proposer,
+            &trade.to_account_info(),
+            seeds,
+            trade.offer_amount,
+        )?;
+
+        let trade = &mut ctx.accounts.trade;
+        trade.status = TradeStatus::Accepted;
+
+        emit!(TradeStatusChanged {
+            trade: trade.key(),
+            actor: recipient.key(),
+            status: TradeStatus::Accepted,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn decline_trade(ctx: Context<DeclineTrade>) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        let authority = &ctx.accounts.authority;
+        let clock = Clock::get()?;
+
+        // Either party can call the trade off while it is pending
+        require!(
+            authority.key() == trade.recipient || authority.key() == trade.proposer,
+            ErrorCode::InvalidTradeParty
+        );
+        require!(trade.status == TradeStatus::Pending, ErrorCode::TradeNotPending);
+
+        // Return the escrowed tokens to the proposer
+        let trade_id = trade.trade_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"trade", trade.proposer.as_ref(), &trade_id, &[trade.bump]];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.proposer_offer_account,
+            &ctx.accounts.proposer,
+            &trade.to_account_info(),
+            seeds,
+            trade.offer_amount,
+        )?;
+
+        let trade = &mut ctx.accounts.trade;
+        trade.status = TradeStatus::Declined;
+
+        emit!(TradeStatusChanged {
+            trade: trade.key(),
+            actor: authority.key(),
+            status: TradeStatus::Declined,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn send_invoice(
+        ctx: Context<SendInvoice>,
+        invoice_id: u64,
+        payer: Pubkey,
+        amount: u64,
+        memo: String,
+        due_ts: i64,
+        period_secs: i64,
+        max_periods: u32,
+    ) -> Result<()> {
+        let invoice = &mut ctx.accounts.invoice;
+        let issuer = &ctx.accounts.issuer;
+        let clock = Clock::get()?;
+
+        // Validate invoice terms
+        require!(amount > 0, ErrorCode::InvalidInvoiceAmount);
+        require!(memo.len() <= Invoice::MAX_MEMO_LEN, ErrorCode::InvoiceMemoTooLong);
+        require!(due_ts > clock.unix_timestamp, ErrorCode::InvalidDueDate);
+        require!(payer != issuer.key(), ErrorCode::InvalidInvoicePayer);
+        require!(period_secs >= 0, ErrorCode::InvalidInvoicePeriod);
+
+        // Initialize invoice account
+        invoice.issuer = issuer.key();
+        invoice.payer = payer;
+        invoice.invoice_id = invoice_id;
+        invoice.mint = ctx.accounts.mint.key();
+        invoice.amount = amount;
+        invoice.memo = memo;
+        invoice.due_ts = due_ts;
+        invoice.status = InvoiceStatus::Unpaid;
+        invoice.period_secs = period_secs;
+        invoice.max_periods = max_periods;
+        invoice.periods_paid = 0;
+        invoice.payer_token_account = Pubkey::default();
+        invoice.timestamp = clock.unix_timestamp;
+        invoice.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.issuer_membership)?;
+        invoice.bump = ctx.bumps.invoice;
+
+        Ok(())
+    }
+
+    pub fn pay_invoice(ctx: Context<PayInvoice>) -> Result<()> {
+        let invoice = &mut ctx.accounts.invoice;
+        let receipt = &mut ctx.accounts.receipt;
+        let payer = &ctx.accounts.payer;
+        let clock = Clock::get()?;
+
+        require!(invoice.payer == payer.key(), ErrorCode::InvalidInvoicePayer);
+        require!(invoice.status == InvoiceStatus::Unpaid, ErrorCode::InvoiceAlreadyPaid);
+        require!(!invoice.is_recurring(), ErrorCode::RecurringInvoice);
+
+        // Transfer the invoiced amount to the issuer
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: ctx.accounts.issuer_token_account.to_account_info(),
+                    authority: payer.to_account_info(),
+                },
+            ),
+            invoice.amount,
+        )?;
+
+        invoice.status = InvoiceStatus::Paid;
+        invoice.periods_paid = 1;
+
+        // Write the receipt referencing the invoice message
+        receipt.invoice = invoice.key();
+        receipt.payer = payer.key();
+        receipt.mint = invoice.mint;
+        receipt.amount = invoice.amount;
+        receipt.paid_at = clock.unix_timestamp;
+        receipt.bump = ctx.bumps.receipt;
+
+        emit!(InvoicePaid {
+            invoice: invoice.key(),
+            receipt: receipt.key(),
+            payer: payer.key(),
+            amount: invoice.amount,
+            period: 0,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn approve_recurring_invoice(ctx: Context<ApproveRecurringInvoice>, periods: u32) -> Result<()> {
+        let invoice = &mut ctx.accounts.invoice;
+        let payer = &ctx.accounts.payer;
+
+        require!(invoice.payer == payer.key(), ErrorCode::InvalidInvoicePayer);
+        require!(invoice.is_recurring(), ErrorCode::NotRecurringInvoice);
+        require!(
+            invoice.status == InvoiceStatus::Unpaid || invoice.status == InvoiceStatus::Active,
+            ErrorCode::InvoiceNotActive
+        );
+        require!(periods > 0, ErrorCode::InvalidInvoicePeriod);
+
+        // Cap the allowance at the periods that can still be collected
+        let periods = if invoice.max_periods > 0 {
+            periods.min(invoice.max_periods.saturating_sub(invoice.periods_paid))
+        } else {
+            periods
+        };
+        let allowance = invoice
+            .amount
+            .checked_mul(periods as u64)
+            .ok_or(ErrorCode::InvalidInvoiceAmount)?;
+
+        // Delegate the allowance to the invoice PDA so the crank can collect
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Approve {
+                    to: ctx.accounts.payer_token_account.to_account_info(),
+                    delegate: invoice.to_account_info(),
+                    authority: payer.to_account_info(),
+                },
+            ),
+            allowance,
+        )?;
+
+        invoice.status = InvoiceStatus::Active;
+        invoice.payer_token_account = ctx.accounts.payer_token_account.key();
+
+        Ok(())
+    }
+
+    pub fn collect_invoice_period(ctx: Context<CollectInvoicePeriod>) -> Result<()> {
+        let invoice = &ctx.accounts.invoice;
+        let clock = Clock::get()?;
+
+        // Permissionless: anyone may crank a period once it falls due
+        require!(invoice.status == InvoiceStatus::Active, ErrorCode::InvoiceNotActive);
+        require!(clock.unix_timestamp >= invoice.due_ts, ErrorCode::InvoicePeriodNotDue);
+
+        let issuer = invoice.issuer;
+        let invoice_id = invoice.invoice_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"invoice", invoice.namespace_seed(), issuer.as_ref(), &invoice_id, &[invoice.bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: ctx.accounts.issuer_token_account.to_account_info(),
+                    authority: invoice.to_account_info(),
+                },
+                &[seeds],
+            ),
+            invoice.amount,
+        )?;
+
+        let invoice = &mut ctx.accounts.invoice;
+        let period = invoice.periods_paid;
+        invoice.periods_paid = period.checked_add(1).ok_or(ErrorCode::InvalidInvoicePeriod)?;
+        invoice.due_ts = invoice
+            .due_ts
+            .checked_add(invoice.period_secs)
+            .ok_or(ErrorCode::InvalidInvoicePeriod)?;
+
+        // Schedule is complete once the last period is collected
+        if invoice.max_periods > 0 && invoice.periods_paid >= invoice.max_periods {
+            invoice.status = InvoiceStatus::Paid;
+        }
+
+        // Nothing is closed here, so the bounty comes out of the treasury
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let treasury_floor = Rent::get()?.minimum_balance(treasury.data_len());
+        pay_crank_bounty(
+            &treasury,
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            treasury_floor,
+        )?;
+
+        emit!(InvoicePaid {
+            invoice: invoice.key(),
+            receipt: Pubkey::default(),
+            payer: invoice.payer,
+            amount: invoice.amount,
+            period,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_recurring_invoice(ctx: Context<CancelRecurringInvoice>) -> Result<()> {
+        let invoice = &mut ctx.accounts.invoice;
+        let authority = &ctx.accounts.authority;
+
+        require!(
+            authority.key() == invoice.payer || authority.key() == invoice.issuer,
+            ErrorCode::UnauthorizedInvoiceParty
+        );
+        require!(invoice.is_recurring(), ErrorCode::NotRecurringInvoice);
+        require!(
+            invoice.status == InvoiceStatus::Unpaid || invoice.status == InvoiceStatus::Active,
+            ErrorCode::InvoiceNotActive
+        );
+
+        // The payer also revokes the outstanding delegate allowance
+        if authority.key() == invoice.payer && invoice.status == InvoiceStatus::Active {
+            token::revoke(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Revoke {
+                    source: ctx.accounts.payer_token_account.to_account_info(),
+                    authority: authority.to_account_info(),
+                },
+            ))?;
+        }
+
+        invoice.status = InvoiceStatus::Cancelled;
+
+        Ok(())
+    }
+
+    pub fn send_conditional_message(
+        ctx: Context<SendConditionalMessage>,
+        message_id: u64,
+        recipient: Pubkey,
+        encrypted_content: String,
+        key_hash: [u8; 32],
+        condition: UnlockCondition,
+    ) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        // Validate message content
+        require!(encrypted_content.len() <= ConditionalMessage::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+        require!(!encrypted_content.is_empty(), ErrorCode::EmptyMessage);
+        match condition {
+            UnlockCondition::AfterTimestamp { unlock_ts } => {
+                require!(unlock_ts > clock.unix_timestamp, ErrorCode::InvalidUnlockCondition);
+            }
+            UnlockCondition::MissedHeartbeat { interval_secs } => {
+                require!(interval_secs > 0, ErrorCode::InvalidUnlockCondition);
+            }
+            _ => {}
+        }
+
+        // Initialize conditional message account
+        message.sender = sender.key();
+        message.recipient = recipient;
+        message.message_id = message_id;
+        message.encrypted_content = encrypted_content;
+        message.key_hash = key_hash;
+        message.condition = condition;
+        message.content_key = [0u8; 32];
+        message.unlocked_at = 0;
+        message.last_heartbeat = clock.unix_timestamp;
+        message.claimed_at = 0;
+        message.public_countdown = false;
+        message.timestamp = clock.unix_timestamp;
+        message.bump = ctx.bumps.message;
+
+        Ok(())
+    }
+
+    pub fn unlock_message(ctx: Context<UnlockMessage>, content_key: [u8; 32]) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        require!(!message.is_unlocked(), ErrorCode::MessageAlreadyUnlocked);
+        require!(
+            hash(&content_key).to_bytes() == message.key_hash,
+            ErrorCode::InvalidContentKey
+        );
+
+        // Verify the on-chain condition before releasing the key
+        let met = match message.condition {
+            UnlockCondition::AfterTimestamp { unlock_ts } => clock.unix_timestamp >= unlock_ts,
+            UnlockCondition::PriceAbove { oracle, threshold } => {
+                let account = ctx.accounts.condition_account.as_ref().ok_or(ErrorCode::MissingConditionAccount)?;
+                require_keys_eq!(account.key(), oracle, ErrorCode::MissingConditionAccount);
+                read_pyth_price(account)?.price > threshold
+            }
+            UnlockCondition::PriceBelow { oracle, threshold } => {
+                let account = ctx.accounts.condition_account.as_ref().ok_or(ErrorCode::MissingConditionAccount)?;
+                require_keys_eq!(account.key(), oracle, ErrorCode::MissingConditionAccount);
+                read_pyth_price(account)?.price < threshold
+            }
+            UnlockCondition::AccountExists { account: expected } => {
+                let account = ctx.accounts.condition_account.as_ref().ok_or(ErrorCode::MissingConditionAccount)?;
+                require_keys_eq!(account.key(), expected, ErrorCode::MissingConditionAccount);
+                account.lamports() > 0
+            }
+            UnlockCondition::MissedHeartbeat { .. } => message.heartbeat_expired(clock.unix_timestamp),
+        };
+        require!(met, ErrorCode::UnlockConditionNotMet);
+
+        message.content_key = content_key;
+        message.unlocked_at = clock.unix_timestamp;
+
+        emit!(MessageUnlocked {
+            message: message.key(),
+            unlocked_by: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        // Checking in is only possible until the switch fires
+        require!(
+            matches!(message.condition, UnlockCondition::MissedHeartbeat { .. }),
+            ErrorCode::InvalidUnlockCondition
+        );
+        require!(!message.is_unlocked(), ErrorCode::MessageAlreadyUnlocked);
+        require!(!message.heartbeat_expired(clock.unix_timestamp), ErrorCode::HeartbeatExpired);
+
+        message.last_heartbeat = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// Lets the beneficiary claim a dead-man message once the sender has missed a heartbeat,
+    /// without anything from the sender. Revealing the content key through `unlock_message`
+    /// stays open to whoever holds it.
+    pub fn claim_dead_man_message(ctx: Context<ClaimDeadManMessage>) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(message.condition, UnlockCondition::MissedHeartbeat { .. }),
+            ErrorCode::InvalidUnlockCondition
+        );
+        require!(message.claimed_at == 0, ErrorCode::DeadManMessageClaimed);
+        require!(message.heartbeat_expired(clock.unix_timestamp), ErrorCode::UnlockConditionNotMet);
+
+        message.claimed_at = clock.unix_timestamp;
+
+        emit!(DeadManMessageClaimed {
+            message: message.key(),
+            beneficiary: message.recipient,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_conditional_message(ctx: Context<CancelConditionalMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        // The sender may withdraw the message any time before it unlocks
+        require!(!message.is_unlocked(), ErrorCode::MessageAlreadyUnlocked);
+        require!(!message.heartbeat_expired(clock.unix_timestamp), ErrorCode::HeartbeatExpired);
+
+        Ok(())
+    }
+
+    pub fn send_time_capsule(
+        ctx: Context<SendConditionalMessage>,
+        message_id: u64,
+        recipient: Pubkey,
+        encrypted_content: String,
+        key_hash: [u8; 32],
+        open_at: i64,
+        public_countdown: bool,
+    ) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        // Validate message content and opening time
+        require!(encrypted_content.len() <= ConditionalMessage::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+        require!(!encrypted_content.is_empty(), ErrorCode::EmptyMessage);
+        require!(open_at > clock.unix_timestamp, ErrorCode::InvalidUnlockCondition);
+
+        // A time capsule is a conditional message that unlocks at a fixed time
+        message.sender = sender.key();
+        message.recipient = recipient;
+        message.message_id = message_id;
+        message.encrypted_content = encrypted_content;
+        message.key_hash = key_hash;
+        message.condition = UnlockCondition::AfterTimestamp { unlock_ts: open_at };
+        message.content_key = [0u8; 32];
+        message.unlocked_at = 0;
+        message.last_heartbeat = clock.unix_timestamp;
+        message.claimed_at = 0;
+        message.public_countdown = public_countdown;
+        message.timestamp = clock.unix_timestamp;
+        message.bump = ctx.bumps.message;
+
+        Ok(())
+    }
+
+    pub fn commit_message(
+        ctx: Context<CommitMessage>,
+        commit_id: u64,
+        recipient: Pubkey,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        let commit = &mut ctx.accounts.commit;
+        let clock = Clock::get()?;
+
+        // Initialize commitment account; content stays hidden until revealed
+        commit.sender = ctx.accounts.sender.key();
+        commit.recipient = recipient;
+        commit.commit_id = commit_id;
+        commit.commitment = commitment;
+        commit.content = String::new();
+        commit.revealed_at = 0;
+        commit.timestamp = clock.unix_timestamp;
+        commit.bump = ctx.bumps.commit;
+
+        Ok(())
+    }
+
+    pub fn reveal_message(ctx: Context<RevealMessage>, content: String, salt: [u8; 32]) -> Result<()> {
+        let commit = &mut ctx.accounts.commit;
+        let clock = Clock::get()?;
+
+        require!(commit.revealed_at == 0, ErrorCode::AlreadyRevealed);
+        require!(content.len() <= CommittedMessage::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+
+        // Content must hash to the earlier commitment
+        require!(
+            hashv(&[content.as_bytes(), &salt]).to_bytes() == commit.commitment,
+            ErrorCode::CommitmentMismatch
+        );
+
+        commit.content = content;
+        commit.revealed_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn create_game(ctx: Context<CreateGame>, game_id: u64, opponent: Pubkey, wager: u64) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let creator = &ctx.accounts.creator;
+        let clock = Clock::get()?;
+
+        require!(opponent != creator.key(), ErrorCode::InvalidOpponent);
+        check_feature_gate(&ctx.accounts.feature_gate, ctx.accounts.app.as_ref())?;
+
+        // Escrow the creator's side of the wager on the game account
+        if wager > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: creator.to_account_info(),
+                        to: game.to_account_info(),
+                    },
+                ),
+                wager,
+            )?;
+        }
+
+        // Initialize game session
+        game.creator = creator.key();
+        game.opponent = opponent;
+        game.game_id = game_id;
+        game.board = [0u8; 9];
+        game.moves = 0;
+        game.status = GameStatus::AwaitingOpponent;
+        game.winner = Pubkey::default();
+        game.wager = wager;
+        game.timestamp = clock.unix_timestamp;
+        game.last_move_at = 0;
+        game.bump = ctx.bumps.game;
+
+        Ok(())
+    }
+
+    pub fn join_game(ctx: Context<JoinGame>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let opponent = &ctx.accounts.opponent;
+
+        require!(game.opponent == opponent.key(), ErrorCode::InvalidOpponent);
+        require!(game.status == GameStatus::AwaitingOpponent, ErrorCode::GameNotActive);
+
+        // Match the creator's wager
+        if game.wager > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: opponent.to_account_info(),
+                        to: game.to_account_info(),
+                    },
+                ),
+                game.wager,
+            )?;
+        }
+
+        game.status = GameStatus::Active;
+        game.last_move_at = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn play_move(ctx: Context<PlayMove>, position: u8) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let player = &ctx.accounts.player;
+        let clock = Clock::get()?;
+
+        // Validate the move against tic-tac-toe rules
+        require!(game.status == GameStatus::Active, ErrorCode::GameNotActive);
+        require!(game.current_player() == player.key(), ErrorCode::NotYourTurn);
+        require!((position as usize) < game.board.len(), ErrorCode::InvalidMove);
+        require!(game.board[position as usize] == 0, ErrorCode::InvalidMove);
+
+        let mark = if game.moves % 2 == 0 { 1 } else { 2 };
+        game.board[position as usize] = mark;
+        game.moves += 1;
+        game.last_move_at = clock.unix_timestamp;
+
+        emit!(GameMovePlayed {
+            game: game.key(),
+            player: player.key(),
+            position,
+            move_number: game.moves,
+            timestamp: clock.unix_timestamp,
+        });
+
+        // Settle the wager once the game is decided
+        let pot = game.wager.checked_mul(2).ok_or(ErrorCode::InvalidMove)?;
+        if game.has_line(mark) {
+            game.status = GameStatus::Won;
+            game.winner = player.key();
+            if pot > 0 {
+                **game.to_account_info().try_borrow_mut_lamports()? -= pot;
+                **player.to_account_info().try_borrow_mut_lamports()? += pot;
+            }
+        } else if game.moves as usize == game.board.len() {
+            game.status = GameStatus::Draw;
+            if pot > 0 {
+                **game.to_account_info().try_borrow_mut_lamports()? -= pot;
+                **player.to_account_info().try_borrow_mut_lamports()? += game.wager;
+                **ctx.accounts.other_player.try_borrow_mut_lamports()? += game.wager;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The player waiting on a move wins by forfeit, taking the pot, once the other side has
+    /// let `MOVE_TIMEOUT_SECS` pass without moving.
+    pub fn claim_game_forfeit(ctx: Context<ClaimGameForfeit>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let player = &ctx.accounts.player;
+        let clock = Clock::get()?;
+
+        require!(game.status == GameStatus::Active, ErrorCode::GameNotActive);
+        require!(game.other_player(game.current_player()) == player.key(), ErrorCode::InvalidOpponent);
+        require!(
+            clock.unix_timestamp >= game.last_move_at + GameSession::MOVE_TIMEOUT_SECS,
+            ErrorCode::MoveTimeoutNotReached
+        );
+
+        game.status = GameStatus::Won;
+        game.winner = player.key();
+
+        let pot = game.wager.checked_mul(2).ok_or(ErrorCode::InvalidMove)?;
+        if pot > 0 {
+            **game.to_account_info().try_borrow_mut_lamports()? -= pot;
+            **player.to_account_info().try_borrow_mut_lamports()? += pot;
+        }
+
+        Ok(())
+    }
+
+    pub fn cancel_game(ctx: Context<CancelGame>) -> Result<()> {
+        let game = &ctx.accounts.game;
+
+        // Only games nobody has joined can be cancelled; the wager returns with the rent
+        require!(game.status == GameStatus::AwaitingOpponent, ErrorCode::GameNotActive);
+
+        Ok(())
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        // The initializer becomes the protocol admin
+        config.admin = ctx.accounts.admin.key();
+        config.max_poll_stake = 0;
+        config.reputation = ReputationParams::default();
+        config.attestor = Pubkey::default();
+        config.crank_bounty_lamports = 0;
+        config.moderator = Pubkey::default();
+        config.charity = Pubkey::default();
+        config.timelock_secs = 0;
+        config.paused = false;
+        config.bump = ctx.bumps.config;
+
+        Ok(())
+    }
+
+    pub fn set_poll_limits(ctx: Context<UpdateConfig>, max_poll_stake: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetPollLimits,
+            config.key(),
+            &config.max_poll_stake,
+            &max_poll_stake,
+        )?;
+        config.max_poll_stake = max_poll_stake;
+
+        Ok(())
+    }
+
+    pub fn create_poll(
+        ctx: Context<CreatePoll>,
+        poll_id: u64,
+        recipient: Pubkey,
+        question: String,
+        options: Vec<String>,
+        stake_per_vote: u64,
+        closes_at: i64,
+        resolver: Pubkey,
+    ) -> Result<()> {
+        let poll = &mut ctx.accounts.poll;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        // Validate poll definition
+        require!(!question.is_empty(), ErrorCode::EmptyPollQuestion);
+        require!(question.len() <= Poll::MAX_QUESTION_LEN, ErrorCode::PollQuestionTooLong);
+        require!(
+            options.len() >= 2 && options.len() <= Poll::MAX_OPTIONS,
+            ErrorCode::InvalidPollOptions
+        );
+        require!(
+            options.iter().all(|o| !o.is_empty() && o.len() <= Poll::MAX_OPTION_LEN),
+            ErrorCode::InvalidPollOptions
+        );
+        require!(closes_at > clock.unix_timestamp, ErrorCode::InvalidPollClose);
+        require!(stake_per_vote <= config.max_poll_stake, ErrorCode::PollStakeTooHigh);
+
+        // Initialize poll account
+        poll.creator = ctx.accounts.creator.key();
+        poll.recipient = recipient;
+        poll.poll_id = poll_id;
+        poll.question = question;
+        poll.options = options;
+        poll.votes = [0u32; Poll::MAX_OPTIONS];
+        poll.stake_per_vote = stake_per_vote;
+        poll.closes_at = closes_at;
+        poll.resolver = resolver;
+        poll.resolved = false;
+        poll.winning_option = 0;
+        poll.timestamp = clock.unix_timestamp;
+        poll.bump = ctx.bumps.poll;
+
+        Ok(())
+    }
+
+    pub fn vote(ctx: Context<Vote>, option: u8) -> Result<()> {
+        let poll = &mut ctx.accounts.poll;
+        let ballot = &mut ctx.accounts.ballot;
+        let voter = &ctx.accounts.voter;
+        let clock = Clock::get()?;
+
+        require!(!poll.resolved && clock.unix_timestamp < poll.closes_at, ErrorCode::PollClosed);
+        require!((option as usize) < poll.options.len(), ErrorCode::InvalidPollOptions);
+
+        // Stake is held on the poll account until resolution
+        if poll.stake_per_vote > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: voter.to_account_info(),
+                        to: poll.to_account_info(),
+                    },
+                ),
+                poll.stake_per_vote,
+            )?;
+        }
+
+        poll.votes[option as usize] += 1;
+
+        ballot.poll = poll.key();
+        ballot.voter = voter.key();
+        ballot.option = option;
+        ballot.stake = poll.stake_per_vote;
+        ballot.bump = ctx.bumps.ballot;
+
+        Ok(())
+    }
+
+    pub fn resolve_poll(ctx: Context<ResolvePoll>, winning_option: u8) -> Result<()> {
+        let poll = &mut ctx.accounts.poll;
+        let clock = Clock::get()?;
+
+        // The configured resolver (creator or an oracle key) settles the poll after it closes,
+        // before voters can take their stakes back
+        require_keys_eq!(poll.resolver, ctx.accounts.resolver.key(), ErrorCode::UnauthorizedResolver);
+        require!(!poll.resolved, ErrorCode::PollClosed);
+        require!(clock.unix_timestamp >= poll.closes_at, ErrorCode::PollStillOpen);
+        require!(!poll.resolution_expired(clock.unix_timestamp), ErrorCode::PollResolutionExpired);
+        require!((winning_option as usize) < poll.options.len(), ErrorCode::InvalidPollOptions);
+
+        poll.resolved = true;
+        poll.winning_option = winning_option;
+
+        emit!(PollResolved {
+            poll: poll.key(),
+            resolver: ctx.accounts.resolver.key(),
+            winning_option,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out a ballot once the poll is resolved. If the resolver lets
+    /// `RESOLVE_TIMEOUT_SECS` pass after closing without resolving, every stake is refunded.
+    pub fn claim_poll_payout(ctx: Context<ClaimPollPayout>) -> Result<()> {
+        let poll = &ctx.accounts.poll;
+        let ballot = &ctx.accounts.ballot;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(poll.resolved || poll.resolution_expired(now), ErrorCode::PollStillOpen);
+
+        // Winners split the whole pot; if nobody picked the winner, stakes are refunded
+        let winning_votes = poll.votes[poll.winning_option as usize] as u64;
+        let payout = if !poll.resolved || winning_votes == 0 {
+            ballot.stake
+        } else if ballot.option == poll.winning_option {
+            poll.total_stake()? / winning_votes
+        } else {
+            0
+        };
+
+        if payout > 0 {
+            **poll.to_account_info().try_borrow_mut_lamports()? -= payout;
+            **ctx.accounts.voter.to_account_info().try_borrow_mut_lamports()? += payout;
+        }
+
+        Ok(())
+    }
+
+    pub fn send_price_quote(
+        ctx: Context<SendPriceQuote>,
+        quote_id: u64,
+        recipient: Pubkey,
+        note: String,
+    ) -> Result<()> {
+        let quote = &mut ctx.accounts.quote;
+        let clock = Clock::get()?;
+
+        require!(note.len() <= PriceQuoteMessage::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+
+        // Snapshot the oracle price at send time
+        let oracle_price = read_pyth_price(&ctx.accounts.price_feed)?;
+
+        quote.sender = ctx.accounts.sender.key();
+        quote.recipient = recipient;
+        quote.quote_id = quote_id;
+        quote.price_feed = ctx.accounts.price_feed.key();
+        quote.price = oracle_price.price;
+        quote.conf = oracle_price.conf;
+        quote.expo = oracle_price.expo;
+        quote.slot = oracle_price.slot;
+        quote.note = note;
+        quote.timestamp = clock.unix_timestamp;
+        quote.bump = ctx.bumps.quote;
+
+        Ok(())
+    }
+
+    pub fn register_governance_notifier(ctx: Context<RegisterGovernanceNotifier>, poster: Pubkey) -> Result<()> {
+        let notifier = &mut ctx.accounts.notifier;
+
+        // The governance itself (signing via proposal execution) delegates a poster key
+        notifier.governance = ctx.accounts.governance.key();
+        notifier.poster = poster;
+        notifier.bump = ctx.bumps.notifier;
+
+        Ok(())
+    }
+
+    pub fn post_proposal_notification(
+        ctx: Context<PostProposalNotification>,
+        title: String,
+        voting_ends_at: i64,
+    ) -> Result<()> {
+        let notification = &mut ctx.accounts.notification;
+        let notifier = &ctx.accounts.notifier;
+        let poster = &ctx.accounts.poster;
+        let clock = Clock::get()?;
+
+        // Either the governance or its delegated bot key may post, and only about its own proposals
+        require!(
+            poster.key() == notifier.poster || poster.key() == notifier.governance,
+            ErrorCode::UnauthorizedNotifier
+        );
+        require_keys_eq!(
+            proposal_governance(&ctx.accounts.proposal)?,
+            notifier.governance,
+            ErrorCode::InvalidProposalAccount
+        );
+        require!(!title.is_empty(), ErrorCode::EmptyMessage);
+        require!(title.len() <= ProposalNotification::MAX_TITLE_LEN, ErrorCode::MessageTooLong);
+        require!(voting_ends_at > clock.unix_timestamp, ErrorCode::InvalidDueDate);
+
+        notification.governance = notifier.governance;
+        notification.proposal = ctx.accounts.proposal.key();
+        notification.title = title;
+        notification.voting_ends_at = voting_ends_at;
+        notification.poster = poster.key();
+        notification.timestamp = clock.unix_timestamp;
+        notification.bump = ctx.bumps.notification;
+
+        Ok(())
+    }
+
+    /// Only the collection's update authority may open its feed.
+    pub fn create_collection_feed(ctx: Context<CreateCollectionFeed>, bot: Pubkey) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+
+        require_keys_eq!(
+            metadata_update_authority(&ctx.accounts.collection_metadata)?,
+            ctx.accounts.owner.key(),
+            ErrorCode::NotCollectionAuthority
+        );
+
+        feed.collection = ctx.accounts.collection_mint.key();
+        feed.owner = ctx.accounts.owner.key();
+        feed.bot = bot;
+        feed.notice_count = 0;
+        feed.bump = ctx.bumps.feed;
+
+        Ok(())
+    }
+
+    pub fn post_market_notice(
+        ctx: Context<PostMarketNotice>,
+        kind: MarketEventKind,
+        nft_mint: Pubkey,
+        price: u64,
+        counterparty: Pubkey,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        let notice = &mut ctx.accounts.notice;
+        let clock = Clock::get()?;
+
+        // Only the feed's registered bot posts market activity
+        require_keys_eq!(feed.bot, ctx.accounts.bot.key(), ErrorCode::UnauthorizedNotifier);
+
+        notice.feed = feed.key();
+        notice.index = feed.notice_count;
+        notice.kind = kind;
+        notice.nft_mint = nft_mint;
+        notice.price = price;
+        notice.counterparty = counterparty;
+        notice.timestamp = clock.unix_timestamp;
+        notice.bump = ctx.bumps.notice;
+
+        feed.notice_count += 1;
+
+        Ok(())
+    }
+
+    pub fn register_notifier(ctx: Context<RegisterNotifier>, name: String) -> Result<()> {
+        let notifier = &mut ctx.accounts.notifier;
+
+        require!(!name.is_empty(), ErrorCode::EmptyMessage);
+        require!(name.len() <= Notifier::MAX_NAME_LEN, ErrorCode::MessageTooLong);
+
+        // The authority is usually a PDA of the integrating program
+        notifier.authority = ctx.accounts.authority.key();
+        notifier.name = name;
+        notifier.notification_count = 0;
+        notifier.bump = ctx.bumps.notifier;
+
+        Ok(())
+    }
+
+    pub fn notify(
+        ctx: Context<Notify>,
+        recipient: Pubkey,
+        severity: NotificationSeverity,
+        content: String,
+    ) -> Result<()> {
+        let notifier = &mut ctx.accounts.notifier;
+        let notification = &mut ctx.accounts.notification;
+        let clock = Clock::get()?;
+
+        // Validate notification content
+        require!(content.len() <= Notification::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+        require!(!content.is_empty(), ErrorCode::EmptyMessage);
+
+        notification.notifier = notifier.key();
+        notification.recipient = recipient;
+        notification.index = notifier.notification_count;
+        notification.severity = severity;
+        notification.content = content;
+        notification.timestamp = clock.unix_timestamp;
+        notification.bump = ctx.bumps.notification;
+
+        notifier.notification_count += 1;
+
+        Ok(())
+    }
+
+    pub fn register_merchant(ctx: Context<RegisterMerchant>, name: String) -> Result<()> {
+        let merchant = &mut ctx.accounts.merchant;
+
+        require!(!name.is_empty(), ErrorCode::EmptyMessage);
+        require!(name.len() <= MerchantProfile::MAX_NAME_LEN, ErrorCode::MessageTooLong);
+
+        merchant.owner = ctx.accounts.owner.key();
+        merchant.name = name;
+        merchant.settlement_account = ctx.accounts.settlement_account.key();
+        merchant.receipt_count = 0;
+        merchant.bump = ctx.bumps.merchant;
+
+        Ok(())
+    }
+
+    pub fn issue_merchant_receipt(
+        ctx: Context<IssueMerchantReceipt>,
+        amount: u64,
+        reference: Pubkey,
+        order_ref: String,
+    ) -> Result<()> {
+        let merchant = &mut ctx.accounts.merchant;
+        let receipt = &mut ctx.accounts.receipt;
+        let customer = &ctx.accounts.customer;
+        let clock = Clock::get()?;
+
+        require!(order_ref.len() <= MerchantReceipt::MAX_ORDER_REF_LEN, ErrorCode::MessageTooLong);
+
+        // The Solana Pay transfer must precede this instruction in the same transaction
+        require!(
+            find_token_payment(
+                &ctx.accounts.instructions,
+                &customer.key(),
+                &merchant.settlement_account,
+                &reference,
+                amount,
+            )?,
+            ErrorCode::PaymentNotFound
+        );
+
+        receipt.merchant = merchant.key();
+        receipt.customer = customer.key();
+        receipt.index = merchant.receipt_count;
+        receipt.amount = amount;
+        receipt.reference = reference;
+        receipt.order_ref = order_ref;
+        receipt.timestamp = clock.unix_timestamp;
+        receipt.bump = ctx.bumps.receipt;
+
+        // One receipt per Solana Pay reference, so a single payment can't back several receipts
+        let marker = &mut ctx.accounts.reference_marker;
+        marker.receipt = receipt.key();
+        marker.bump = ctx.bumps.reference_marker;
+
+        merchant.receipt_count += 1;
+
+        Ok(())
+    }
+
+    pub fn create_team_inbox(ctx: Context<CreateTeamInbox>, name: String) -> Result<()> {
+        let inbox = &mut ctx.accounts.team_inbox;
+
+        require!(!name.is_empty(), ErrorCode::EmptyMessage);
+        require!(name.len() <= TeamInbox::MAX_NAME_LEN, ErrorCode::MessageTooLong);
+
+        inbox.owner = ctx.accounts.owner.key();
+        inbox.name = name;
+        inbox.members = vec![ctx.accounts.owner.key()];
+        inbox.ticket_count = 0;
+        inbox.bump = ctx.bumps.team_inbox;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.team_inbox = inbox.key();
+        stats.rating_count = 0;
+        stats.rating_sum = 0;
+        stats.rating_histogram = [0u32; 5];
+        stats.bump = ctx.bumps.stats;
+
+        Ok(())
+    }
+
+    pub fn add_team_member(ctx: Context<ManageTeamInbox>, member: Pubkey) -> Result<()> {
+        let inbox = &mut ctx.accounts.team_inbox;
+
+        require!(!inbox.members.contains(&member), ErrorCode::AlreadyTeamMember);
+        require!(inbox.members.len() < TeamInbox::MAX_MEMBERS, ErrorCode::TeamInboxFull);
+
+        inbox.members.push(member);
+
+        Ok(())
+    }
+
+    pub fn remove_team_member(ctx: Context<ManageTeamInbox>, member: Pubkey) -> Result<()> {
+        let inbox = &mut ctx.accounts.team_inbox;
+
+        require!(member != inbox.owner, ErrorCode::UnauthorizedTeamMember);
+        inbox.members.retain(|m| *m != member);
+
+        Ok(())
+    }
+
+    pub fn open_ticket(ctx: Context<OpenTicket>, subject: String) -> Result<()> {
+        let inbox = &mut ctx.accounts.team_inbox;
+        let ticket = &mut ctx.accounts.ticket;
+        let clock = Clock::get()?;
+
+        require!(!subject.is_empty(), ErrorCode::EmptyMessage);
+        require!(subject.len() <= Ticket::MAX_SUBJECT_LEN, ErrorCode::MessageTooLong);
+
+        ticket.team_inbox = inbox.key();
+        ticket.opener = ctx.accounts.opener.key();
+        ticket.index = inbox.ticket_count;
+        ticket.subject = subject;
+        ticket.status = TicketStatus::Open;
+        ticket.opened_at = clock.unix_timestamp;
+        ticket.first_response_at = 0;
+        ticket.resolved_at = 0;
+        ticket.reopen_count = 0;
+        ticket.rating = 0;
+        ticket.rating_comment = String::new();
+        ticket.bump = ctx.bumps.ticket;
+
+        inbox.ticket_count += 1;
+
+        emit!(TicketStatusChanged {
+            ticket: ticket.key(),
+            actor: ticket.opener,
+            status: TicketStatus::Open,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn advance_ticket(ctx: Context<AdvanceTicket>) -> Result<()> {
+        let inbox = &ctx.accounts.team_inbox;
+        let ticket = &mut ctx.accounts.ticket;
+        let member = &ctx.accounts.member;
+        let clock = Clock::get()?;
+
+        // Only the team moves tickets forward: open -> in progress -> resolved
+        require!(inbox.members.contains(&member.key()), ErrorCode::UnauthorizedTeamMember);
+        ticket.status = match ticket.status {
+            TicketStatus::Open => {
+                if ticket.first_response_at == 0 {
+                    ticket.first_response_at = clock.unix_timestamp;
+                }
+                TicketStatus::InProgress
+            }
+            TicketStatus::InProgress => {
+                ticket.resolved_at = clock.unix_timestamp;
+                record_reputation(
+                    ctx.accounts.member_reputation.as_mut(),
+                    ctx.accounts.config.as_ref(),
+                    ReputationEvent::TicketResolved,
+                    clock.unix_timestamp,
+                )?;
+                TicketStatus::Resolved
+            }
+            TicketStatus::Resolved => return err!(ErrorCode::InvalidTicketTransition),
+        };
+
+        emit!(TicketStatusChanged {
+            ticket: ticket.key(),
+            actor: member.key(),
+            status: ticket.status,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn reopen_ticket(ctx: Context<ReopenTicket>) -> Result<()> {
+        let ticket = &mut ctx.accounts.ticket;
+        let clock = Clock::get()?;
+
+        // Only the opener can reopen a resolved ticket
+        require!(ticket.status == TicketStatus::Resolved, ErrorCode::InvalidTicketTransition);
+
+        ticket.status = TicketStatus::Open;
+        ticket.resolved_at = 0;
+        ticket.reopen_count += 1;
+
+        emit!(TicketStatusChanged {
+            ticket: ticket.key(),
+            actor: ticket.opener,
+            status: TicketStatus::Open,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn rate_ticket(ctx: Context<RateTicket>, rating: u8, comment: String) -> Result<()> {
+        let ticket = &mut ctx.accounts.ticket;
+        let stats = &mut ctx.accounts.stats;
+
+        // The opener rates a resolved ticket once
+        require!(ticket.status == TicketStatus::Resolved, ErrorCode::TicketNotResolved);
+        require!(ticket.rating == 0, ErrorCode::TicketAlreadyRated);
+        require!((1..=5).contains(&rating), ErrorCode::InvalidRating);
+        require!(comment.len() <= Ticket::MAX_RATING_COMMENT_LEN, ErrorCode::MessageTooLong);
+
+        ticket.rating = rating;
+        ticket.rating_comment = comment;
+
+        // Aggregate onto the team inbox's public stats
+        stats.rating_count += 1;
+        stats.rating_sum += rating as u64;
+        stats.rating_histogram[(rating - 1) as usize] += 1;
+
+        Ok(())
+    }
+
+    pub fn open_deal_room(
+        ctx: Context<OpenDealRoom>,
+        deal_id: u64,
+        buyer: Pubkey,
+        arbiter: Pubkey,
+    ) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let seller = &ctx.accounts.seller;
+
+        require!(buyer != seller.key(), ErrorCode::InvalidTradeParty);
+        require!(arbiter != buyer && arbiter != seller.key(), ErrorCode::InvalidArbiter);
+
+        // Pubkey::default() as arbiter means the deal has no dispute path
+        deal.seller = seller.key();
+        deal.buyer = buyer;
+        deal.arbiter = arbiter;
+        deal.deal_id = deal_id;
+        deal.mint = ctx.accounts.mint.key();
+        deal.offer_amount = 0;
+        deal.offer_by = Pubkey::default();
+        deal.price = 0;
+        deal.status = DealStatus::Negotiating;
+        deal.message_count = 0;
+        deal.funded_at = 0;
+        deal.bump = ctx.bumps.deal;
+        deal.vault_bump = ctx.bumps.escrow_vault;
+
+        Ok(())
+    }
+
+    pub fn post_deal_offer(ctx: Context<PostDealMessage>, amount: u64, note: String) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let author = ctx.accounts.author.key();
+        let clock = Clock::get()?;
+
+        require!(deal.is_party(&author), ErrorCode::InvalidTradeParty);
+        require!(deal.status == DealStatus::Negotiating, ErrorCode::InvalidDealState);
+        require!(amount > 0, ErrorCode::InvalidTradeAmount);
+        require!(note.len() <= DealMessage::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+
+        deal.offer_amount = amount;
+        deal.offer_by = author;
+
+        ctx.accounts.message.record(deal, author, DealMessageKind::Offer, amount, note, clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn accept_deal_offer(ctx: Context<PostDealMessage>) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let author = ctx.accounts.author.key();
+        let clock = Clock::get()?;
+
+        // The counterparty of the latest offer accepts its terms
+        require!(deal.is_party(&author), ErrorCode::InvalidTradeParty);
+        require!(deal.status == DealStatus::Negotiating, ErrorCode::InvalidDealState);
+        require!(deal.offer_amount > 0 && deal.offer_by != author, ErrorCode::InvalidDealState);
+
+        deal.price = deal.offer_amount;
+        deal.status = DealStatus::Agreed;
+
+        let price = deal.price;
+        ctx.accounts.message.record(deal, author, DealMessageKind::Accept, price, String::new(), clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn fund_deal(ctx: Context<FundDeal>) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let buyer = &ctx.accounts.buyer;
+        let clock = Clock::get()?;
+
+        require!(deal.status == DealStatus::Agreed, ErrorCode::InvalidDealState);
+
+        // Buyer locks the agreed price in the deal vault
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.buyer_token_account.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: buyer.to_account_info(),
+                },
+            ),
+            deal.price,
+        )?;
+
+        deal.status = DealStatus::Funded;
+        deal.funded_at = clock.unix_timestamp;
+
+        let price = deal.price;
+        ctx.accounts.message.record(deal, buyer.key(), DealMessageKind::Fund, price, String::new(), clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn release_deal(ctx: Context<ReleaseDeal>) -> Result<()> {
+        let deal = &ctx.accounts.deal;
+        let buyer = &ctx.accounts.buyer;
+        let clock = Clock::get()?;
+
+        require!(deal.status == DealStatus::Funded, ErrorCode::InvalidDealState);
+
+        // Buyer confirms delivery and the escrow pays the seller
+        let deal_id = deal.deal_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"deal", deal.seller.as_ref(), &deal_id, &[deal.bump]];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.seller_token_account,
+            &ctx.accounts.seller,
+            &deal.to_account_info(),
+            seeds,
+            deal.price,
+        )?;
+
+        let deal = &mut ctx.accounts.deal;
+        deal.status = DealStatus::Released;
+
+        let price = deal.price;
+        ctx.accounts.message.record(deal, buyer.key(), DealMessageKind::Release, price, String::new(), clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    /// Returns the escrow to the buyer: at any time by the seller, or by the buyer once the deal
+    /// has gone `SETTLE_TIMEOUT_SECS` without being released or ruled on.
+    pub fn refund_deal(ctx: Context<RefundDeal>) -> Result<()> {
+        let deal = &ctx.accounts.deal;
+        let authority = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(deal.status, DealStatus::Funded | DealStatus::Disputed),
+            ErrorCode::InvalidDealState
+        );
+        if authority != deal.seller {
+            require_keys_eq!(authority, deal.buyer, ErrorCode::InvalidTradeParty);
+            require!(
+                clock.unix_timestamp >= deal.funded_at + DealRoom::SETTLE_TIMEOUT_SECS,
+                ErrorCode::DealTimeoutNotReached
+            );
+        }
+
+        let deal_id = deal.deal_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"deal", deal.seller.as_ref(), &deal_id, &[deal.bump]];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.buyer_token_account,
+            &ctx.accounts.seller,
+            &deal.to_account_info(),
+            seeds,
+            deal.price,
+        )?;
+
+        let deal = &mut ctx.accounts.deal;
+        deal.status = DealStatus::Refunded;
+
+        let price = deal.price;
+        ctx.accounts.message.record(deal, authority, DealMessageKind::Refund, price, String::new(), clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn dispute_deal(ctx: Context<PostDealMessage>, note: String) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let author = ctx.accounts.author.key();
+        let clock = Clock::get()?;
+
+        // Funded deals with an arbiter can be escalated by either side
+        require!(deal.is_party(&author), ErrorCode::InvalidTradeParty);
+        require!(deal.status == DealStatus::Funded, ErrorCode::InvalidDealState);
+        require!(deal.arbiter != Pubkey::default(), ErrorCode::InvalidArbiter);
+        require!(note.len() <= DealMessage::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+
+        deal.status = DealStatus::Disputed;
+
+        ctx.accounts.message.record(deal, author, DealMessageKind::Dispute, 0, note, clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn assign_arbiter(ctx: Context<AssignArbiter>, arbiter: Pubkey) -> Result<()> {
+        let deal = &mut ctx.accounts.deal;
+        let clock = Clock::get()?;
+
+        // Both parties must agree on the arbiter before a dispute is raised
+        require!(deal.arbiter == Pubkey::default(), ErrorCode::InvalidArbiter);
+        require!(arbiter != deal.buyer && arbiter != deal.seller, ErrorCode::InvalidArbiter);
+        require!(arbiter != Pubkey::default(), ErrorCode::InvalidArbiter);
+        require!(
+            matches!(deal.status, DealStatus::Negotiating | DealStatus::Agreed | DealStatus::Funded),
+            ErrorCode::InvalidDealState
+        );
+
+        deal.arbiter = arbiter;
+
+        let seller = ctx.accounts.seller.key();
+        ctx.accounts.message.record(deal, seller, DealMessageKind::ArbiterAssigned, 0, String::new(), clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn rule_dispute(ctx: Context<RuleDispute>, seller_share_bps: u16, note: String) -> Result<()> {
+        let deal = &ctx.accounts.deal;
+        let clock = Clock::get()?;
+
+        require!(deal.status == DealStatus::Disputed, ErrorCode::InvalidDealState);
+        require!(seller_share_bps <= 10_000, ErrorCode::InvalidRuling);
+        require!(note.len() <= DealMessage::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+
+        // Split the escrow per the ruling and close the vault
+        let seller_amount = (deal.price as u128 * seller_share_bps as u128 / 10_000) as u64;
+        let buyer_amount = deal.price - seller_amount;
+        let deal_id = deal.deal_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"deal", deal.seller.as_ref(), &deal_id, &[deal.bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow_vault.to_account_info(),
+                    to: ctx.accounts.seller_token_account.to_account_info(),
+                    authority: deal.to_account_info(),
+                },
+                &[seeds],
+            ),
+            seller_amount,
+        )?;
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.buyer_token_account,
+            &ctx.accounts.seller,
+            &deal.to_account_info(),
+            seeds,
+            buyer_amount,
+        )?;
+
+        // The ruling is recorded as a system message in the deal conversation
+        // The side awarded less than half of the escrow lost the dispute
+        if seller_share_bps != 5_000 {
+            let loser = if seller_share_bps < 5_000 { deal.seller } else { deal.buyer };
+            if let Some(reputation) = &ctx.accounts.loser_reputation {
+                require_keys_eq!(reputation.user, loser, ErrorCode::InvalidReputationAccount);
+            }
+            record_reputation(
+                ctx.accounts.loser_reputation.as_mut(),
+                ctx.accounts.config.as_ref(),
+                ReputationEvent::DisputeLost,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        let deal = &mut ctx.accounts.deal;
+        deal.status = match seller_share_bps {
+            0 => DealStatus::Refunded,
+            10_000 => DealStatus::Released,
+            _ => DealStatus::Ruled,
+        };
+
+        let arbiter = ctx.accounts.arbiter.key();
+        ctx.accounts.message.record(deal, arbiter, DealMessageKind::Ruling, seller_amount, note, clock.unix_timestamp);
+        ctx.accounts.message.bump = ctx.bumps.message;
+        deal.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn set_reputation_params(ctx: Context<UpdateConfig>, params: ReputationParams) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(params.half_life_secs >= 0, ErrorCode::InvalidReputationParams);
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetReputationParams,
+            config.key(),
+            &config.reputation,
+            &params,
+        )?;
+        config.reputation = params;
+
+        Ok(())
+    }
+
+    pub fn init_reputation(ctx: Context<InitReputation>, user: Pubkey) -> Result<()> {
+        let reputation = &mut ctx.accounts.reputation;
+        let clock = Clock::get()?;
+
+        reputation.user = user;
+        reputation.score = 0;
+        reputation.messages_answered = 0;
+        reputation.tickets_resolved = 0;
+        reputation.disputes_lost = 0;
+        reputation.spam_flags = 0;
+        reputation.vouches = 0;
+        reputation.last_updated = clock.unix_timestamp;
+        reputation.bump = ctx.bumps.reputation;
+
+        Ok(())
+    }
+
+    pub fn vouch_for(ctx: Context<VouchFor>, weight: u8, stake: u64) -> Result<()> {
+        let vouch = &mut ctx.accounts.vouch;
+        let voucher = &ctx.accounts.voucher;
+        let reputation = &mut ctx.accounts.vouchee_reputation;
+        let clock = Clock::get()?;
+
+        require!(reputation.user != voucher.key(), ErrorCode::InvalidVouch);
+        require!((1..=Vouch::MAX_WEIGHT).contains(&weight), ErrorCode::InvalidVouch);
+
+        // Optional stake backs the vouch and is slashable if the vouchee gets flagged
+        if stake > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: voucher.to_account_info(),
+                        to: vouch.to_account_info(),
+                    },
+                ),
+                stake,
+            )?;
+        }
+
+        vouch.voucher = voucher.key();
+        vouch.vouchee = reputation.user;
+        vouch.weight = weight;
+        vouch.stake = stake;
+        vouch.vouchee_flags_at = reputation.spam_flags;
+        vouch.timestamp = clock.unix_timestamp;
+        vouch.bump = ctx.bumps.vouch;
+
+        reputation.record(
+            ReputationEvent::Vouched { weight },
+            &ctx.accounts.config.reputation,
+            clock.unix_timestamp,
+        );
+
+        Ok(())
+    }
+
+    pub fn revoke_vouch(ctx: Context<RevokeVouch>) -> Result<()> {
+        let vouch = &ctx.accounts.vouch;
+        let reputation = &mut ctx.accounts.vouchee_reputation;
+        let clock = Clock::get()?;
+
+        // A vouch for a user flagged since cannot be withdrawn ahead of slashing
+        require!(reputation.spam_flags == vouch.vouchee_flags_at, ErrorCode::VouchUnderReview);
+
+        reputation.record(
+            ReputationEvent::VouchRevoked { weight: vouch.weight },
+            &ctx.accounts.config.reputation,
+            clock.unix_timestamp,
+        );
+
+        Ok(())
+    }
+
+    pub fn slash_vouch(ctx: Context<SlashVouch>) -> Result<()> {
+        let vouch = &ctx.accounts.vouch;
+        let reputation = &mut ctx.accounts.vouchee_reputation;
+        let clock = Clock::get()?;
+
+        // Only vouches for users flagged after the vouch was made are slashable
+        require!(reputation.spam_flags > vouch.vouchee_flags_at, ErrorCode::VouchNotSlashable);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SlashVouch,
+            vouch.key(),
+            &(vouch.voucher, vouch.vouchee, vouch.weight),
+            &(),
+        )?;
+
+        // Closing the vouch sends the stake and rent to the admin
+        reputation.record(
+            ReputationEvent::VouchRevoked { weight: vouch.weight },
+            &ctx.accounts.config.reputation,
+            clock.unix_timestamp,
+        );
+
+        Ok(())
+    }
+
+    /// The moderator marks a direct message as spam. The flag counts against the sender's
+    /// reputation, if they have one, and makes vouches made for them before it slashable.
+    pub fn flag_spam_message(ctx: Context<FlagSpamMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let flag = &mut ctx.accounts.spam_flag;
+        let moderator = ctx.accounts.moderator.key();
+        let clock = Clock::get()?;
+
+        require!(is_moderator(&ctx.accounts.config, &moderator), ErrorCode::NotModerator);
+
+        flag.message = message.key();
+        flag.sender = message.sender;
+        flag.flagged_by = moderator;
+        flag.flagged_at = clock.unix_timestamp;
+        flag.bump = ctx.bumps.spam_flag;
+
+        record_reputation_at(
+            &ctx.accounts.sender_reputation,
+            &ctx.accounts.config.reputation,
+            ReputationEvent::SpamFlagged,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(SpamMessageFlagged {
+            message: message.key(),
+            sender: message.sender,
+            flagged_by: moderator,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_attestor(ctx: Context<UpdateConfig>, attestor: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetAttestor,
+            config.key(),
+            &config.attestor,
+            &attestor,
+        )?;
+        config.attestor = attestor;
+
+        Ok(())
+    }
+
+    pub fn attest_account(
+        ctx: Context<AttestAccount>,
+        subject: Pubkey,
+        tx_count: u64,
+        first_activity_ts: i64,
+    ) -> Result<()> {
+        let attestation = &mut ctx.accounts.attestation;
+        let clock = Clock::get()?;
+
+        attestation.subject = subject;
+        attestation.tx_count = tx_count;
+        attestation.first_activity_ts = first_activity_ts;
+        attestation.attested_at = clock.unix_timestamp;
+        attestation.bump = ctx.bumps.attestation;
+
+        Ok(())
+    }
+
+    pub fn refresh_attestation(
+        ctx: Context<RefreshAttestation>,
+        tx_count: u64,
+        first_activity_ts: i64,
+    ) -> Result<()> {
+        let attestation = &mut ctx.accounts.attestation;
+        let clock = Clock::get()?;
+
+        attestation.tx_count = tx_count;
+        attestation.first_activity_ts = first_activity_ts;
+        attestation.attested_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn set_inbox_gate(
+        ctx: Context<SetInboxGate>,
+        min_stake_age_epochs: u64,
+        min_tx_count: u64,
+        min_account_age_secs: i64,
+    ) -> Result<()> {
+        let gate = &mut ctx.accounts.gate;
+
+        gate.owner = ctx.accounts.owner.key();
+        gate.min_stake_age_epochs = min_stake_age_epochs;
+        gate.min_tx_count = min_tx_count;
+        gate.min_account_age_secs = min_account_age_secs;
+        gate.bump = ctx.bumps.gate;
+
+        Ok(())
+    }
+
+    pub fn clear_inbox_gate(_ctx: Context<ClearInboxGate>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_encryption_key(ctx: Context<SetEncryptionKey>, key: [u8; 32]) -> Result<()> {
+        let entry = &mut ctx.accounts.encryption_key;
+        let clock = Clock::get()?;
+
+        entry.owner = ctx.accounts.owner.key();
+        entry.key = key;
+        entry.version = 1;
+        entry.updated_at = clock.unix_timestamp;
+        entry.bump = ctx.bumps.encryption_key;
+
+        ctx.accounts.key_log.append(entry)?;
+
+        Ok(())
+    }
+
+    pub fn rotate_encryption_key(ctx: Context<RotateEncryptionKey>, key: [u8; 32]) -> Result<()> {
+        let entry = &mut ctx.accounts.encryption_key;
+        let clock = Clock::get()?;
+
+        entry.key = key;
+        entry.version += 1;
+        entry.updated_at = clock.unix_timestamp;
+
+        ctx.accounts.key_log.append(entry)?;
+
+        Ok(())
+    }
+
+    pub fn set_guardians(
+        ctx: Context<SetGuardians>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+        timelock_secs: i64,
+    ) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+
+        validate_guardians(&ctx.accounts.owner.key(), &guardians, threshold, timelock_secs)?;
+
+        guardian_set.owner = ctx.accounts.owner.key();
+        guardian_set.guardians = guardians;
+        guardian_set.threshold = threshold;
+        guardian_set.timelock_secs = timelock_secs;
+        guardian_set.bump = ctx.bumps.guardian_set;
+
+        Ok(())
+    }
+
+    pub fn update_guardians(
+        ctx: Context<UpdateGuardians>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+        timelock_secs: i64,
+    ) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+
+        validate_guardians(&guardian_set.owner, &guardians, threshold, timelock_secs)?;
+
+        guardian_set.guardians = guardians;
+        guardian_set.threshold = threshold;
+        guardian_set.timelock_secs = timelock_secs;
+
+        Ok(())
+    }
+
+    pub fn propose_key_recovery(ctx: Context<ProposeKeyRecovery>, new_key: [u8; 32]) -> Result<()> {
+        let guardian_set = &ctx.accounts.guardian_set;
+        let request = &mut ctx.accounts.recovery_request;
+        let guardian = ctx.accounts.guardian.key();
+        let clock = Clock::get()?;
+
+        // Validate the proposer is one of the owner's guardians
+        require!(guardian_set.guardians.contains(&guardian), ErrorCode::NotGuardian);
+
+        request.owner = guardian_set.owner;
+        request.proposer = guardian;
+        request.new_key = new_key;
+        request.approvals = vec![guardian];
+        request.proposed_at = clock.unix_timestamp;
+        request.bump = ctx.bumps.recovery_request;
+
+        Ok(())
+    }
+
+    pub fn approve_key_recovery(ctx: Context<ApproveKeyRecovery>) -> Result<()> {
+        let guardian_set = &ctx.accounts.guardian_set;
+        let request = &mut ctx.accounts.recovery_request;
+        let guardian = ctx.accounts.guardian.key();
+
+        require!(guardian_set.guardians.contains(&guardian), ErrorCode::NotGuardian);
+        require!(!request.approvals.contains(&guardian), ErrorCode::AlreadyApproved);
+
+        request.approvals.push(guardian);
+
+        Ok(())
+    }
+
+    pub fn execute_key_recovery(ctx: Context<ExecuteKeyRecovery>) -> Result<()> {
+        let guardian_set = &ctx.accounts.guardian_set;
+        let request = &ctx.accounts.recovery_request;
+        let entry = &mut ctx.accounts.encryption_key;
+        let clock = Clock::get()?;
+
+        // Only approvals from current guardians count, in case the set changed mid-recovery
+        let approvals = request
+            .approvals
+            .iter()
+            .filter(|approver| guardian_set.guardians.contains(approver))
+            .count();
+        require!(approvals >= guardian_set.threshold as usize, ErrorCode::RecoveryThresholdNotMet);
+        require!(
+            clock.unix_timestamp >= request.proposed_at.saturating_add(guardian_set.timelock_secs),
+            ErrorCode::RecoveryTimelockActive
+        );
+
+        entry.key = request.new_key;
+        entry.version += 1;
+        entry.updated_at = clock.unix_timestamp;
+
+        ctx.accounts.key_log.append(entry)?;
+
+        Ok(())
+    }
+
+    pub fn cancel_key_recovery(_ctx: Context<CancelKeyRecovery>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn migrate_inbox(ctx: Context<MigrateInbox>) -> Result<()> {
+        let forwarding = &mut ctx.accounts.forwarding;
+        let old_wallet = ctx.accounts.old_wallet.key();
+        let new_wallet = ctx.accounts.new_wallet.key();
+        let clock = Clock::get()?;
+
+        // Validate the new wallet is fresh so forwarding chains can't loop
+        require!(old_wallet != new_wallet, ErrorCode::InvalidMigration);
+        require!(ctx.accounts.new_wallet_forwarding.data_is_empty(), ErrorCode::InvalidMigration);
+
+        forwarding.old_wallet = old_wallet;
+        forwarding.new_wallet = new_wallet;
+        forwarding.migrated_at = clock.unix_timestamp;
+        forwarding.bump = ctx.bumps.forwarding;
+
+        Ok(())
+    }
+
+    pub fn migrate_reputation(ctx: Context<MigrateReputation>) -> Result<()> {
+        let old = &ctx.accounts.old_reputation;
+        let new = &mut ctx.accounts.new_reputation;
+
+        // Vouch PDAs are keyed by the old wallet, so they have to be settled first
+        require!(old.vouches == 0, ErrorCode::OutstandingVouches);
+
+        new.user = ctx.accounts.forwarding.new_wallet;
+        new.score = old.score;
+        new.messages_answered = old.messages_answered;
+        new.tickets_resolved = old.tickets_resolved;
+        new.disputes_lost = old.disputes_lost;
+        new.spam_flags = old.spam_flags;
+        new.vouches = 0;
+        new.last_updated = old.last_updated;
+        new.bump = ctx.bumps.new_reputation;
+
+        Ok(())
+    }
+
+    pub fn migrate_encryption_key(ctx: Context<MigrateEncryptionKey>) -> Result<()> {
+        let old = &ctx.accounts.old_encryption_key;
+        let new = &mut ctx.accounts.new_encryption_key;
+
+        new.owner = ctx.accounts.forwarding.new_wallet;
+        new.key = old.key;
+        new.version = old.version;
+        new.updated_at = old.updated_at;
+        new.bump = ctx.bumps.new_encryption_key;
+
+        ctx.accounts.key_log.append(new)?;
+
+        Ok(())
+    }
+
+    pub fn create_dm_link(ctx: Context<CreateDmLink>, slug: String, max_uses: u32, expires_at: i64) -> Result<()> {
+        let dm_link = &mut ctx.accounts.dm_link;
+        let clock = Clock::get()?;
+
+        // Validate slug and limits
+        require!(!slug.is_empty(), ErrorCode::InvalidDmLink);
+        require!(slug.len() <= DmLink::MAX_SLUG_LEN, ErrorCode::InvalidDmLink);
+        require!(max_uses > 0, ErrorCode::InvalidDmLink);
+        require!(expires_at == 0 || expires_at > clock.unix_timestamp, ErrorCode::InvalidDmLink);
+
+        dm_link.owner = ctx.accounts.owner.key();
+        dm_link.slug = slug;
+        dm_link.max_uses = max_uses;
+        dm_link.uses = 0;
+        dm_link.expires_at = expires_at;
+        dm_link.created_at = clock.unix_timestamp;
+        dm_link.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.owner_membership)?;
+        dm_link.bump = ctx.bumps.dm_link;
+
+        Ok(())
+    }
+
+    pub fn revoke_dm_link(_ctx: Context<RevokeDmLink>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn open_ama_inbox(ctx: Context<OpenAmaInbox>, title: String) -> Result<()> {
+        let inbox = &mut ctx.accounts.ama_inbox;
+
+        require!(!title.is_empty(), ErrorCode::EmptyMessage);
+        require!(title.len() <= AmaInbox::MAX_TITLE_LEN, ErrorCode::MessageTooLong);
+
+        inbox.owner = ctx.accounts.owner.key();
+        inbox.title = title;
+        inbox.question_count = 0;
+        inbox.is_open = true;
+        inbox.bump = ctx.bumps.ama_inbox;
+
+        Ok(())
+    }
+
+    pub fn set_ama_open(ctx: Context<ManageAmaInbox>, is_open: bool) -> Result<()> {
+        ctx.accounts.ama_inbox.is_open = is_open;
+
+        Ok(())
+    }
+
+    /// Submitted by a relayer: the asker is only represented by `commitment`, a hash of their
+    /// wallet and a secret nonce they can later reveal off-chain to prove authorship.
+    pub fn ask_anonymous(ctx: Context<AskAnonymous>, commitment: [u8; 32], content: String) -> Result<()> {
+        let inbox = &mut ctx.accounts.ama_inbox;
+        let question = &mut ctx.accounts.question;
+        let clock = Clock::get()?;
+
+        require!(inbox.is_open, ErrorCode::AmaInboxClosed);
+        require!(!content.is_empty(), ErrorCode::EmptyMessage);
+        require!(content.len() <= AmaQuestion::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+
+        question.ama_inbox = inbox.key();
+        question.index = inbox.question_count;
+        question.commitment = commitment;
+        question.content = content;
+        question.asked_at = clock.unix_timestamp;
+        question.answer = String::new();
+        question.answered_at = 0;
+        question.bump = ctx.bumps.question;
+
+        inbox.question_count += 1;
+
+        Ok(())
+    }
+
+    pub fn answer_question(ctx: Context<AnswerQuestion>, answer: String) -> Result<()> {
+        let question = &mut ctx.accounts.question;
+        let clock = Clock::get()?;
+
+        require!(question.answered_at == 0, ErrorCode::AlreadyAnswered);
+        require!(!answer.is_empty(), ErrorCode::EmptyMessage);
+        require!(answer.len() <= AmaQuestion::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+
+        question.answer = answer;
+        question.answered_at = clock.unix_timestamp;
+
+        emit!(AmaAnswered {
+            ama_inbox: question.ama_inbox,
+            question: question.key(),
+            index: question.index,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn create_room(ctx: Context<CreateRoom>, room_id: u64, name: String, ttl_secs: i64) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+        let clock = Clock::get()?;
+
+        require!(!name.is_empty(), ErrorCode::EmptyMessage);
+        require!(name.len() <= Room::MAX_NAME_LEN, ErrorCode::MessageTooLong);
+        require!(ttl_secs > 0 && ttl_secs <= Room::MAX_TTL_SECS, ErrorCode::InvalidRoomTtl);
+
+        room.creator = ctx.accounts.creator.key();
+        room.room_id = room_id;
+        room.name = name;
+        room.created_at = clock.unix_timestamp;
+        room.expires_at = clock.unix_timestamp + ttl_secs;
+        room.message_count = 0;
+        room.min_age = 0;
+        room.region_mode = RegionMode::Unrestricted;
+        room.regions = Vec::new();
+        room.moderated = false;
+        room.hide_threshold_lamports = 0;
+        room.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.creator_membership)?;
+        room.bump = ctx.bumps.room;
+
+        Ok(())
+    }
+
+    /// Declares advisory audience restrictions that compliant clients and the directory enforce.
+    pub fn set_room_restrictions(
+        ctx: Context<SetRoomRestrictions>,
+        min_age: u8,
+        region_mode: RegionMode,
+        regions: Vec<[u8; 2]>,
+    ) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+
+        // Validate regions are distinct ISO 3166-1 alpha-2 codes
+        require!(regions.len() <= Room::MAX_REGIONS, ErrorCode::InvalidRegions);
+        require!(
+            regions.iter().all(|code| code.iter().all(u8::is_ascii_uppercase)),
+            ErrorCode::InvalidRegions
+        );
+        require!(
+            regions.iter().enumerate().all(|(i, code)| !regions[..i].contains(code)),
+            ErrorCode::InvalidRegions
+        );
+        require!(
+            (region_mode == RegionMode::Unrestricted) == regions.is_empty(),
+            ErrorCode::InvalidRegions
+        );
+
+        room.min_age = min_age;
+        room.region_mode = region_mode;
+        room.regions = regions;
+
+        Ok(())
+    }
+
+    pub fn post_room_message(ctx: Context<PostRoomMessage>, content: String) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        require!(clock.unix_timestamp < room.expires_at, ErrorCode::RoomExpired);
+        require!(!content.is_empty(), ErrorCode::EmptyMessage);
+        require!(content.len() <= RoomMessage::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+
+        message.room = room.key();
+        message.author = ctx.accounts.author.key();
+        message.room_creator = room.creator;
+        message.index = room.message_count;
+        message.content = content;
+        message.timestamp = clock.unix_timestamp;
+        message.expires_at = room.expires_at;
+        message.imported = false;
+        // In moderated rooms only the creator's own posts skip the queue
+        message.status = if room.moderated && message.author != room.creator {
+            RoomMessageStatus::Pending
+        } else {
+            RoomMessageStatus::Published
+        };
+        message.bump = ctx.bumps.message;
+
+        room.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn set_room_moderation(ctx: Context<SetRoomModeration>, moderated: bool) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+
+        room.moderated = moderated;
+
+        Ok(())
+    }
+
+    pub fn set_community_moderation(ctx: Context<SetRoomModeration>, hide_threshold_lamports: u64) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+
+        room.hide_threshold_lamports = hide_threshold_lamports;
+
+        Ok(())
+    }
+
+    pub fn report_room_message(ctx: Context<ReportRoomMessage>) -> Result<()> {
+        let room = &ctx.accounts.room;
+        let report = &mut ctx.accounts.report;
+
+        require!(room.hide_threshold_lamports > 0, ErrorCode::CommunityModerationDisabled);
+        require!(ctx.accounts.message.status == RoomMessageStatus::Published, ErrorCode::RoomMessageNotPublished);
+
+        report.room = room.key();
+        report.message = ctx.accounts.message.key();
+        report.reporter = ctx.accounts.reporter.key();
+        // Snapshot the threshold so it can't be moved while votes are coming in
+        report.threshold_lamports = room.hide_threshold_lamports;
+        report.tally_lamports = 0;
+        report.votes = 0;
+        report.created_at = Clock::get()?.unix_timestamp;
+        report.bump = ctx.bumps.report;
+
+        Ok(())
+    }
+
+    /// Votes to hide a reported post. Each voter gets one vote per report, weighted by the active
+    /// stake of the stake account they present, so splitting a stake doesn't buy extra votes.
+    pub fn vote_hide_room_message(ctx: Context<VoteHideRoomMessage>) -> Result<()> {
+        let report = &mut ctx.accounts.report;
+        let message = &mut ctx.accounts.message;
+        let voter = ctx.accounts.voter.key();
+        let clock = Clock::get()?;
+
+        // Validate the voter is a member: they have a published post in the room
+        let membership = &ctx.accounts.membership;
+        require!(
+            membership.room == report.room
+                && membership.author == voter
+                && membership.status == RoomMessageStatus::Published,
+            ErrorCode::NotRoomMember
+        );
+        require!(message.status == RoomMessageStatus::Published, ErrorCode::RoomMessageNotPublished);
+
+        let stake = active_stake(&ctx.accounts.stake_account, &voter, clock.epoch)?;
+        require!(stake > 0, ErrorCode::NoActiveStake);
+
+        let vote = &mut ctx.accounts.vote;
+        vote.report = report.key();
+        vote.voter = voter;
+        vote.stake_account = ctx.accounts.stake_account.key();
+        vote.stake_lamports = stake;
+        vote.bump = ctx.bumps.vote;
+
+        report.tally_lamports = report.tally_lamports.saturating_add(stake);
+        report.votes += 1;
+
+        if report.tally_lamports >= report.threshold_lamports {
+            message.status = RoomMessageStatus::Hidden;
+
+            emit!(RoomMessageHidden {
+                room: report.room,
+                message: message.key(),
+                tally_lamports: report.tally_lamports,
+                votes: report.votes,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn approve_room_message(ctx: Context<ModerateRoomMessage>) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+
+        require!(message.status == RoomMessageStatus::Pending, ErrorCode::RoomMessageNotPending);
+
+        message.status = RoomMessageStatus::Published;
+
+        emit!(RoomMessageApproved {
+            room: message.room,
+            message: message.key(),
+            author: message.author,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Drops a pending post, refunding its rent to the author.
+    pub fn reject_room_message(ctx: Context<RejectRoomMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+
+        require!(message.status == RoomMessageStatus::Pending, ErrorCode::RoomMessageNotPending);
+
+        emit!(RoomMessageRejected {
+            room: message.room,
+            message: message.key(),
+            author: message.author,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Migrates a message from another platform's history into the room, keeping its original
+    /// time. Imported messages are flagged so clients can render them as system messages.
+    pub fn import_room_message(ctx: Context<ImportRoomMessage>, content: String, original_timestamp: i64) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        require!(clock.unix_timestamp < room.expires_at, ErrorCode::RoomExpired);
+        require!(!content.is_empty(), ErrorCode::EmptyMessage);
+        require!(content.len() <= RoomMessage::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+        // Validate the import is backdated, never future-dated
+        require!(original_timestamp <= clock.unix_timestamp, ErrorCode::ImportNotBackdated);
+
+        message.room = room.key();
+        message.author = ctx.accounts.creator.key();
+        message.room_creator = room.creator;
+        message.index = room.message_count;
+        message.content = content;
+        message.timestamp = original_timestamp;
+        message.expires_at = room.expires_at;
+        message.imported = true;
+        message.status = RoomMessageStatus::Published;
+        message.bump = ctx.bumps.message;
+
+        room.message_count += 1;
+
+        Ok(())
+    }
+
+    pub fn close_room(ctx: Context<CloseRoom>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(clock.unix_timestamp >= ctx.accounts.room.expires_at, ErrorCode::RoomNotExpired);
+
+        pay_crank_bounty(
+            &ctx.accounts.room.to_account_info(),
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn close_room_message(ctx: Context<CloseRoomMessage>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(clock.unix_timestamp >= ctx.accounts.message.expires_at, ErrorCode::RoomNotExpired);
+
+        pay_crank_bounty(
+            &ctx.accounts.message.to_account_info(),
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    /// Creates the treasury that pays crank bounties when no account is closed. It is funded
+    /// with plain lamport transfers.
+    pub fn init_treasury(ctx: Context<InitTreasury>) -> Result<()> {
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+
+        Ok(())
+    }
+
+    pub fn create_content_blob(ctx: Context<CreateContentBlob>, content_hash: [u8; 32], content: String) -> Result<()> {
+        let blob = &mut ctx.accounts.blob;
+
+        require!(!content.is_empty(), ErrorCode::EmptyMessage);
+        require!(content.len() <= ContentBlob::MAX_CONTENT_LEN, ErrorCode::MessageTooLong);
+        // Validate the PDA key really is the content's hash so lookups by hash can be trusted
+        require!(hash(content.as_bytes()).to_bytes() == content_hash, ErrorCode::ContentHashMismatch);
+
+        blob.payer = ctx.accounts.payer.key();
+        blob.content_hash = content_hash;
+        blob.content = content;
+        blob.refcount = 0;
+        blob.bump = ctx.bumps.blob;
+
+        Ok(())
+    }
+
+    /// `nonce` lets one sender keep several messages on the same blob open to the same recipient.
+    pub fn send_blob_message(ctx: Context<SendBlobMessage>, recipient: Pubkey, nonce: u64) -> Result<()> {
+        let blob = &mut ctx.accounts.blob;
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        check_feature_gate(&ctx.accounts.feature_gate, ctx.accounts.app.as_ref())?;
+
+        message.sender = ctx.accounts.sender.key();
+        message.recipient = recipient;
+        message.nonce = nonce;
+        message.blob = blob.key();
+        message.timestamp = clock.unix_timestamp;
+        message.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.sender_membership)?;
+        message.bump = ctx.bumps.message;
+
+        blob.refcount = blob.refcount.checked_add(1).ok_or(ErrorCode::BlobRefcountOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn close_blob_message(ctx: Context<CloseBlobMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let blob = &mut ctx.accounts.blob;
+
+        // Either side of the conversation may drop it
+        let closer = ctx.accounts.closer.key();
+        require!(closer == message.sender || closer == message.recipient, ErrorCode::UnauthorizedBlobAccess);
+
+        blob.refcount = blob.refcount.saturating_sub(1);
+
+        Ok(())
+    }
+
+    /// Permissionless: reclaims a blob nothing references any more, refunding whoever created it.
+    pub fn garbage_collect_blob(ctx: Context<GarbageCollectBlob>) -> Result<()> {
+        require!(ctx.accounts.blob.refcount == 0, ErrorCode::BlobStillReferenced);
+
+        Ok(())
+    }
+
+    pub fn init_dictionary(ctx: Context<InitDictionary>) -> Result<()> {
+        let dictionary = &mut ctx.accounts.dictionary;
+
+        dictionary.entries = Vec::new();
+        dictionary.bump = ctx.bumps.dictionary;
+
+        Ok(())
+    }
+
+    /// Entries are append-only so stored indices keep their meaning.
+    pub fn add_dictionary_entry(ctx: Context<AddDictionaryEntry>, word: String) -> Result<()> {
+        let dictionary = &mut ctx.accounts.dictionary;
+
+        require!(!word.is_empty(), ErrorCode::EmptyMessage);
+        require!(word.len() <= MessageDictionary::MAX_WORD_LEN, ErrorCode::MessageTooLong);
+        require!(dictionary.entries.len() < MessageDictionary::MAX_ENTRIES, ErrorCode::DictionaryFull);
+        require!(!dictionary.entries.contains(&word), ErrorCode::DuplicateDictionaryEntry);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::AddDictionaryEntry,
+            dictionary.key(),
+            &(),
+            &word,
+        )?;
+        dictionary.entries.push(word);
+
+        Ok(())
+    }
+
+    /// `nonce` lets one sender keep several interned messages open to the same recipient.
+    pub fn send_interned_message(
+        ctx: Context<SendInternedMessage>,
+        recipient: Pubkey,
+        nonce: u64,
+        word_index: u16,
+    ) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        require!(
+            (word_index as usize) < ctx.accounts.dictionary.entries.len(),
+            ErrorCode::UnknownDictionaryEntry
+        );
+        check_feature_gate(&ctx.accounts.feature_gate, ctx.accounts.app.as_ref())?;
+
+        message.sender = ctx.accounts.sender.key();
+        message.recipient = recipient;
+        message.nonce = nonce;
+        message.word_index = word_index;
+        message.timestamp = clock.unix_timestamp;
+        message.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.sender_membership)?;
+        message.bump = ctx.bumps.message;
+
+        Ok(())
+    }
+
+    pub fn close_interned_message(ctx: Context<CloseInternedMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+
+        // Either side of the conversation may drop it
+        let closer = ctx.accounts.closer.key();
+        require!(closer == message.sender || closer == message.recipient, ErrorCode::NotConversationParticipant);
+
+        Ok(())
+    }
+
+    /// Permissionless crank that opens the aggregate account for the current epoch.
+    pub fn roll_epoch_stats(ctx: Context<RollEpochStats>, epoch: u64) -> Result<()> {
+        let stats = &mut ctx.accounts.epoch_stats;
+        let clock = Clock::get()?;
+
+        require!(epoch == clock.epoch, ErrorCode::InvalidEpoch);
+
+        stats.epoch = epoch;
+        stats.messages = 0;
+        stats.active_senders = 0;
+        stats.tips_volume_lamports = 0;
+        stats.sender_bloom = [0u8; EpochStats::BLOOM_BYTES];
+        stats.opened_at = clock.unix_timestamp;
+        stats.bump = ctx.bumps.epoch_stats;
+
+        Ok(())
+    }
+
+    pub fn init_user_stats(ctx: Context<InitUserStats>, user: Pubkey) -> Result<()> {
+        let stats = &mut ctx.accounts.stats;
+
+        stats.user = user;
+        stats.messages_sent = 0;
+        stats.activity_bitmap = [0u8; UserStats::BITMAP_BYTES];
+        stats.last_active_day = 0;
+        stats.bump = ctx.bumps.stats;
+
+        Ok(())
+    }
+
+    pub fn init_key_log(ctx: Context<InitKeyLog>) -> Result<()> {
+        let log = &mut ctx.accounts.key_log;
+
+        log.leaf_count = 0;
+        log.frontier = [[0u8; 32]; KeyTransparencyLog::DEPTH];
+        log.root = KeyTransparencyLog::empty_root();
+        log.bump = ctx.bumps.key_log;
+
+        Ok(())
+    }
+
+    pub fn open_conversation_settings(
+        ctx: Context<OpenConversationSettings>,
+        participant_a: Pubkey,
+        participant_b: Pubkey,
+    ) -> Result<()> {
+        let settings = &mut ctx.accounts.settings;
+
+        // Validate participants are ordered and include the payer
+        require!(participant_a < participant_b, ErrorCode::ParticipantsNotOrdered);
+        let payer = ctx.accounts.participant.key();
+        require!(payer == participant_a || payer == participant_b, ErrorCode::NotConversationParticipant);
+
+        settings.participants = [participant_a, participant_b];
+        settings.confirmed = [false; 2];
+        settings.verified = false;
+        settings.key_versions = [0; 2];
+        settings.safety_number = [0; 32];
+        settings.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.participant_membership)?;
+        settings.bump = ctx.bumps.settings;
+
+        Ok(())
+    }
+
+    pub fn confirm_safety_number(ctx: Context<ConfirmSafetyNumber>, safety_number: [u8; 32]) -> Result<()> {
+        let settings = &mut ctx.accounts.settings;
+        let key_a = &ctx.accounts.key_a;
+        let key_b = &ctx.accounts.key_b;
+
+        let side = settings.side_of(&ctx.accounts.participant.key())?;
+
+        // Validate the number the user compared out-of-band matches the current keys
+        let current = ConversationSettings::safety_number(key_a, key_b);
+        require!(safety_number == current, ErrorCode::SafetyNumberMismatch);
+
+        // A confirmation only counts for the keys it was made against
+        if settings.safety_number != current {
+            settings.confirmed = [false; 2];
+            settings.safety_number = current;
+            settings.key_versions = [key_a.version, key_b.version];
+        }
+
+        settings.confirmed[side] = true;
+        settings.verified = settings.confirmed == [true; 2];
+
+        emit!(SafetyNumberConfirmed {
+            settings: settings.key(),
+            participant: ctx.accounts.participant.key(),
+            safety_number: current,
+            verified: settings.verified,
+        });
+
+        Ok(())
+    }
+
+    pub fn check_safety_number(ctx: Context<CheckSafetyNumber>) -> Result<()> {
+        let settings = &mut ctx.accounts.settings;
+        let key_a = &ctx.accounts.key_a;
+        let key_b = &ctx.accounts.key_b;
+
+        let versions = [key_a.version, key_b.version];
+        if settings.safety_number == [0; 32] || versions == settings.key_versions {
+            return Ok(());
+        }
+
+        // Either key changed after confirmation: drop verification and tell both clients
+        for (side, key) in [key_a, key_b].into_iter().enumerate() {
+            if key.version != settings.key_versions[side] {
+                emit!(SafetyNumberChanged {
+                    settings: settings.key(),
+                    participant: key.owner,
+                    old_version: settings.key_versions[side],
+                    new_version: key.version,
+                    timestamp: key.updated_at,
+                });
+            }
+        }
+
+        settings.confirmed = [false; 2];
+        settings.verified = false;
+        settings.safety_number = [0; 32];
+        settings.key_versions = versions;
+
+        Ok(())
+    }
+
+    pub fn wipe_my_data(ctx: Context<WipeMyData>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+
+        // Revoke the registered key in the transparency log before the entry disappears
+        if let Some(entry) = ctx.accounts.encryption_key.as_mut() {
+            let key_log = ctx.accounts.key_log.as_mut().ok_or(ErrorCode::KeyLogRequired)?;
+            entry.key = [0; 32];
+            entry.version += 1;
+            entry.updated_at = Clock::get()?.unix_timestamp;
+            key_log.append(entry)?;
+        }
+
+        if let Some(member) = &ctx.accounts.namespace_member {
+            let namespace = ctx.accounts.member_namespace.as_ref().ok_or(ErrorCode::NotWipeable)?;
+            let admin = ctx.accounts.namespace_admin.as_ref().ok_or(ErrorCode::NotWipeable)?;
+            require_keys_eq!(member.namespace, namespace.key(), ErrorCode::NotWipeable);
+            require_keys_eq!(admin.key(), namespace.admin, ErrorCode::NotWipeable);
+            close_and_zero(&member.to_account_info(), &admin.to_account_info())?;
+        }
+
+        // DM links, reminders, snoozes and conversation settings are passed as remaining accounts
+        for account in ctx.remaining_accounts {
+            require_keys_eq!(*account.owner, crate::ID, ErrorCode::NotWipeable);
+            let mut data = account.try_borrow_mut_data()?;
+            let account_owner = if data.starts_with(&DmLink::DISCRIMINATOR) {
+                Some(DmLink::try_deserialize(&mut &data[..])?.owner)
+            } else if data.starts_with(&Reminder::DISCRIMINATOR) {
+                Some(Reminder::try_deserialize(&mut &data[..])?.owner)
+            } else if data.starts_with(&Snooze::DISCRIMINATOR) {
+                Some(Snooze::try_deserialize(&mut &data[..])?.owner)
+            } else {
+                None
+            };
+            if let Some(account_owner) = account_owner {
+                require_keys_eq!(account_owner, owner, ErrorCode::NotWipeable);
+                drop(data);
+                close_and_zero(account, &ctx.accounts.owner.to_account_info())?;
+            } else if data.starts_with(&ConversationSettings::DISCRIMINATOR) {
+                // Settings are shared with the peer, who may have paid for them: clear the
+                // owner's side and the safety number derived from their key, and leave the rest
+                let mut settings = ConversationSettings::try_deserialize(&mut &data[..])?;
+                let side = settings.side_of(&owner).map_err(|_| error!(ErrorCode::NotWipeable))?;
+                settings.confirmed[side] = false;
+                settings.key_versions[side] = 0;
+                settings.verified = false;
+                settings.safety_number = [0; 32];
+                settings.try_serialize(&mut &mut data[..])?;
+            } else {
+                return err!(ErrorCode::NotWipeable);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn disclose_message(ctx: Context<DiscloseMessage>, decryption_key: Vec<u8>) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let disclosure = &mut ctx.accounts.disclosure;
+        let discloser = ctx.accounts.discloser.key();
+
+        // Validate either participant is disclosing
+        require!(
+            discloser == message.sender || discloser == message.recipient,
+            ErrorCode::NotConversationParticipant
+        );
+        require!(
+            !decryption_key.is_empty() && decryption_key.len() <= Disclosure::MAX_KEY_LEN,
+            ErrorCode::DecryptionKeyTooLong
+        );
+
+        let clock = Clock::get()?;
+
+        disclosure.message = message.key();
+        disclosure.discloser = discloser;
+        disclosure.decryption_key = decryption_key;
+        disclosure.disclosed_at = clock.unix_timestamp;
+        disclosure.bump = ctx.bumps.disclosure;
+
+        emit!(MessageDisclosed {
+            message: message.key(),
+            discloser,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn grant_observer(ctx: Context<GrantObserver>, observer: Pubkey, label: String) -> Result<()> {
+        let settings = &ctx.accounts.settings;
+        let grant = &mut ctx.accounts.grant;
+
+        // Validate the granter is in the conversation and the observer isn't
+        settings.side_of(&ctx.accounts.granter.key())?;
+        require!(!settings.participants.contains(&observer), ErrorCode::ObserverIsParticipant);
+        require!(label.len() <= ObserverGrant::MAX_LABEL_LEN, ErrorCode::ObserverLabelTooLong);
+
+        let clock = Clock::get()?;
+
+        grant.conversation = settings.key();
+        grant.granter = ctx.accounts.granter.key();
+        grant.observer = observer;
+        grant.label = label;
+        grant.granted_at = clock.unix_timestamp;
+        grant.bump = ctx.bumps.grant;
+
+        emit!(ObserverGranted {
+            conversation: grant.conversation,
+            granter: grant.granter,
+            observer,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn revoke_observer(ctx: Context<RevokeObserver>) -> Result<()> {
+        emit!(ObserverRevoked {
+            conversation: ctx.accounts.grant.conversation,
+            granter: ctx.accounts.granter.key(),
+            observer: ctx.accounts.grant.observer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn share_observer_key(ctx: Context<ShareObserverKey>, wrapped_key: Vec<u8>) -> Result<()> {
+        let grant = &ctx.accounts.grant;
+        let message = &ctx.accounts.message;
+        let settings = &ctx.accounts.settings;
+        let observer_key = &mut ctx.accounts.observer_key;
+
+        // Validate the message belongs to the observed conversation and postdates the grant
+        require!(
+            settings.participants.contains(&message.sender) && settings.participants.contains(&message.recipient),
+            ErrorCode::NotConversationParticipant
+        );
+        require!(message.timestamp >= grant.granted_at, ErrorCode::MessageBeforeObserverGrant);
+        require!(
+            !wrapped_key.is_empty() && wrapped_key.len() <= ObserverKey::MAX_WRAPPED_KEY_LEN,
+            ErrorCode::WrappedKeyTooLong
+        );
+
+        observer_key.grant = grant.key();
+        observer_key.message = message.key();
+        observer_key.wrapped_key = wrapped_key;
+        observer_key.bump = ctx.bumps.observer_key;
+
+        Ok(())
+    }
+
+    pub fn set_delivery_window(ctx: Context<SetDeliveryWindow>, start_minute: u16, end_minute: u16) -> Result<()> {
+        let window = &mut ctx.accounts.window;
+
+        DeliveryWindow::validate(start_minute, end_minute)?;
+
+        window.owner = ctx.accounts.owner.key();
+        window.start_minute = start_minute;
+        window.end_minute = end_minute;
+        window.bump = ctx.bumps.window;
+
+        Ok(())
+    }
+
+    pub fn update_delivery_window(ctx: Context<UpdateDeliveryWindow>, start_minute: u16, end_minute: u16) -> Result<()> {
+        let window = &mut ctx.accounts.window;
+
+        DeliveryWindow::validate(start_minute, end_minute)?;
+
+        window.start_minute = start_minute;
+        window.end_minute = end_minute;
+
+        Ok(())
+    }
+
+    pub fn clear_delivery_window(_ctx: Context<ClearDeliveryWindow>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn deliver_queued(ctx: Context<DeliverQueued>) -> Result<()> {
+        let delivery = &mut ctx.accounts.delivery;
+        let clock = Clock::get()?;
+
+        // Permissionless: anyone may flip a queued message once the recipient's window opens
+        require!(delivery.state == DeliveryState::Queued, ErrorCode::MessageNotQueued);
+        require!(clock.unix_timestamp >= delivery.deliver_at, ErrorCode::DeliveryWindowClosed);
+
+        delivery.state = DeliveryState::Delivered;
+        delivery.delivered_at = clock.unix_timestamp;
+
+        emit!(MessageDelivered {
+            message: delivery.message,
+            recipient: delivery.recipient,
+            delivered_at: clock.unix_timestamp,
+        });
+
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let treasury_floor = Rent::get()?.minimum_balance(treasury.data_len());
+        pay_crank_bounty(
+            &treasury,
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            treasury_floor,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_priority_policy(
+        ctx: Context<SetPriorityPolicy>,
+        urgent_fee_lamports: u64,
+        urgent_from_contacts: bool,
+    ) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+
+        policy.owner = ctx.accounts.owner.key();
+        policy.urgent_fee_lamports = urgent_fee_lamports;
+        policy.urgent_from_contacts = urgent_from_contacts;
+        policy.bump = ctx.bumps.policy;
+
+        Ok(())
+    }
+
+    pub fn update_priority_policy(
+        ctx: Context<UpdatePriorityPolicy>,
+        urgent_fee_lamports: u64,
+        urgent_from_contacts: bool,
+    ) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+
+        policy.urgent_fee_lamports = urgent_fee_lamports;
+        policy.urgent_from_contacts = urgent_from_contacts;
+
+        Ok(())
+    }
+
+    pub fn set_reminder(ctx: Context<SetReminder>, remind_at: i64) -> Result<()> {
+        let reminder = &mut ctx.accounts.reminder;
+        let clock = Clock::get()?;
+
+        require!(remind_at > clock.unix_timestamp, ErrorCode::ReminderInPast);
+
+        reminder.owner = ctx.accounts.owner.key();
+        reminder.message = ctx.accounts.message.key();
+        reminder.remind_at = remind_at;
+        reminder.created_at = clock.unix_timestamp;
+        reminder.bump = ctx.bumps.reminder;
+
+        Ok(())
+    }
+
+    pub fn cancel_reminder(_ctx: Context<CancelReminder>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn fire_reminder(ctx: Context<FireReminder>) -> Result<()> {
+        let reminder = &ctx.accounts.reminder;
+        let clock = Clock::get()?;
+
+        // Permissionless: the crank turns a due reminder into a self-notification
+        require!(clock.unix_timestamp >= reminder.remind_at, ErrorCode::ReminderNotDue);
+
+        emit!(ReminderDue {
+            owner: reminder.owner,
+            message: reminder.message,
+            remind_at: reminder.remind_at,
+            fired_at: clock.unix_timestamp,
+        });
+
+        pay_crank_bounty(
+            &ctx.accounts.reminder.to_account_info(),
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn snooze_conversation(ctx: Context<SnoozeConversation>, peer: Pubkey, until: i64) -> Result<()> {
+        let snooze = &mut ctx.accounts.snooze;
+        let clock = Clock::get()?;
+
+        require!(until > clock.unix_timestamp, ErrorCode::SnoozeInPast);
+
+        snooze.owner = ctx.accounts.owner.key();
+        snooze.peer = peer;
+        snooze.until = until;
+        snooze.bump = ctx.bumps.snooze;
+
+        Ok(())
+    }
+
+    pub fn unsnooze_conversation(ctx: Context<UnsnoozeConversation>) -> Result<()> {
+        emit!(ConversationUnsnoozed {
+            owner: ctx.accounts.snooze.owner,
+            peer: ctx.accounts.snooze.peer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn expire_snooze(ctx: Context<ExpireSnooze>) -> Result<()> {
+        let snooze = &ctx.accounts.snooze;
+        let clock = Clock::get()?;
+
+        // Permissionless: the crank brings the thread back once the snooze lapses
+        require!(clock.unix_timestamp >= snooze.until, ErrorCode::SnoozeNotExpired);
+
+        emit!(ConversationUnsnoozed {
+            owner: snooze.owner,
+            peer: snooze.peer,
+            timestamp: clock.unix_timestamp,
+        });
+
+        pay_crank_bounty(
+            &ctx.accounts.snooze.to_account_info(),
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn attach_translations(
+        ctx: Context<AttachTranslations>,
+        primary_language: String,
+        segments: Vec<ContentSegment>,
+    ) -> Result<()> {
+        let translations = &mut ctx.accounts.translations;
+
+        // Validate the segments are distinct, well-tagged languages within the size bound
+        require!(is_language_tag(&primary_language), ErrorCode::InvalidLanguageTag);
+        require!(
+            !segments.is_empty() && segments.len() <= MessageTranslations::MAX_SEGMENTS,
+            ErrorCode::TooManySegments
+        );
+        let mut total_len = 0;
+        for (i, segment) in segments.iter().enumerate() {
+            require!(is_language_tag(&segment.language), ErrorCode::InvalidLanguageTag);
+            require!(!segment.content.is_empty(), ErrorCode::EmptyMessage);
+            require!(
+                segment.language != primary_language && segments[..i].iter().all(|s| s.language != segment.language),
+                ErrorCode::DuplicateLanguage
+            );
+            total_len += segment.content.len();
+        }
+        require!(total_len <= MessageTranslations::MAX_TOTAL_CONTENT_LEN, ErrorCode::TranslationsTooLong);
+
+        translations.message = ctx.accounts.message.key();
+        translations.primary_language = primary_language;
+        translations.segments = segments;
+        translations.bump = ctx.bumps.translations;
+
+        Ok(())
+    }
+
+    pub fn remove_translations(_ctx: Context<RemoveTranslations>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn attach_media(ctx: Context<AttachMedia>, attachments: Vec<Attachment>) -> Result<()> {
+        let media = &mut ctx.accounts.media;
+
+        require!(
+            !attachments.is_empty() && attachments.len() <= MessageMedia::MAX_ATTACHMENTS,
+            ErrorCode::TooManyAttachments
+        );
+        for attachment in &attachments {
+            require!(
+                !attachment.uri.is_empty() && attachment.uri.len() <= MessageMedia::MAX_URI_LEN,
+                ErrorCode::InvalidAttachment
+            );
+            require!(
+                !attachment.mime_type.is_empty() && attachment.mime_type.len() <= MessageMedia::MAX_MIME_TYPE_LEN,
+                ErrorCode::InvalidAttachment
+            );
+            // Validate every attachment is described for screen readers
+            require!(!attachment.alt_text.trim().is_empty(), ErrorCode::AltTextRequired);
+            require!(attachment.alt_text.len() <= MessageMedia::MAX_ALT_TEXT_LEN, ErrorCode::AltTextTooLong);
+        }
+
+        media.message = ctx.accounts.message.key();
+        media.attachments = attachments;
+        media.bump = ctx.bumps.media;
+
+        Ok(())
+    }
+
+    pub fn set_content_warning(ctx: Context<SetContentWarning>, category: ContentWarningCategory) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let warning = &mut ctx.accounts.warning;
+        let flagger = ctx.accounts.flagger.key();
+        let by_moderator = is_moderator(&ctx.accounts.config, &flagger);
+
+        // Validate the sender is labelling their own message, or the moderator is
+        require!(flagger == message.sender || by_moderator, ErrorCode::NotSenderOrModerator);
+
+        warning.message = message.key();
+        warning.category = category;
+        warning.by_moderator = by_moderator;
+        warning.payer = flagger;
+        warning.bump = ctx.bumps.warning;
+
+        emit!(ContentWarningSet {
+            message: message.key(),
+            category,
+            by_moderator,
+        });
+
+        Ok(())
+    }
+
+    pub fn clear_content_warning(ctx: Context<ClearContentWarning>) -> Result<()> {
+        let clearer = ctx.accounts.clearer.key();
+
+        // The sender can't lift a warning the moderator applied
+        if !is_moderator(&ctx.accounts.config, &clearer) {
+            require!(clearer == ctx.accounts.message.sender, ErrorCode::NotSenderOrModerator);
+            require!(!ctx.accounts.warning.by_moderator, ErrorCode::WarningSetByModerator);
+        }
+
+        Ok(())
+    }
+
+    pub fn set_content_filter(
+        ctx: Context<SetContentFilter>,
+        actions: [WarningAction; ContentWarningCategory::COUNT],
+    ) -> Result<()> {
+        let filter = &mut ctx.accounts.filter;
+
+        filter.owner = ctx.accounts.owner.key();
+        filter.actions = actions;
+        filter.bump = ctx.bumps.filter;
+
+        Ok(())
+    }
+
+    pub fn update_content_filter(
+        ctx: Context<UpdateContentFilter>,
+        actions: [WarningAction; ContentWarningCategory::COUNT],
+    ) -> Result<()> {
+        let filter = &mut ctx.accounts.filter;
+
+        filter.actions = actions;
+
+        Ok(())
+    }
+
+    pub fn init_moderation_council(ctx: Context<InitModerationCouncil>, members: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let council = &mut ctx.accounts.council;
+
+        validate_council(&members, threshold)?;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetModerationCouncil,
+            council.key(),
+            &(),
+            &(&members, threshold),
+        )?;
+        council.members = members;
+        council.threshold = threshold;
+        council.vote_mint = Pubkey::default();
+        council.seats = 0;
+        council.candidate_bond = 0;
+        council.bump = ctx.bumps.council;
+
+        Ok(())
+    }
+
+    /// Escalates a moderation action against the caller's content to the moderation council:
+    /// a room post hidden by community vote, or a content warning the moderator applied.
+    pub fn appeal_moderation(ctx: Context<AppealModeration>) -> Result<()> {
+        let appeal = &mut ctx.accounts.appeal;
+        let appellant = ctx.accounts.appellant.key();
+        let target = &ctx.accounts.target;
+        let clock = Clock::get()?;
+
+        let kind = {
+            let data = target.try_borrow_data()?;
+            if data.starts_with(&RoomMessage::DISCRIMINATOR) {
+                let message = RoomMessage::try_deserialize(&mut &data[..])?;
+                require!(message.status == RoomMessageStatus::Hidden, ErrorCode::NothingToAppeal);
+                require_keys_eq!(message.author, appellant, ErrorCode::NotAppellant);
+                AppealKind::RoomHide
+            } else if data.starts_with(&ContentWarning::DISCRIMINATOR) {
+                let warning = ContentWarning::try_deserialize(&mut &data[..])?;
+                let warned = ctx.accounts.warned_message.as_ref().ok_or(ErrorCode::NotAppellant)?;
+                require!(warning.by_moderator, ErrorCode::NothingToAppeal);
+                require_keys_eq!(warned.key(), warning.message, ErrorCode::NotAppellant);
+                require_keys_eq!(warned.sender, appellant, ErrorCode::NotAppellant);
+                AppealKind::ContentWarning
+            } else {
+                return err!(ErrorCode::NothingToAppeal);
+            }
+        };
+
+        appeal.target = target.key();
+        appeal.appellant = appellant;
+        appeal.kind = kind;
+        appeal.status = AppealStatus::Open;
+        appeal.upholds = Vec::new();
+        appeal.reversals = Vec::new();
+        appeal.filed_at = clock.unix_timestamp;
+        appeal.resolved_at = 0;
+        appeal.bump = ctx.bumps.appeal;
+
+        emit!(AppealFiled {
+            appeal: appeal.key(),
+            target: appeal.target,
+            appellant,
+            kind,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// A council member's ruling. Once `threshold` current members agree the appeal resolves;
+    /// reversing restores the hidden post or lifts the warning.
+    pub fn rule_on_appeal(ctx: Context<RuleOnAppeal>, reverse: bool) -> Result<()> {
+        let appeal = &mut ctx.accounts.appeal;
+        let council = &ctx.accounts.council;
+        let member = ctx.accounts.member.key();
+        let clock = Clock::get()?;
+
+        require!(council.members.contains(&member), ErrorCode::NotCouncilMember);
+        require!(appeal.status == AppealStatus::Open, ErrorCode::AppealResolved);
+        require!(
+            !appeal.upholds.contains(&member) && !appeal.reversals.contains(&member),
+            ErrorCode::AlreadyRuled
+        );
+
+        if reverse {
+            appeal.reversals.push(member);
+        } else {
+            appeal.upholds.push(member);
+        }
+
+        emit!(AppealRuling {
+            appeal: appeal.key(),
+            member,
+            reverse,
+            timestamp: clock.unix_timestamp,
+        });
+
+        // Only members still on the council count, in case it changed since they ruled
+        let count = |rulings: &[Pubkey]| rulings.iter().filter(|key| council.members.contains(key)).count();
+        let threshold = council.threshold as usize;
+        let reversed = count(&appeal.reversals) >= threshold;
+        if !reversed && count(&appeal.upholds) < threshold {
+            return Ok(());
+        }
+
+        if reversed {
+            let target = &ctx.accounts.target;
+            match appeal.kind {
+                AppealKind::RoomHide => {
+                    let mut data = target.try_borrow_mut_data()?;
+                    let mut message = RoomMessage::try_deserialize(&mut &data[..])?;
+                    message.status = RoomMessageStatus::Published;
+                    message.try_serialize(&mut &mut data[..])?;
+                }
+                AppealKind::ContentWarning => {
+                    let payer = ctx.accounts.warning_payer.as_ref().ok_or(ErrorCode::WarningPayerRequired)?;
+                    let warning = ContentWarning::try_deserialize(&mut &target.try_borrow_data()?[..])?;
+                    require_keys_eq!(payer.key(), warning.payer, ErrorCode::WarningPayerRequired);
+                    close_and_zero(&target.to_account_info(), &payer.to_account_info())?;
+                }
+            }
+        }
+
+        appeal.status = if reversed { AppealStatus::Reversed } else { AppealStatus::Upheld };
+        appeal.resolved_at = clock.unix_timestamp;
+
+        emit!(AppealDecided {
+            appeal: appeal.key(),
+            target: appeal.target,
+            reversed,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Opens the current epoch's election. Permissionless; anyone can start it once elections
+    /// are enabled.
+    pub fn open_council_election(ctx: Context<OpenCouncilElection>, epoch: u64) -> Result<()> {
+        let election = &mut ctx.accounts.election;
+        let council = &ctx.accounts.council;
+
+        require!(council.vote_mint != Pubkey::default(), ErrorCode::ElectionsDisabled);
+        require!(epoch == Clock::get()?.epoch, ErrorCode::ElectionClosed);
+
+        election.epoch = epoch;
+        election.vote_mint = council.vote_mint;
+        election.candidate_bond = council.candidate_bond;
+        election.candidates = Vec::new();
+        election.votes = Vec::new();
+        election.bonds = Vec::new();
+        election.finalized = false;
+        election.bump = ctx.bumps.election;
+
+        Ok(())
+    }
+
+    /// Enters the current election by locking `bond` vote tokens, at least the election's
+    /// candidate bond. Once the list is full, a newcomer has to outbid the lowest bond, which
+    /// evicts that candidate along with the votes they had.
+    pub fn stand_for_council(ctx: Context<StandForCouncil>, bond: u64) -> Result<()> {
+        let election = &mut ctx.accounts.election;
+        let candidacy = &mut ctx.accounts.candidacy;
+        let candidate = &ctx.accounts.candidate;
+
+        require!(election.epoch == Clock::get()?.epoch, ErrorCode::ElectionClosed);
+        require!(!election.candidates.contains(&candidate.key()), ErrorCode::AlreadyCandidate);
+        require!(bond > 0 && bond >= election.candidate_bond, ErrorCode::InsufficientCandidateBond);
+
+        if election.candidates.len() >= CouncilElection::MAX_CANDIDATES {
+            // Lowest bond goes; among equal bonds, the latest to stand
+            let lowest = (0..election.bonds.len()).rev().min_by_key(|&i| election.bonds[i]).unwrap();
+            require!(bond > election.bonds[lowest], ErrorCode::TooManyCandidates);
+
+            let evicted = election.candidates.remove(lowest);
+            let votes = election.votes.remove(lowest);
+            let evicted_bond = election.bonds.remove(lowest);
+            emit!(CouncilCandidateEvicted {
+                election: election.key(),
+                candidate: evicted,
+                bond: evicted_bond,
+                votes,
+            });
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.candidate_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: candidate.to_account_info(),
+                },
+            ),
+            bond,
+        )?;
+
+        election.candidates.push(candidate.key());
+        election.votes.push(0);
+        election.bonds.push(bond);
+
+        candidacy.election = election.key();
+        candidacy.candidate = candidate.key();
+        candidacy.bond = bond;
+        candidacy.bump = ctx.bumps.candidacy;
+
+        Ok(())
+    }
+
+    /// Returns a candidate's bond once the election is finalized, or straight away if they
+    /// were evicted.
+    pub fn reclaim_candidate_bond(ctx: Context<ReclaimCandidateBond>) -> Result<()> {
+        let election = &ctx.accounts.election;
+        let candidacy = &ctx.accounts.candidacy;
+
+        require!(
+            election.finalized || !election.candidates.contains(&candidacy.candidate),
+            ErrorCode::ElectionOpen
+        );
+
+        let seeds: &[&[u8]] = &[
+            b"council_candidacy",
+            candidacy.election.as_ref(),
+            candidacy.candidate.as_ref(),
+            &[candidacy.bump],
+        ];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            &ctx.accounts.candidate_token_account,
+            &ctx.accounts.candidate.to_account_info(),
+            &candidacy.to_account_info(),
+            seeds,
+            candidacy.bond,
+        )?;
+
+        Ok(())
+    }
+
+    /// Backs a candidate with `amount` vote tokens. The tokens stay escrowed until the election
+    /// is finalized, so the same tokens can't be moved to another wallet and counted twice.
+    pub fn vote_council(ctx: Context<VoteCouncil>, candidate: Pubkey, amount: u64) -> Result<()> {
+        let election = &mut ctx.accounts.election;
+        let vote = &mut ctx.accounts.vote;
+        let voter = &ctx.accounts.voter;
+
+        require!(election.epoch == Clock::get()?.epoch, ErrorCode::ElectionClosed);
+        require!(amount > 0, ErrorCode::InvalidVoteAmount);
+        let index = election
+            .candidates
+            .iter()
+            .position(|key| *key == candidate)
+            .ok_or(ErrorCode::NotCandidate)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.voter_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: voter.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        election.votes[index] = election.votes[index].checked_add(amount).ok_or(ErrorCode::InvalidVoteAmount)?;
+
+        vote.election = election.key();
+        vote.voter = voter.key();
+        vote.candidate = candidate;
+        vote.amount = amount;
+        vote.bump = ctx.bumps.vote;
+
+        emit!(CouncilVoteCast {
+            election: election.key(),
+            voter: voter.key(),
+            candidate,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Seats the top candidates once the epoch is over. Permissionless; an election nobody
+    /// voted in leaves the sitting council in place.
+    pub fn finalize_council_election(ctx: Context<FinalizeCouncilElection>) -> Result<()> {
+        let election = &mut ctx.accounts.election;
+        let council = &mut ctx.accounts.council;
+
+        require!(Clock::get()?.epoch > election.epoch, ErrorCode::ElectionOpen);
+        require!(!election.finalized, ErrorCode::ElectionFinalized);
+        election.finalized = true;
+
+        // Most votes first; earlier candidacies win ties
+        let mut ranked: Vec<usize> = (0..election.candidates.len()).filter(|&i| election.votes[i] > 0).collect();
+        ranked.sort_by(|&a, &b| election.votes[b].cmp(&election.votes[a]).then(a.cmp(&b)));
+        ranked.truncate(council.seats as usize);
+        if ranked.is_empty() || council.vote_mint != election.vote_mint {
+            return Ok(());
+        }
+
+        let members: Vec<Pubkey> = ranked.iter().map(|&i| election.candidates[i]).collect();
+        council.threshold = (members.len() / 2 + 1) as u8;
+        council.members = members;
+
+        emit!(CouncilElected {
+            election: election.key(),
+            epoch: election.epoch,
+            members: council.members.clone(),
+            threshold: council.threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a voter's escrowed tokens after the election is finalized.
+    pub fn reclaim_council_vote(ctx: Context<ReclaimCouncilVote>) -> Result<()> {
+        let vote = &ctx.accounts.vote;
+
+        require!(ctx.accounts.election.finalized, ErrorCode::ElectionOpen);
+
+        let seeds: &[&[u8]] = &[b"council_vote", vote.election.as_ref(), vote.voter.as_ref(), &[vote.bump]];
+        release_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            &ctx.accounts.voter_token_account,
+            &ctx.accounts.voter.to_account_info(),
+            &vote.to_account_info(),
+            seeds,
+            vote.amount,
+        )?;
+
+        Ok(())
+    }
+
+    /// Proposes paying `amount_lamports` from the treasury to `recipient`, e.g. a bounty or an
+    /// infra bill. Only the admin and council members may propose.
+    pub fn propose_treasury_spend(
+        ctx: Context<ProposeTreasurySpend>,
+        proposal_id: u64,
+        recipient: Pubkey,
+        amount_lamports: u64,
+        memo: String,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let proposer = ctx.accounts.proposer.key();
+        let clock = Clock::get()?;
+
+        // Validate the proposal
+        require!(
+            treasury_signer(&ctx.accounts.config, ctx.accounts.council.as_deref(), &proposer).is_some(),
+            ErrorCode::NotTreasurySigner
+        );
+        require!(amount_lamports > 0, ErrorCode::InvalidSpendAmount);
+        require!(memo.len() <= TreasuryProposal::MAX_MEMO_LEN, ErrorCode::SpendMemoTooLong);
+
+        proposal.proposer = proposer;
+        proposal.proposal_id = proposal_id;
+        proposal.recipient = recipient;
+        proposal.amount_lamports = amount_lamports;
+        proposal.memo = memo;
+        proposal.approvals = Vec::new();
+        proposal.status = SpendStatus::Open;
+        proposal.created_at = clock.unix_timestamp;
+        proposal.bump = ctx.bumps.proposal;
+
+        emit!(TreasurySpendStatusChanged {
+            proposal: proposal.key(),
+            actor: proposer,
+            status: SpendStatus::Open,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Approves a spend. The admin's approval alone is enough; otherwise `threshold` current
+    /// council members must approve.
+    pub fn approve_treasury_spend(ctx: Context<ApproveTreasurySpend>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let approver = ctx.accounts.approver.key();
+        let council = ctx.accounts.council.as_deref();
+        let clock = Clock::get()?;
+
+        let signer = treasury_signer(&ctx.accounts.config, council, &approver).ok_or(ErrorCode::NotTreasurySigner)?;
+        require!(proposal.status == SpendStatus::Open, ErrorCode::SpendNotOpen);
+        require!(!proposal.approvals.contains(&approver), ErrorCode::SpendAlreadyApproved);
+
+        let approved = match signer {
+            TreasurySigner::Admin => true,
+            TreasurySigner::CouncilMember(council) => {
+                // Members who left the council since approving no longer count
+                proposal.approvals.push(approver);
+                let count = proposal.approvals.iter().filter(|key| council.members.contains(key)).count();
+                count >= council.threshold as usize
+            }
+        };
+        if !approved {
+            return Ok(());
+        }
+
+        proposal.status = SpendStatus::Approved;
+
+        emit!(TreasurySpendStatusChanged {
+            proposal: proposal.key(),
+            actor: approver,
+            status: SpendStatus::Approved,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out an approved spend. Permissionless, so a grant recipient can collect it. The
+    /// treasury always keeps its rent-exempt minimum.
+    pub fn execute_treasury_spend(ctx: Context<ExecuteTreasurySpend>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let clock = Clock::get()?;
+
+        require!(proposal.status == SpendStatus::Approved, ErrorCode::SpendNotApproved);
+        let available = treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(treasury.data_len()));
+        require!(available >= proposal.amount_lamports, ErrorCode::TreasuryInsufficientFunds);
+
+        **treasury.try_borrow_mut_lamports()? -= proposal.amount_lamports;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += proposal.amount_lamports;
+
+        proposal.status = SpendStatus::Executed;
+
+        emit!(TreasurySpendStatusChanged {
+            proposal: proposal.key(),
+            actor: ctx.accounts.executor.key(),
+            status: SpendStatus::Executed,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws a spend that hasn't been paid out; the proposer or the admin may cancel it.
+    pub fn cancel_treasury_spend(ctx: Context<CancelTreasurySpend>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let authority = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        require!(
+            authority == proposal.proposer || authority == ctx.accounts.config.admin,
+            ErrorCode::NotTreasurySigner
+        );
+        require!(
+            matches!(proposal.status, SpendStatus::Open | SpendStatus::Approved),
+            ErrorCode::SpendNotOpen
+        );
+
+        proposal.status = SpendStatus::Cancelled;
+
+        emit!(TreasurySpendStatusChanged {
+            proposal: proposal.key(),
+            actor: authority,
+            status: SpendStatus::Cancelled,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the protocol fee schedule with the fee off. Fees are then set through
+    /// `queue_config_change`, so users see them coming.
+    pub fn init_fee_schedule(ctx: Context<InitFeeSchedule>) -> Result<()> {
+        let schedule = &mut ctx.accounts.fee_schedule;
+
+        schedule.send_fee_lamports = 0;
+        schedule.tiers = Vec::new();
+        schedule.pass_discount_bps = 0;
+        schedule.app_share_bps = 0;
+        schedule.bump = ctx.bumps.fee_schedule;
+
+        Ok(())
+    }
+
+    pub fn set_pass_offer(ctx: Context<SetPassOffer>, price_lamports: u64, duration_secs: i64) -> Result<()> {
+        let offer = &mut ctx.accounts.pass_offer;
+
+        require!(duration_secs > 0, ErrorCode::InvalidPassOffer);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetPassOffer,
+            offer.key(),
+            &(),
+            &(price_lamports, duration_secs),
+        )?;
+        offer.price_lamports = price_lamports;
+        offer.duration_secs = duration_secs;
+        offer.bump = ctx.bumps.pass_offer;
+
+        Ok(())
+    }
+
+    pub fn update_pass_offer(ctx: Context<UpdatePassOffer>, price_lamports: u64, duration_secs: i64) -> Result<()> {
+        let offer = &mut ctx.accounts.pass_offer;
+
+        require!(duration_secs > 0, ErrorCode::InvalidPassOffer);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetPassOffer,
+            offer.key(),
+            &(offer.price_lamports, offer.duration_secs),
+            &(price_lamports, duration_secs),
+        )?;
+        offer.price_lamports = price_lamports;
+        offer.duration_secs = duration_secs;
+
+        Ok(())
+    }
+
+    /// Sells a protocol pass: a one-of-one NFT whose record carries its expiry. Whoever holds the
+    /// NFT gets the pass perks until then. Proceeds go to the treasury.
+    ///
+    /// The pass is a classic SPL Token NFT rather than a Token-2022 mint with the expiry in its
+    /// metadata: the spl-token-2022 release anchor-spl 0.28 builds against has no metadata
+    /// extension. The expiry lives in the program-owned `ProtocolPass` instead, which is also
+    /// what `renew_pass` extends and what the sends that grant perks read.
+    pub fn mint_pass(ctx: Context<MintPass>) -> Result<()> {
+        let offer = &ctx.accounts.pass_offer;
+        let pass = &mut ctx.accounts.pass;
+        let buyer = &ctx.accounts.buyer;
+        let clock = Clock::get()?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: buyer.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            offer.price_lamports,
+        )?;
+
+        // Mint the single token, then drop the mint authority so the supply stays at one
+        let signer: &[&[&[u8]]] = &[&[b"pass_offer", &[offer.bump]]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.pass_mint.to_account_info(),
+                    to: ctx.accounts.pass_token.to_account_info(),
+                    authority: offer.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+        fail_point(ctx.remaining_accounts, ChaosPoint::AfterPassMint)?;
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: offer.to_account_info(),
+                    account_or_mint: ctx.accounts.pass_mint.to_account_info(),
+                },
+                signer,
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+
+        pass.mint = ctx.accounts.pass_mint.key();
+        pass.buyer = buyer.key();
+        pass.expires_at = clock.unix_timestamp + offer.duration_secs;
+        pass.bump = ctx.bumps.pass;
+
+        emit!(PassPurchased {
+            pass: pass.key(),
+            mint: pass.mint,
+            buyer: buyer.key(),
+            expires_at: pass.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Extends a pass by one offer period from its expiry, or from now if it has lapsed.
+    /// Anyone may pay, so a partner can keep a user's pass running.
+    pub fn renew_pass(ctx: Context<RenewPass>) -> Result<()> {
+        let offer = &ctx.accounts.pass_offer;
+        let pass = &mut ctx.accounts.pass;
+        let payer = &ctx.accounts.payer;
+        let clock = Clock::get()?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            offer.price_lamports,
+        )?;
+
+        pass.expires_at = pass.expires_at.max(clock.unix_timestamp) + offer.duration_secs;
+
+        emit!(PassPurchased {
+            pass: pass.key(),
+            mint: pass.mint,
+            buyer: payer.key(),
+            expires_at: pass.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Registers a front-end under a unique name. Sends made through it are attributed to it
+    /// and earn it the fee schedule's app share.
+    pub fn register_app(ctx: Context<RegisterApp>, name: String) -> Result<()> {
+        let app = &mut ctx.accounts.app;
+        let clock = Clock::get()?;
+
+        // Validate the name
+        require!(!name.is_empty() && name.len() <= App::MAX_NAME_LEN, ErrorCode::InvalidAppName);
+
+        app.authority = ctx.accounts.authority.key();
+        app.name = name;
+        app.messages_sent = 0;
+        app.fees_lamports = 0;
+        app.revenue_owed_lamports = 0;
+        app.revenue_paid_lamports = 0;
+        app.registered_at = clock.unix_timestamp;
+        app.bump = ctx.bumps.app;
+
+        Ok(())
+    }
+
+    /// Pays an app's accrued revenue share out of the treasury, as far as the treasury can
+    /// cover it above its rent-exempt minimum.
+    pub fn claim_app_revenue(ctx: Context<ClaimAppRevenue>) -> Result<()> {
+        let app = &mut ctx.accounts.app;
+        let treasury = ctx.accounts.treasury.to_account_info();
+
+        let available = treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(treasury.data_len()));
+        let amount = app.revenue_owed_lamports.min(available);
+        require!(amount > 0, ErrorCode::NoRevenueToClaim);
+
+        **treasury.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += amount;
+
+        app.revenue_owed_lamports -= amount;
+        app.revenue_paid_lamports += amount;
+
+        emit!(AppRevenueClaimed {
+            app: app.key(),
+            amount,
+            remaining: app.revenue_owed_lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Restricts a beta feature to the listed apps during its rollout.
+    pub fn set_feature_gate(ctx: Context<SetFeatureGate>, feature: BetaFeature, apps: Vec<Pubkey>) -> Result<()> {
+        let gate = &mut ctx.accounts.feature_gate;
+
+        require!(apps.len() <= FeatureGate::MAX_APPS, ErrorCode::TooManyGatedApps);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetFeatureGate,
+            gate.key(),
+            &(),
+            &apps,
+        )?;
+        gate.feature = feature;
+        gate.apps = apps;
+        gate.bump = ctx.bumps.feature_gate;
+
+        Ok(())
+    }
+
+    pub fn update_feature_gate(ctx: Context<UpdateFeatureGate>, apps: Vec<Pubkey>) -> Result<()> {
+        let gate = &mut ctx.accounts.feature_gate;
+
+        require!(apps.len() <= FeatureGate::MAX_APPS, ErrorCode::TooManyGatedApps);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetFeatureGate,
+            gate.key(),
+            &gate.apps,
+            &apps,
+        )?;
+        gate.apps = apps;
+
+        Ok(())
+    }
+
+    /// Opens the feature to everyone once its rollout is done.
+    pub fn remove_feature_gate(ctx: Context<RemoveFeatureGate>) -> Result<()> {
+        let gate = &ctx.accounts.feature_gate;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::RemoveFeatureGate,
+            gate.key(),
+            &gate.apps,
+            &(),
+        )
+    }
+
+    /// Publishes a payload layout under `name` and `version`. Version 1 claims the name; later
+    /// versions must be registered by the same authority, which `previous` proves.
+    pub fn register_schema(
+        ctx: Context<RegisterSchema>,
+        name: String,
+        version: u16,
+        format: SchemaFormat,
+        layout_hash: [u8; 32],
+        uri: String,
+    ) -> Result<()> {
+        let schema = &mut ctx.accounts.schema;
+        let authority = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        // Validate the name and version chain
+        require!(!name.is_empty() && name.len() <= Schema::MAX_NAME_LEN, ErrorCode::InvalidSchema);
+        require!(uri.len() <= Schema::MAX_URI_LEN, ErrorCode::InvalidSchema);
+        require!(version > 0, ErrorCode::InvalidSchema);
+        if version > 1 {
+            let previous = ctx.accounts.previous.as_ref().ok_or(ErrorCode::InvalidSchema)?;
+            require!(
+                previous.name == name && previous.version == version - 1,
+                ErrorCode::InvalidSchema
+            );
+            require_keys_eq!(previous.authority, authority, ErrorCode::NotSchemaAuthority);
+        }
+
+        schema.authority = authority;
+        schema.name = name;
+        schema.version = version;
+        schema.format = format;
+        schema.layout_hash = layout_hash;
+        schema.uri = uri;
+        schema.created_at = clock.unix_timestamp;
+        schema.bump = ctx.bumps.schema;
+
+        Ok(())
+    }
+
+    /// Sends a machine-readable payload laid out by a registered schema. `nonce` lets one sender
+    /// keep several open to the same recipient.
+    pub fn send_structured_message(
+        ctx: Context<SendStructuredMessage>,
+        recipient: Pubkey,
+        nonce: u64,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        let message = &mut ctx.accounts.message;
+        let clock = Clock::get()?;
+
+        // Validate the payload
+        require!(!payload.is_empty(), ErrorCode::EmptyMessage);
+        require!(payload.len() <= StructuredMessage::MAX_PAYLOAD_LEN, ErrorCode::MessageTooLong);
+
+        message.sender = ctx.accounts.sender.key();
+        message.recipient = recipient;
+        message.nonce = nonce;
+        message.schema = ctx.accounts.schema.key();
+        message.payload = payload;
+        message.timestamp = clock.unix_timestamp;
+        message.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.sender_membership)?;
+        message.bump = ctx.bumps.message;
+
+        emit!(StructuredMessageSent {
+            message: message.key(),
+            sender: message.sender,
+            recipient,
+            schema: message.schema,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_structured_message(ctx: Context<CloseStructuredMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+
+        // Either side of the conversation may drop it
+        let closer = ctx.accounts.closer.key();
+        require!(closer == message.sender || closer == message.recipient, ErrorCode::NotConversationParticipant);
+
+        Ok(())
+    }
+
+    /// Attaches a note to the token transfer immediately before this instruction, replacing an
+    /// SPL Memo "payment note". The transfer must be signed by the sender and pay into a token
+    /// account of `recipient`; its details are copied onto the note, so the note and the
+    /// transaction signature that created it identify the payment.
+    pub fn attach_payment_note(ctx: Context<AttachPaymentNote>, nonce: u64, recipient: Pubkey, note: String) -> Result<()> {
+        let payment_note = &mut ctx.accounts.payment_note;
+        let sender = ctx.accounts.sender.key();
+        let clock = Clock::get()?;
+
+        // Validate the note
+        require!(!note.is_empty(), ErrorCode::EmptyMessage);
+        require!(note.len() <= PaymentNote::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+
+        let transfer = preceding_token_transfer(&ctx.accounts.instructions)?;
+        require_keys_eq!(transfer.authority, sender, ErrorCode::PaymentTransferNotFound);
+
+        // The destination must be a token account owned by the recipient
+        let destination = &ctx.accounts.destination;
+        require_keys_eq!(destination.key(), transfer.destination, ErrorCode::PaymentTransferNotFound);
+        require!(
+            *destination.owner == token::ID || *destination.owner == anchor_spl::token_2022::ID,
+            ErrorCode::PaymentTransferNotFound
+        );
+        let data = destination.try_borrow_data()?;
+        // Token account: mint, owner, amount, ...
+        require!(data.len() >= 64 && data[32..64] == recipient.to_bytes(), ErrorCode::PaymentTransferNotFound);
+        let mint = Pubkey::try_from(&data[..32]).unwrap();
+        drop(data);
+
+        payment_note.sender = sender;
+        payment_note.recipient = recipient;
+        payment_note.nonce = nonce;
+        payment_note.token_program = transfer.program_id;
+        payment_note.mint = mint;
+        payment_note.source = transfer.source;
+        payment_note.destination = transfer.destination;
+        payment_note.amount = transfer.amount;
+        payment_note.note = note;
+        payment_note.timestamp = clock.unix_timestamp;
+        payment_note.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.sender_membership)?;
+        payment_note.bump = ctx.bumps.payment_note;
+
+        emit!(PaymentNoteAttached {
+            payment_note: payment_note.key(),
+            sender,
+            recipient,
+            mint,
+            amount: transfer.amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Tips `recipient` in their profile's preferred mint, paid from any token the sender
+    /// holds, by running a Jupiter route whose output lands in the recipient's token account.
+    /// The route's accounts are the remaining accounts. Slippage is checked here on the actual
+    /// balance changes rather than trusted to the route.
+    pub fn swap_and_tip<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapAndTip<'info>>,
+        recipient: Pubkey,
+        max_amount_in: u64,
+        min_amount_out: u64,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        require!(min_amount_out > 0, ErrorCode::InvalidSwapLimits);
+        require_no_preceding_drain(&ctx.accounts.instructions, &sender.key())?;
+        if let Some(profile) = Profile::load(&ctx.accounts.recipient_profile)? {
+            require!(
+                profile.preferred_tip_mint == Pubkey::default()
+                    || profile.preferred_tip_mint == ctx.accounts.recipient_token_account.mint,
+                ErrorCode::NotPreferredTipMint
+            );
+        }
+
+        let paid_before = ctx.accounts.sender_source.amount;
+        let received_before = ctx.accounts.recipient_token_account.amount;
+
+        let route = Instruction {
+            program_id: JUPITER_PROGRAM_ID,
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.key(),
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: route_data,
+        };
+        invoke(&route, ctx.remaining_accounts)?;
+
+        ctx.accounts.sender_source.reload()?;
+        ctx.accounts.recipient_token_account.reload()?;
+        let paid = paid_before.saturating_sub(ctx.accounts.sender_source.amount);
+        let received = ctx.accounts.recipient_token_account.amount.saturating_sub(received_before);
+        require!(paid <= max_amount_in, ErrorCode::SlippageExceeded);
+        require!(received >= min_amount_out, ErrorCode::SlippageExceeded);
+
+        emit!(TipSent {
+            sender: sender.key(),
+            recipient,
+            mint: ctx.accounts.recipient_token_account.mint,
+            amount: received,
+            paid_mint: ctx.accounts.sender_source.mint,
+            paid_amount: paid,
+            charity: Pubkey::default(),
+            charity_amount: 0,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_profile(ctx: Context<SetProfile>, preferred_tip_mint: Pubkey) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+
+        profile.owner = ctx.accounts.owner.key();
+        profile.preferred_tip_mint = preferred_tip_mint;
+        profile.links = Vec::new();
+        profile.bump = ctx.bumps.profile;
+
+        Ok(())
+    }
+
+    pub fn update_profile(ctx: Context<UpdateProfile>, preferred_tip_mint: Pubkey) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+
+        profile.preferred_tip_mint = preferred_tip_mint;
+
+        Ok(())
+    }
+
+    /// Opts the sender into rounding tips up to a multiple of `unit_lamports`, with the
+    /// difference going to the charity in the config.
+    pub fn set_round_up(ctx: Context<SetRoundUp>, unit_lamports: u64) -> Result<()> {
+        let round_up = &mut ctx.accounts.round_up;
+
+        round_up.owner = ctx.accounts.owner.key();
+        round_up.unit_lamports = unit_lamports;
+        round_up.bump = ctx.bumps.round_up;
+
+        Ok(())
+    }
+
+    /// A zero unit turns round-ups off.
+    pub fn update_round_up(ctx: Context<UpdateRoundUp>, unit_lamports: u64) -> Result<()> {
+        let round_up = &mut ctx.accounts.round_up;
+
+        round_up.unit_lamports = unit_lamports;
+
+        Ok(())
+    }
+
+    /// Tips `recipient` in lamports, rounding up for charity if the sender opted in.
+    pub fn send_tip(ctx: Context<SendTip>, recipient: Pubkey, amount_lamports: u64) -> Result<()> {
+        let sender = &ctx.accounts.sender;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        require!(amount_lamports > 0, ErrorCode::InvalidTipAmount);
+        require_no_preceding_drain(&ctx.accounts.instructions, &sender.key())?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: sender.to_account_info(),
+                    to: ctx.accounts.recipient_wallet.to_account_info(),
+                },
+            ),
+            amount_lamports,
+        )?;
+
+        // Round-ups only apply while the config names a charity
+        let unit = match ctx.accounts.round_up.as_ref() {
+            Some(round_up) if config.charity != Pubkey::default() => round_up.unit_lamports,
+            _ => 0,
+        };
+        let charity_amount = if unit > 0 { amount_lamports.div_ceil(unit) * unit - amount_lamports } else { 0 };
+        if charity_amount > 0 {
+            let charity = ctx.accounts.charity.as_ref().ok_or(ErrorCode::CharityRequired)?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: sender.to_account_info(),
+                        to: charity.to_account_info(),
+                    },
+                ),
+                charity_amount,
+            )?;
+        }
+
+        if let Some(stats) = ctx.accounts.epoch_stats.as_mut() {
+            stats.record_tip(amount_lamports, clock.epoch)?;
+        }
+
+        // Tips above the threshold can come with a compressed patron receipt for the tipper
+        if let Some(patron) = ctx.accounts.patron_config.as_mut() {
+            require!(
+                patron.threshold_lamports > 0 && amount_lamports > patron.threshold_lamports,
+                ErrorCode::PatronThresholdNotMet
+            );
+            let missing = || error!(ErrorCode::PatronAccountsMissing);
+            let message = ctx.accounts.tip_message.as_ref().ok_or_else(missing)?;
+            let merkle_tree = ctx.accounts.merkle_tree.as_ref().ok_or_else(missing)?;
+            require_keys_eq!(message.sender, recipient, ErrorCode::InvalidTipDestination);
+            require_keys_eq!(merkle_tree.key(), patron.merkle_tree, ErrorCode::PatronAccountsMissing);
+            require!(ctx.accounts.bubblegum_program.is_some(), ErrorCode::PatronAccountsMissing);
+
+            mint_patron_receipt(
+                patron,
+                ctx.accounts.tree_authority.as_ref().ok_or_else(missing)?,
+                merkle_tree,
+                &sender.to_account_info(),
+                ctx.accounts.log_wrapper.as_ref().ok_or_else(missing)?,
+                ctx.accounts.compression_program.as_ref().ok_or_else(missing)?,
+                &ctx.accounts.system_program.to_account_info(),
+                recipient,
+                message.key(),
+                amount_lamports,
+            )?;
+            emit!(PatronReceiptMinted {
+                tipper: sender.key(),
+                recipient,
+                message: message.key(),
+                amount_lamports,
+                receipt_index: patron.receipts_minted,
+                timestamp: clock.unix_timestamp,
+            });
+            patron.receipts_minted += 1;
+        }
+
+        emit!(TipSent {
+            sender: sender.key(),
+            recipient,
+            mint: Pubkey::default(),
+            amount: amount_lamports,
+            paid_mint: Pubkey::default(),
+            paid_amount: amount_lamports,
+            charity: if charity_amount > 0 { config.charity } else { Pubkey::default() },
+            charity_amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Locks `total_lamports` that vest to the recipient over `duration_secs` of active
+    /// conversation. With an idle timeout, vesting stops once either side has been silent that
+    /// long and picks up again when both have pinged.
+    pub fn open_tip_stream(
+        ctx: Context<OpenTipStream>,
+        recipient: Pubkey,
+        stream_id: u64,
+        total_lamports: u64,
+        duration_secs: i64,
+        idle_timeout_secs: i64,
+    ) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        // Validate the stream terms
+        require!(recipient != sender.key(), ErrorCode::InvalidTipStream);
+        require!(total_lamports > 0 && duration_secs > 0 && idle_timeout_secs >= 0, ErrorCode::InvalidTipStream);
+        require_no_preceding_drain(&ctx.accounts.instructions, &sender.key())?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: sender.to_account_info(),
+                    to: stream.to_account_info(),
+                },
+            ),
+            total_lamports,
+        )?;
+
+        stream.sender = sender.key();
+        stream.recipient = recipient;
+        stream.stream_id = stream_id;
+        stream.total_lamports = total_lamports;
+        stream.withdrawn_lamports = 0;
+        stream.duration_secs = duration_secs;
+        stream.idle_timeout_secs = idle_timeout_secs;
+        stream.accrued_secs = 0;
+        stream.resumed_at = clock.unix_timestamp;
+        stream.paused = false;
+        stream.last_active_at = [clock.unix_timestamp; 2];
+        stream.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.sender_membership)?;
+        stream.bump = ctx.bumps.stream;
+
+        Ok(())
+    }
+
+    /// Marks the caller's side of the conversation as active.
+    pub fn ping_tip_stream(ctx: Context<PingTipStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let now = Clock::get()?.unix_timestamp;
+
+        let side = stream.side_of(&ctx.accounts.participant.key())?;
+        stream.checkpoint(now);
+        stream.last_active_at[side] = now;
+
+        Ok(())
+    }
+
+    pub fn pause_tip_stream(ctx: Context<UpdateTipStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+
+        require!(!stream.paused, ErrorCode::TipStreamPaused);
+
+        stream.checkpoint(Clock::get()?.unix_timestamp);
+        stream.paused = true;
+
+        Ok(())
+    }
+
+    pub fn resume_tip_stream(ctx: Context<UpdateTipStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(stream.paused, ErrorCode::TipStreamNotPaused);
+
+        stream.paused = false;
+        stream.resumed_at = now;
+        stream.last_active_at[0] = now;
+
+        Ok(())
+    }
+
+    /// Pays the recipient everything vested so far.
+    pub fn withdraw_tip_stream(ctx: Context<WithdrawTipStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+
+        stream.checkpoint(Clock::get()?.unix_timestamp);
+        let amount = stream.vested_lamports() - stream.withdrawn_lamports;
+        require!(amount > 0, ErrorCode::NothingVested);
+
+        **stream.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+        stream.withdrawn_lamports += amount;
+
+        Ok(())
+    }
+
+    /// Ends the stream: the recipient gets what has vested, the sender the rest and the rent.
+    pub fn cancel_tip_stream(ctx: Context<CancelTipStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+
+        stream.checkpoint(Clock::get()?.unix_timestamp);
+        let amount = stream.vested_lamports() - stream.withdrawn_lamports;
+        if amount > 0 {
+            **stream.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+            stream.withdrawn_lamports += amount;
+        }
+
+        Ok(())
+    }
+
+    /// Requests a paid consultation. The fee for the slot is escrowed on the booking until the
+    /// consultant is paid or the client refunded.
+    pub fn propose_booking(
+        ctx: Context<ProposeBooking>,
+        consultant: Pubkey,
+        booking_id: u64,
+        start_at: i64,
+        duration_mins: u16,
+        rate_lamports_per_hour: u64,
+        note: String,
+    ) -> Result<()> {
+        let booking = &mut ctx.accounts.booking;
+        let client = &ctx.accounts.client;
+        let clock = Clock::get()?;
+
+        // Validate the slot and terms
+        require!(consultant != client.key(), ErrorCode::InvalidBooking);
+        require!(start_at > clock.unix_timestamp, ErrorCode::InvalidBooking);
+        require!(duration_mins > 0, ErrorCode::InvalidBooking);
+        require!(note.len() <= BookingMessage::MAX_NOTE_LEN, ErrorCode::MessageTooLong);
+        let escrow = (rate_lamports_per_hour as u128 * duration_mins as u128 / 60) as u64;
+
+        // A consultant who publishes availability can only be booked into open slots
+        if let Some(availability) = Availability::load(&ctx.accounts.consultant_availability)? {
+            require!(
+                availability.is_open(start_at, start_at + duration_mins as i64 * 60),
+                ErrorCode::SlotUnavailable
+            );
+        }
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: client.to_account_info(),
+                    to: booking.to_account_info(),
+                },
+            ),
+            escrow,
+        )?;
+
+        booking.client = client.key();
+        booking.consultant = consultant;
+        booking.booking_id = booking_id;
+        booking.start_at = start_at;
+        booking.duration_mins = duration_mins;
+        booking.rate_lamports_per_hour = rate_lamports_per_hour;
+        booking.escrow_lamports = escrow;
+        booking.note = note;
+        booking.status = BookingStatus::Proposed;
+        booking.timestamp = clock.unix_timestamp;
+        booking.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.client_membership)?;
+        booking.bump = ctx.bumps.booking;
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: client.key(),
+            status: BookingStatus::Proposed,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_booking(ctx: Context<RespondToBooking>) -> Result<()> {
+        let booking = &mut ctx.accounts.booking;
+        let clock = Clock::get()?;
+
+        require!(booking.status == BookingStatus::Proposed, ErrorCode::BookingNotProposed);
+        require!(booking.start_at > clock.unix_timestamp, ErrorCode::BookingSlotPassed);
+
+        booking.status = BookingStatus::Accepted;
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: booking.consultant,
+            status: BookingStatus::Accepted,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// The consultant turns the request down; the escrow and rent go back to the client.
+    pub fn decline_booking(ctx: Context<CloseBookingByConsultant>) -> Result<()> {
+        let booking = &ctx.accounts.booking;
+
+        require!(booking.status == BookingStatus::Proposed, ErrorCode::BookingNotProposed);
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: booking.consultant,
+            status: BookingStatus::Declined,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// The consultant gives the money back on an accepted or disputed booking, e.g. when they
+    /// can't make it.
+    pub fn refund_booking(ctx: Context<CloseBookingByConsultant>) -> Result<()> {
+        let booking = &ctx.accounts.booking;
+
+        require!(
+            matches!(booking.status, BookingStatus::Accepted | BookingStatus::Disputed),
+            ErrorCode::BookingNotAccepted
+        );
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: booking.consultant,
+            status: BookingStatus::Refunded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// The client withdraws a request the consultant hasn't answered.
+    pub fn cancel_booking(ctx: Context<CancelBooking>) -> Result<()> {
+        let booking = &ctx.accounts.booking;
+
+        require!(booking.status == BookingStatus::Proposed, ErrorCode::BookingNotProposed);
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: booking.client,
+            status: BookingStatus::Cancelled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the consultant once the session is over. The client confirms; if they stay silent,
+    /// the consultant can settle it themselves after `CLAIM_GRACE_SECS`, unless the client
+    /// disputed it in the meantime. A client can also confirm a booking they disputed.
+    pub fn complete_booking(ctx: Context<CompleteBooking>) -> Result<()> {
+        let booking = &ctx.accounts.booking;
+        let authority = ctx.accounts.authority.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            matches!(booking.status, BookingStatus::Accepted | BookingStatus::Disputed),
+            ErrorCode::BookingNotAccepted
+        );
+        if authority == booking.client {
+            require!(now >= booking.start_at, ErrorCode::BookingNotOver);
+        } else {
+            require_keys_eq!(authority, booking.consultant, ErrorCode::NotBookingParty);
+            require!(booking.status != BookingStatus::Disputed, ErrorCode::BookingDisputed);
+            require!(now >= booking.ends_at() + BookingMessage::CLAIM_GRACE_SECS, ErrorCode::BookingNotOver);
+        }
+
+        **booking.to_account_info().try_borrow_mut_lamports()? -= booking.escrow_lamports;
+        **ctx.accounts.consultant.try_borrow_mut_lamports()? += booking.escrow_lamports;
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: authority,
+            status: BookingStatus::Completed,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// The client contests a session, from its start until the consultant could settle it alone.
+    /// This freezes the escrow until the client confirms, the consultant refunds or the
+    /// moderator rules.
+    pub fn dispute_booking(ctx: Context<DisputeBooking>) -> Result<()> {
+        let booking = &mut ctx.accounts.booking;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(booking.status == BookingStatus::Accepted, ErrorCode::BookingNotAccepted);
+        require!(now >= booking.start_at, ErrorCode::BookingNotOver);
+        require!(now < booking.ends_at() + BookingMessage::CLAIM_GRACE_SECS, ErrorCode::DisputeWindowClosed);
+
+        booking.status = BookingStatus::Disputed;
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: booking.client,
+            status: BookingStatus::Disputed,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// The moderator splits a disputed booking's escrow, giving the consultant
+    /// `consultant_share_bps` of it and the client the rest.
+    pub fn rule_booking_dispute(ctx: Context<RuleBookingDispute>, consultant_share_bps: u16) -> Result<()> {
+        let booking = &ctx.accounts.booking;
+        let moderator = ctx.accounts.moderator.key();
+
+        require!(is_moderator(&ctx.accounts.config, &moderator), ErrorCode::NotModerator);
+        require!(booking.status == BookingStatus::Disputed, ErrorCode::BookingNotDisputed);
+        require!(consultant_share_bps <= 10_000, ErrorCode::InvalidRuling);
+
+        // The client's share leaves with the rent when the booking closes
+        let consultant_amount = (booking.escrow_lamports as u128 * consultant_share_bps as u128 / 10_000) as u64;
+        **booking.to_account_info().try_borrow_mut_lamports()? -= consultant_amount;
+        **ctx.accounts.consultant.try_borrow_mut_lamports()? += consultant_amount;
+
+        emit!(BookingStatusChanged {
+            booking: booking.key(),
+            actor: moderator,
+            status: if consultant_amount == 0 { BookingStatus::Refunded } else { BookingStatus::Completed },
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_availability(ctx: Context<SetAvailability>, utc_offset_mins: i16, slots: [u8; 42]) -> Result<()> {
+        let availability = &mut ctx.accounts.availability;
+
+        Availability::validate(utc_offset_mins)?;
+
+        availability.owner = ctx.accounts.owner.key();
+        availability.utc_offset_mins = utc_offset_mins;
+        availability.slots = slots;
+        availability.bump = ctx.bumps.availability;
+
+        Ok(())
+    }
+
+    pub fn update_availability(ctx: Context<UpdateAvailability>, utc_offset_mins: i16, slots: [u8; 42]) -> Result<()> {
+        let availability = &mut ctx.accounts.availability;
+
+        Availability::validate(utc_offset_mins)?;
+
+        availability.utc_offset_mins = utc_offset_mins;
+        availability.slots = slots;
+
+        Ok(())
+    }
+
+    pub fn clear_availability(_ctx: Context<ClearAvailability>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Replaces the profile's links. A link that keeps its label and URI keeps its verification;
+    /// anything new or edited starts out unverified.
+    pub fn set_profile_links(ctx: Context<UpdateProfile>, links: Vec<ProfileLink>) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+
+        // Validate links
+        require!(links.len() <= Profile::MAX_LINKS, ErrorCode::TooManyProfileLinks);
+        let mut updated = Vec::with_capacity(links.len());
+        for link in links {
+            ProfileLink::validate(&link.label, &link.uri)?;
+            let kept = profile.links.iter().find(|old| old.label == link.label && old.uri == link.uri);
+            updated.push(ProfileLink {
+                verification: kept.map_or(LinkVerification::Unverified, |old| old.verification),
+                verified_at: kept.map_or(0, |old| old.verified_at),
+                ..link
+            });
+        }
+        profile.links = updated;
+
+        Ok(())
+    }
+
+    /// Records the attestor's off-chain check of a link, or withdraws it with `Unverified`.
+    /// `uri` must match the link as it stands, so an edit made in the meantime isn't verified.
+    pub fn set_link_verification(
+        ctx: Context<SetLinkVerification>,
+        index: u8,
+        uri: String,
+        verification: LinkVerification,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        let clock = Clock::get()?;
+
+        let link = profile.links.get_mut(index as usize).ok_or(ErrorCode::ProfileLinkChanged)?;
+        require!(link.uri == uri, ErrorCode::ProfileLinkChanged);
+
+        link.verification = verification;
+        link.verified_at = if verification == LinkVerification::Unverified { 0 } else { clock.unix_timestamp };
+
+        emit!(ProfileLinkVerificationChanged {
+            owner: profile.owner,
+            uri,
+            verification,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Registers a white-label deployment. Its messages and the other accounts listed on
+    /// `Namespace` are derived under the namespace address, so they can't collide with those of
+    /// the public network or other deployments.
+    pub fn create_namespace(ctx: Context<CreateNamespace>, name: String, namespace_admin: Pubkey) -> Result<()> {
+        let namespace = &mut ctx.accounts.namespace;
+
+        // Validate name
+        require!(
+            !name.is_empty() && name.len() <= Namespace::MAX_NAME_LEN,
+            ErrorCode::InvalidNamespace
+        );
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::CreateNamespace,
+            namespace.key(),
+            &(),
+            &(&name, namespace_admin),
+        )?;
+        namespace.admin = namespace_admin;
+        namespace.branding = NamespaceBranding {
+            display_name: name.clone(),
+            ..NamespaceBranding::default()
+        };
+        namespace.name = name;
+        namespace.federation = FederationPolicy::Open;
+        namespace.peers = Vec::new();
+        namespace.created_at = Clock::get()?.unix_timestamp;
+        namespace.bump = ctx.bumps.namespace;
+
+        Ok(())
+    }
+
+    pub fn set_namespace_admin(ctx: Context<UpdateNamespace>, new_admin: Pubkey) -> Result<()> {
+        let namespace = &mut ctx.accounts.namespace;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetNamespaceAdmin,
+            namespace.key(),
+            &namespace.admin,
+            &new_admin,
+        )?;
+        namespace.admin = new_admin;
+
+        Ok(())
+    }
+
+    pub fn set_namespace_branding(ctx: Context<UpdateNamespace>, branding: NamespaceBranding) -> Result<()> {
+        let namespace = &mut ctx.accounts.namespace;
+
+        branding.validate()?;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetNamespaceBranding,
+            namespace.key(),
+            &namespace.branding,
+            &branding,
+        )?;
+        namespace.branding = branding;
+
+        Ok(())
+    }
+
+    /// Declares which namespaces members may message; an empty allow-list closes the namespace.
+    pub fn set_federation(ctx: Context<UpdateNamespace>, federation: FederationPolicy, peers: Vec<Pubkey>) -> Result<()> {
+        let namespace = &mut ctx.accounts.namespace;
+
+        require!(peers.len() <= Namespace::MAX_PEERS, ErrorCode::TooManyFederationPeers);
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetFederation,
+            namespace.key(),
+            &(namespace.federation, &namespace.peers),
+            &(federation, &peers),
+        )?;
+        namespace.federation = federation;
+        namespace.peers = peers;
+
+        Ok(())
+    }
+
+    /// Enrols a user in the namespace. The user co-signs, since membership decides who they
+    /// can message. A user belongs to at most one namespace.
+    pub fn add_namespace_member(ctx: Context<AddNamespaceMember>) -> Result<()> {
+        let member = &mut ctx.accounts.member;
+
+        member.user = ctx.accounts.user.key();
+        member.namespace = ctx.accounts.namespace.key();
+        member.bump = ctx.bumps.member;
+
+        Ok(())
+    }
+
+    pub fn remove_namespace_member(_ctx: Context<RemoveNamespaceMember>) -> Result<()> {
+        Ok(())
+    }
+
+    /// The user leaves their namespace and is back on the public network. The rent goes back
+    /// to the namespace admin, who paid it.
+    pub fn leave_namespace(_ctx: Context<LeaveNamespace>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Creates the admin audit log. Admin instructions fail until it exists.
+    pub fn init_audit_log(ctx: Context<InitAuditLog>) -> Result<()> {
+        let log = &mut ctx.accounts.audit_log;
+
+        log.entries = Vec::new();
+        log.next_sequence = 0;
+        log.bump = ctx.bumps.audit_log;
+
+        Ok(())
+    }
+
+    /// Queues a sensitive config change. It can be executed once the config's timelock has
+    /// passed, giving users notice before fees, bounties, moderation or the pause switch change.
+    pub fn queue_config_change(ctx: Context<QueueConfigChange>, change_id: u64, change: ConfigChange) -> Result<()> {
+        let pending = &mut ctx.accounts.pending_change;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        // Validate the change now so a queued change can't fail at execution
+        match &change {
+            ConfigChange::FeeSchedule { tiers, pass_discount_bps, app_share_bps, .. } => {
+                validate_fee_schedule(tiers, *pass_discount_bps, *app_share_bps)?
+            }
+            ConfigChange::ModerationCouncil { members, threshold } => validate_council(members, *threshold)?,
+            ConfigChange::Timelock { secs } => {
+                require!(*secs >= 0 && *secs <= ConfigChange::MAX_TIMELOCK_SECS, ErrorCode::InvalidTimelock)
+            }
+            ConfigChange::SpamDeposit { window_secs, .. } => require!(*window_secs > 0, ErrorCode::InvalidSpamFilter),
+            ConfigChange::CrankBounty { lamports } => {
+                require!(*lamports <= Config::MAX_CRANK_BOUNTY_LAMPORTS, ErrorCode::CrankBountyTooHigh)
+            }
+            ConfigChange::CouncilElections { vote_mint, seats, candidate_bond } => {
+                validate_council_elections(vote_mint, *seats, *candidate_bond)?
+            }
+            ConfigChange::Moderator { .. } | ConfigChange::Paused { .. } | ConfigChange::Charity { .. } => {}
+        }
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::QueueConfigChange,
+            pending.key(),
+            &(),
+            &change,
+        )?;
+
+        pending.change_id = change_id;
+        pending.eta = clock.unix_timestamp + config.timelock_secs;
+        emit!(ConfigChangeQueued {
+            change_id,
+            change: change.clone(),
+            eta: pending.eta,
+        });
+        pending.change = change;
+        pending.queued_at = clock.unix_timestamp;
+        pending.bump = ctx.bumps.pending_change;
+
+        Ok(())
+    }
+
+    /// Applies a queued change whose timelock has passed. Anyone may execute it.
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_change;
+        let config = &mut ctx.accounts.config;
+        let audit_log = &mut ctx.accounts.audit_log;
+        let admin = config.admin;
+
+        require!(Clock::get()?.unix_timestamp >= pending.eta, ErrorCode::TimelockNotElapsed);
+
+        match pending.change.clone() {
+            ConfigChange::FeeSchedule { send_fee_lamports, tiers, pass_discount_bps, app_share_bps } => {
+                let schedule = ctx.accounts.fee_schedule.as_mut().ok_or(ErrorCode::ConfigChangeAccountMissing)?;
+                record_admin_action(
+                    audit_log,
+                    admin,
+                    AdminAction::SetFeeSchedule,
+                    schedule.key(),
+                    &(schedule.send_fee_lamports, &schedule.tiers, schedule.pass_discount_bps, schedule.app_share_bps),
+                    &(send_fee_lamports, &tiers, pass_discount_bps, app_share_bps),
+                )?;
+                schedule.send_fee_lamports = send_fee_lamports;
+                schedule.tiers = tiers;
+                schedule.pass_discount_bps = pass_discount_bps;
+                schedule.app_share_bps = app_share_bps;
+            }
+            ConfigChange::ModerationCouncil { members, threshold } => {
+                let council = ctx.accounts.council.as_mut().ok_or(ErrorCode::ConfigChangeAccountMissing)?;
+                record_admin_action(
+                    audit_log,
+                    admin,
+                    AdminAction::SetModerationCouncil,
+                    council.key(),
+                    &(&council.members, council.threshold),
+                    &(&members, threshold),
+                )?;
+                council.members = members;
+                council.threshold = threshold;
+            }
+            ConfigChange::Moderator { moderator } => {
+                record_admin_action(audit_log, admin, AdminAction::SetModerator, config.key(), &config.moderator, &moderator)?;
+                config.moderator = moderator;
+            }
+            ConfigChange::Paused { paused } => {
+                record_admin_action(audit_log, admin, AdminAction::SetPaused, config.key(), &config.paused, &paused)?;
+                config.paused = paused;
+            }
+            ConfigChange::Timelock { secs } => {
+                record_admin_action(audit_log, admin, AdminAction::SetTimelock, config.key(), &config.timelock_secs, &secs)?;
+                config.timelock_secs = secs;
+            }
+            ConfigChange::SpamDeposit { deposit_lamports, window_secs } => {
+                let filter = ctx.accounts.spam_filter.as_mut().ok_or(ErrorCode::ConfigChangeAccountMissing)?;
+                record_admin_action(
+                    audit_log,
+                    admin,
+                    AdminAction::SetSpamDeposit,
+                    filter.key(),
+                    &(filter.deposit_lamports, filter.window_secs),
+                    &(deposit_lamports, window_secs),
+                )?;
+                filter.deposit_lamports = deposit_lamports;
+                filter.window_secs = window_secs;
+            }
+            ConfigChange::CrankBounty { lamports } => {
+                record_admin_action(
+                    audit_log,
+                    admin,
+                    AdminAction::SetCrankBounty,
+                    config.key(),
+                    &config.crank_bounty_lamports,
+                    &lamports,
+                )?;
+                config.crank_bounty_lamports = lamports;
+            }
+            ConfigChange::Charity { charity } => {
+                record_admin_action(audit_log, admin, AdminAction::SetCharity, config.key(), &config.charity, &charity)?;
+                config.charity = charity;
+            }
+            ConfigChange::CouncilElections { vote_mint, seats, candidate_bond } => {
+                let council = ctx.accounts.council.as_mut().ok_or(ErrorCode::ConfigChangeAccountMissing)?;
+                record_admin_action(
+                    audit_log,
+                    admin,
+                    AdminAction::SetCouncilElections,
+                    council.key(),
+                    &(council.vote_mint, council.seats, council.candidate_bond),
+                    &(vote_mint, seats, candidate_bond),
+                )?;
+                council.vote_mint = vote_mint;
+                council.seats = seats;
+                council.candidate_bond = candidate_bond;
+            }
+        }
+
+        emit!(ConfigChangeExecuted { change_id: pending.change_id });
+
+        Ok(())
+    }
+
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_change;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::CancelConfigChange,
+            pending.key(),
+            &pending.change,
+            &(),
+        )
+    }
+
+    /// Arms a fail point for integration tests: the instruction reaching `point` fails once it
+    /// has passed it `skip_hits` times in the transaction. Fails unless built with `chaos`.
+    pub fn arm_fail_point(ctx: Context<ArmFailPoint>, point: ChaosPoint, skip_hits: u8) -> Result<()> {
+        require!(cfg!(feature = "chaos"), ErrorCode::ChaosDisabled);
+
+        let plan = &mut ctx.accounts.chaos_plan;
+        plan.point = point;
+        plan.skip_hits = skip_hits;
+        plan.hits = 0;
+        plan.bump = ctx.bumps.chaos_plan;
+
+        Ok(())
+    }
+
+    pub fn disarm_fail_point(_ctx: Context<DisarmFailPoint>) -> Result<()> {
+        require!(cfg!(feature = "chaos"), ErrorCode::ChaosDisabled);
+        Ok(())
+    }
+
+    /// Creates the owner's inbox index, the list of messages clients read their inbox from. A
+    /// private index holds salted entries that only the owner can match to messages, and needs a
+    /// published encryption key for senders to derive the salts against.
+    pub fn init_inbox_index(ctx: Context<InitInboxIndex>, private: bool) -> Result<()> {
+        let index = &mut ctx.accounts.index;
+
+        require!(!private || ctx.accounts.encryption_key.is_some(), ErrorCode::EncryptionKeyRequired);
+
+        index.owner = ctx.accounts.owner.key();
+        index.private = private;
+        index.delegate = Pubkey::default();
+        index.entries = Vec::new();
+        index.next_sequence = 0;
+        index.namespace = namespace_for(ctx.accounts.namespace.as_ref(), &ctx.accounts.owner_membership)?;
+        index.bump = ctx.bumps.index;
+
+        Ok(())
+    }
+
+    /// Switching modes drops the existing entries, so addresses indexed in public mode don't
+    /// stay readable after going private.
+    pub fn set_inbox_privacy(ctx: Context<SetInboxPrivacy>, private: bool) -> Result<()> {
+        let index = &mut ctx.accounts.index;
+
+        require!(!private || ctx.accounts.encryption_key.is_some(), ErrorCode::EncryptionKeyRequired);
+
+        if index.private != private {
+            index.private = private;
+            index.entries = Vec::new();
+        }
+
+        Ok(())
+    }
+
+    /// Lets `delegate`, such as the owner's relay, append private entries for them. Pass the
+    /// default key to revoke it.
+    pub fn set_inbox_delegate(ctx: Context<SetInboxDelegate>, delegate: Pubkey) -> Result<()> {
+        ctx.accounts.index.delegate = delegate;
+
+        Ok(())
+    }
+
+    /// Adds a message to its recipient's inbox index. Public indexes store the message address
+    /// and are written by its sender. Private ones store the salted entry as given: the salt is a
+    /// secret between sender and recipient, so it can't be checked here, and only the owner or
+    /// their delegate may write them, so nobody else can flood the ring and evict real entries.
+    pub fn index_message(ctx: Context<IndexMessage>, salted: Option<SaltedIndexEntry>) -> Result<()> {
+        let index = &mut ctx.accounts.index;
+        let authority = ctx.accounts.authority.key();
+
+        if index.private {
+            require!(
+                authority == index.owner || (index.delegate != Pubkey::default() && authority == index.delegate),
+                ErrorCode::NotIndexAuthority
+            );
+        } else {
+            require!(authority == ctx.accounts.message.sender, ErrorCode::NotIndexAuthority);
+        }
+
+        let entry = match (index.private, salted) {
+            (false, None) => IndexedMessage::Public(ctx.accounts.message.key()),
+            (true, Some(salted)) => IndexedMessage::Private(salted),
+            _ => return err!(ErrorCode::InvalidIndexEntry),
+        };
+        require!(!index.entries.contains(&entry), ErrorCode::AlreadyIndexed);
+
+        index.append(entry);
+
+        Ok(())
+    }
+
+    /// Creates the spam filter with no deposit; the deposit and window are then set through
+    /// a timelocked config change.
+    pub fn init_spam_filter(ctx: Context<InitSpamFilter>) -> Result<()> {
+        let filter = &mut ctx.accounts.spam_filter;
+
+        filter.current = [0; SpamFilter::BYTES];
+        filter.previous = [0; SpamFilter::BYTES];
+        filter.rotated_at = Clock::get()?.unix_timestamp;
+        filter.window_secs = SpamFilter::DEFAULT_WINDOW_SECS;
+        filter.deposit_lamports = 0;
+        filter.bump = ctx.bumps.spam_filter;
+
+        Ok(())
+    }
+
+    /// Adds content to the spam filter. Sends of the same content cost a deposit for the next
+    /// one to two windows.
+    pub fn flag_spam_content(ctx: Context<FlagSpamContent>, content_hash: [u8; 32]) -> Result<()> {
+        let moderator = ctx.accounts.moderator.key();
+        let clock = Clock::get()?;
+
+        require!(is_moderator(&ctx.accounts.config, &moderator), ErrorCode::NotModerator);
+
+        ctx.accounts.spam_filter.insert(&content_hash, clock.unix_timestamp);
+        emit!(SpamContentFlagged {
+            content_hash,
+            flagged_by: moderator,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a spam deposit to the sender: at any time by the recipient, or by the sender
+    /// once the hold has passed.
+    pub fn release_spam_deposit(ctx: Context<ReleaseSpamDeposit>) -> Result<()> {
+        let deposit = &ctx.accounts.deposit;
+        let authority = ctx.accounts.authority.key();
+
+        if authority != deposit.recipient {
+            require_keys_eq!(authority, deposit.sender, ErrorCode::NotDepositParty);
+            require!(Clock::get()?.unix_timestamp >= deposit.held_until, ErrorCode::SpamDepositHeld);
+        }
+
+        emit!(SpamDepositSettled {
+            message: deposit.message,
+            sender: deposit.sender,
+            amount_lamports: deposit.amount_lamports,
+            forfeited: false,
+        });
+
+        Ok(())
+    }
+
+    /// Sends a spam deposit to the treasury, for messages the moderator confirms as spam. The
+    /// confirmation is the message's spam flag against the sender's reputation.
+    pub fn forfeit_spam_deposit(ctx: Context<ForfeitSpamDeposit>) -> Result<()> {
+        let deposit = &ctx.accounts.deposit;
+        let flag = &mut ctx.accounts.spam_flag;
+        let moderator = ctx.accounts.moderator.key();
+        let clock = Clock::get()?;
+
+        require!(is_moderator(&ctx.accounts.config, &moderator), ErrorCode::NotModerator);
+
+        flag.message = deposit.message;
+        flag.sender = deposit.sender;
+        flag.flagged_by = moderator;
+        flag.flagged_at = clock.unix_timestamp;
+        flag.bump = ctx.bumps.spam_flag;
+
+        record_reputation_at(
+            &ctx.accounts.sender_reputation,
+            &ctx.accounts.config.reputation,
+            ReputationEvent::SpamFlagged,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(SpamDepositSettled {
+            message: deposit.message,
+            sender: deposit.sender,
+            amount_lamports: deposit.amount_lamports,
+            forfeited: true,
+        });
+        emit!(SpamMessageFlagged {
+            message: deposit.message,
+            sender: deposit.sender,
+            flagged_by: moderator,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Designates `inbox` as a spam trap: an address nobody gives out, so whatever reaches it
+    /// is unsolicited.
+    pub fn set_spam_trap(ctx: Context<SetSpamTrap>, inbox: Pubkey) -> Result<()> {
+        let trap = &mut ctx.accounts.spam_trap;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetSpamTrap,
+            trap.key(),
+            &(),
+            &inbox,
+        )?;
+
+        trap.inbox = inbox;
+        trap.messages_caught = 0;
+        trap.last_caught_at = 0;
+        trap.created_at = Clock::get()?.unix_timestamp;
+        trap.bump = ctx.bumps.spam_trap;
+
+        Ok(())
+    }
+
+    pub fn remove_spam_trap(ctx: Context<RemoveSpamTrap>) -> Result<()> {
+        let trap = &ctx.accounts.spam_trap;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::RemoveSpamTrap,
+            trap.key(),
+            &trap.inbox,
+            &(),
+        )
+    }
+
+    /// Reports a message caught by a spam trap: its content goes into the spam filter, the
+    /// trap's stats are updated and the message is closed. Anyone may crank this; the bounty
+    /// comes out of the message's rent and the rest goes to the treasury.
+    pub fn process_spam_trap_message(ctx: Context<ProcessSpamTrapMessage>) -> Result<()> {
+        let message = &ctx.accounts.message;
+        let trap = &mut ctx.accounts.spam_trap;
+        let clock = Clock::get()?;
+
+        // Messages from before the address became a trap may have been wanted
+        require!(message.timestamp >= trap.created_at, ErrorCode::MessagePredatesTrap);
+
+        let content_hash = SpamFilter::content_hash(&message.content);
+        ctx.accounts.spam_filter.insert(&content_hash, clock.unix_timestamp);
+        trap.messages_caught += 1;
+        trap.last_caught_at = clock.unix_timestamp;
+
+        record_reputation_at(
+            &ctx.accounts.sender_reputation,
+            &ctx.accounts.config.reputation,
+            ReputationEvent::SpamFlagged,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(SpamTrapHit {
+            trap: trap.inbox,
+            message: message.key(),
+            sender: message.sender,
+            content_hash,
+            timestamp: clock.unix_timestamp,
+        });
+
+        pay_crank_bounty(
+            &message.to_account_info(),
+            &ctx.accounts.cranker,
+            ctx.accounts.config.crank_bounty_lamports,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    /// Enables patron receipts. The admin creates `merkle_tree` with Bubblegum beforehand and
+    /// makes the patron config PDA its tree delegate, so receipts can be minted from tips.
+    pub fn set_patron_config(
+        ctx: Context<SetPatronConfig>,
+        merkle_tree: Pubkey,
+        threshold_lamports: u64,
+        uri_base: String,
+    ) -> Result<()> {
+        let patron = &mut ctx.accounts.patron_config;
+
+        PatronConfig::validate(&uri_base)?;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetPatronConfig,
+            patron.key(),
+            &(),
+            &(merkle_tree, threshold_lamports, &uri_base),
+        )?;
+        patron.merkle_tree = merkle_tree;
+        patron.threshold_lamports = threshold_lamports;
+        patron.uri_base = uri_base;
+        patron.receipts_minted = 0;
+        patron.bump = ctx.bumps.patron_config;
+
+        Ok(())
+    }
+
+    /// A zero threshold stops new receipts.
+    pub fn update_patron_config(
+        ctx: Context<UpdatePatronConfig>,
+        merkle_tree: Pubkey,
+        threshold_lamports: u64,
+        uri_base: String,
+    ) -> Result<()> {
+        let patron = &mut ctx.accounts.patron_config;
+
+        PatronConfig::validate(&uri_base)?;
+
+        record_admin_action(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.admin.key(),
+            AdminAction::SetPatronConfig,
+            patron.key(),
+            &(patron.merkle_tree, patron.threshold_lamports, &patron.uri_base),
+            &(merkle_tree, threshold_lamports, &uri_base),
+        )?;
+        patron.merkle_tree = merkle_tree;
+        patron.threshold_lamports = threshold_lamports;
+        patron.uri_base = uri_base;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(
+    message_content: String,
+    recipient: Pubkey,
+    priority: MessagePriority,
+    client_message_id: Option<[u8; 16]>,
+)]
+pub struct SendMessage<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = Message::space_for(message_content.len()),
+        seeds = [
+            b"message".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            sender.key().as_ref(),
+            recipient.as_ref(),
+            client_message_id.as_ref().map_or(&[][..], |id| &id[..]),
+        ],
+        bump
+    )]
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = sender,
+        mint::decimals = 0,
+        mint::authority = sender,
+    )]
+    pub nft_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = sender,
+        associated_token::mint = nft_mint,
+        associated_token::authority = recipient,
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = sender,
+        associated_token::mint = nft_mint,
+        associated_token::authority = sender,
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: This account is validated in the instruction
+    #[account(mut)]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: Recipient's inbox gate PDA, empty when the recipient has no gate
+    #[account(seeds = [b"inbox_gate", recipient.as_ref()], bump)]
+    pub recipient_gate: UncheckedAccount<'info>,
+
+    /// CHECK: Native stake account offered as proof of stake age, parsed in the instruction
+    pub sender_stake_account: Option<UncheckedAccount<'info>>,
+
+    #[account(seeds = [b"attestation", sender.key().as_ref()], bump = sender_attestation.bump)]
+    pub sender_attestation: Option<Account<'info, Attestation>>,
+
+    #[account(
+        mut,
+        seeds = [b"dm_link", dm_link.namespace_seed(), dm_link.owner.as_ref(), dm_link.slug.as_bytes()],
+        bump = dm_link.bump,
+    )]
+    pub dm_link: Option<Account<'info, DmLink>>,
+
+    #[account(mut, seeds = [b"epoch_stats".as_ref(), &epoch_stats.epoch.to_le_bytes()], bump = epoch_stats.bump)]
+    pub epoch_stats: Option<Account<'info, EpochStats>>,
+
+    #[account(mut, seeds = [b"user_stats", sender.key().as_ref()], bump = sender_stats.bump)]
+    pub sender_stats: Option<Account<'info, UserStats>>,
+
+    pub reply_to: Option<Account<'info, Message>>,
+
+    #[account(mut, seeds = [b"reputation", sender.key().as_ref()], bump = sender_reputation.bump)]
+    pub sender_reputation: Option<Account<'info, Reputation>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Recipient's delivery window PDA, empty when the recipient accepts messages at any time
+    #[account(seeds = [b"delivery_window", recipient.as_ref()], bump)]
+    pub recipient_window: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = MessageDelivery::LEN,
+        seeds = [b"delivery", message.key().as_ref()],
+        bump
+    )]
+    pub delivery: Option<Account<'info, MessageDelivery>>,
+
+    /// CHECK: Recipient's priority policy PDA, empty when the recipient has none
+    #[account(seeds = [b"priority_policy", recipient.as_ref()], bump)]
+    pub recipient_priority_policy: UncheckedAccount<'info>,
+
+    pub contact_settings: Option<Account<'info, ConversationSettings>>,
+
+    /// CHECK: Receives the urgent fee; must be the recipient
+    #[account(mut, address = recipient)]
+    pub recipient_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Protocol fee schedule PDA, empty while the fee switch is off
+    #[account(seeds = [b"fee_schedule"], bump)]
+    pub fee_schedule: UncheckedAccount<'info>,
+
+    /// Receives the protocol fee; required while the fee switch is on
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    /// Token account holding the sender's protocol pass
+    #[account(token::authority = sender)]
+    pub sender_pass: Option<Account<'info, TokenAccount>>,
+
+    /// The pass's record, for its expiry
+    pub sender_pass_record: Option<Account<'info, ProtocolPass>>,
+
+    /// Registered app the send is made through
+    #[account(mut, seeds = [b"app", app.name.as_bytes()], bump = app.bump)]
+    pub app: Option<Account<'info, App>>,
+
+    /// The app's authority, co-signing to earn its share of the fee
+    pub app_authority: Option<Signer<'info>>,
+
+    /// White-label deployment the message belongs to; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+
+    /// CHECK: Recipient's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", recipient.as_ref()], bump)]
+    pub recipient_membership: UncheckedAccount<'info>,
+
+    /// The recipient's namespace, for its federation policy on cross-namespace sends
+    #[account(seeds = [b"namespace", recipient_namespace.name.as_bytes()], bump = recipient_namespace.bump)]
+    pub recipient_namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Instructions sysvar, used to screen what runs before a paid send
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Spam filter PDA, empty until the admin creates it
+    #[account(seeds = [b"spam_filter"], bump)]
+    pub spam_filter: UncheckedAccount<'info>,
+
+    /// Required when the content is in the spam filter
+    #[account(
+        init,
+        payer = sender,
+        space = SpamDeposit::LEN,
+        seeds = [b"spam_deposit", message.key().as_ref()],
+        bump
+    )]
+    pub spam_deposit: Option<Account<'info, SpamDeposit>>,
+}
+
+#[derive(Accounts)]
+pub struct ReceiveMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"message",
+            message.namespace_seed(),
+            message.sender.as_ref(),
+            message.recipient.as_ref(),
+            message.client_id_seed(),
+        ],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, Message>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: u64)]
+pub struct SendEvent<'info> {
+    #[account(
+        init,
+        payer = organizer,
+        space = EventMessage::LEN,
+        seeds = [b"event", organizer.key().as_ref(), &event_id.to_le_bytes()],
+        bump
+    )]
+    pub event: Account<'info, EventMessage>,
+
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Rsvp<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event.organizer.as_ref(), &event.event_id.to_le_bytes()],
+        bump = event.bump,
+    )]
+    pub event: Account<'info, EventMessage>,
+
+    #[account(
+        init,
+        payer = user,
+        space = Attendee::LEN,
+        seeds = [b"attendee", event.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub attendee: Account<'info, Attendee>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRsvp<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event.organizer.as_ref(), &event.event_id.to_le_bytes()],
+        bump = event.bump,
+    )]
+    pub event: Account<'info, EventMessage>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"attendee", event.key().as_ref(), user.key().as_ref()],
+        bump = attendee.bump,
+    )]
+    pub attendee: Account<'info, Attendee>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(task_id: u64)]
+pub struct SendTask<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = TaskMessage::LEN,
+        seeds = [b"task", creator.key().as_ref(), &task_id.to_le_bytes()],
+        bump
+    )]
+    pub task: Account<'info, TaskMessage>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteTask<'info> {
+    #[account(
+        mut,
+        seeds = [b"task", task.creator.as_ref(), &task.task_id.to_le_bytes()],
+        bump = task.bump,
+    )]
+    pub task: Account<'info, TaskMessage>,
+
+    pub assignee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(trade_id: u64)]
+pub struct SendTradeProposal<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = TradeProposal::LEN,
+        seeds = [b"trade", proposer.key().as_ref(), &trade_id.to_le_bytes()],
+        bump
+    )]
+    pub trade: Account<'info, TradeProposal>,
+
+    #[account(
+        init,
+        payer = proposer,
+        token::mint = offer_mint,
+        token::authority = trade,
+        seeds = [b"trade_vault", trade.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    pub offer_mint: Account<'info, Mint>,
+
+    pub ask_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = offer_mint,
+        token::authority = proposer,
+    )]
+    pub proposer_offer_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptTrade<'info> {
+    #[account(
+        mut,
+        seeds = [b"trade", trade.proposer.as_ref(), &trade.trade_id.to_le_bytes()],
+        bump = trade.bump,
+    )]
+    pub trade: Account<'info, TradeProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"trade_vault", trade.key().as_ref()],
+        bump = trade.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = trade.ask_mint,
+        token::authority = recipient,
+    )]
+    pub recipient_ask_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = trade.offer_mint,
+        token::authority = recipient,
+    )]
+    pub recipient_offer_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = trade.ask_mint,
+        token::authority = trade.proposer,
+    )]
+    pub proposer_ask_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow vault rent, validated against the trade
+    #[account(mut, address = trade.proposer)]
+    pub proposer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DeclineTrade<'info> {
+    #[account(
+        mut,
+        seeds = [b"trade", trade.proposer.as_ref(), &trade.trade_id.to_le_bytes()],
+        bump = trade.bump,
+    )]
+    pub trade: Account<'info, TradeProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"trade_vault", trade.key().as_ref()],
+        bump = trade.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = trade.offer_mint,
+        token::authority = trade.proposer,
+    )]
+    pub proposer_offer_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow vault rent, validated against the trade
+    #[account(mut, address = trade.proposer)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(invoice_id: u64)]
+pub struct SendInvoice<'info> {
+    #[account(
+        init,
+        payer = issuer,
+        space = Invoice::LEN,
+        seeds = [
+            b"invoice".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            issuer.key().as_ref(),
+            &invoice_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Issuer's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", issuer.key().as_ref()], bump)]
+    pub issuer_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayInvoice<'info> {
+    #[account(
+        mut,
+        seeds = [b"invoice", invoice.namespace_seed(), invoice.issuer.as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Receipt::LEN,
+        seeds = [b"receipt", invoice.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(
+        mut,
+        token::mint = invoice.mint,
+        token::authority = payer,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = invoice.mint,
+        token::authority = invoice.issuer,
+    )]
+    pub issuer_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecurringInvoice<'info> {
+    #[account(
+        mut,
+        seeds = [b"invoice", invoice.namespace_seed(), invoice.issuer.as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(
+        mut,
+        token::mint = invoice.mint,
+        token::authority = payer,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CollectInvoicePeriod<'info> {
+    #[account(
+        mut,
+        seeds = [b"invoice", invoice.namespace_seed(), invoice.issuer.as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(mut, address = invoice.payer_token_account)]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = invoice.mint,
+        token::authority = invoice.issuer,
+    )]
+    pub issuer_token_account: Account<'info, TokenAccount>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRecurringInvoice<'info> {
+    #[account(
+        mut,
+        seeds = [b"invoice", invoice.namespace_seed(), invoice.issuer.as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    /// CHECK: Only used for revocation when the payer cancels; must be the approved account
+    #[account(mut, address = invoice.payer_token_account)]
+    pub payer_token_account: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(message_id: u64)]
+pub struct SendConditionalMessage<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = ConditionalMessage::LEN,
+        seeds = [b"conditional", sender.key().as_ref(), &message_id.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, ConditionalMessage>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnlockMessage<'info> {
+    #[account(
+        mut,
+        seeds = [b"conditional", message.sender.as_ref(), &message.message_id.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, ConditionalMessage>,
+
+    /// CHECK: Oracle or watched account named by the unlock condition, validated in the instruction
+    pub condition_account: Option<UncheckedAccount<'info>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        has_one = sender,
+        seeds = [b"conditional", message.sender.as_ref(), &message.message_id.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, ConditionalMessage>,
+
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDeadManMessage<'info> {
+    #[account(
+        mut,
+        has_one = recipient,
+        seeds = [b"conditional", message.sender.as_ref(), &message.message_id.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, ConditionalMessage>,
+
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConditionalMessage<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        seeds = [b"conditional", message.sender.as_ref(), &message.message_id.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, ConditionalMessage>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(commit_id: u64)]
+pub struct CommitMessage<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = CommittedMessage::LEN,
+        seeds = [b"commit", sender.key().as_ref(), &commit_id.to_le_bytes()],
+        bump
+    )]
+    pub commit: Account<'info, CommittedMessage>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealMessage<'info> {
+    #[account(
+        mut,
+        has_one = sender,
+        seeds = [b"commit", commit.sender.as_ref(), &commit.commit_id.to_le_bytes()],
+        bump = commit.bump,
+    )]
+    pub commit: Account<'info, CommittedMessage>,
+
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: u64)]
+pub struct CreateGame<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = GameSession::LEN,
+        seeds = [b"game", creator.key().as_ref(), &game_id.to_le_bytes()],
+        bump
+    )]
+    pub game: Account<'info, GameSession>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Rollout gate PDA for this feature, empty once it is generally available
+    #[account(seeds = [b"feature_gate".as_ref(), &[BetaFeature::Games as u8]], bump)]
+    pub feature_gate: UncheckedAccount<'info>,
+
+    /// Registered app the instruction is made through
+    #[account(seeds = [b"app", app.name.as_bytes()], bump = app.bump)]
+    pub app: Option<Account<'info, App>>,
+}
+
+#[derive(Accounts)]
+pub struct JoinGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game.creator.as_ref(), &game.game_id.to_le_bytes()],
+        bump = game.bump,
+    )]
+    pub game: Account<'info, GameSession>,
+
+    #[account(mut)]
+    pub opponent: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlayMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game.creator.as_ref(), &game.game_id.to_le_bytes()],
+        bump = game.bump,
+    )]
+    pub game: Account<'info, GameSession>,
+
+    /// CHECK: The other participant, refunded on a draw
+    #[account(
+        mut,
+        constraint = other_player.key() == game.other_player(player.key()) @ ErrorCode::InvalidOpponent
+    )]
+    pub other_player: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimGameForfeit<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game.creator.as_ref(), &game.game_id.to_le_bytes()],
+        bump = game.bump,
+    )]
+    pub game: Account<'info, GameSession>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelGame<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator,
+        seeds = [b"game", game.creator.as_ref(), &game.game_id.to_le_bytes()],
+        bump = game.bump,
+    )]
+    pub game: Account<'info, GameSession>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Config::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(poll_id: u64)]
+pub struct CreatePoll<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Poll::LEN,
+        seeds = [b"poll", creator.key().as_ref(), &poll_id.to_le_bytes()],
+        bump
+    )]
+    pub poll: Account<'info, Poll>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Vote<'info> {
+    #[account(
+        mut,
+        seeds = [b"poll", poll.creator.as_ref(), &poll.poll_id.to_le_bytes()],
+        bump = poll.bump,
+    )]
+    pub poll: Account<'info, Poll>,
+
+    #[account(
+        init,
+        payer = voter,
+        space = Ballot::LEN,
+        seeds = [b"ballot", poll.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub ballot: Account<'info, Ballot>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolvePoll<'info> {
+    #[account(
+        mut,
+        seeds = [b"poll", poll.creator.as_ref(), &poll.poll_id.to_le_bytes()],
+        bump = poll.bump,
+    )]
+    pub poll: Account<'info, Poll>,
+
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPollPayout<'info> {
+    #[account(
+        mut,
+        seeds = [b"poll", poll.creator.as_ref(), &poll.poll_id.to_le_bytes()],
+        bump = poll.bump,
+    )]
+    pub poll: Account<'info, Poll>,
+
+    #[account(
+        mut,
+        close = voter,
+        has_one = voter,
+        seeds = [b"ballot", poll.key().as_ref(), voter.key().as_ref()],
+        bump = ballot.bump,
+    )]
+    pub ballot: Account<'info, Ballot>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quote_id: u64)]
+pub struct SendPriceQuote<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = PriceQuoteMessage::LEN,
+        seeds = [b"quote", sender.key().as_ref(), &quote_id.to_le_bytes()],
+        bump
+    )]
+    pub quote: Account<'info, PriceQuoteMessage>,
+
+    /// CHECK: Pyth price account, validated when read
+    pub price_feed: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterGovernanceNotifier<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = GovernanceNotifier::LEN,
+        seeds = [b"gov_notifier", governance.key().as_ref()],
+        bump
+    )]
+    pub notifier: Account<'info, GovernanceNotifier>,
+
+    #[account(owner = SPL_GOVERNANCE_PROGRAM_ID @ ErrorCode::UnauthorizedNotifier)]
+    pub governance: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PostProposalNotification<'info> {
+    #[account(
+        init,
+        payer = poster,
+        space = ProposalNotification::LEN,
+        seeds = [b"proposal_notice", notifier.governance.as_ref(), proposal.key().as_ref()],
+        bump
+    )]
+    pub notification: Account<'info, ProposalNotification>,
+
+    #[account(
+        seeds = [b"gov_notifier", notifier.governance.as_ref()],
+        bump = notifier.bump,
+    )]
+    pub notifier: Account<'info, GovernanceNotifier>,
+
+    /// CHECK: SPL Governance proposal account; its governance is checked in the instruction
+    #[account(owner = SPL_GOVERNANCE_PROGRAM_ID @ ErrorCode::InvalidProposalAccount)]
+    pub proposal: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub poster: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCollectionFeed<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = CollectionFeed::LEN,
+        seeds = [b"collection_feed", collection_mint.key().as_ref()],
+        bump
+    )]
+    pub feed: Account<'info, CollectionFeed>,
+
+    pub collection_mint: Account<'info, Mint>,
+
+    /// CHECK: The collection's Metaplex metadata, read for its update authority
+    #[account(
+        seeds = [b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = TOKEN_METADATA_PROGRAM_ID,
+        owner = TOKEN_METADATA_PROGRAM_ID @ ErrorCode::InvalidCollectionMetadata,
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+
+    /// The collection's update authority
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PostMarketNotice<'info> {
+    #[account(
+        mut,
+        seeds = [b"collection_feed", feed.collection.as_ref()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, CollectionFeed>,
+
+    #[account(
+        init,
+        payer = bot,
+        space = MarketNotice::LEN,
+        seeds = [b"market_notice", feed.key().as_ref(), &feed.notice_count.to_le_bytes()],
+        bump
+    )]
+    pub notice: Account<'info, MarketNotice>,
+
+    #[account(mut)]
+    pub bot: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterNotifier<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = Notifier::LEN,
+        seeds = [b"notifier", authority.key().as_ref()],
+        bump
+    )]
+    pub notifier: Account<'info, Notifier>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Notify<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"notifier", authority.key().as_ref()],
+        bump = notifier.bump,
+    )]
+    pub notifier: Account<'info, Notifier>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Notification::LEN,
+        seeds = [b"notification", notifier.key().as_ref(), &notifier.notification_count.to_le_bytes()],
+        bump
+    )]
+    pub notification: Account<'info, Notification>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMerchant<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = MerchantProfile::LEN,
+        seeds = [b"merchant", owner.key().as_ref()],
+        bump
+    )]
+    pub merchant: Account<'info, MerchantProfile>,
+
+    #[account(token::authority = owner)]
+    pub settlement_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, reference: Pubkey)]
+pub struct IssueMerchantReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant.owner.as_ref()],
+        bump = merchant.bump,
+    )]
+    pub merchant: Account<'info, MerchantProfile>,
+
+    #[account(
+        init,
+        payer = customer,
+        space = MerchantReceipt::LEN,
+        seeds = [b"merchant_receipt", merchant.key().as_ref(), &merchant.receipt_count.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Account<'info, MerchantReceipt>,
+
+    #[account(
+        init,
+        payer = customer,
+        space = ReceiptReference::LEN,
+        seeds = [b"receipt", reference.as_ref()],
+        bump
+    )]
+    pub reference_marker: Account<'info, ReceiptReference>,
+
+    #[account(mut)]
+    pub customer: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, used to find the payment in this transaction
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTeamInbox<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = TeamInbox::LEN,
+        seeds = [b"team_inbox", owner.key().as_ref()],
+        bump
+    )]
+    pub team_inbox: Account<'info, TeamInbox>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = TeamInboxStats::LEN,
+        seeds = [b"team_stats", team_inbox.key().as_ref()],
+        bump
+    )]
+    pub stats: Account<'info, TeamInboxStats>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageTeamInbox<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"team_inbox", team_inbox.owner.as_ref()],
+        bump = team_inbox.bump,
+    )]
+    pub team_inbox: Account<'info, TeamInbox>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenTicket<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_inbox", team_inbox.owner.as_ref()],
+        bump = team_inbox.bump,
+    )]
+    pub team_inbox: Account<'info, TeamInbox>,
+
+    #[account(
+        init,
+        payer = opener,
+        space = Ticket::LEN,
+        seeds = [b"ticket", team_inbox.key().as_ref(), &team_inbox.ticket_count.to_le_bytes()],
+        bump
+    )]
+    pub ticket: Account<'info, Ticket>,
+
+    #[account(mut)]
+    pub opener: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceTicket<'info> {
+    #[account(
+        seeds = [b"team_inbox", team_inbox.owner.as_ref()],
+        bump = team_inbox.bump,
+    )]
+    pub team_inbox: Account<'info, TeamInbox>,
+
+    #[account(
+        mut,
+        has_one = team_inbox,
+        seeds = [b"ticket", team_inbox.key().as_ref(), &ticket.index.to_le_bytes()],
+        bump = ticket.bump,
+    )]
+    pub ticket: Account<'info, Ticket>,
+
+    pub member: Signer<'info>,
+
+    #[account(mut, seeds = [b"reputation", member.key().as_ref()], bump = member_reputation.bump)]
+    pub member_reputation: Option<Account<'info, Reputation>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct ReopenTicket<'info> {
+    #[account(
+        mut,
+        has_one = opener,
+        seeds = [b"ticket", ticket.team_inbox.as_ref(), &ticket.index.to_le_bytes()],
+        bump = ticket.bump,
+    )]
+    pub ticket: Account<'info, Ticket>,
+
+    pub opener: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RateTicket<'info> {
+    #[account(
+        mut,
+        has_one = opener,
+        seeds = [b"ticket", ticket.team_inbox.as_ref(), &ticket.index.to_le_bytes()],
+        bump = ticket.bump,
+    )]
+    pub ticket: Account<'info, Ticket>,
+
+    #[account(
+        mut,
+        seeds = [b"team_stats", ticket.team_inbox.as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, TeamInboxStats>,
+
+    pub opener: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(deal_id: u64)]
+pub struct OpenDealRoom<'info> {
+    #[account(
+        init,
+        payer = seller,
+        space = DealRoom::LEN,
+        seeds = [b"deal", seller.key().as_ref(), &deal_id.to_le_bytes()],
+        bump
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = mint,
+        token::authority = deal,
+        seeds = [b"deal_vault", deal.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PostDealMessage<'info> {
+    #[account(
+        mut,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = author,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundDeal<'info> {
+    #[account(
+        mut,
+        has_one = buyer,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    #[account(
+        mut,
+        seeds = [b"deal_vault", deal.key().as_ref()],
+        bump = deal.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = deal.mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseDeal<'info> {
+    #[account(
+        mut,
+        has_one = buyer,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    #[account(
+        mut,
+        seeds = [b"deal_vault", deal.key().as_ref()],
+        bump = deal.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = deal.mint,
+        token::authority = deal.seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow vault rent, validated against the deal
+    #[account(mut, address = deal.seller)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundDeal<'info> {
+    #[account(
+        mut,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    #[account(
+        mut,
+        seeds = [b"deal_vault", deal.key().as_ref()],
+        bump = deal.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = deal.mint,
+        token::authority = deal.buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow vault rent, validated against the deal
+    #[account(mut, address = deal.seller)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// The seller, or the buyer after the timeout
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AssignArbiter<'info> {
+    #[account(
+        mut,
+        has_one = buyer,
+        has_one = seller,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    pub buyer: Signer<'info>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RuleDispute<'info> {
+    #[account(
+        mut,
+        has_one = arbiter,
+        seeds = [b"deal", deal.seller.as_ref(), &deal.deal_id.to_le_bytes()],
+        bump = deal.bump,
+    )]
+    pub deal: Account<'info, DealRoom>,
+
+    #[account(
+        init,
+        payer = arbiter,
+        space = DealMessage::LEN,
+        seeds = [b"deal_msg", deal.key().as_ref(), &deal.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, DealMessage>,
+
+    #[account(
+        mut,
+        seeds = [b"deal_vault", deal.key().as_ref()],
+        bump = deal.vault_bump,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = deal.mint,
+        token::authority = deal.seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = deal.mint,
+        token::authority = deal.buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow vault rent, validated against the deal
+    #[account(mut, address = deal.seller)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+
+    #[account(mut, seeds = [b"reputation", loser_reputation.user.as_ref()], bump = loser_reputation.bump)]
+    pub loser_reputation: Option<Account<'info, Reputation>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct InitReputation<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = Reputation::LEN,
+        seeds = [b"reputation", user.as_ref()],
+        bump
+    )]
+    pub reputation: Account<'info, Reputation>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VouchFor<'info> {
+    #[account(
+        init,
+        payer = voucher,
+        space = Vouch::LEN,
+        seeds = [b"vouch", voucher.key().as_ref(), vouchee_reputation.user.as_ref()],
+        bump
+    )]
+    pub vouch: Account<'info, Vouch>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", vouchee_reputation.user.as_ref()],
+        bump = vouchee_reputation.bump,
+    )]
+    pub vouchee_reputation: Account<'info, Reputation>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub voucher: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeVouch<'info> {
+    #[account(
+        mut,
+        close = voucher,
+        has_one = voucher,
+        seeds = [b"vouch", voucher.key().as_ref(), vouch.vouchee.as_ref()],
+        bump = vouch.bump,
+    )]
+    pub vouch: Account<'info, Vouch>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", vouch.vouchee.as_ref()],
+        bump = vouchee_reputation.bump,
+    )]
+    pub vouchee_reputation: Account<'info, Reputation>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub voucher: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashVouch<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"vouch", vouch.voucher.as_ref(), vouch.vouchee.as_ref()],
+        bump = vouch.bump,
+    )]
+    pub vouch: Account<'info, Vouch>,
+
+    #[account(
+        mut,
+        seeds = [b"reputation", vouch.vouchee.as_ref()],
+        bump = vouchee_reputation.bump,
+    )]
+    pub vouchee_reputation: Account<'info, Reputation>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FlagSpamMessage<'info> {
+    pub message: Account<'info, Message>,
+
+    /// One flag per message, so a single message can't be counted twice
+    #[account(
+        init,
+        payer = moderator,
+        space = SpamFlag::LEN,
+        seeds = [b"spam_flag", message.key().as_ref()],
+        bump
+    )]
+    pub spam_flag: Account<'info, SpamFlag>,
+
+    /// CHECK: Sender's reputation PDA, empty when the sender has none
+    #[account(mut, seeds = [b"reputation", message.sender.as_ref()], bump)]
+    pub sender_reputation: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub moderator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(subject: Pubkey)]
+pub struct AttestAccount<'info> {
+    #[account(
+        init,
+        payer = attestor,
+        space = Attestation::LEN,
+        seeds = [b"attestation", subject.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.attestor == attestor.key() @ ErrorCode::UnauthorizedAttestor
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub attestor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshAttestation<'info> {
+    #[account(
+        mut,
+        seeds = [b"attestation", attestation.subject.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.attestor == attestor.key() @ ErrorCode::UnauthorizedAttestor
+    )]
+    pub config: Account<'info, Config>,
+
+    pub attestor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInboxGate<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = InboxGate::LEN,
+        seeds = [b"inbox_gate", owner.key().as_ref()],
+        bump
+    )]
+    pub gate: Account<'info, InboxGate>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearInboxGate<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"inbox_gate", owner.key().as_ref()],
+        bump = gate.bump,
+    )]
+    pub gate: Account<'info, InboxGate>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEncryptionKey<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = EncryptionKey::LEN,
+        seeds = [b"encryption_key", owner.key().as_ref()],
+        bump
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(mut, seeds = [b"key_log"], bump = key_log.bump)]
+    pub key_log: Account<'info, KeyTransparencyLog>,
+}
+
+#[derive(Accounts)]
+pub struct RotateEncryptionKey<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"encryption_key", owner.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds = [b"key_log"], bump = key_log.bump)]
+    pub key_log: Account<'info, KeyTransparencyLog>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = GuardianSet::LEN,
+        seeds = [b"guardians", owner.key().as_ref()],
+        bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGuardians<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"guardians", owner.key().as_ref()],
+        bump = guardian_set.bump,
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeKeyRecovery<'info> {
+    #[account(
+        init,
+        payer = guardian,
+        space = RecoveryRequest::LEN,
+        seeds = [b"recovery", guardian_set.owner.as_ref()],
+        bump
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    #[account(seeds = [b"guardians", guardian_set.owner.as_ref()], bump = guardian_set.bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(mut)]
+    pub guardian: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveKeyRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"recovery", guardian_set.owner.as_ref()],
+        bump = recovery_request.bump,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    #[account(seeds = [b"guardians", guardian_set.owner.as_ref()], bump = guardian_set.bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteKeyRecovery<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = proposer,
+        seeds = [b"recovery", guardian_set.owner.as_ref()],
+        bump = recovery_request.bump,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    #[account(seeds = [b"guardians", guardian_set.owner.as_ref()], bump = guardian_set.bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        seeds = [b"encryption_key", guardian_set.owner.as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+
+    /// CHECK: Guardian who opened the request and receives its rent back
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"key_log"], bump = key_log.bump)]
+    pub key_log: Account<'info, KeyTransparencyLog>,
+}
+
+#[derive(Accounts)]
+pub struct CancelKeyRecovery<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = owner,
+        has_one = proposer,
+        seeds = [b"recovery", owner.key().as_ref()],
+        bump = recovery_request.bump,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    /// CHECK: Guardian who opened the request and receives its rent back
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateInbox<'info> {
+    #[account(
+        init,
+        payer = new_wallet,
+        space = Forwarding::LEN,
+        seeds = [b"forward", old_wallet.key().as_ref()],
+        bump
+    )]
+    pub forwarding: Account<'info, Forwarding>,
+
+    /// CHECK: Forwarding PDA of the new wallet, which must not exist
+    #[account(seeds = [b"forward", new_wallet.key().as_ref()], bump)]
+    pub new_wallet_forwarding: UncheckedAccount<'info>,
+
+    pub old_wallet: Signer<'info>,
+
+    #[account(mut)]
+    pub new_wallet: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateReputation<'info> {
+    #[account(seeds = [b"forward", forwarding.old_wallet.as_ref()], bump = forwarding.bump)]
+    pub forwarding: Account<'info, Forwarding>,
+
+    #[account(
+        mut,
+        close = new_wallet,
+        seeds = [b"reputation", forwarding.old_wallet.as_ref()],
+        bump = old_reputation.bump,
+    )]
+    pub old_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init,
+        payer = new_wallet,
+        space = Reputation::LEN,
+        seeds = [b"reputation", forwarding.new_wallet.as_ref()],
+        bump
+    )]
+    pub new_reputation: Account<'info, Reputation>,
+
+    #[account(mut, address = forwarding.new_wallet @ ErrorCode::InvalidMigration)]
+    pub new_wallet: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateEncryptionKey<'info> {
+    #[account(seeds = [b"forward", forwarding.old_wallet.as_ref()], bump = forwarding.bump)]
+    pub forwarding: Account<'info, Forwarding>,
+
+    #[account(
+        mut,
+        close = new_wallet,
+        seeds = [b"encryption_key", forwarding.old_wallet.as_ref()],
+        bump = old_encryption_key.bump,
+    )]
+    pub old_encryption_key: Account<'info, EncryptionKey>,
+
+    #[account(
+        init,
+        payer = new_wallet,
+        space = EncryptionKey::LEN,
+        seeds = [b"encryption_key", forwarding.new_wallet.as_ref()],
+        bump
+    )]
+    pub new_encryption_key: Account<'info, EncryptionKey>,
+
+    #[account(mut, address = forwarding.new_wallet @ ErrorCode::InvalidMigration)]
+    pub new_wallet: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(mut, seeds = [b"key_log"], bump = key_log.bump)]
+    pub key_log: Account<'info, KeyTransparencyLog>,
+}
+
+#[derive(Accounts)]
+#[instruction(slug: String)]
+pub struct CreateDmLink<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = DmLink::LEN,
+        seeds = [
+            b"dm_link".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            owner.key().as_ref(),
+            slug.as_bytes(),
+        ],
+        bump
+    )]
+    pub dm_link: Account<'info, DmLink>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Owner's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", owner.key().as_ref()], bump)]
+    pub owner_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDmLink<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"dm_link", dm_link.namespace_seed(), owner.key().as_ref(), dm_link.slug.as_bytes()],
+        bump = dm_link.bump,
+    )]
+    pub dm_link: Account<'info, DmLink>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenAmaInbox<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = AmaInbox::LEN,
+        seeds = [b"ama", owner.key().as_ref()],
+        bump
+    )]
+    pub ama_inbox: Account<'info, AmaInbox>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageAmaInbox<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"ama", owner.key().as_ref()],
+        bump = ama_inbox.bump,
+    )]
+    pub ama_inbox: Account<'info, AmaInbox>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct AskAnonymous<'info> {
+    #[account(
+        mut,
+        seeds = [b"ama", ama_inbox.owner.as_ref()],
+        bump = ama_inbox.bump,
+    )]
+    pub ama_inbox: Account<'info, AmaInbox>,
+
+    // Keyed by commitment so each blinded asker gets one question per inbox
+    #[account(
+        init,
+        payer = relayer,
+        space = AmaQuestion::LEN,
+        seeds = [b"ama_question", ama_inbox.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub question: Account<'info, AmaQuestion>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AnswerQuestion<'info> {
+    #[account(has_one = owner, seeds = [b"ama", owner.key().as_ref()], bump = ama_inbox.bump)]
+    pub ama_inbox: Account<'info, AmaInbox>,
+
+    #[account(
+        mut,
+        seeds = [b"ama_question", ama_inbox.key().as_ref(), question.commitment.as_ref()],
+        bump = question.bump,
+    )]
+    pub question: Account<'info, AmaQuestion>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(room_id: u64)]
+pub struct CreateRoom<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Room::LEN,
+        seeds = [
+            b"room".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            creator.key().as_ref(),
+            &room_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub room: Account<'info, Room>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Creator's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", creator.key().as_ref()], bump)]
+    pub creator_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PostRoomMessage<'info> {
+    #[account(
+        mut,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    #[account(
+        init,
+        payer = author,
+        space = RoomMessage::LEN,
+        seeds = [b"room_msg", room.key().as_ref(), &room.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ImportRoomMessage<'info> {
+    #[account(
+        mut,
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = RoomMessage::LEN,
+        seeds = [b"room_msg", room.key().as_ref(), &room.message_count.to_le_bytes()],
+        bump
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoomRestrictions<'info> {
+    #[account(
+        mut,
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoomModeration<'info> {
+    #[account(
+        mut,
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ModerateRoomMessage<'info> {
+    #[account(
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    #[account(
+        mut,
+        has_one = room,
+        seeds = [b"room_msg", room.key().as_ref(), &message.index.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RejectRoomMessage<'info> {
+    #[account(
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    #[account(
+        mut,
+        close = author,
+        has_one = room,
+        has_one = author,
+        seeds = [b"room_msg", room.key().as_ref(), &message.index.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    /// CHECK: Post author, who paid its rent and receives the refund
+    #[account(mut)]
+    pub author: UncheckedAccount<'info>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReportRoomMessage<'info> {
+    #[account(seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()], bump = room.bump)]
+    pub room: Account<'info, Room>,
+
+    #[account(
+        has_one = room,
+        seeds = [b"room_msg", room.key().as_ref(), &message.index.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    #[account(
+        init,
+        payer = reporter,
+        space = RoomReport::LEN,
+        seeds = [b"room_report", message.key().as_ref()],
+        bump
+    )]
+    pub report: Account<'info, RoomReport>,
+
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoteHideRoomMessage<'info> {
+    #[account(
+        mut,
+        has_one = message,
+        seeds = [b"room_report", message.key().as_ref()],
+        bump = report.bump,
+    )]
+    pub report: Account<'info, RoomReport>,
+
+    #[account(mut, seeds = [b"room_msg", message.room.as_ref(), &message.index.to_le_bytes()], bump = message.bump)]
+    pub message: Account<'info, RoomMessage>,
+
+    /// Any published post by the voter in the same room
+    pub membership: Account<'info, RoomMessage>,
+
+    /// CHECK: Native stake account authorized to the voter, parsed in the instruction
+    pub stake_account: UncheckedAccount<'info>,
+
+    /// One per voter, not per stake account, so splitting a stake doesn't buy extra votes
+    #[account(
+        init,
+        payer = voter,
+        space = ModerationVote::LEN,
+        seeds = [b"moderation_vote", report.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote: Account<'info, ModerationVote>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Permissionless once the room has expired
+#[derive(Accounts)]
+pub struct CloseRoom<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator,
+        seeds = [b"room", room.namespace_seed(), room.creator.as_ref(), &room.room_id.to_le_bytes()],
+        bump = room.bump,
+    )]
+    pub room: Account<'info, Room>,
+
+    /// CHECK: Room creator receiving the rent refund
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+// Permissionless once the room has expired; works after the room itself is closed
+#[derive(Accounts)]
+pub struct CloseRoomMessage<'info> {
+    #[account(
+        mut,
+        close = room_creator,
+        has_one = room_creator,
+        seeds = [b"room_msg", message.room.as_ref(), &message.index.to_le_bytes()],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, RoomMessage>,
+
+    /// CHECK: Room creator receiving the rent refund, matched against the message
+    #[account(mut)]
+    pub room_creator: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitTreasury<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Treasury::LEN,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(content_hash: [u8; 32])]
+pub struct CreateContentBlob<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = ContentBlob::LEN,
+        seeds = [b"content_blob", content_hash.as_ref()],
+        bump
+    )]
+    pub blob: Account<'info, ContentBlob>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey, nonce: u64)]
+pub struct SendBlobMessage<'info> {
+    #[account(
+        mut,
+        seeds = [b"content_blob", blob.content_hash.as_ref()],
+        bump = blob.bump,
+    )]
+    pub blob: Account<'info, ContentBlob>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = BlobMessage::LEN,
+        seeds = [
+            b"blob_message".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            blob.key().as_ref(),
+            sender.key().as_ref(),
+            recipient.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub message: Account<'info, BlobMessage>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Rollout gate PDA for this feature, empty once it is generally available
+    #[account(seeds = [b"feature_gate".as_ref(), &[BetaFeature::BlobSends as u8]], bump)]
+    pub feature_gate: UncheckedAccount<'info>,
+
+    /// Registered app the instruction is made through
+    #[account(seeds = [b"app", app.name.as_bytes()], bump = app.bump)]
+    pub app: Option<Account<'info, App>>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBlobMessage<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        has_one = blob,
+        seeds = [
+            b"blob_message",
+            message.namespace_seed(),
+            blob.key().as_ref(),
+            message.sender.as_ref(),
+            message.recipient.as_ref(),
+            &message.nonce.to_le_bytes(),
+        ],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, BlobMessage>,
+
+    #[account(mut)]
+    pub blob: Account<'info, ContentBlob>,
+
+    /// CHECK: Original sender, who paid the message rent and receives it back
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GarbageCollectBlob<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        seeds = [b"content_blob", blob.content_hash.as_ref()],
+        bump = blob.bump,
+    )]
+    pub blob: Account<'info, ContentBlob>,
+
+    /// CHECK: Account that paid for the blob and receives its rent back
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitDictionary<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = MessageDictionary::LEN,
+        seeds = [b"dictionary"],
+        bump
+    )]
+    pub dictionary: Account<'info, MessageDictionary>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddDictionaryEntry<'info> {
+    #[account(mut, seeds = [b"dictionary"], bump = dictionary.bump)]
+    pub dictionary: Account<'info, MessageDictionary>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey, nonce: u64)]
+pub struct SendInternedMessage<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = InternedMessage::LEN,
+        seeds = [
+            b"interned".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            sender.key().as_ref(),
+            recipient.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub message: Account<'info, InternedMessage>,
+
+    #[account(seeds = [b"dictionary"], bump = dictionary.bump)]
+    pub dictionary: Account<'info, MessageDictionary>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Rollout gate PDA for this feature, empty once it is generally available
+    #[account(seeds = [b"feature_gate".as_ref(), &[BetaFeature::InternedSends as u8]], bump)]
+    pub feature_gate: UncheckedAccount<'info>,
+
+    /// Registered app the instruction is made through
+    #[account(seeds = [b"app", app.name.as_bytes()], bump = app.bump)]
+    pub app: Option<Account<'info, App>>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseInternedMessage<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        seeds = [
+            b"interned",
+            message.namespace_seed(),
+            message.sender.as_ref(),
+            message.recipient.as_ref(),
+            &message.nonce.to_le_bytes(),
+        ],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, InternedMessage>,
+
+    /// CHECK: Receives the rent; must be the message sender
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct RollEpochStats<'info> {
+    #[account(
+        init,
+        payer = cranker,
+        space = EpochStats::LEN,
+        seeds = [b"epoch_stats".as_ref(), &epoch.to_le_bytes()],
+        bump
+    )]
+    pub epoch_stats: Account<'info, EpochStats>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct InitUserStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = UserStats::LEN,
+        seeds = [b"user_stats", user.as_ref()],
+        bump
+    )]
+    pub stats: Account<'info, UserStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitKeyLog<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = KeyTransparencyLog::LEN,
+        seeds = [b"key_log"],
+        bump
+    )]
+    pub key_log: Account<'info, KeyTransparencyLog>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(participant_a: Pubkey, participant_b: Pubkey)]
+pub struct OpenConversationSettings<'info> {
+    #[account(
+        init,
+        payer = participant,
+        space = ConversationSettings::LEN,
+        seeds = [
+            b"conversation_settings".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            participant_a.as_ref(),
+            participant_b.as_ref(),
+        ],
+        bump
+    )]
+    pub settings: Account<'info, ConversationSettings>,
+
+    #[account(mut)]
+    pub participant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Participant's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", participant.key().as_ref()], bump)]
+    pub participant_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmSafetyNumber<'info> {
+    #[account(mut)]
+    pub settings: Account<'info, ConversationSettings>,
+
+    #[account(
+        seeds = [b"encryption_key", settings.participants[0].as_ref()],
+        bump = key_a.bump,
+    )]
+    pub key_a: Account<'info, EncryptionKey>,
+
+    #[account(
+        seeds = [b"encryption_key", settings.participants[1].as_ref()],
+        bump = key_b.bump,
+    )]
+    pub key_b: Account<'info, EncryptionKey>,
+
+    pub participant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckSafetyNumber<'info> {
+    #[account(mut)]
+    pub settings: Account<'info, ConversationSettings>,
+
+    #[account(
+        seeds = [b"encryption_key", settings.participants[0].as_ref()],
+        bump = key_a.bump,
+    )]
+    pub key_a: Account<'info, EncryptionKey>,
+
+    #[account(
+        seeds = [b"encryption_key", settings.participants[1].as_ref()],
+        bump = key_b.bump,
+    )]
+    pub key_b: Account<'info, EncryptionKey>,
+}
+
+#[derive(Accounts)]
+pub struct WipeMyData<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"encryption_key", owner.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Option<Account<'info, EncryptionKey>>,
+
+    #[account(mut, seeds = [b"key_log"], bump = key_log.bump)]
+    pub key_log: Option<Account<'info, KeyTransparencyLog>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"guardians", owner.key().as_ref()],
+        bump = guardian_set.bump,
+    )]
+    pub guardian_set: Option<Account<'info, GuardianSet>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"recovery", owner.key().as_ref()],
+        bump = recovery_request.bump,
+    )]
+    pub recovery_request: Option<Account<'info, RecoveryRequest>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"inbox_gate", owner.key().as_ref()],
+        bump = inbox_gate.bump,
+    )]
+    pub inbox_gate: Option<Account<'info, InboxGate>>,
+
+    #[account(
+        mut,
+        close = owner,
+        constraint = user_stats.user == owner.key() @ ErrorCode::NotWipeable,
+        seeds = [b"user_stats", owner.key().as_ref()],
+        bump = user_stats.bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"merchant", owner.key().as_ref()],
+        bump = merchant_profile.bump,
+    )]
+    pub merchant_profile: Option<Account<'info, MerchantProfile>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"profile", owner.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Option<Account<'info, Profile>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"delivery_window", owner.key().as_ref()],
+        bump = delivery_window.bump,
+    )]
+    pub delivery_window: Option<Account<'info, DeliveryWindow>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"priority_policy", owner.key().as_ref()],
+        bump = priority_policy.bump,
+    )]
+    pub priority_policy: Option<Account<'info, PriorityPolicy>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"content_filter", owner.key().as_ref()],
+        bump = content_filter.bump,
+    )]
+    pub content_filter: Option<Account<'info, ContentFilter>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"round_up", owner.key().as_ref()],
+        bump = round_up.bump,
+    )]
+    pub round_up: Option<Account<'info, RoundUp>>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"availability", owner.key().as_ref()],
+        bump = availability.bump,
+    )]
+    pub availability: Option<Account<'info, Availability>>,
+
+    /// Membership rent goes back to the namespace admin who paid it, as in `leave_namespace`
+    #[account(
+        mut,
+        constraint = namespace_member.user == owner.key() @ ErrorCode::NotWipeable,
+        seeds = [b"namespace_member", owner.key().as_ref()],
+        bump = namespace_member.bump,
+    )]
+    pub namespace_member: Option<Account<'info, NamespaceMember>>,
+
+    pub member_namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Receives the membership rent; checked against the namespace admin in the handler
+    #[account(mut)]
+    pub namespace_admin: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DiscloseMessage<'info> {
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = discloser,
+        space = Disclosure::LEN,
+        seeds = [b"disclosure", message.key().as_ref(), discloser.key().as_ref()],
+        bump
+    )]
+    pub disclosure: Account<'info, Disclosure>,
+
+    #[account(mut)]
+    pub discloser: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(observer: Pubkey)]
+pub struct GrantObserver<'info> {
+    pub settings: Account<'info, ConversationSettings>,
+
+    #[account(
+        init,
+        payer = granter,
+        space = ObserverGrant::LEN,
+        seeds = [b"observer", settings.key().as_ref(), observer.as_ref()],
+        bump
+    )]
+    pub grant: Account<'info, ObserverGrant>,
+
+    #[account(mut)]
+    pub granter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeObserver<'info> {
+    #[account(
+        mut,
+        close = granter,
+        has_one = granter,
+        seeds = [b"observer", grant.conversation.as_ref(), grant.observer.as_ref()],
+        bump = grant.bump,
+    )]
+    pub grant: Account<'info, ObserverGrant>,
+
+    #[account(mut)]
+    pub granter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ShareObserverKey<'info> {
+    #[account(address = grant.conversation)]
+    pub settings: Account<'info, ConversationSettings>,
+
+    #[account(has_one = granter)]
+    pub grant: Account<'info, ObserverGrant>,
+
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = granter,
+        space = ObserverKey::LEN,
+        seeds = [b"observer_key", grant.key().as_ref(), message.key().as_ref()],
+        bump
+    )]
+    pub observer_key: Account<'info, ObserverKey>,
+
+    #[account(mut)]
+    pub granter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDeliveryWindow<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = DeliveryWindow::LEN,
+        seeds = [b"delivery_window", owner.key().as_ref()],
+        bump
+    )]
+    pub window: Account<'info, DeliveryWindow>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDeliveryWindow<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"delivery_window", owner.key().as_ref()],
+        bump = window.bump,
+    )]
+    pub window: Account<'info, DeliveryWindow>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClearDeliveryWindow<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"delivery_window", owner.key().as_ref()],
+        bump = window.bump,
+    )]
+    pub window: Account<'info, DeliveryWindow>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeliverQueued<'info> {
+    #[account(mut, seeds = [b"delivery", delivery.message.as_ref()], bump = delivery.bump)]
+    pub delivery: Account<'info, MessageDelivery>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPriorityPolicy<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = PriorityPolicy::LEN,
+        seeds = [b"priority_policy", owner.key().as_ref()],
+        bump
+    )]
+    pub policy: Account<'info, PriorityPolicy>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriorityPolicy<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"priority_policy", owner.key().as_ref()],
+        bump = policy.bump,
+    )]
+    pub policy: Account<'info, PriorityPolicy>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReminder<'info> {
+    /// CHECK: Any message-like account of this program; only its address is recorded
+    #[account(owner = crate::ID)]
+    pub message: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Reminder::LEN,
+        seeds = [b"reminder", owner.key().as_ref(), message.key().as_ref()],
+        bump
+    )]
+    pub reminder: Account<'info, Reminder>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelReminder<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"reminder", owner.key().as_ref(), reminder.message.as_ref()],
+        bump = reminder.bump,
+    )]
+    pub reminder: Account<'info, Reminder>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FireReminder<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"reminder", owner.key().as_ref(), reminder.message.as_ref()],
+        bump = reminder.bump,
+    )]
+    pub reminder: Account<'info, Reminder>,
+
+    /// CHECK: Reminder owner, who paid its rent and receives the refund
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(peer: Pubkey)]
+pub struct SnoozeConversation<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Snooze::LEN,
+        seeds = [b"snooze", owner.key().as_ref(), peer.as_ref()],
+        bump
+    )]
+    pub snooze: Account<'info, Snooze>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnsnoozeConversation<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"snooze", owner.key().as_ref(), snooze.peer.as_ref()],
+        bump = snooze.bump,
+    )]
+    pub snooze: Account<'info, Snooze>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireSnooze<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"snooze", owner.key().as_ref(), snooze.peer.as_ref()],
+        bump = snooze.bump,
+    )]
+    pub snooze: Account<'info, Snooze>,
+
+    /// CHECK: Snooze owner, who paid its rent and receives the refund
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AttachTranslations<'info> {
+    #[account(has_one = sender)]
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = MessageTranslations::LEN,
+        seeds = [b"translations", message.key().as_ref()],
+        bump
+    )]
+    pub translations: Account<'info, MessageTranslations>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveTranslations<'info> {
+    #[account(has_one = sender)]
+    pub message: Account<'info, Message>,
+
+    #[account(
+        mut,
+        close = sender,
+        seeds = [b"translations", message.key().as_ref()],
+        bump = translations.bump,
+    )]
+    pub translations: Account<'info, MessageTranslations>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AttachMedia<'info> {
+    #[account(has_one = sender)]
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = MessageMedia::LEN,
+        seeds = [b"media", message.key().as_ref()],
+        bump
+    )]
+    pub media: Account<'info, MessageMedia>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetContentWarning<'info> {
+    pub message: Account<'info, Message>,
+
+    #[account(
+        init,
+        payer = flagger,
+        space = ContentWarning::LEN,
+        seeds = [b"content_warning", message.key().as_ref()],
+        bump
+    )]
+    pub warning: Account<'info, ContentWarning>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub flagger: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearContentWarning<'info> {
+    pub message: Account<'info, Message>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        seeds = [b"content_warning", message.key().as_ref()],
+        bump = warning.bump,
+    )]
+    pub warning: Account<'info, ContentWarning>,
+
+    /// CHECK: Whoever set the warning, receiving the rent refund
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub clearer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetContentFilter<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = ContentFilter::LEN,
+        seeds = [b"content_filter", owner.key().as_ref()],
+        bump
+    )]
+    pub filter: Account<'info, ContentFilter>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateContentFilter<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"content_filter", owner.key().as_ref()],
+        bump = filter.bump,
+    )]
+    pub filter: Account<'info, ContentFilter>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitModerationCouncil<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ModerationCouncil::LEN,
+        seeds = [b"moderation_council"],
+        bump
+    )]
+    pub council: Account<'info, ModerationCouncil>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppealModeration<'info> {
+    /// CHECK: The moderated account, a `RoomMessage` or `ContentWarning`, parsed in the instruction
+    #[account(owner = crate::ID)]
+    pub target: UncheckedAccount<'info>,
+
+    /// The warned message, when appealing a content warning
+    pub warned_message: Option<Account<'info, Message>>,
+
+    #[account(
+        init,
+        payer = appellant,
+        space = Appeal::LEN,
+        seeds = [b"appeal", target.key().as_ref()],
+        bump
+    )]
+    pub appeal: Account<'info, Appeal>,
+
+    #[account(mut)]
+    pub appellant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RuleOnAppeal<'info> {
+    #[account(mut, seeds = [b"appeal", appeal.target.as_ref()], bump = appeal.bump)]
+    pub appeal: Account<'info, Appeal>,
+
+    #[account(seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Account<'info, ModerationCouncil>,
+
+    /// CHECK: The appealed account; only modified when the ruling reverses the action
+    #[account(mut, owner = crate::ID, address = appeal.target)]
+    pub target: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the rent of a lifted content warning; checked against its payer
+    #[account(mut)]
+    pub warning_payer: Option<UncheckedAccount<'info>>,
+
+    /// A council key, which may itself be a multisig vault signing through CPI
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct OpenCouncilElection<'info> {
+    #[account(seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Account<'info, ModerationCouncil>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = CouncilElection::LEN,
+        seeds = [b"council_election".as_ref(), &epoch.to_le_bytes()],
+        bump
+    )]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StandForCouncil<'info> {
+    #[account(mut, seeds = [b"council_election".as_ref(), &election.epoch.to_le_bytes()], bump = election.bump)]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(
+        init,
+        payer = candidate,
+        space = CouncilCandidacy::LEN,
+        seeds = [b"council_candidacy", election.key().as_ref(), candidate.key().as_ref()],
+        bump
+    )]
+    pub candidacy: Account<'info, CouncilCandidacy>,
+
+    #[account(
+        init,
+        payer = candidate,
+        token::mint = vote_mint,
+        token::authority = candidacy,
+        seeds = [b"council_candidacy_vault", candidacy.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(address = election.vote_mint)]
+    pub vote_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = vote_mint,
+        token::authority = candidate,
+    )]
+    pub candidate_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub candidate: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimCandidateBond<'info> {
+    #[account(seeds = [b"council_election".as_ref(), &election.epoch.to_le_bytes()], bump = election.bump)]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(
+        mut,
+        close = candidate,
+        has_one = election,
+        has_one = candidate,
+        seeds = [b"council_candidacy", election.key().as_ref(), candidate.key().as_ref()],
+        bump = candidacy.bump
+    )]
+    pub candidacy: Account<'info, CouncilCandidacy>,
+
+    #[account(mut, seeds = [b"council_candidacy_vault", candidacy.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = election.vote_mint,
+        token::authority = candidate,
+    )]
+    pub candidate_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub candidate: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct VoteCouncil<'info> {
+    #[account(mut, seeds = [b"council_election".as_ref(), &election.epoch.to_le_bytes()], bump = election.bump)]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(
+        init,
+        payer = voter,
+        space = CouncilVote::LEN,
+        seeds = [b"council_vote", election.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote: Account<'info, CouncilVote>,
+
+    #[account(
+        init,
+        payer = voter,
+        token::mint = vote_mint,
+        token::authority = vote,
+        seeds = [b"council_vote_vault", vote.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(address = election.vote_mint)]
+    pub vote_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = vote_mint,
+        token::authority = voter,
+    )]
+    pub voter_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeCouncilElection<'info> {
+    #[account(mut, seeds = [b"council_election".as_ref(), &election.epoch.to_le_bytes()], bump = election.bump)]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(mut, seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Account<'info, ModerationCouncil>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimCouncilVote<'info> {
+    #[account(seeds = [b"council_election".as_ref(), &election.epoch.to_le_bytes()], bump = election.bump)]
+    pub election: Account<'info, CouncilElection>,
+
+    #[account(
+        mut,
+        close = voter,
+        has_one = election,
+        has_one = voter,
+        seeds = [b"council_vote", election.key().as_ref(), voter.key().as_ref()],
+        bump = vote.bump
+    )]
+    pub vote: Account<'info, CouncilVote>,
+
+    #[account(mut, seeds = [b"council_vote_vault", vote.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = election.vote_mint,
+        token::authority = voter,
+    )]
+    pub voter_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ProposeTreasurySpend<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = TreasuryProposal::LEN,
+        seeds = [b"treasury_proposal", proposer.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, TreasuryProposal>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Needed when a council member rather than the admin proposes
+    #[account(seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Option<Account<'info, ModerationCouncil>>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveTreasurySpend<'info> {
+    #[account(
+        mut,
+        seeds = [b"treasury_proposal", proposal.proposer.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, TreasuryProposal>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Needed when a council member rather than the admin approves
+    #[account(seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Option<Account<'info, ModerationCouncil>>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTreasurySpend<'info> {
+    #[account(
+        mut,
+        has_one = recipient,
+        seeds = [b"treasury_proposal", proposal.proposer.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, TreasuryProposal>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    /// CHECK: Receives the payout; checked against the proposal
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTreasurySpend<'info> {
+    #[account(
+        mut,
+        seeds = [b"treasury_proposal", proposal.proposer.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, TreasuryProposal>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitFeeSchedule<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = FeeSchedule::LEN,
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPassOffer<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = PassOffer::LEN,
+        seeds = [b"pass_offer"],
+        bump
+    )]
+    pub pass_offer: Account<'info, PassOffer>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePassOffer<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut, seeds = [b"pass_offer"], bump = pass_offer.bump)]
+    pub pass_offer: Account<'info, PassOffer>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MintPass<'info> {
+    #[account(seeds = [b"pass_offer"], bump = pass_offer.bump)]
+    pub pass_offer: Account<'info, PassOffer>,
+
+    #[account(
+        init,
+        payer = buyer,
+        mint::decimals = 0,
+        mint::authority = pass_offer,
+    )]
+    pub pass_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = buyer,
+        token::mint = pass_mint,
+        token::authority = buyer,
+        seeds = [b"pass_token", pass_mint.key().as_ref()],
+        bump
+    )]
+    pub pass_token: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = ProtocolPass::LEN,
+        seeds = [b"pass", pass_mint.key().as_ref()],
+        bump
+    )]
+    pub pass: Account<'info, ProtocolPass>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RenewPass<'info> {
+    #[account(seeds = [b"pass_offer"], bump = pass_offer.bump)]
+    pub pass_offer: Account<'info, PassOffer>,
+
+    #[account(mut, seeds = [b"pass", pass.mint.as_ref()], bump = pass.bump)]
+    pub pass: Account<'info, ProtocolPass>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RegisterApp<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = App::LEN,
+        seeds = [b"app", name.as_bytes()],
+        bump
+    )]
+    pub app: Account<'info, App>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAppRevenue<'info> {
+    #[account(mut, has_one = authority, seeds = [b"app", app.name.as_bytes()], bump = app.bump)]
+    pub app: Account<'info, App>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(feature: BetaFeature)]
+pub struct SetFeatureGate<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = FeatureGate::LEN,
+        seeds = [b"feature_gate".as_ref(), &[feature as u8]],
+        bump
+    )]
+    pub feature_gate: Account<'info, FeatureGate>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeatureGate<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut, seeds = [b"feature_gate".as_ref(), &[feature_gate.feature as u8]], bump = feature_gate.bump)]
+    pub feature_gate: Account<'info, FeatureGate>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFeatureGate<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"feature_gate".as_ref(), &[feature_gate.feature as u8]],
+        bump = feature_gate.bump
+    )]
+    pub feature_gate: Account<'info, FeatureGate>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, version: u16)]
+pub struct RegisterSchema<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Schema::LEN,
+        seeds = [b"schema", name.as_bytes(), &version.to_le_bytes()],
+        bump
+    )]
+    pub schema: Account<'info, Schema>,
+
+    /// The schema's previous version, required from version 2 on
+    pub previous: Option<Account<'info, Schema>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey, nonce: u64)]
+pub struct SendStructuredMessage<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = StructuredMessage::LEN,
+        seeds = [
+            b"structured".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            sender.key().as_ref(),
+            recipient.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub message: Account<'info, StructuredMessage>,
+
+    #[account(seeds = [b"schema", schema.name.as_bytes(), &schema.version.to_le_bytes()], bump = schema.bump)]
+    pub schema: Account<'info, Schema>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStructuredMessage<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        seeds = [
+            b"structured",
+            message.namespace_seed(),
+            message.sender.as_ref(),
+            message.recipient.as_ref(),
+            &message.nonce.to_le_bytes(),
+        ],
+        bump = message.bump,
+    )]
+    pub message: Account<'info, StructuredMessage>,
+
+    /// CHECK: Receives the rent; must be the message sender
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct AttachPaymentNote<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = PaymentNote::LEN,
+        seeds = [
+            b"payment_note".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            sender.key().as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment_note: Account<'info, PaymentNote>,
+
+    /// CHECK: The transfer's destination token account; owner program and owner checked in the instruction
+    pub destination: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, used to read the preceding transfer
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct SwapAndTip<'info> {
+    #[account(mut, token::authority = sender)]
+    pub sender_source: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = recipient_token_account.owner == recipient @ ErrorCode::InvalidTipDestination)]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Recipient's profile PDA, empty when they have no profile
+    #[account(seeds = [b"profile", recipient.as_ref()], bump)]
+    pub recipient_profile: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Jupiter aggregator, invoked with the route
+    #[account(address = JUPITER_PROGRAM_ID)]
+    pub jupiter_program: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, used to screen what runs before the tip
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetProfile<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Profile::LEN,
+        seeds = [b"profile", owner.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, Profile>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"profile", owner.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, Profile>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoundUp<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = RoundUp::LEN,
+        seeds = [b"round_up", owner.key().as_ref()],
+        bump
+    )]
+    pub round_up: Account<'info, RoundUp>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRoundUp<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"round_up", owner.key().as_ref()],
+        bump = round_up.bump,
+    )]
+    pub round_up: Account<'info, RoundUp>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct SendTip<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Receives the tip; must be the recipient
+    #[account(mut, address = recipient)]
+    pub recipient_wallet: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(seeds = [b"round_up", sender.key().as_ref()], bump = round_up.bump)]
+    pub round_up: Option<Account<'info, RoundUp>>,
+
+    /// CHECK: Receives the round-up; must be the config's charity
+    #[account(mut, address = config.charity)]
+    pub charity: Option<UncheckedAccount<'info>>,
+
+    #[account(mut, seeds = [b"epoch_stats".as_ref(), &epoch_stats.epoch.to_le_bytes()], bump = epoch_stats.bump)]
+    pub epoch_stats: Option<Account<'info, EpochStats>>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Instructions sysvar, used to screen what runs before the tip
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// Pass to mint a patron receipt; the tip must exceed its threshold
+    #[account(mut, seeds = [b"patron_config"], bump = patron_config.bump)]
+    pub patron_config: Option<Account<'info, PatronConfig>>,
+
+    /// The recipient's message being tipped, referenced by the receipt
+    pub tip_message: Option<Account<'info, Message>>,
+
+    /// CHECK: Bubblegum tree config PDA, checked by Bubblegum
+    #[account(mut)]
+    pub tree_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The patron config's tree, checked in the instruction
+    #[account(mut)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Noop
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Account Compression
+    #[account(address = SPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum, invoked to mint the receipt
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey, stream_id: u64)]
+pub struct OpenTipStream<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = TipStream::LEN,
+        seeds = [
+            b"tip_stream".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            sender.key().as_ref(),
+            recipient.as_ref(),
+            &stream_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub stream: Account<'info, TipStream>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, used to screen what runs before the stream is funded
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Sender's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", sender.key().as_ref()], bump)]
+    pub sender_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PingTipStream<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"tip_stream",
+            stream.namespace_seed(),
+            stream.sender.as_ref(),
+            stream.recipient.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump = stream.bump,
+    )]
+    pub stream: Account<'info, TipStream>,
+
+    pub participant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTipStream<'info> {
+    #[account(
+        mut,
+        has_one = sender,
+        seeds = [
+            b"tip_stream",
+            stream.namespace_seed(),
+            stream.sender.as_ref(),
+            stream.recipient.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump = stream.bump,
+    )]
+    pub stream: Account<'info, TipStream>,
+
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTipStream<'info> {
+    #[account(
+        mut,
+        has_one = recipient,
+        seeds = [
+            b"tip_stream",
+            stream.namespace_seed(),
+            stream.sender.as_ref(),
+            stream.recipient.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump = stream.bump,
+    )]
+    pub stream: Account<'info, TipStream>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTipStream<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        has_one = recipient,
+        seeds = [
+            b"tip_stream",
+            stream.namespace_seed(),
+            stream.sender.as_ref(),
+            stream.recipient.as_ref(),
+            &stream.stream_id.to_le_bytes(),
+        ],
+        bump = stream.bump,
+    )]
+    pub stream: Account<'info, TipStream>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Receives the vested remainder; checked against the stream
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(consultant: Pubkey, booking_id: u64)]
+pub struct ProposeBooking<'info> {
+    #[account(
+        init,
+        payer = client,
+        space = BookingMessage::LEN,
+        seeds = [
+            b"booking".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            client.key().as_ref(),
+            consultant.as_ref(),
+            &booking_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    /// CHECK: Consultant's availability PDA, empty when they take bookings at any time
+    #[account(seeds = [b"availability", consultant.as_ref()], bump)]
+    pub consultant_availability: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub client: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Client's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", client.key().as_ref()], bump)]
+    pub client_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RespondToBooking<'info> {
+    #[account(
+        mut,
+        has_one = consultant,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    pub consultant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBookingByConsultant<'info> {
+    #[account(
+        mut,
+        close = client,
+        has_one = consultant,
+        has_one = client,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    pub consultant: Signer<'info>,
+
+    /// CHECK: Receives the escrow and rent; checked against the booking
+    #[account(mut)]
+    pub client: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBooking<'info> {
+    #[account(
+        mut,
+        close = client,
+        has_one = client,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    #[account(mut)]
+    pub client: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteBooking<'info> {
+    #[account(
+        mut,
+        close = client,
+        has_one = client,
+        has_one = consultant,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    /// The client, or the consultant once the grace period is over
+    pub authority: Signer<'info>,
+
+    /// CHECK: Receives the rent; checked against the booking
+    #[account(mut)]
+    pub client: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the fee; checked against the booking
+    #[account(mut)]
+    pub consultant: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeBooking<'info> {
+    #[account(
+        mut,
+        has_one = client,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    pub client: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RuleBookingDispute<'info> {
+    #[account(
+        mut,
+        close = client,
+        has_one = client,
+        has_one = consultant,
+        seeds = [
+            b"booking",
+            booking.namespace_seed(),
+            booking.client.as_ref(),
+            booking.consultant.as_ref(),
+            &booking.booking_id.to_le_bytes(),
+        ],
+        bump = booking.bump,
+    )]
+    pub booking: Account<'info, BookingMessage>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub moderator: Signer<'info>,
+
+    /// CHECK: Receives the rest of the escrow and the rent; checked against the booking
+    #[account(mut)]
+    pub client: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the consultant's share; checked against the booking
+    #[account(mut)]
+    pub consultant: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAvailability<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Availability::LEN,
+        seeds = [b"availability", owner.key().as_ref()],
+        bump
+    )]
+    pub availability: Account<'info, Availability>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAvailability<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"availability", owner.key().as_ref()],
+        bump = availability.bump,
+    )]
+    pub availability: Account<'info, Availability>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClearAvailability<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"availability", owner.key().as_ref()],
+        bump = availability.bump,
+    )]
+    pub availability: Account<'info, Availability>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLinkVerification<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", profile.owner.as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, Profile>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.attestor == attestor.key() @ ErrorCode::UnauthorizedAttestor
+    )]
+    pub config: Account<'info, Config>,
+
+    pub attestor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateNamespace<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Namespace::LEN,
+        seeds = [b"namespace", name.as_bytes()],
+        bump
+    )]
+    pub namespace: Account<'info, Namespace>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateNamespace<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"namespace", namespace.name.as_bytes()],
+        bump = namespace.bump,
+    )]
+    pub namespace: Account<'info, Namespace>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddNamespaceMember<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = NamespaceMember::LEN,
+        seeds = [b"namespace_member", user.key().as_ref()],
+        bump
+    )]
+    pub member: Account<'info, NamespaceMember>,
+
+    #[account(has_one = admin, seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Account<'info, Namespace>,
+
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveNamespaceMember<'info> {
+    #[account(
+        mut,
+        close = admin,
+        has_one = namespace,
+        seeds = [b"namespace_member", member.user.as_ref()],
+        bump = member.bump,
+    )]
+    pub member: Account<'info, NamespaceMember>,
+
+    #[account(has_one = admin, seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Account<'info, Namespace>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveNamespace<'info> {
+    #[account(
+        mut,
+        close = admin,
+        has_one = namespace,
+        has_one = user,
+        seeds = [b"namespace_member", user.key().as_ref()],
+        bump = member.bump,
+    )]
+    pub member: Account<'info, NamespaceMember>,
+
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Account<'info, Namespace>,
+
+    pub user: Signer<'info>,
+
+    /// CHECK: Receives the membership rent; must be the namespace admin
+    #[account(mut, address = namespace.admin)]
+    pub admin: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitAuditLog<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = AuditLog::LEN,
+        seeds = [b"audit_log"],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64)]
+pub struct QueueConfigChange<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = PendingConfigChange::LEN,
+        seeds = [b"config_change".as_ref(), &change_id.to_le_bytes()],
+        bump
+    )]
+    pub pending_change: Account<'info, PendingConfigChange>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"config_change".as_ref(), &pending_change.change_id.to_le_bytes()],
+        bump = pending_change.bump,
+    )]
+    pub pending_change: Account<'info, PendingConfigChange>,
+
+    #[account(mut, has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    /// Required for fee schedule changes
+    #[account(mut, seeds = [b"fee_schedule"], bump = fee_schedule.bump)]
+    pub fee_schedule: Option<Account<'info, FeeSchedule>>,
+
+    /// Required for moderation council and council election changes
+    #[account(mut, seeds = [b"moderation_council"], bump = council.bump)]
+    pub council: Option<Account<'info, ModerationCouncil>>,
+
+    /// Required for spam deposit changes
+    #[account(mut, seeds = [b"spam_filter"], bump = spam_filter.bump)]
+    pub spam_filter: Option<Account<'info, SpamFilter>>,
+
+    /// CHECK: Receives the queued change's rent; checked against the config
+    #[account(mut)]
+    pub admin: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConfigChange<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"config_change".as_ref(), &pending_change.change_id.to_le_bytes()],
+        bump = pending_change.bump,
+    )]
+    pub pending_change: Account<'info, PendingConfigChange>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ArmFailPoint<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ChaosPlan::LEN,
+        seeds = [b"chaos_plan"],
+        bump
+    )]
+    pub chaos_plan: Account<'info, ChaosPlan>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisarmFailPoint<'info> {
+    #[account(mut, close = admin, seeds = [b"chaos_plan"], bump = chaos_plan.bump)]
+    pub chaos_plan: Account<'info, ChaosPlan>,
+
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitInboxIndex<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = InboxIndex::LEN,
+        seeds = [
+            b"inbox_index".as_ref(),
+            namespace.as_ref().map_or(&[][..], |namespace| namespace.to_account_info().key.as_ref()),
+            owner.key().as_ref(),
+        ],
+        bump
+    )]
+    pub index: Account<'info, InboxIndex>,
+
+    /// Required for a private index
+    #[account(seeds = [b"encryption_key", owner.key().as_ref()], bump = encryption_key.bump)]
+    pub encryption_key: Option<Account<'info, EncryptionKey>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// White-label deployment to create it in; omitted on the public network
+    #[account(seeds = [b"namespace", namespace.name.as_bytes()], bump = namespace.bump)]
+    pub namespace: Option<Account<'info, Namespace>>,
+
+    /// CHECK: Owner's namespace membership PDA, empty when they're on the public network
+    #[account(seeds = [b"namespace_member", owner.key().as_ref()], bump)]
+    pub owner_membership: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInboxPrivacy<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inbox_index", index.namespace_seed(), owner.key().as_ref()],
+        bump = index.bump,
+    )]
+    pub index: Account<'info, InboxIndex>,
+
+    /// Required to go private
+    #[account(seeds = [b"encryption_key", owner.key().as_ref()], bump = encryption_key.bump)]
+    pub encryption_key: Option<Account<'info, EncryptionKey>>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInboxDelegate<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inbox_index", index.namespace_seed(), owner.key().as_ref()],
+        bump = index.bump,
+    )]
+    pub index: Account<'info, InboxIndex>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IndexMessage<'info> {
+    #[account(
+        mut,
+        seeds = [b"inbox_index", index.namespace_seed(), message.recipient.as_ref()],
+        bump = index.bump,
+    )]
+    pub index: Account<'info, InboxIndex>,
+
+    pub message: Account<'info, Message>,
+
+    /// Message sender for a public index; the index owner or their delegate for a private one
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitSpamFilter<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = SpamFilter::LEN,
+        seeds = [b"spam_filter"],
+        bump
+    )]
+    pub spam_filter: Account<'info, SpamFilter>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FlagSpamContent<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"spam_filter"], bump = spam_filter.bump)]
+    pub spam_filter: Account<'info, SpamFilter>,
+
+    pub moderator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseSpamDeposit<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender,
+        seeds = [b"spam_deposit", deposit.message.as_ref()],
+        bump = deposit.bump,
+    )]
+    pub deposit: Account<'info, SpamDeposit>,
+
+    /// CHECK: Receives the deposit back; checked against the deposit
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    /// The recipient, or the sender once the hold has passed
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ForfeitSpamDeposit<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [b"spam_deposit", deposit.message.as_ref()],
+        bump = deposit.bump,
+    )]
+    pub deposit: Account<'info, SpamDeposit>,
+
+    /// Forfeiting counts as the message's spam flag
+    #[account(
+        init,
+        payer = moderator,
+        space = SpamFlag::LEN,
+        seeds = [b"spam_flag", deposit.message.as_ref()],
+        bump
+    )]
+    pub spam_flag: Account<'info, SpamFlag>,
+
+    /// CHECK: Sender's reputation PDA, empty when the sender has none
+    #[account(mut, seeds = [b"reputation", deposit.sender.as_ref()], bump)]
+    pub sender_reputation: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub moderator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(inbox: Pubkey)]
+pub struct SetSpamTrap<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = SpamTrap::LEN,
+        seeds = [b"spam_trap", inbox.as_ref()],
+        bump
+    )]
+    pub spam_trap: Account<'info, SpamTrap>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveSpamTrap<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"spam_trap", spam_trap.inbox.as_ref()],
+        bump = spam_trap.bump
+    )]
+    pub spam_trap: Account<'info, SpamTrap>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+// Permissionless; the trap's seeds tie the message to a designated trap
+#[derive(Accounts)]
+pub struct ProcessSpamTrapMessage<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"spam_trap", message.recipient.as_ref()], bump = spam_trap.bump)]
+    pub spam_trap: Account<'info, SpamTrap>,
+
+    #[account(mut, close = treasury)]
+    pub message: Account<'info, Message>,
+
+    #[account(mut, seeds = [b"spam_filter"], bump = spam_filter.bump)]
+    pub spam_filter: Account<'info, SpamFilter>,
+
+    /// CHECK: Sender's reputation PDA, empty when the sender has none
+    #[account(mut, seeds = [b"reputation", message.sender.as_ref()], bump)]
+    pub sender_reputation: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPatronConfig<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = PatronConfig::LEN,
+        seeds = [b"patron_config"],
+        bump
+    )]
+    pub patron_config: Account<'info, PatronConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePatronConfig<'info> {
+    #[account(has_one = admin, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [b"audit_log"], bump = audit_log.bump)]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut, seeds = [b"patron_config"], bump = patron_config.bump)]
+    pub patron_config: Account<'info, PatronConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[account]
+pub struct Message {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub content: String,
+    pub timestamp: i64,
+    pub nft_mint: Pubkey,
+    pub priority: MessagePriority,
+    /// Client-generated ID included in the PDA seeds; all zero for messages sent without one
+    pub client_message_id: [u8; 16],
+    /// Registered app the message was sent through; default when none
+    pub app: Pubkey,
+    /// White-label namespace; default on the public network
+    pub namespace: Pubkey,
+    pub cost: MessageCost,
+    pub bump: u8,
+}
+
+/// Lamports a send took from the sender, apart from the transaction fee, which the program
+/// can't observe.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct MessageCost {
+    /// Rent for the accounts the send created: the message, its NFT mint and token accounts,
+    /// and any delivery record or spam deposit account
+    pub rent_lamports: u64,
+    /// Paid to the treasury
+    pub protocol_fee_lamports: u64,
+    /// Paid to the recipient for an urgent send
+    pub urgent_fee_lamports: u64,
+    /// Refundable spam deposit
+    pub deposit_lamports: u64,
+}
+
+impl MessageCost {
+    pub const LEN: usize = 8 + // rent_lamports
+        8 + // protocol_fee_lamports
+        8 + // urgent_fee_lamports
+        8; // deposit_lamports
+}
+
+impl Message {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        4 + 500 + // content (String with max 500 chars)
+        8 + // timestamp
+        32 + // nft_mint
+        1 + // priority
+        16 + // client_message_id
+        32 + // app
+        32 + // namespace
+        MessageCost::LEN + // cost
+        1; // bump
+
+    pub const MAX_CONTENT_LEN: usize = 500;
+    /// Content limit for senders holding a live protocol pass
+    pub const MAX_PASS_CONTENT_LEN: usize = 1000;
+
+    /// Account space for `content_len` bytes of content; only pass-length content needs more
+    /// than `LEN`.
+    pub const fn space_for(content_len: usize) -> usize {
+        if content_len > Self::MAX_CONTENT_LEN {
+            Self::LEN + Self::MAX_PASS_CONTENT_LEN - Self::MAX_CONTENT_LEN
+        } else {
+            Self::LEN
+        }
+    }
+
+    /// Last PDA seed: the client message ID, or nothing for messages sent without one, which
+    /// keeps their original address.
+    pub fn client_id_seed(&self) -> &[u8] {
+        if self.client_message_id == [0; 16] {
+            &[]
+        } else {
+            &self.client_message_id
+        }
+    }
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+#[account]
+pub struct EventMessage {
+    pub organizer: Pubkey,
+    pub recipient: Pubkey,
+    pub event_id: u64,
+    pub title: String,
+    pub location_uri: String,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub capacity: u32,
+    pub attendee_count: u32,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl EventMessage {
+    pub const MAX_TITLE_LEN: usize = 64;
+    pub const MAX_LOCATION_URI_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // organizer
+        32 + // recipient
+        8 + // event_id
+        4 + Self::MAX_TITLE_LEN + // title
+        4 + Self::MAX_LOCATION_URI_LEN + // location_uri
+        8 + // start_ts
+        8 + // end_ts
+        4 + // capacity
+        4 + // attendee_count
+        8 + // timestamp
+        1; // bump
+}
+
+#[account]
+pub struct Attendee {
+    pub event: Pubkey,
+    pub attendee: Pubkey,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl Attendee {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // event
+        32 + // attendee
+        8 + // timestamp
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskStatus {
+    Open,
+    Completed,
+}
+
+#[account]
+pub struct TaskMessage {
+    pub creator: Pubkey,
+    pub assignee: Pubkey,
+    pub task_id: u64,
+    pub description: String,
+    pub due_ts: i64,
+    pub status: TaskStatus,
+    pub completed_at: i64,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl TaskMessage {
+    pub const MAX_DESCRIPTION_LEN: usize = 280;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // creator
+        32 + // assignee
+        8 + // task_id
+        4 + Self::MAX_DESCRIPTION_LEN + // description
+        8 + // due_ts
+        1 + // status
+        8 + // completed_at
+        8 + // timestamp
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TradeStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+#[account]
+pub struct TradeProposal {
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    pub trade_id: u64,
+    pub offer_mint: Pubkey,
+    pub offer_amount: u64,
+    pub ask_mint: Pubkey,
+    pub ask_amount: u64,
+    pub status: TradeStatus,
+    pub timestamp: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl TradeProposal {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // proposer
+        32 + // recipient
+        8 + // trade_id
+        32 + // offer_mint
+        8 + // offer_amount
+        32 + // ask_mint
+        8 + // ask_amount
+        1 + // status
+        8 + // timestamp
+        1 + // bump
+        1; // vault_bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvoiceStatus {
+    Unpaid,
+    Paid,
+    Active,
+    Cancelled,
+}
+
+#[account]
+pub struct Invoice {
+    pub issuer: Pubkey,
+    pub payer: Pubkey,
+    pub invoice_id: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub memo: String,
+    pub due_ts: i64,
+    pub status: InvoiceStatus,
+    pub period_secs: i64,
+    pub max_periods: u32,
+    pub periods_paid: u32,
+    pub payer_token_account: Pubkey,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl Invoice {
+    pub const MAX_MEMO_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // issuer
+        32 + // payer
+        8 + // invoice_id
+        32 + // mint
+        8 + // amount
+        4 + Self::MAX_MEMO_LEN + // memo
+        8 + // due_ts
+        1 + // status
+        8 + // period_secs
+        4 + // max_periods
+        4 + // periods_paid
+        32 + // payer_token_account
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn is_recurring(&self) -> bool {
+        self.period_secs > 0
+    }
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+#[account]
+pub struct Receipt {
+    pub invoice: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub paid_at: i64,
+    pub bump: u8,
+}
+
+impl Receipt {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // invoice
+        32 + // payer
+        32 + // mint
+        8 + // amount
+        8 + // paid_at
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnlockCondition {
+    AfterTimestamp { unlock_ts: i64 },
+    PriceAbove { oracle: Pubkey, threshold: i64 },
+    PriceBelow { oracle: Pubkey, threshold: i64 },
+    AccountExists { account: Pubkey },
+    MissedHeartbeat { interval_secs: i64 },
+}
+
+impl UnlockCondition {
+    pub const LEN: usize = 1 + // variant
+        32 + // oracle / account
+        8; // threshold
+}
+
+#[account]
+pub struct ConditionalMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub message_id: u64,
+    pub encrypted_content: String,
+    pub key_hash: [u8; 32],
+    pub condition: UnlockCondition,
+    pub content_key: [u8; 32],
+    pub unlocked_at: i64,
+    pub last_heartbeat: i64,
+    /// When the beneficiary claimed a dead-man message; 0 until then
+    pub claimed_at: i64,
+    pub public_countdown: bool,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl ConditionalMessage {
+    pub const MAX_CONTENT_LEN: usize = 500;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // message_id
+        4 + Self::MAX_CONTENT_LEN + // encrypted_content
+        32 + // key_hash
+        UnlockCondition::LEN + // condition
+        32 + // content_key
+        8 + // unlocked_at
+        8 + // last_heartbeat
+        8 + // claimed_at
+        1 + // public_countdown
+        8 + // timestamp
+        1; // bump
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked_at != 0
+    }
+
+    /// Whether a dead-man switch has fired because the sender stopped checking in.
+    pub fn heartbeat_expired(&self, now: i64) -> bool {
+        match self.condition {
+            UnlockCondition::MissedHeartbeat { interval_secs } => {
+                now >= self.last_heartbeat.saturating_add(interval_secs)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[account]
+pub struct CommittedMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub commit_id: u64,
+    pub commitment: [u8; 32],
+    pub content: String,
+    pub revealed_at: i64,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl CommittedMessage {
+    pub const MAX_CONTENT_LEN: usize = 500;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // commit_id
+        32 + // commitment
+        4 + Self::MAX_CONTENT_LEN + // content
+        8 + // revealed_at
+        8 + // timestamp
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    AwaitingOpponent,
+    Active,
+    Won,
+    Draw,
+}
+
+#[account]
+pub struct GameSession {
+    pub creator: Pubkey,
+    pub opponent: Pubkey,
+    pub game_id: u64,
+    pub board: [u8; 9],
+    pub moves: u8,
+    pub status: GameStatus,
+    pub winner: Pubkey,
+    pub wager: u64,
+    pub timestamp: i64,
+    /// When the game started or the last move was played; 0 until the opponent joins
+    pub last_move_at: i64,
+    pub bump: u8,
+}
+
+impl GameSession {
+    /// How long a player may take to move before the other can claim the game
+    pub const MOVE_TIMEOUT_SECS: i64 = 3 * 24 * 60 * 60;
+
+    const LINES: [[usize; 3]; 8] = [
+        [0, 1, 2], [3, 4, 5], [6, 7, 8],
+        [0, 3, 6], [1, 4, 7], [2, 5, 8],
+        [0, 4, 8], [2, 4, 6],
+    ];
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // creator
+        32 + // opponent
+        8 + // game_id
+        9 + // board
+        1 + // moves
+        1 + // status
+        32 + // winner
+        8 + // wager
+        8 + // timestamp
+        8 + // last_move_at
+        1; // bump
+
+    /// The creator plays X and always moves first.
+    pub fn current_player(&self) -> Pubkey {
+        if self.moves % 2 == 0 { self.creator } else { self.opponent }
+    }
+
+    pub fn other_player(&self, player: Pubkey) -> Pubkey {
+        if player == self.creator { self.opponent } else { self.creator }
+    }
+
+    pub fn has_line(&self, mark: u8) -> bool {
+        Self::LINES
+            .iter()
+            .any(|line| line.iter().all(|&cell| self.board[cell] == mark))
+    }
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub max_poll_stake: u64,
+    pub reputation: ReputationParams,
+    pub attestor: Pubkey,
+    pub crank_bounty_lamports: u64,
+    /// May set and clear content warnings on any message; unset by default
+    pub moderator: Pubkey,
+    /// Receives tip round-ups; unset disables them
+    pub charity: Pubkey,
+    /// Delay between queueing a sensitive change and being able to execute it
+    pub timelock_secs: i64,
+    /// Stops `send_message` while set
+    pub paused: bool,
+    pub bump: u8,
+}
+
+impl Config {
+    /// Cap on the crank bounty, so the treasury can't be drained one crank at a time
+    pub const MAX_CRANK_BOUNTY_LAMPORTS: u64 = 10_000_000;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // admin
+        8 + // max_poll_stake
+        ReputationParams::LEN + // reputation
+        32 + // attestor
+        8 + // crank_bounty_lamports
+        32 + // moderator
+        32 + // charity
+        8 + // timelock_secs
+        1 + // paused
+        1; // bump
+}
+
+#[account]
+pub struct Poll {
+    pub creator: Pubkey,
+    pub recipient: Pubkey,
+    pub poll_id: u64,
+    pub question: String,
+    pub options: Vec<String>,
+    pub votes: [u32; Poll::MAX_OPTIONS],
+    pub stake_per_vote: u64,
+    pub closes_at: i64,
+    pub resolver: Pubkey,
+    pub resolved: bool,
+    pub winning_option: u8,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl Poll {
+    pub const MAX_QUESTION_LEN: usize = 200;
+    pub const MAX_OPTIONS: usize = 4;
+    pub const MAX_OPTION_LEN: usize = 50;
+    /// How long after closing the resolver has to resolve the poll
+    pub const RESOLVE_TIMEOUT_SECS: i64 = 7 * 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // creator
+        32 + // recipient
+        8 + // poll_id
+        4 + Self::MAX_QUESTION_LEN + // question
+        4 + Self::MAX_OPTIONS * (4 + Self::MAX_OPTION_LEN) + // options
+        4 * Self::MAX_OPTIONS + // votes
+        8 + // stake_per_vote
+        8 + // closes_at
+        32 + // resolver
+        1 + // resolved
+        1 + // winning_option
+        8 + // timestamp
+        1; // bump
+
+    pub fn total_stake(&self) -> Result<u64> {
+        let votes: u64 = self.votes.iter().map(|&v| v as u64).sum();
+        Ok(votes.checked_mul(self.stake_per_vote).ok_or(ErrorCode::Overflow)?)
+    }
+
+    /// Whether the resolver has run out of time, leaving the stakes to be refunded.
+    pub fn resolution_expired(&self, now: i64) -> bool {
+        !self.resolved && now >= self.closes_at + Self::RESOLVE_TIMEOUT_SECS
+    }
+}
+
+#[account]
+pub struct Ballot {
+    pub poll: Pubkey,
+    pub voter: Pubkey,
+    pub option: u8,
+    pub stake: u64,
+    pub bump: u8,
+}
+
+impl Ballot {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // poll
+        32 + // voter
+        1 + // option
+        8 + // stake
+        1; // bump
+}
+
+#[account]
+pub struct PriceQuoteMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub quote_id: u64,
+    pub price_feed: Pubkey,
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub slot: u64,
+    pub note: String,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl PriceQuoteMessage {
+    pub const MAX_NOTE_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // quote_id
+        32 + // price_feed
+        8 + // price
+        8 + // conf
+        4 + // expo
+        8 + // slot
+        4 + Self::MAX_NOTE_LEN + // note
+        8 + // timestamp
+        1; // bump
+}
+
+#[account]
+pub struct GovernanceNotifier {
+    pub governance: Pubkey,
+    pub poster: Pubkey,
+    pub bump: u8,
+}
+
+impl GovernanceNotifier {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // governance
+        32 + // poster
+        1; // bump
+}
+
+#[account]
+pub struct ProposalNotification {
+    pub governance: Pubkey,
+    pub proposal: Pubkey,
+    pub title: String,
+    pub voting_ends_at: i64,
+    pub poster: Pubkey,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl ProposalNotification {
+    pub const MAX_TITLE_LEN: usize = 100;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // governance
+        32 + // proposal
+        4 + Self::MAX_TITLE_LEN + // title
+        8 + // voting_ends_at
+        32 + // poster
+        8 + // timestamp
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketEventKind {
+    Listing,
+    Sale,
+    Bid,
+}
+
+#[account]
+pub struct CollectionFeed {
+    pub collection: Pubkey,
+    pub owner: Pubkey,
+    pub bot: Pubkey,
+    pub notice_count: u64,
+    pub bump: u8,
+}
+
+impl CollectionFeed {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // collection
+        32 + // owner
+        32 + // bot
+        8 + // notice_count
+        1; // bump
+}
+
+#[account]
+pub struct MarketNotice {
+    pub feed: Pubkey,
+    pub index: u64,
+    pub kind: MarketEventKind,
+    pub nft_mint: Pubkey,
+    pub price: u64,
+    pub counterparty: Pubkey,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl MarketNotice {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // feed
+        8 + // index
+        1 + // kind
+        32 + // nft_mint
+        8 + // price
+        32 + // counterparty
+        8 + // timestamp
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NotificationSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[account]
+pub struct Notifier {
+    pub authority: Pubkey,
+    pub name: String,
+    pub notification_count: u64,
+    pub bump: u8,
+}
+
+impl Notifier {
+    pub const MAX_NAME_LEN: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority
+        4 + Self::MAX_NAME_LEN + // name
+        8 + // notification_count
+        1; // bump
+}
+
+#[account]
+pub struct Notification {
+    pub notifier: Pubkey,
+    pub recipient: Pubkey,
+    pub index: u64,
+    pub severity: NotificationSeverity,
+    pub content: String,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl Notification {
+    pub const MAX_CONTENT_LEN: usize = 280;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // notifier
+        32 + // recipient
+        8 + // index
+        1 + // severity
+        4 + Self::MAX_CONTENT_LEN + // content
+        8 + // timestamp
+        1; // bump
+}
+
+#[account]
+pub struct MerchantProfile {
+    pub owner: Pubkey,
+    pub name: String,
+    pub settlement_account: Pubkey,
+    pub receipt_count: u64,
+    pub bump: u8,
+}
+
+impl MerchantProfile {
+    pub const MAX_NAME_LEN: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + Self::MAX_NAME_LEN + // name
+        32 + // settlement_account
+        8 + // receipt_count
+        1; // bump
+}
+
+#[account]
+pub struct MerchantReceipt {
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub reference: Pubkey,
+    pub order_ref: String,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl MerchantReceipt {
+    pub const MAX_ORDER_REF_LEN: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // merchant
+        32 + // customer
+        8 + // index
+        8 + // amount
+        32 + // reference
+        4 + Self::MAX_ORDER_REF_LEN + // order_ref
+        8 + // timestamp
+        1; // bump
+}
+
+/// Marks a Solana Pay reference as used by a merchant receipt.
+#[account]
+pub struct ReceiptReference {
+    pub receipt: Pubkey,
+    pub bump: u8,
+}
+
+impl ReceiptReference {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // receipt
+        1; // bump
+}
+
+#[account]
+pub struct TeamInbox {
+    pub owner: Pubkey,
+    pub name: String,
+    pub members: Vec<Pubkey>,
+    pub ticket_count: u64,
+    pub bump: u8,
+}
+
+impl TeamInbox {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_MEMBERS: usize = 10;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + Self::MAX_NAME_LEN + // name
+        4 + 32 * Self::MAX_MEMBERS + // members
+        8 + // ticket_count
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TicketStatus {
+    Open,
+    InProgress,
+    Resolved,
+}
+
+#[account]
+pub struct Ticket {
+    pub team_inbox: Pubkey,
+    pub opener: Pubkey,
+    pub index: u64,
+    pub subject: String,
+    pub status: TicketStatus,
+    pub opened_at: i64,
+    pub first_response_at: i64,
+    pub resolved_at: i64,
+    pub reopen_count: u16,
+    pub rating: u8,
+    pub rating_comment: String,
+    pub bump: u8,
+}
+
+impl Ticket {
+    pub const MAX_SUBJECT_LEN: usize = 100;
+    pub const MAX_RATING_COMMENT_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // team_inbox
+        32 + // opener
+        8 + // index
+        4 + Self::MAX_SUBJECT_LEN + // subject
+        1 + // status
+        8 + // opened_at
+        8 + // first_response_at
+        8 + // resolved_at
+        2 + // reopen_count
+        1 + // rating
+        4 + Self::MAX_RATING_COMMENT_LEN + // rating_comment
+        1; // bump
+}
+
+#[account]
+pub struct TeamInboxStats {
+    pub team_inbox: Pubkey,
+    pub rating_count: u64,
+    pub rating_sum: u64,
+    pub rating_histogram: [u32; 5],
+    pub bump: u8,
+}
+
+impl TeamInboxStats {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // team_inbox
+        8 + // rating_count
+        8 + // rating_sum
+        4 * 5 + // rating_histogram
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DealStatus {
+    Negotiating,
+    Agreed,
+    Funded,
+    Disputed,
+    Released,
+    Refunded,
+    Ruled,
+}
+
+#[account]
+pub struct DealRoom {
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub arbiter: Pubkey,
+    pub deal_id: u64,
+    pub mint: Pubkey,
+    pub offer_amount: u64,
+    pub offer_by: Pubkey,
+    pub price: u64,
+    pub status: DealStatus,
+    pub message_count: u64,
+    /// When the buyer funded the escrow; 0 before
+    pub funded_at: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl DealRoom {
+    /// How long a funded deal can go unsettled before the buyer may take the escrow back
+    pub const SETTLE_TIMEOUT_SECS: i64 = 30 * 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // seller
+        32 + // buyer
+        32 + // arbiter
+        8 + // deal_id
+        32 + // mint
+        8 + // offer_amount
+        32 + // offer_by
+        8 + // price
+        1 + // status
+        8 + // message_count
+        8 + // funded_at
+        1 + // bump
+        1; // vault_bump
+
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.buyer || *key == self.seller
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DealMessageKind {
+    Offer,
+    Accept,
+    Fund,
+    Dispute,
+    Release,
+    ArbiterAssigned,
+    Ruling,
+    Refund,
+}
+
+#[account]
+pub struct DealMessage {
+    pub deal: Pubkey,
+    pub index: u64,
+    pub author: Pubkey,
+    pub kind: DealMessageKind,
+    pub amount: u64,
+    pub note: String,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl DealMessage {
+    pub const MAX_NOTE_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // deal
+        8 + // index
+        32 + // author
+        1 + // kind
+        8 + // amount
+        4 + Self::MAX_NOTE_LEN + // note
+        8 + // timestamp
+        1; // bump
+
+    /// Writes the next typed message of a deal room's conversation; the caller sets the bump.
+    pub fn record(
+        &mut self,
+        deal: &Account<DealRoom>,
+        author: Pubkey,
+        kind: DealMessageKind,
+        amount: u64,
+        note: String,
+        timestamp: i64,
+    ) {
+        self.deal = deal.key();
+        self.index = deal.message_count;
+        self.author = author;
+        self.kind = kind;
+        self.amount = amount;
+        self.note = note;
+        self.timestamp = timestamp;
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReputationParams {
+    pub answered_weight: i32,
+    pub resolved_weight: i32,
+    pub dispute_penalty: i32,
+    pub spam_penalty: i32,
+    /// Score added per point of vouch weight
+    pub vouch_weight: i32,
+    /// Score halves every `half_life_secs`; zero disables decay
+    pub half_life_secs: i64,
+}
+
+impl ReputationParams {
+    pub const LEN: usize = 4 + // answered_weight
+        4 + // resolved_weight
+        4 + // dispute_penalty
+        4 + // spam_penalty
+        4 + // vouch_weight
+        8; // half_life_secs
+}
+
+impl Default for ReputationParams {
+    fn default() -> Self {
+        Self {
+            answered_weight: 1,
+            resolved_weight: 5,
+            dispute_penalty: 20,
+            spam_penalty: 10,
+            vouch_weight: 2,
+            half_life_secs: 90 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReputationEvent {
+    MessageAnswered,
+    TicketResolved,
+    DisputeLost,
+    SpamFlagged,
+    Vouched { weight: u8 },
+    VouchRevoked { weight: u8 },
+}
+
+#[account]
+pub struct Reputation {
+    pub user: Pubkey,
+    pub score: i64,
+    pub messages_answered: u32,
+    pub tickets_resolved: u32,
+    pub disputes_lost: u32,
+    pub spam_flags: u32,
+    pub vouches: u32,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl Reputation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // user
+        8 + // score
+        4 + // messages_answered
+        4 + // tickets_resolved
+        4 + // disputes_lost
+        4 + // spam_flags
+        4 + // vouches
+        8 + // last_updated
+        1; // bump
+
+    /// Score decayed to `now`: halved per elapsed half-life, linearly within the current one.
+    pub fn decayed_score(&self, params: &ReputationParams, now: i64) -> i64 {
+        let elapsed = now.saturating_sub(self.last_updated).max(0);
+        if params.half_life_secs == 0 || elapsed == 0 {
+            return self.score;
+        }
+
+        let halvings = elapsed / params.half_life_secs;
+        if halvings >= 63 {
+            return 0;
+        }
+        let score = self.score / (1i64 << halvings);
+        let remainder = elapsed % params.half_life_secs;
+        score - (score as i128 * remainder as i128 / (2 * params.half_life_secs as i128)) as i64
+    }
+
+    pub fn record(&mut self, event: ReputationEvent, params: &ReputationParams, now: i64) {
+        let delta = match event {
+            ReputationEvent::MessageAnswered => {
+                self.messages_answered = self.messages_answered.saturating_add(1);
+                params.answered_weight
+            }
+            ReputationEvent::TicketResolved => {
+                self.tickets_resolved = self.tickets_resolved.saturating_add(1);
+                params.resolved_weight
+            }
+            ReputationEvent::DisputeLost => {
+                self.disputes_lost = self.disputes_lost.saturating_add(1);
+                -params.dispute_penalty
+            }
+            ReputationEvent::SpamFlagged => {
+                self.spam_flags = self.spam_flags.saturating_add(1);
+                -params.spam_penalty
+            }
+            ReputationEvent::Vouched { weight } => {
+                self.vouches = self.vouches.saturating_add(1);
+                params.vouch_weight.saturating_mul(weight as i32)
+            }
+            ReputationEvent::VouchRevoked { weight } => {
+                self.vouches = self.vouches.saturating_sub(1);
+                -params.vouch_weight.saturating_mul(weight as i32)
+            }
+        };
+
+        self.score = self.decayed_score(params, now).saturating_add(delta as i64);
+        self.last_updated = now;
+    }
+}
+
+#[account]
+pub struct Vouch {
+    pub voucher: Pubkey,
+    pub vouchee: Pubkey,
+    pub weight: u8,
+    pub stake: u64,
+    pub vouchee_flags_at: u32,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl Vouch {
+    pub const MAX_WEIGHT: u8 = 10;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // voucher
+        32 + // vouchee
+        1 + // weight
+        8 + // stake
+        4 + // vouchee_flags_at
+        8 + // timestamp
+        1; // bump
+}
+
+/// A direct message the moderator marked as spam
+#[account]
+pub struct SpamFlag {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub flagged_by: Pubkey,
+    pub flagged_at: i64,
+    pub bump: u8,
+}
+
+impl SpamFlag {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        32 + // sender
+        32 + // flagged_by
+        8 + // flagged_at
+        1; // bump
+}
+
+#[account]
+pub struct Attestation {
+    pub subject: Pubkey,
+    pub tx_count: u64,
+    pub first_activity_ts: i64,
+    pub attested_at: i64,
+    pub bump: u8,
+}
+
+impl Attestation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // subject
+        8 + // tx_count
+        8 + // first_activity_ts
+        8 + // attested_at
+        1; // bump
+}
+
+#[account]
+pub struct InboxGate {
+    pub owner: Pubkey,
+    pub min_stake_age_epochs: u64,
+    pub min_tx_count: u64,
+    pub min_account_age_secs: i64,
+    pub bump: u8,
+}
+
+impl InboxGate {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        8 + // min_stake_age_epochs
+        8 + // min_tx_count
+        8 + // min_account_age_secs
+        1; // bump
+}
+
+#[account]
+pub struct EncryptionKey {
+    pub owner: Pubkey,
+    pub key: [u8; 32],
+    pub version: u32,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl EncryptionKey {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // key
+        4 + // version
+        8 + // updated_at
+        1; // bump
+}
+
+#[account]
+pub struct GuardianSet {
+    pub owner: Pubkey,
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timelock_secs: i64,
+    pub bump: u8,
+}
+
+impl GuardianSet {
+    pub const MAX_GUARDIANS: usize = 10;
+    pub const MIN_TIMELOCK_SECS: i64 = 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + 32 * Self::MAX_GUARDIANS + // guardians
+        1 + // threshold
+        8 + // timelock_secs
+        1; // bump
+}
+
+#[account]
+pub struct RecoveryRequest {
+    pub owner: Pubkey,
+    pub proposer: Pubkey,
+    pub new_key: [u8; 32],
+    pub approvals: Vec<Pubkey>,
+    pub proposed_at: i64,
+    pub bump: u8,
+}
+
+impl RecoveryRequest {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // proposer
+        32 + // new_key
+        4 + 32 * GuardianSet::MAX_GUARDIANS + // approvals
+        8 + // proposed_at
+        1; // bump
+}
+
+#[account]
+pub struct Forwarding {
+    pub old_wallet: Pubkey,
+    pub new_wallet: Pubkey,
+    pub migrated_at: i64,
+    pub bump: u8,
+}
+
+impl Forwarding {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // old_wallet
+        32 + // new_wallet
+        8 + // migrated_at
+        1; // bump
+}
+
+#[account]
+pub struct DmLink {
+    pub owner: Pubkey,
+    pub slug: String,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub expires_at: i64,
+    pub created_at: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl DmLink {
+    pub const MAX_SLUG_LEN: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + Self::MAX_SLUG_LEN + // slug
+        4 + // max_uses
+        4 + // uses
+        8 + // expires_at (0 = never)
+        8 + // created_at
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+#[account]
+pub struct AmaInbox {
+    pub owner: Pubkey,
+    pub title: String,
+    pub question_count: u64,
+    pub is_open: bool,
+    pub bump: u8,
+}
+
+impl AmaInbox {
+    pub const MAX_TITLE_LEN: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + Self::MAX_TITLE_LEN + // title
+        8 + // question_count
+        1 + // is_open
+        1; // bump
+}
+
+#[account]
+pub struct AmaQuestion {
+    pub ama_inbox: Pubkey,
+    pub index: u64,
+    pub commitment: [u8; 32],
+    pub content: String,
+    pub asked_at: i64,
+    pub answer: String,
+    pub answered_at: i64,
+    pub bump: u8,
+}
+
+impl AmaQuestion {
+    pub const MAX_CONTENT_LEN: usize = 280;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // ama_inbox
+        8 + // index
+        32 + // commitment
+        4 + Self::MAX_CONTENT_LEN + // content
+        8 + // asked_at
+        4 + Self::MAX_CONTENT_LEN + // answer
+        8 + // answered_at
+        1; // bump
+}
+
+#[account]
+pub struct Room {
+    pub creator: Pubkey,
+    pub room_id: u64,
+    pub name: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub message_count: u64,
+    /// Advisory minimum reader age; 0 when unrestricted
+    pub min_age: u8,
+    pub region_mode: RegionMode,
+    /// ISO 3166-1 alpha-2 codes the mode applies to
+    pub regions: Vec<[u8; 2]>,
+    /// Member posts wait for the creator's approval
+    pub moderated: bool,
+    /// Active stake that must vote to hide a reported post; 0 disables community moderation
+    pub hide_threshold_lamports: u64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl Room {
+    pub const MAX_NAME_LEN: usize = 64;
+    pub const MAX_TTL_SECS: i64 = 30 * 24 * 60 * 60;
+    pub const MAX_REGIONS: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // creator
+        8 + // room_id
+        4 + Self::MAX_NAME_LEN + // name
+        8 + // created_at
+        8 + // expires_at
+        8 + // message_count
+        1 + // min_age
+        1 + // region_mode
+        4 + Self::MAX_REGIONS * 2 + // regions
+        1 + // moderated
+        8 + // hide_threshold_lamports
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+/// How a room's region list applies. `Unrestricted` is first so it is the zero value.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegionMode {
+    Unrestricted,
+    /// Only readers in the listed regions
+    Allow,
+    /// Everyone except readers in the listed regions
+    Deny,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoomMessageStatus {
+    Published,
+    /// Waiting in a moderated room's queue; clients show it only to the author and creator
+    Pending,
+    /// Hidden by a community vote
+    Hidden,
+}
+
+#[account]
+pub struct RoomMessage {
+    pub room: Pubkey,
+    pub author: Pubkey,
+    /// Copied from the room so the rent refund still has a destination after the room is closed
+    pub room_creator: Pubkey,
+    pub index: u64,
+    pub content: String,
+    /// Original send time for imported messages
+    pub timestamp: i64,
+    pub expires_at: i64,
+    /// Migrated from another platform by the room creator rather than posted by `author`
+    pub imported: bool,
+    pub status: RoomMessageStatus,
+    pub bump: u8,
+}
+
+impl RoomMessage {
+    pub const MAX_CONTENT_LEN: usize = 280;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // room
+        32 + // author
+        32 + // room_creator
+        8 + // index
+        4 + Self::MAX_CONTENT_LEN + // content
+        8 + // timestamp
+        8 + // expires_at
+        1 + // imported
+        1 + // status
+        1; // bump
+}
+
+#[account]
+pub struct Treasury {
+    pub bump: u8,
+}
+
+impl Treasury {
+    pub const LEN: usize = 8 + // discriminator
+        1; // bump
+}
+
+#[account]
+pub struct ContentBlob {
+    pub payer: Pubkey,
+    pub content_hash: [u8; 32],
+    pub content: String,
+    pub refcount: u32,
+    pub bump: u8,
+}
+
+impl ContentBlob {
+    pub const MAX_CONTENT_LEN: usize = 500;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // payer
+        32 + // content_hash
+        4 + Self::MAX_CONTENT_LEN + // content
+        4 + // refcount
+        1; // bump
+}
+
+/// A message whose content lives in a shared `ContentBlob`, so broadcasts only pay rent for
+/// the content once.
+#[account]
+pub struct BlobMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub blob: Pubkey,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl BlobMessage {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // nonce
+        32 + // blob
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+#[account]
+pub struct MessageDictionary {
+    pub entries: Vec<String>,
+    pub bump: u8,
+}
+
+impl MessageDictionary {
+    pub const MAX_ENTRIES: usize = 64;
+    pub const MAX_WORD_LEN: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        4 + Self::MAX_ENTRIES * (4 + Self::MAX_WORD_LEN) + // entries
+        1; // bump
+}
+
+/// A one-word message stored as an index into the `MessageDictionary`.
+#[account]
+pub struct InternedMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub word_index: u16,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl InternedMessage {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // nonce
+        2 + // word_index
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+#[account]
+pub struct EpochStats {
+    pub epoch: u64,
+    pub messages: u64,
+    pub active_senders: u32,
+    pub tips_volume_lamports: u64,
+    pub sender_bloom: [u8; 256],
+    pub opened_at: i64,
+    pub bump: u8,
+}
+
+impl EpochStats {
+    pub const BLOOM_BYTES: usize = 256;
+    pub const BLOOM_HASHES: usize = 3;
+
+    pub const LEN: usize = 8 + // discriminator
+        8 + // epoch
+        8 + // messages
+        4 + // active_senders (approximate, from bloom insertions)
+        8 + // tips_volume_lamports
+        Self::BLOOM_BYTES + // sender_bloom
+        8 + // opened_at
+        1; // bump
+
+    /// Adds a tip to the epoch's volume; ignored once the account's epoch has passed.
+    pub fn record_tip(&mut self, lamports: u64, current_epoch: u64) -> Result<()> {
+        if self.epoch == current_epoch {
+            self.tips_volume_lamports = self.tips_volume_lamports.checked_add(lamports).ok_or(ErrorCode::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Counts a message; ignored once the account's epoch has passed.
+    pub fn record_message(&mut self, sender: &Pubkey, current_epoch: u64) -> Result<()> {
+        if self.epoch != current_epoch {
+            return Ok(());
+        }
+        self.messages = self.messages.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        if self.insert_sender(sender) {
+            self.active_senders = self.active_senders.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `sender` to the bloom filter, returning whether it was (probably) not seen before.
+    fn insert_sender(&mut self, sender: &Pubkey) -> bool {
+        let digest = hash(sender.as_ref()).to_bytes();
+        let bits = Self::BLOOM_BYTES * 8;
+        let mut is_new = false;
+
+        for i in 0..Self::BLOOM_HASHES {
+            let bit = u16::from_le_bytes([digest[2 * i], digest[2 * i + 1]]) as usize % bits;
+            let mask = 1u8 << (bit % 8);
+            if self.sender_bloom[bit / 8] & mask == 0 {
+                self.sender_bloom[bit / 8] |= mask;
+                is_new = true;
+            }
+        }
+
+        is_new
+    }
+}
+
+#[account]
+pub struct UserStats {
+    pub user: Pubkey,
+    pub messages_sent: u64,
+    /// Ring of the last 52 weeks, one bit per UTC day, indexed by `day % HEATMAP_DAYS`
+    pub activity_bitmap: [u8; 46],
+    /// UTC day number (days since the unix epoch) of the last recorded activity
+    pub last_active_day: i64,
+    pub bump: u8,
+}
+
+impl UserStats {
+    pub const HEATMAP_DAYS: i64 = 52 * 7;
+    pub const BITMAP_BYTES: usize = 46;
+    const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // user
+        8 + // messages_sent
+        Self::BITMAP_BYTES + // activity_bitmap
+        8 + // last_active_day
+        1; // bump
+
+    pub fn record_activity(&mut self, now: i64) {
+        let today = now.div_euclid(Self::SECS_PER_DAY);
+        self.messages_sent += 1;
+
+        // Days skipped since the last activity fall back into the ring and must read as idle
+        if today > self.last_active_day {
+            let stale_days = (today - self.last_active_day).min(Self::HEATMAP_DAYS + 1);
+            for day in (today - stale_days + 1)..=today {
+                self.set_day(day, false);
+            }
+            self.last_active_day = today;
+        }
+        self.set_day(today, true);
+    }
+
+    pub fn was_active(&self, day: i64) -> bool {
+        if day > self.last_active_day || self.last_active_day - day >= Self::HEATMAP_DAYS {
+            return false;
+        }
+        let bit = day.rem_euclid(Self::HEATMAP_DAYS) as usize;
+        self.activity_bitmap[bit / 8] & (1 << (bit % 8)) != 0
+    }
+
+    fn set_day(&mut self, day: i64, active: bool) {
+        let bit = day.rem_euclid(Self::HEATMAP_DAYS) as usize;
+        if active {
+            self.activity_bitmap[bit / 8] |= 1 << (bit % 8);
+        } else {
+            self.activity_bitmap[bit / 8] &= !(1 << (bit % 8));
+        }
+    }
+}
+
+/// Append-only Merkle log of every encryption key registry change. Only the frontier is kept
+/// on-chain; clients rebuild the tree from `KeyLogAppended` events and compare roots.
+#[account]
+pub struct KeyTransparencyLog {
+    pub leaf_count: u64,
+    pub frontier: [[u8; 32]; 20],
+    pub root: [u8; 32],
+    pub bump: u8,
+}
+
+impl KeyTransparencyLog {
+    pub const DEPTH: usize = 20;
+
+    pub const LEN: usize = 8 + // discriminator
+        8 + // leaf_count
+        32 * Self::DEPTH + // frontier
+        32 + // root
+        1; // bump
+
+    pub fn leaf_hash(index: u64, entry: &EncryptionKey) -> [u8; 32] {
+        hashv(&[
+            b"key_log_leaf",
+            &index.to_le_bytes(),
+            entry.owner.as_ref(),
+            &entry.key,
+            &entry.version.to_le_bytes(),
+            &entry.updated_at.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+
+    pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        hashv(&[b"key_log_node", left, right]).to_bytes()
+    }
+
+    pub fn empty_root() -> [u8; 32] {
+        let mut zero = [0u8; 32];
+        for _ in 0..Self::DEPTH {
+            zero = Self::node_hash(&zero, &zero);
+        }
+        zero
+    }
+
+    pub fn append(&mut self, entry: &EncryptionKey) -> Result<()> {
+        require!(self.leaf_count < 1 << Self::DEPTH, ErrorCode::KeyLogFull);
+
+        let index = self.leaf_count;
+        let leaf = Self::leaf_hash(index, entry);
+        let mut node = leaf;
+        let mut zero = [0u8; 32];
+        let mut position = index;
+
+        // Left children are remembered in the frontier and paired with an empty right subtree
+        for level in 0..Self::DEPTH {
+            if position & 1 == 0 {
+                self.frontier[level] = node;
+                node = Self::node_hash(&node, &zero);
+            } else {
+                node = Self::node_hash(&self.frontier[level], &node);
+            }
+            zero = Self::node_hash(&zero, &zero);
+            position >>= 1;
+        }
+
+        self.root = node;
+        self.leaf_count += 1;
+
+        emit!(KeyLogAppended {
+            index,
+            owner: entry.owner,
+            key: entry.key,
+            version: entry.version,
+            updated_at: entry.updated_at,
+            leaf,
+            root: self.root,
+        });
+
+        Ok(())
+    }
+}
+
+#[account]
+pub struct ConversationSettings {
+    /// Ordered so each pair has exactly one settings account
+    pub participants: [Pubkey; 2],
+    pub confirmed: [bool; 2],
+    pub verified: bool,
+    /// `EncryptionKey::version` of each participant when the safety number was confirmed
+    pub key_versions: [u32; 2],
+    pub safety_number: [u8; 32],
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl ConversationSettings {
+    pub const LEN: usize = 8 + // discriminator
+        32 * 2 + // participants
+        2 + // confirmed
+        1 + // verified
+        4 * 2 + // key_versions
+        32 + // safety_number
+        32 + // namespace
+        1; // bump
+
+    /// Hash of both registered keys. Clients display it as digits for out-of-band comparison.
+    pub fn safety_number(key_a: &EncryptionKey, key_b: &EncryptionKey) -> [u8; 32] {
+        hashv(&[b"safety_number", key_a.owner.as_ref(), &key_a.key, key_b.owner.as_ref(), &key_b.key]).to_bytes()
+    }
+
+    pub fn side_of(&self, participant: &Pubkey) -> Result<usize> {
+        self.participants
+            .iter()
+            .position(|p| p == participant)
+            .ok_or_else(|| error!(ErrorCode::NotConversationParticipant))
+    }
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+/// A per-message decryption key published by one of the participants, making an encrypted
+/// message publicly readable. Sender and recipient each get their own disclosure account.
+/// Clients derive the key with HKDF from the conversation key and the message address, so it
+/// opens no other message in the conversation.
+#[account]
+pub struct Disclosure {
+    pub message: Pubkey,
+    pub discloser: Pubkey,
+    pub decryption_key: Vec<u8>,
+    pub disclosed_at: i64,
+    pub bump: u8,
+}
+
+impl Disclosure {
+    pub const MAX_KEY_LEN: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        32 + // discloser
+        4 + Self::MAX_KEY_LEN + // decryption_key
+        8 + // disclosed_at
+        1; // bump
+}
+
+/// Read-only access to a conversation for a named observer, e.g. a compliance officer or a
+/// parent. Observers get no send rights; the grant only scopes which keys may be shared.
+#[account]
+pub struct ObserverGrant {
+    /// `ConversationSettings` of the observed pair
+    pub conversation: Pubkey,
+    pub granter: Pubkey,
+    pub observer: Pubkey,
+    pub label: String,
+    pub granted_at: i64,
+    pub bump: u8,
+}
+
+impl ObserverGrant {
+    pub const MAX_LABEL_LEN: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // conversation
+        32 + // granter
+        32 + // observer
+        4 + Self::MAX_LABEL_LEN + // label
+        8 + // granted_at
+        1; // bump
+}
+
+/// A message key re-encrypted to an observer
+#[account]
+pub struct ObserverKey {
+    pub grant: Pubkey,
+    pub message: Pubkey,
+    pub wrapped_key: Vec<u8>,
+    pub bump: u8,
+}
+
+impl ObserverKey {
+    /// Room for a 64-byte key sealed to the observer's X25519 key
+    pub const MAX_WRAPPED_KEY_LEN: usize = 128;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // grant
+        32 + // message
+        4 + Self::MAX_WRAPPED_KEY_LEN + // wrapped_key
+        1; // bump
+}
+
+/// Daily UTC window in which the owner accepts deliveries. A window whose end is before its
+/// start wraps past midnight.
+#[account]
+pub struct DeliveryWindow {
+    pub owner: Pubkey,
+    /// Minutes after UTC midnight
+    pub start_minute: u16,
+    pub end_minute: u16,
+    pub bump: u8,
+}
+
+impl DeliveryWindow {
+    pub const MINUTES_PER_DAY: u16 = 24 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        2 + // start_minute
+        2 + // end_minute
+        1; // bump
+
+    pub fn validate(start_minute: u16, end_minute: u16) -> Result<()> {
+        require!(
+            start_minute < Self::MINUTES_PER_DAY && end_minute < Self::MINUTES_PER_DAY && start_minute != end_minute,
+            ErrorCode::InvalidDeliveryWindow
+        );
+        Ok(())
+    }
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+
+    /// `now` if the window is open, otherwise the start of the next window.
+    pub fn next_open(&self, now: i64) -> i64 {
+        const SECS_PER_DAY: i64 = 86_400;
+
+        let minute = (now.rem_euclid(SECS_PER_DAY) / 60) as u16;
+        let open = if self.start_minute < self.end_minute {
+            minute >= self.start_minute && minute < self.end_minute
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        };
+        if open {
+            return now;
+        }
+
+        let start_today = now - now.rem_euclid(SECS_PER_DAY) + self.start_minute as i64 * 60;
+        if start_today > now {
+            start_today
+        } else {
+            start_today + SECS_PER_DAY
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeliveryState {
+    Queued,
+    Delivered,
+}
+
+/// Delivery status of a `Message` sent outside the recipient's delivery window
+#[account]
+pub struct MessageDelivery {
+    pub message: Pubkey,
+    pub recipient: Pubkey,
+    pub state: DeliveryState,
+    /// Start of the recipient's next window when the message was sent
+    pub deliver_at: i64,
+    pub delivered_at: i64,
+    pub bump: u8,
+}
+
+impl MessageDelivery {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        32 + // recipient
+        1 + // state
+        8 + // deliver_at
+        8 + // delivered_at
+        1; // bump
+}
+
+/// Sort hint chosen by the sender. `Normal` is first so it is the zero value.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessagePriority {
+    Normal,
+    Low,
+    Urgent,
+}
+
+/// Who may mark messages to the owner as urgent. Without a policy, urgent is limited to contacts.
+#[account]
+pub struct PriorityPolicy {
+    pub owner: Pubkey,
+    /// Lamports a non-contact pays the owner to send an urgent message; 0 disables paying
+    pub urgent_fee_lamports: u64,
+    /// Lets urgent messages through for free from peers whose safety number the owner confirmed
+    pub urgent_from_contacts: bool,
+    pub bump: u8,
+}
+
+impl PriorityPolicy {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        8 + // urgent_fee_lamports
+        1 + // urgent_from_contacts
+        1; // bump
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+}
+
+/// "Remind me about this message", synced across the owner's clients
+#[account]
+pub struct Reminder {
+    pub owner: Pubkey,
+    pub message: Pubkey,
+    pub remind_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Reminder {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // message
+        8 + // remind_at
+        8 + // created_at
+        1; // bump
+}
+
+/// Hides the thread with `peer` from the owner's inbox until `until`
+#[account]
+pub struct Snooze {
+    pub owner: Pubkey,
+    pub peer: Pubkey,
+    pub until: i64,
+    pub bump: u8,
+}
+
+impl Snooze {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // peer
+        8 + // until
+        1; // bump
+}
+
+/// Author-provided translation of a message's content.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ContentSegment {
+    /// BCP 47 tag, e.g. "es" or "pt-BR"
+    pub language: String,
+    pub content: String,
+}
+
+/// Translations of a message, so one post serves several languages and clients pick the
+/// reader's. The message's own content is in `primary_language`.
+#[account]
+pub struct MessageTranslations {
+    pub message: Pubkey,
+    pub primary_language: String,
+    pub segments: Vec<ContentSegment>,
+    pub bump: u8,
+}
+
+impl MessageTranslations {
+    pub const MAX_SEGMENTS: usize = 4;
+    pub const MAX_LANGUAGE_LEN: usize = 16;
+    /// Across all segments
+    pub const MAX_TOTAL_CONTENT_LEN: usize = 1000;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        4 + Self::MAX_LANGUAGE_LEN + // primary_language
+        4 + Self::MAX_SEGMENTS * (4 + Self::MAX_LANGUAGE_LEN + 4) + Self::MAX_TOTAL_CONTENT_LEN + // segments
+        1; // bump
+}
+
+/// A media file stored off-chain (IPFS or HTTPS) and described on-chain.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Attachment {
+    pub uri: String,
+    pub mime_type: String,
+    /// SHA-256 of the file, so clients can detect a swapped upload
+    pub content_hash: [u8; 32],
+    /// Accessible description, required for every attachment
+    pub alt_text: String,
+}
+
+#[account]
+pub struct MessageMedia {
+    pub message: Pubkey,
+    pub attachments: Vec<Attachment>,
+    pub bump: u8,
+}
+
+impl MessageMedia {
+    pub const MAX_ATTACHMENTS: usize = 4;
+    pub const MAX_URI_LEN: usize = 128;
+    pub const MAX_MIME_TYPE_LEN: usize = 64;
+    pub const MAX_ALT_TEXT_LEN: usize = 250;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        4 + Self::MAX_ATTACHMENTS * (
+            4 + Self::MAX_URI_LEN + // uri
+            4 + Self::MAX_MIME_TYPE_LEN + // mime_type
+            32 + // content_hash
+            4 + Self::MAX_ALT_TEXT_LEN // alt_text
+        ) + // attachments
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContentWarningCategory {
+    Nsfw,
+    Violence,
+    Spoiler,
+    /// Self-harm, abuse and other distressing topics
+    Sensitive,
+}
+
+impl ContentWarningCategory {
+    pub const COUNT: usize = 4;
+}
+
+/// Warning label on a message, so every client blurs or gates it the same way.
+#[account]
+pub struct ContentWarning {
+    pub message: Pubkey,
+    pub category: ContentWarningCategory,
+    /// Set by the moderator rather than the sender; only the moderator can clear it
+    pub by_moderator: bool,
+    pub payer: Pubkey,
+    pub bump: u8,
+}
+
+impl ContentWarning {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        1 + // category
+        1 + // by_moderator
+        32 + // payer
+        1; // bump
+}
+
+/// What a client does with a warned message. `Blur` is first so it is the default.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WarningAction {
+    Blur,
+    Hide,
+    Show,
+}
+
+/// A recipient's auto-hide rules, one action per `ContentWarningCategory`.
+#[account]
+pub struct ContentFilter {
+    pub owner: Pubkey,
+    pub actions: [WarningAction; ContentWarningCategory::COUNT],
+    pub bump: u8,
+}
+
+impl ContentFilter {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        ContentWarningCategory::COUNT + // actions
+        1; // bump
+}
+
+/// A community report against a room post, tallying stake-weighted votes to hide it.
+#[account]
+pub struct RoomReport {
+    pub room: Pubkey,
+    pub message: Pubkey,
+    pub reporter: Pubkey,
+    pub threshold_lamports: u64,
+    pub tally_lamports: u64,
+    pub votes: u32,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl RoomReport {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // room
+        32 + // message
+        32 + // reporter
+        8 + // threshold_lamports
+        8 + // tally_lamports
+        4 + // votes
+        8 + // created_at
+        1; // bump
+}
+
+#[account]
+pub struct ModerationVote {
+    pub report: Pubkey,
+    pub voter: Pubkey,
+    pub stake_account: Pubkey,
+    /// Active stake counted at vote time
+    pub stake_lamports: u64,
+    pub bump: u8,
+}
+
+impl ModerationVote {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // report
+        32 + // voter
+        32 + // stake_account
+        8 + // stake_lamports
+        1; // bump
+}
+
+/// Protocol-level reviewers of moderation appeals.
+#[account]
+pub struct ModerationCouncil {
+    pub members: Vec<Pubkey>,
+    /// Matching rulings needed to resolve an appeal
+    pub threshold: u8,
+    /// Token whose holders elect the council each epoch; default while the admin appoints it
+    pub vote_mint: Pubkey,
+    /// Members each election seats
+    pub seats: u8,
+    /// Vote tokens a candidate must lock to stand
+    pub candidate_bond: u64,
+    pub bump: u8,
+}
+
+impl ModerationCouncil {
+    pub const MAX_MEMBERS: usize = 7;
+
+    pub const LEN: usize = 8 + // discriminator
+        4 + 32 * Self::MAX_MEMBERS + // members
+        1 + // threshold
+        32 + // vote_mint
+        1 + // seats
+        8 + // candidate_bond
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppealKind {
+    RoomHide,
+    ContentWarning,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppealStatus {
+    Open,
+    Upheld,
+    Reversed,
+}
+
+#[account]
+pub struct Appeal {
+    /// The moderated `RoomMessage` or `ContentWarning`
+    pub target: Pubkey,
+    pub appellant: Pubkey,
+    pub kind: AppealKind,
+    pub status: AppealStatus,
+    /// Council members who ruled to keep the action
+    pub upholds: Vec<Pubkey>,
+    /// Council members who ruled to reverse it
+    pub reversals: Vec<Pubkey>,
+    pub filed_at: i64,
+    pub resolved_at: i64,
+    pub bump: u8,
+}
+
+impl Appeal {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // target
+        32 + // appellant
+        1 + // kind
+        1 + // status
+        4 + 32 * ModerationCouncil::MAX_MEMBERS + // upholds
+        4 + 32 * ModerationCouncil::MAX_MEMBERS + // reversals
+        8 + // filed_at
+        8 + // resolved_at
+        1; // bump
+}
+
+/// One epoch's vote for the moderation council.
+#[account]
+pub struct CouncilElection {
+    pub epoch: u64,
+    pub vote_mint: Pubkey,
+    /// Council's candidate bond when the election opened
+    pub candidate_bond: u64,
+    pub candidates: Vec<Pubkey>,
+    /// Tokens backing each candidate, parallel to `candidates`
+    pub votes: Vec<u64>,
+    /// Tokens each candidate locked to stand, parallel to `candidates`
+    pub bonds: Vec<u64>,
+    pub finalized: bool,
+    pub bump: u8,
+}
+
+impl CouncilElection {
+    pub const MAX_CANDIDATES: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        8 + // epoch
+        32 + // vote_mint
+        8 + // candidate_bond
+        4 + 32 * Self::MAX_CANDIDATES + // candidates
+        4 + 8 * Self::MAX_CANDIDATES + // votes
+        4 + 8 * Self::MAX_CANDIDATES + // bonds
+        1 + // finalized
+        1; // bump
+}
+
+/// A candidate's bond in one election; owns the vault holding the tokens.
+#[account]
+pub struct CouncilCandidacy {
+    pub election: Pubkey,
+    pub candidate: Pubkey,
+    pub bond: u64,
+    pub bump: u8,
+}
+
+impl CouncilCandidacy {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // election
+        32 + // candidate
+        8 + // bond
+        1; // bump
+}
+
+/// A voter's escrowed backing of one candidate; owns the vault holding the tokens.
+#[account]
+pub struct CouncilVote {
+    pub election: Pubkey,
+    pub voter: Pubkey,
+    pub candidate: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl CouncilVote {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // election
+        32 + // voter
+        32 + // candidate
+        8 + // amount
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpendStatus {
+    Open,
+    Approved,
+    Executed,
+    Cancelled,
+}
+
+/// A proposed payment out of the treasury, kept after execution as a public record.
+#[account]
+pub struct TreasuryProposal {
+    pub proposer: Pubkey,
+    pub proposal_id: u64,
+    pub recipient: Pubkey,
+    pub amount_lamports: u64,
+    /// What the spend is for
+    pub memo: String,
+    /// Council members who approved
+    pub approvals: Vec<Pubkey>,
+    pub status: SpendStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl TreasuryProposal {
+    pub const MAX_MEMO_LEN: usize = 200;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // proposer
+        8 + // proposal_id
+        32 + // recipient
+        8 + // amount_lamports
+        4 + Self::MAX_MEMO_LEN + // memo
+        4 + 32 * ModerationCouncil::MAX_MEMBERS + // approvals
+        1 + // status
+        8 + // created_at
+        1; // bump
+}
+
+/// Discount for senders who have sent at least `min_messages_sent` messages.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FeeTier {
+    pub min_messages_sent: u64,
+    pub discount_bps: u16,
+}
+
+impl FeeTier {
+    pub const LEN: usize = 8 + // min_messages_sent
+        2; // discount_bps
+}
+
+/// The protocol fee switch; while this account doesn't exist sends are free.
+#[account]
+pub struct FeeSchedule {
+    pub send_fee_lamports: u64,
+    /// Activity tiers, in ascending `min_messages_sent` order
+    pub tiers: Vec<FeeTier>,
+    /// Discount for holders of a live protocol pass
+    pub pass_discount_bps: u16,
+    /// Share of each fee owed to the app the send came through
+    pub app_share_bps: u16,
+    pub bump: u8,
+}
+
+impl FeeSchedule {
+    pub const MAX_TIERS: usize = 4;
+
+    pub const LEN: usize = 8 + // discriminator
+        8 + // send_fee_lamports
+        4 + FeeTier::LEN * Self::MAX_TIERS + // tiers
+        2 + // pass_discount_bps
+        2 + // app_share_bps
+        1; // bump
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+
+    /// The best discount the sender qualifies for; discounts don't stack.
+    pub fn discount_bps(&self, messages_sent: u64, holds_pass: bool) -> u16 {
+        let activity = self
+            .tiers
+            .iter()
+            .filter(|tier| messages_sent >= tier.min_messages_sent)
+            .map(|tier| tier.discount_bps)
+            .max()
+            .unwrap_or(0);
+        let pass = if holds_pass { self.pass_discount_bps } else { 0 };
+        activity.max(pass)
+    }
+
+    pub fn fee_for(&self, messages_sent: u64, holds_pass: bool) -> u64 {
+        let discount = self.discount_bps(messages_sent, holds_pass) as u128;
+        (self.send_fee_lamports as u128 * (10_000 - discount) / 10_000) as u64
+    }
+}
+
+/// Price and length of a protocol pass; also the mint authority while a pass is minted.
+#[account]
+pub struct PassOffer {
+    pub price_lamports: u64,
+    pub duration_secs: i64,
+    pub bump: u8,
+}
+
+impl PassOffer {
+    pub const LEN: usize = 8 + // discriminator
+        8 + // price_lamports
+        8 + // duration_secs
+        1; // bump
+}
+
+/// Expiry of one pass NFT, kept here rather than in token metadata (see `mint_pass`). The perks
+/// follow the token, not the original buyer.
+#[account]
+pub struct ProtocolPass {
+    pub mint: Pubkey,
+    pub buyer: Pubkey,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl ProtocolPass {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // mint
+        32 + // buyer
+        8 + // expires_at
+        1; // bump
+}
+
+/// A registered front-end, with running totals of the sends made through it.
+#[account]
+pub struct App {
+    pub authority: Pubkey,
+    pub name: String,
+    pub messages_sent: u64,
+    /// Protocol fees paid on sends made through the app
+    pub fees_lamports: u64,
+    /// The app's share of those fees, not yet claimed
+    pub revenue_owed_lamports: u64,
+    pub revenue_paid_lamports: u64,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl App {
+    pub const MAX_NAME_LEN: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority
+        4 + Self::MAX_NAME_LEN + // name
+        8 + // messages_sent
+        8 + // fees_lamports
+        8 + // revenue_owed_lamports
+        8 + // revenue_paid_lamports
+        8 + // registered_at
+        1; // bump
+
+    pub fn record_send(&mut self, fee: u64, share_bps: u16) {
+        self.messages_sent += 1;
+        self.fees_lamports += fee;
+        self.revenue_owed_lamports += (fee as u128 * share_bps as u128 / 10_000) as u64;
+    }
+}
+
+/// Instructions that can be held back to allow-listed apps while they roll out.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BetaFeature {
+    BlobSends,
+    InternedSends,
+    Games,
+}
+
+/// While this account exists, only sends through the listed apps may use `feature`.
+#[account]
+pub struct FeatureGate {
+    pub feature: BetaFeature,
+    pub apps: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl FeatureGate {
+    pub const MAX_APPS: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        1 + // feature
+        4 + 32 * Self::MAX_APPS + // apps
+        1; // bump
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+}
+
+/// How a schema's payloads are encoded.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchemaFormat {
+    /// UTF-8 JSON, described by a JSON Schema document
+    Json,
+    /// Borsh, described by a layout document
+    Borsh,
+}
+
+/// A registered payload layout. Registrations are immutable; changes get a new version.
+#[account]
+pub struct Schema {
+    pub authority: Pubkey,
+    pub name: String,
+    pub version: u16,
+    pub format: SchemaFormat,
+    /// SHA-256 of the JSON Schema or layout document
+    pub layout_hash: [u8; 32],
+    /// Where the document can be fetched; clients check it against `layout_hash`
+    pub uri: String,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Schema {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_URI_LEN: usize = 128;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority
+        4 + Self::MAX_NAME_LEN + // name
+        2 + // version
+        1 + // format
+        32 + // layout_hash
+        4 + Self::MAX_URI_LEN + // uri
+        8 + // created_at
+        1; // bump
+}
+
+/// A machine-readable message. Clients that don't know its schema can skip it.
+#[account]
+pub struct StructuredMessage {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub schema: Pubkey,
+    pub payload: Vec<u8>,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl StructuredMessage {
+    pub const MAX_PAYLOAD_LEN: usize = 512;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // nonce
+        32 + // schema
+        4 + Self::MAX_PAYLOAD_LEN + // payload
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+/// A note attached to a token transfer made in the same transaction.
+#[account]
+pub struct PaymentNote {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    /// SPL Token or Token-2022
+    pub token_program: Pubkey,
+    pub mint: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub note: String,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl PaymentNote {
+    pub const MAX_NOTE_LEN: usize = 280;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // nonce
+        32 + // token_program
+        32 + // mint
+        32 + // source
+        32 + // destination
+        8 + // amount
+        4 + Self::MAX_NOTE_LEN + // note
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+/// Public settings a user shares with everyone who messages them.
+#[account]
+pub struct Profile {
+    pub owner: Pubkey,
+    /// Mint tips should arrive in; default to accept whatever is sent
+    pub preferred_tip_mint: Pubkey,
+    pub links: Vec<ProfileLink>,
+    pub bump: u8,
+}
+
+impl Profile {
+    pub const MAX_LINKS: usize = 5;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // preferred_tip_mint
+        4 + Self::MAX_LINKS * ProfileLink::LEN + // links
+        1; // bump
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+}
+
+/// A sender's opt-in to rounding tips up for charity.
+#[account]
+pub struct RoundUp {
+    pub owner: Pubkey,
+    /// Tips are rounded up to a multiple of this; zero disables
+    pub unit_lamports: u64,
+    pub bump: u8,
+}
+
+impl RoundUp {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        8 + // unit_lamports
+        1; // bump
+}
+
+/// Lamports vesting from a patron to the other side of a conversation while it stays active.
+/// The escrow is the account's own balance above rent.
+#[account]
+pub struct TipStream {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub stream_id: u64,
+    pub total_lamports: u64,
+    pub withdrawn_lamports: u64,
+    pub duration_secs: i64,
+    /// Vesting stops once either side has been silent this long; zero never stops it
+    pub idle_timeout_secs: i64,
+    /// Vesting seconds counted up to `resumed_at`
+    pub accrued_secs: i64,
+    pub resumed_at: i64,
+    pub paused: bool,
+    /// Last ping of the sender and the recipient
+    pub last_active_at: [i64; 2],
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl TipStream {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // sender
+        32 + // recipient
+        8 + // stream_id
+        8 + // total_lamports
+        8 + // withdrawn_lamports
+        8 + // duration_secs
+        8 + // idle_timeout_secs
+        8 + // accrued_secs
+        8 + // resumed_at
+        1 + // paused
+        8 * 2 + // last_active_at
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+
+    pub fn side_of(&self, participant: &Pubkey) -> Result<usize> {
+        [self.sender, self.recipient]
+            .iter()
+            .position(|p| p == participant)
+            .ok_or_else(|| error!(ErrorCode::NotConversationParticipant))
+    }
+
+    /// Vesting seconds at `now`, counting only time the stream ran with both sides active.
+    pub fn vested_secs(&self, now: i64) -> i64 {
+        let mut until = now;
+        if self.idle_timeout_secs > 0 {
+            let quietest = self.last_active_at[0].min(self.last_active_at[1]);
+            until = until.min(quietest + self.idle_timeout_secs);
+        }
+        let running = if self.paused { 0 } else { (until - self.resumed_at).max(0) };
+        (self.accrued_secs + running).min(self.duration_secs)
+    }
+
+    /// Folds the time vested so far into `accrued_secs`. Idle time before `now` is dropped.
+    pub fn checkpoint(&mut self, now: i64) {
+        self.accrued_secs = self.vested_secs(now);
+        self.resumed_at = now;
+    }
+
+    /// Lamports vested as of the last checkpoint.
+    pub fn vested_lamports(&self) -> u64 {
+        (self.total_lamports as u128 * self.accrued_secs as u128 / self.duration_secs as u128) as u64
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BookingStatus {
+    Proposed,
+    Accepted,
+    Declined,
+    Cancelled,
+    Refunded,
+    Completed,
+    Disputed,
+}
+
+/// A paid consultation slot, with its fee escrowed on the account. Closed when it settles.
+#[account]
+pub struct BookingMessage {
+    pub client: Pubkey,
+    pub consultant: Pubkey,
+    pub booking_id: u64,
+    pub start_at: i64,
+    pub duration_mins: u16,
+    pub rate_lamports_per_hour: u64,
+    pub escrow_lamports: u64,
+    pub note: String,
+    pub status: BookingStatus,
+    pub timestamp: i64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl BookingMessage {
+    pub const MAX_NOTE_LEN: usize = 200;
+    /// How long after the session the consultant waits before settling without the client
+    pub const CLAIM_GRACE_SECS: i64 = 3 * 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // client
+        32 + // consultant
+        8 + // booking_id
+        8 + // start_at
+        2 + // duration_mins
+        8 + // rate_lamports_per_hour
+        8 + // escrow_lamports
+        4 + Self::MAX_NOTE_LEN + // note
+        1 + // status
+        8 + // timestamp
+        32 + // namespace
+        1; // bump
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+
+    pub fn ends_at(&self) -> i64 {
+        self.start_at + self.duration_mins as i64 * 60
+    }
+}
+
+/// Weekly schedule of half-hour slots the owner can be booked into, in their local time.
+/// Bit `i` of the bitmap is slot `i % 48` of day `i / 48`, with day 0 being Monday.
+#[account]
+pub struct Availability {
+    pub owner: Pubkey,
+    /// Local time minus UTC
+    pub utc_offset_mins: i16,
+    pub slots: [u8; 42],
+    pub bump: u8,
+}
+
+impl Availability {
+    pub const SLOT_SECS: i64 = 30 * 60;
+    pub const SLOTS_PER_WEEK: i64 = 7 * 48;
+    /// Widest offsets in use, UTC-12:00 to UTC+14:00
+    pub const MIN_UTC_OFFSET_MINS: i16 = -12 * 60;
+    pub const MAX_UTC_OFFSET_MINS: i16 = 14 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        2 + // utc_offset_mins
+        42 + // slots
+        1; // bump
+
+    pub fn validate(utc_offset_mins: i16) -> Result<()> {
+        require!(
+            (Self::MIN_UTC_OFFSET_MINS..=Self::MAX_UTC_OFFSET_MINS).contains(&utc_offset_mins),
+            ErrorCode::InvalidAvailability
+        );
+        Ok(())
+    }
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+
+    /// Index into the weekly bitmap of the slot containing `timestamp`.
+    pub fn slot_index(&self, timestamp: i64) -> usize {
+        // The Unix epoch fell on a Thursday, three days after the week starts
+        let local = timestamp + self.utc_offset_mins as i64 * 60 + 3 * 86_400;
+        (local.div_euclid(Self::SLOT_SECS)).rem_euclid(Self::SLOTS_PER_WEEK) as usize
+    }
+
+    pub fn is_slot_open(&self, index: usize) -> bool {
+        self.slots[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Whether every slot overlapping `[start, end)` is open.
+    pub fn is_open(&self, start: i64, end: i64) -> bool {
+        let first = start.div_euclid(Self::SLOT_SECS);
+        let last = (end - 1).div_euclid(Self::SLOT_SECS);
+        (first..=last).all(|slot| self.is_slot_open(self.slot_index(slot * Self::SLOT_SECS)))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinkVerification {
+    Unverified,
+    /// `gmchat-verify=<owner>` TXT record on the link's domain
+    DnsTxt,
+    /// `<owner>` served at `/.well-known/gmchat.txt` on the link's origin
+    WellKnownFile,
+}
+
+/// A labelled link on a profile. Verification is set only by the configured attestor.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProfileLink {
+    pub label: String,
+    pub uri: String,
+    pub verification: LinkVerification,
+    pub verified_at: i64,
+}
+
+impl ProfileLink {
+    pub const MAX_LABEL_LEN: usize = 32;
+    pub const MAX_URI_LEN: usize = 128;
+
+    pub const LEN: usize = 4 + Self::MAX_LABEL_LEN + // label
+        4 + Self::MAX_URI_LEN + // uri
+        1 + // verification
+        8; // verified_at
+
+    pub fn validate(label: &str, uri: &str) -> Result<()> {
+        require!(
+            !label.is_empty() && label.len() <= Self::MAX_LABEL_LEN,
+            ErrorCode::InvalidProfileLink
+        );
+        require!(
+            uri.starts_with("https://") && uri.len() <= Self::MAX_URI_LEN,
+            ErrorCode::InvalidProfileLink
+        );
+        Ok(())
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.verification != LinkVerification::Unverified
+    }
+}
+
+/// A white-label deployment sharing this program, administered by its operator.
+///
+/// Accounts a sender creates between wallets (messages, rooms, structured messages, payment
+/// notes, tip streams, bookings, DM links, invoices, conversation settings, inbox indexes) take
+/// the namespace address as their second seed. Per-wallet singletons such as `Profile`,
+/// `EncryptionKey`, `DeliveryWindow`, `PriorityPolicy`, `ContentFilter`, `RoundUp`,
+/// `Availability`, `InboxGate`, `UserStats` and `Reputation` stay global: they describe the
+/// wallet itself, senders in any deployment look them up by wallet alone, and a wallet belongs
+/// to at most one namespace (`NamespaceMember` is keyed by the wallet), so there is only ever
+/// one of each to find. Protocol singletons such as `Config` and `Treasury` are shared by design.
+#[account]
+pub struct Namespace {
+    pub admin: Pubkey,
+    pub name: String,
+    pub branding: NamespaceBranding,
+    pub federation: FederationPolicy,
+    /// Namespaces members may exchange messages with under `AllowList`; default is the public
+    /// network
+    pub peers: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Namespace {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_PEERS: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // admin
+        4 + Self::MAX_NAME_LEN + // name
+        NamespaceBranding::LEN + // branding
+        1 + // federation
+        4 + Self::MAX_PEERS * 32 + // peers
+        8 + // created_at
+        1; // bump
+
+    /// Whether members may exchange messages with members of `other`.
+    pub fn federates_with(&self, other: Pubkey) -> bool {
+        match self.federation {
+            FederationPolicy::Open => true,
+            FederationPolicy::AllowList => self.peers.contains(&other),
+        }
+    }
+}
+
+/// How clients should present a namespace's messages. Colors are hints; clients may adjust
+/// them for contrast.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct NamespaceBranding {
+    pub display_name: String,
+    /// Square logo; empty for none
+    pub logo_uri: String,
+    /// RGB
+    pub primary_color: [u8; 3],
+    pub accent_color: [u8; 3],
+}
+
+impl NamespaceBranding {
+    pub const MAX_DISPLAY_NAME_LEN: usize = 32;
+    pub const MAX_LOGO_URI_LEN: usize = 128;
+
+    pub const LEN: usize = 4 + Self::MAX_DISPLAY_NAME_LEN + // display_name
+        4 + Self::MAX_LOGO_URI_LEN + // logo_uri
+        3 + // primary_color
+        3; // accent_color
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.display_name.is_empty() && self.display_name.len() <= Self::MAX_DISPLAY_NAME_LEN,
+            ErrorCode::InvalidBranding
+        );
+        require!(self.logo_uri.len() <= Self::MAX_LOGO_URI_LEN, ErrorCode::InvalidBranding);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FederationPolicy {
+    /// Members may message anyone
+    Open,
+    /// Members may message only their own namespace and the listed peers
+    AllowList,
+}
+
+/// A user's enrolment in a namespace; users without one are on the public network.
+#[account]
+pub struct NamespaceMember {
+    pub user: Pubkey,
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl NamespaceMember {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // user
+        32 + // namespace
+        1; // bump
+
+    /// Namespace of the membership PDA `info`, default for the public network.
+    pub fn namespace_of(info: &AccountInfo) -> Result<Pubkey> {
+        if info.data_is_empty() {
+            return Ok(Pubkey::default());
+        }
+        Ok(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?.namespace)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdminAction {
+    SetPollLimits,
+    SetReputationParams,
+    SetAttestor,
+    SetCrankBounty,
+    SetModerator,
+    SetCharity,
+    AddDictionaryEntry,
+    SetModerationCouncil,
+    SetCouncilElections,
+    SetFeeSchedule,
+    SetPassOffer,
+    SetFeatureGate,
+    RemoveFeatureGate,
+    SlashVouch,
+    CreateNamespace,
+    SetNamespaceAdmin,
+    SetNamespaceBranding,
+    SetFederation,
+    SetPaused,
+    SetTimelock,
+    QueueConfigChange,
+    CancelConfigChange,
+    SetSpamDeposit,
+    SetSpamTrap,
+    RemoveSpamTrap,
+    SetPatronConfig,
+}
+
+/// One admin mutation. Values are recorded as SHA-256 of their Borsh encoding; an absent
+/// value (before a create, after a removal) hashes the empty encoding.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub actor: Pubkey,
+    pub action: AdminAction,
+    /// Account the action changed
+    pub target: Pubkey,
+    pub old_hash: [u8; 32],
+    pub new_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+impl AuditEntry {
+    pub const LEN: usize = 8 + // sequence
+        32 + // actor
+        1 + // action
+        32 + // target
+        32 + // old_hash
+        32 + // new_hash
+        8; // timestamp
+}
+
+/// Ring buffer of the most recent admin actions. The full history is in `AdminActionRecorded`
+/// events; `next_sequence` lets readers spot entries that have been overwritten.
+#[account]
+pub struct AuditLog {
+    pub entries: Vec<AuditEntry>,
+    pub next_sequence: u64,
+    pub bump: u8,
+}
+
+impl AuditLog {
+    pub const CAPACITY: usize = 32;
+
+    pub const LEN: usize = 8 + // discriminator
+        4 + Self::CAPACITY * AuditEntry::LEN + // entries
+        8 + // next_sequence
+        1; // bump
+
+    pub fn append(&mut self, mut entry: AuditEntry) -> AuditEntry {
+        entry.sequence = self.next_sequence;
+        let slot = (self.next_sequence % Self::CAPACITY as u64) as usize;
+        if slot < self.entries.len() {
+            self.entries[slot] = entry;
+        } else {
+            self.entries.push(entry);
+        }
+        self.next_sequence += 1;
+        entry
+    }
+}
+
+/// A sensitive config update that must wait out the timelock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ConfigChange {
+    FeeSchedule {
+        send_fee_lamports: u64,
+        tiers: Vec<FeeTier>,
+        pass_discount_bps: u16,
+        app_share_bps: u16,
+    },
+    ModerationCouncil {
+        members: Vec<Pubkey>,
+        threshold: u8,
+    },
+    Moderator {
+        moderator: Pubkey,
+    },
+    Paused {
+        paused: bool,
+    },
+    Timelock {
+        secs: i64,
+    },
+    SpamDeposit {
+        deposit_lamports: u64,
+        window_secs: i64,
+    },
+    CrankBounty {
+        lamports: u64,
+    },
+    Charity {
+        charity: Pubkey,
+    },
+    /// Hands the council to holders of `vote_mint`, who elect `seats` members each epoch among
+    /// candidates locking at least `candidate_bond`. A default `vote_mint` returns it to
+    /// admin appointment.
+    CouncilElections {
+        vote_mint: Pubkey,
+        seats: u8,
+        candidate_bond: u64,
+    },
+}
+
+impl ConfigChange {
+    pub const MAX_TIMELOCK_SECS: i64 = 30 * 24 * 60 * 60;
+
+    /// Largest variant, the moderation council
+    pub const LEN: usize = 1 + // variant
+        4 + ModerationCouncil::MAX_MEMBERS * 32 + // members
+        1; // threshold
+}
+
+#[account]
+pub struct PendingConfigChange {
+    pub change_id: u64,
+    pub change: ConfigChange,
+    pub queued_at: i64,
+    /// Earliest time it can be executed
+    pub eta: i64,
+    pub bump: u8,
+}
+
+impl PendingConfigChange {
+    pub const LEN: usize = 8 + // discriminator
+        8 + // change_id
+        ConfigChange::LEN + // change
+        8 + // queued_at
+        8 + // eta
+        1; // bump
+}
+
+/// Places in handlers where a `chaos` build can inject a failure.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChaosPoint {
+    /// `mint_pass`, after the token is minted and before the mint authority is dropped
+    AfterPassMint,
+    /// `send_message`, after the message account is written and before fees and stats
+    AfterMessageInit,
+}
+
+/// An armed fail point. Handlers find it among their remaining accounts, so arming one never
+/// changes an instruction's account list.
+#[account]
+pub struct ChaosPlan {
+    pub point: ChaosPoint,
+    pub skip_hits: u8,
+    pub hits: u8,
+    pub bump: u8,
+}
+
+impl ChaosPlan {
+    pub const LEN: usize = 8 + // discriminator
+        1 + // point
+        1 + // skip_hits
+        1 + // hits
+        1; // bump
+}
+
+/// A salted inbox entry. The sender picks a one-off X25519 key, and the digest is SHA-256 over
+/// the tag "gmchat-inbox-index", the shared secret between that key and the recipient's
+/// encryption key, and the message address.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SaltedIndexEntry {
+    pub ephemeral_key: [u8; 32],
+    pub digest: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndexedMessage {
+    Public(Pubkey),
+    Private(SaltedIndexEntry),
+}
+
+impl IndexedMessage {
+    pub const LEN: usize = 1 + 64;
+}
+
+/// Most recent messages to `owner`, oldest overwritten first.
+#[account]
+pub struct InboxIndex {
+    pub owner: Pubkey,
+    pub private: bool,
+    /// May write private entries for the owner; default when there is none
+    pub delegate: Pubkey,
+    pub entries: Vec<IndexedMessage>,
+    pub next_sequence: u64,
+    /// White-label deployment it belongs to; default on the public network
+    pub namespace: Pubkey,
+    pub bump: u8,
+}
+
+impl InboxIndex {
+    pub const CAPACITY: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        1 + // private
+        32 + // delegate
+        4 + Self::CAPACITY * IndexedMessage::LEN + // entries
+        8 + // next_sequence
+        32 + // namespace
+        1; // bump
+
+    pub fn append(&mut self, entry: IndexedMessage) {
+        let slot = (self.next_sequence % Self::CAPACITY as u64) as usize;
+        if slot < self.entries.len() {
+            self.entries[slot] = entry;
+        } else {
+            self.entries.push(entry);
+        }
+        self.next_sequence += 1;
+    }
+
+    pub fn namespace_seed(&self) -> &[u8] {
+        namespace_seed(&self.namespace)
+    }
+}
+
+/// Rolling bloom filter of content hashes the moderator flagged as spam. Content flagged during
+/// the current window stays in the filter through the next one, so entries age out after one
+/// to two windows.
+#[account]
+pub struct SpamFilter {
+    pub current: [u8; SpamFilter::BYTES],
+    pub previous: [u8; SpamFilter::BYTES],
+    /// Start of the current window
+    pub rotated_at: i64,
+    pub window_secs: i64,
+    /// Charged on sends of flagged content; zero turns the pre-screen off
+    pub deposit_lamports: u64,
+    pub bump: u8,
+}
+
+impl SpamFilter {
+    /// 4096 bits per window, set by 4 hashes: about a 2% false positive rate at 500 entries
+    pub const BYTES: usize = 512;
+    pub const HASHES: usize = 4;
+    pub const DEFAULT_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        Self::BYTES + // current
+        Self::BYTES + // previous
+        8 + // rotated_at
+        8 + // window_secs
+        8 + // deposit_lamports
+        1; // bump
+
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+    }
+
+    pub fn content_hash(content: &str) -> [u8; 32] {
+        hash(content.as_bytes()).to_bytes()
+    }
+
+    fn bits(content_hash: &[u8; 32]) -> [usize; Self::HASHES] {
+        let mut bits = [0; Self::HASHES];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            let word = u32::from_le_bytes(content_hash[i * 4..i * 4 + 4].try_into().unwrap());
+            *bit = word as usize % (Self::BYTES * 8);
+        }
+        bits
+    }
+
+    fn filter_contains(filter: &[u8; Self::BYTES], bits: &[usize; Self::HASHES]) -> bool {
+        bits.iter().all(|&bit| filter[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn contains(&self, content_hash: &[u8; 32], now: i64) -> bool {
+        let bits = Self::bits(content_hash);
+        let elapsed = now - self.rotated_at;
+        if elapsed < self.window_secs {
+            Self::filter_contains(&self.current, &bits) || Self::filter_contains(&self.previous, &bits)
+        } else if elapsed < 2 * self.window_secs {
+            Self::filter_contains(&self.current, &bits)
+        } else {
+            false
+        }
+    }
+
+    pub fn insert(&mut self, content_hash: &[u8; 32], now: i64) {
+        let elapsed = now - self.rotated_at;
+        if elapsed >= 2 * self.window_secs {
+            self.previous = [0; Self::BYTES];
+            self.current = [0; Self::BYTES];
+            self.rotated_at = now;
+        } else if elapsed >= self.window_secs {
+            self.previous = self.current;
+            self.current = [0; Self::BYTES];
+            self.rotated_at = now;
+        }
+
+        for bit in Self::bits(content_hash) {
+            self.current[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+}
+
+/// Deposit paid on a send of flagged content, held against the message.
+#[account]
+pub struct SpamDeposit {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub amount_lamports: u64,
+    /// The sender can reclaim the deposit from this time
+    pub held_until: i64,
+    pub bump: u8,
+}
+
+impl SpamDeposit {
+    pub const HOLD_SECS: i64 = 14 * 24 * 60 * 60;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // message
+        32 + // sender
+        32 + // recipient
+        8 + // amount_lamports
+        8 + // held_until
+        1; // bump
+}
+
+/// An inbox whose incoming messages are treated as spam reports.
+#[account]
+pub struct SpamTrap {
+    pub inbox: Pubkey,
+    pub messages_caught: u64,
+    pub last_caught_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl SpamTrap {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // inbox
+        8 + // messages_caught
+        8 + // last_caught_at
+        8 + // created_at
+        1; // bump
+}
+
+/// Compressed "patron" receipts minted to tippers whose tip exceeds the threshold.
+#[account]
+pub struct PatronConfig {
+    /// Bubblegum tree the receipts go into; this account is its tree delegate
+    pub merkle_tree: Pubkey,
+    /// Tips must exceed this; zero disables receipts
+    pub threshold_lamports: u64,
+    /// Receipt metadata lives at `<uri_base>/<message>/<amount>`
+    pub uri_base: String,
+    pub receipts_minted: u64,
+    pub bump: u8,
+}
+
+impl PatronConfig {
+    pub const MAX_URI_BASE_LEN: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // merkle_tree
+        8 + // threshold_lamports
+        4 + Self::MAX_URI_BASE_LEN + // uri_base
+        8 + // receipts_minted
+        1; // bump
+
+    pub fn validate(uri_base: &str) -> Result<()> {
+        require!(
+            uri_base.starts_with("https://") && uri_base.len() <= Self::MAX_URI_BASE_LEN,
+            ErrorCode::InvalidPatronConfig
+        );
+        Ok(())
+    }
+}
+
+#[event]
+pub struct TaskStatusChanged {
+    pub task: Pubkey,
+    pub actor: Pubkey,
+    pub status: TaskStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TradeStatusChanged {
+    pub trade: Pubkey,
+    pub actor: Pubkey,
+    pub status: TradeStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InvoicePaid {
+    pub invoice: Pubkey,
+    pub receipt: Pubkey,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub period: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MessageUnlocked {
+    pub message: Pubkey,
+    pub unlocked_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DeadManMessageClaimed {
+    pub message: Pubkey,
+    pub beneficiary: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GameMovePlayed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub position: u8,
+    pub move_number: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TicketStatusChanged {
+    pub ticket: Pubkey,
+    pub actor: Pubkey,
+    pub status: TicketStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AmaAnswered {
+    pub ama_inbox: Pubkey,
+    pub question: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct KeyLogAppended {
+    pub index: u64,
+    pub owner: Pubkey,
+    pub key: [u8; 32],
+    pub version: u32,
+    pub updated_at: i64,
+    pub leaf: [u8; 32],
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct SafetyNumberConfirmed {
+    pub settings: Pubkey,
+    pub participant: Pubkey,
+    pub safety_number: [u8; 32],
+    pub verified: bool,
+}
+
+/// Rendered by clients as a system message in the conversation
+#[event]
+pub struct SafetyNumberChanged {
+    pub settings: Pubkey,
+    pub participant: Pubkey,
+    pub old_version: u32,
+    pub new_version: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MessageDisclosed {
+    pub message: Pubkey,
+    pub discloser: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ObserverGranted {
+    pub conversation: Pubkey,
+    pub granter: Pubkey,
+    pub observer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ObserverRevoked {
+    pub conversation: Pubkey,
+    pub granter: Pubkey,
+    pub observer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MessageQueued {
+    pub message: Pubkey,
+    pub recipient: Pubkey,
+    pub deliver_at: i64,
+}
+
+#[event]
+pub struct MessageDelivered {
+    pub message: Pubkey,
+    pub recipient: Pubkey,
+    pub delivered_at: i64,
+}
+
+#[event]
+pub struct ReminderDue {
+    pub owner: Pubkey,
+    pub message: Pubkey,
+    pub remind_at: i64,
+    pub fired_at: i64,
+}
+
+#[event]
+pub struct ConversationUnsnoozed {
+    pub owner: Pubkey,
+    pub peer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PollResolved {
+    pub poll: Pubkey,
+    pub resolver: Pubkey,
+    pub winning_option: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MessageSent {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub client_message_id: [u8; 16],
+    pub cost: MessageCost,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ContentWarningSet {
+    pub message: Pubkey,
+    pub category: ContentWarningCategory,
+    pub by_moderator: bool,
+}
+
+#[event]
+pub struct RoomMessageApproved {
+    pub room: Pubkey,
+    pub message: Pubkey,
+    pub author: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoomMessageRejected {
+    pub room: Pubkey,
+    pub message: Pubkey,
+    pub author: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoomMessageHidden {
+    pub room: Pubkey,
+    pub message: Pubkey,
+    pub tally_lamports: u64,
+    pub votes: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AppealFiled {
+    pub appeal: Pubkey,
+    pub target: Pubkey,
+    pub appellant: Pubkey,
+    pub kind: AppealKind,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AppealRuling {
+    pub appeal: Pubkey,
+    pub member: Pubkey,
+    pub reverse: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AppealDecided {
+    pub appeal: Pubkey,
+    pub target: Pubkey,
+    pub reversed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CouncilVoteCast {
+    pub election: Pubkey,
+    pub voter: Pubkey,
+    pub candidate: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CouncilElected {
+    pub election: Pubkey,
+    pub epoch: u64,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct TreasurySpendStatusChanged {
+    pub proposal: Pubkey,
+    pub actor: Pubkey,
+    pub status: SpendStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PassPurchased {
+    pub pass: Pubkey,
+    pub mint: Pubkey,
+    pub buyer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct AppRevenueClaimed {
+    pub app: Pubkey,
+    pub amount: u64,
+    /// Still owed because the treasury couldn't cover it all
+    pub remaining: u64,
+}
+
+#[event]
+pub struct StructuredMessageSent {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub schema: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentNoteAttached {
+    pub payment_note: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TipSent {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    /// What the recipient received
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// What the sender paid, which differs when the tip was swapped
+    pub paid_mint: Pubkey,
+    pub paid_amount: u64,
+    /// Where the sender's round-up went, and how much; default and zero without one
+    pub charity: Pubkey,
+    pub charity_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BookingStatusChanged {
+    pub booking: Pubkey,
+    pub actor: Pubkey,
+    pub status: BookingStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProfileLinkVerificationChanged {
+    pub owner: Pubkey,
+    pub uri: String,
+    pub verification: LinkVerification,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminActionRecorded {
+    pub entry: AuditEntry,
+}
+
+#[event]
+pub struct ConfigChangeQueued {
+    pub change_id: u64,
+    pub change: ConfigChange,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ConfigChangeExecuted {
+    pub change_id: u64,
+}
+
+#[event]
+pub struct SpamContentFlagged {
+    pub content_hash: [u8; 32],
+    pub flagged_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpamDepositSettled {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub amount_lamports: u64,
+    pub forfeited: bool,
+}
+
+#[event]
+pub struct SpamTrapHit {
+    pub trap: Pubkey,
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub content_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PatronReceiptMinted {
+    pub tipper: Pubkey,
+    pub recipient: Pubkey,
+    pub message: Pubkey,
+    pub amount_lamports: u64,
+    /// Receipts minted before this one
+    pub receipt_index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpamMessageFlagged {
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub flagged_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CouncilCandidateEvicted {
+    pub election: Pubkey,
+    pub candidate: Pubkey,
+    pub bond: u64,
+    pub votes: u64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Message content is too long")]
+    MessageTooLong,
+    #[msg("Message content cannot be empty")]
+    EmptyMessage,
+    #[msg("Unauthorized recipient")]
+    UnauthorizedRecipient,
+    #[msg("Event title cannot be empty")]
+    EmptyEventTitle,
+    #[msg("Event title is too long")]
+    EventTitleTooLong,
+    #[msg("Location URI is too long")]
+    LocationUriTooLong,
+    #[msg("Event must end after it starts and in the future")]
+    InvalidEventTimes,
+    #[msg("Event capacity must be greater than zero")]
+    InvalidEventCapacity,
+    #[msg("Event has already ended")]
+    EventEnded,
+    #[msg("Event is at capacity")]
+    EventFull,
+    #[msg("Task description cannot be empty")]
+    EmptyTaskDescription,
+    #[msg("Task description is too long")]
+    TaskDescriptionTooLong,
+    #[msg("Due date must be in the future")]
+    InvalidDueDate,
+    #[msg("Only the assignee can complete this task")]
+    UnauthorizedAssignee,
+    #[msg("Task is not open")]
+    TaskNotOpen,
+    #[msg("Trade amount must be greater than zero")]
+    InvalidTradeAmount,
+    #[msg("Signer is not a party to this trade")]
+    InvalidTradeParty,
+    #[msg("Trade is no longer pending")]
+    TradeNotPending,
+    #[msg("Invoice amount must be greater than zero")]
+    InvalidInvoiceAmount,
+    #[msg("Invoice memo is too long")]
+    InvoiceMemoTooLong,
+    #[msg("Invalid invoice payer")]
+    InvalidInvoicePayer,
+    #[msg("Invoice has already been paid")]
+    InvoiceAlreadyPaid,
+    #[msg("Invalid invoice period")]
+    InvalidInvoicePeriod,
+    #[msg("Recurring invoices are collected through the schedule")]
+    RecurringInvoice,
+    #[msg("Invoice is not recurring")]
+    NotRecurringInvoice,
+    #[msg("Invoice is not active")]
+    InvoiceNotActive,
+    #[msg("Invoice period is not due yet")]
+    InvoicePeriodNotDue,
+    #[msg("Signer is not a party to this invoice")]
+    UnauthorizedInvoiceParty,
+    #[msg("Invalid unlock condition")]
+    InvalidUnlockCondition,
+    #[msg("Unlock condition has not been met")]
+    UnlockConditionNotMet,
+    #[msg("Content key does not match the committed hash")]
+    InvalidContentKey,
+    #[msg("Message has already been unlocked")]
+    MessageAlreadyUnlocked,
+    #[msg("Account required by the unlock condition is missing")]
+    MissingConditionAccount,
+    #[msg("Invalid oracle price account")]
+    InvalidOracleAccount,
+    #[msg("Oracle price is not currently trading")]
+    OraclePriceUnavailable,
+    #[msg("Heartbeat window has expired")]
+    HeartbeatExpired,
+    #[msg("Revealed content does not match the commitment")]
+    CommitmentMismatch,
+    #[msg("Commitment has already been revealed")]
+    AlreadyRevealed,
+    #[msg("Invalid opponent")]
+    InvalidOpponent,
+    #[msg("Game is not active")]
+    GameNotActive,
+    #[msg("It is not your turn")]
+    NotYourTurn,
+    #[msg("Invalid move")]
+    InvalidMove,
+    #[msg("Poll question cannot be empty")]
+    EmptyPollQuestion,
+    #[msg("Poll question is too long")]
+    PollQuestionTooLong,
+    #[msg("Invalid poll options")]
+    InvalidPollOptions,
+    #[msg("Poll must close in the future")]
+    InvalidPollClose,
+    #[msg("Stake per vote exceeds the configured limit")]
+    PollStakeTooHigh,
+    #[msg("Poll is closed")]
+    PollClosed,
+    #[msg("Poll has not closed yet")]
+    PollStillOpen,
+    #[msg("Only the poll resolver can resolve this poll")]
+    UnauthorizedResolver,
+    #[msg("Signer may not post notifications for this governance")]
+    UnauthorizedNotifier,
+    #[msg("Invalid governance proposal account")]
+    InvalidProposalAccount,
+    #[msg("No matching payment to the merchant found in this transaction")]
+    PaymentNotFound,
+    #[msg("Signer is not a member of this team inbox")]
+    UnauthorizedTeamMember,
+    #[msg("Already a member of this team inbox")]
+    AlreadyTeamMember,
+    #[msg("Team inbox has reached its member limit")]
+    TeamInboxFull,
+    #[msg("Invalid ticket status transition")]
+    InvalidTicketTransition,
+    #[msg("Ticket has not been resolved")]
+    TicketNotResolved,
+    #[msg("Ticket has already been rated")]
+    TicketAlreadyRated,
+    #[msg("Rating must be between 1 and 5")]
+    InvalidRating,
+    #[msg("Invalid arbiter")]
+    InvalidArbiter,
+    #[msg("Action not allowed in the current deal state")]
+    InvalidDealState,
+    #[msg("Ruling share must be at most 10000 basis points")]
+    InvalidRuling,
+    #[msg("Reply target is not a message from the recipient")]
+    InvalidReplyTarget,
+    #[msg("Reputation account does not belong to the expected user")]
+    InvalidReputationAccount,
+    #[msg("Config account is required to update reputation")]
+    MissingConfig,
+    #[msg("Invalid reputation parameters")]
+    InvalidReputationParams,
+    #[msg("Invalid vouch")]
+    InvalidVouch,
+    #[msg("Vouchee has been flagged since this vouch was made")]
+    VouchUnderReview,
+    #[msg("Vouch is not slashable")]
+    VouchNotSlashable,
+    #[msg("Signer is not the configured attestor")]
+    UnauthorizedAttestor,
+    #[msg("Sender does not meet the recipient's inbox gate")]
+    InboxGateNotMet,
+    #[msg("Invalid guardian set")]
+    InvalidGuardianSet,
+    #[msg("Signer is not a guardian of this account")]
+    NotGuardian,
+    #[msg("Guardian has already approved this recovery")]
+    AlreadyApproved,
+    #[msg("Not enough guardian approvals for recovery")]
+    RecoveryThresholdNotMet,
+    #[msg("Recovery timelock has not elapsed")]
+    RecoveryTimelockActive,
+    #[msg("Invalid inbox migration")]
+    InvalidMigration,
+    #[msg("Outstanding vouches must be revoked before migrating reputation")]
+    OutstandingVouches,
+    #[msg("Invalid DM link")]
+    InvalidDmLink,
+    #[msg("DM link has expired")]
+    DmLinkExpired,
+    #[msg("DM link has no uses left")]
+    DmLinkExhausted,
+    #[msg("AMA inbox is not accepting questions")]
+    AmaInboxClosed,
+    #[msg("Question has already been answered")]
+    AlreadyAnswered,
+    #[msg("Room TTL must be positive and at most 30 days")]
+    InvalidRoomTtl,
+    #[msg("Room has expired")]
+    RoomExpired,
+    #[msg("Room has not expired yet")]
+    RoomNotExpired,
+    #[msg("Content blob reference count overflow")]
+    BlobRefcountOverflow,
+    #[msg("Content blob is still referenced by messages")]
+    BlobStillReferenced,
+    #[msg("Content does not match its hash")]
+    ContentHashMismatch,
+    #[msg("Not allowed to use this content blob")]
+    UnauthorizedBlobAccess,
+    #[msg("Message dictionary is full")]
+    DictionaryFull,
+    #[msg("Word is already in the message dictionary")]
+    DuplicateDictionaryEntry,
+    #[msg("Unknown message dictionary index")]
+    UnknownDictionaryEntry,
+    #[msg("Epoch does not match the current epoch")]
+    InvalidEpoch,
+    #[msg("Key transparency log is full")]
+    KeyLogFull,
+    #[msg("Conversation participants must be in ascending order")]
+    ParticipantsNotOrdered,
+    #[msg("Signer is not a participant in this conversation")]
+    NotConversationParticipant,
+    #[msg("Safety number does not match the participants' current keys")]
+    SafetyNumberMismatch,
+    #[msg("Key transparency log is required to revoke an encryption key")]
+    KeyLogRequired,
+    #[msg("Account does not belong to the signer or cannot be wiped")]
+    NotWipeable,
+    #[msg("Decryption key must be between 1 and 64 bytes")]
+    DecryptionKeyTooLong,
+    #[msg("Conversation participants cannot be observers")]
+    ObserverIsParticipant,
+    #[msg("Observer label is too long")]
+    ObserverLabelTooLong,
+    #[msg("Message was sent before the observer was granted access")]
+    MessageBeforeObserverGrant,
+    #[msg("Wrapped key must be between 1 and 128 bytes")]
+    WrappedKeyTooLong,
+    #[msg("Delivery window minutes must be distinct and within a day")]
+    InvalidDeliveryWindow,
+    #[msg("Recipient's delivery window is closed; pass a delivery account to queue the message")]
+    DeliveryRecordRequired,
+    #[msg("Message is not queued for delivery")]
+    MessageNotQueued,
+    #[msg("Recipient's delivery window has not opened yet")]
+    DeliveryWindowClosed,
+    #[msg("Recipient does not accept urgent messages from this sender")]
+    UrgentNotAllowed,
+    #[msg("Reminder time must be in the future")]
+    ReminderInPast,
+    #[msg("Reminder is not due yet")]
+    ReminderNotDue,
+    #[msg("Snooze must end in the future")]
+    SnoozeInPast,
+    #[msg("Snooze has not ended yet")]
+    SnoozeNotExpired,
+    #[msg("Client message ID must not be all zeros")]
+    InvalidClientMessageId,
+    #[msg("Imported messages must keep a past timestamp")]
+    ImportNotBackdated,
+    #[msg("Language tags must be 1-16 ASCII letters, digits or hyphens")]
+    InvalidLanguageTag,
+    #[msg("A message carries between 1 and 4 translations")]
+    TooManySegments,
+    #[msg("Each language may appear only once")]
+    DuplicateLanguage,
+    #[msg("Translations exceed the total size limit")]
+    TranslationsTooLong,
+    #[msg("A message carries between 1 and 4 attachments")]
+    TooManyAttachments,
+    #[msg("Attachment URI or MIME type is empty or too long")]
+    InvalidAttachment,
+    #[msg("Every attachment needs alt text")]
+    AltTextRequired,
+    #[msg("Alt text is too long")]
+    AltTextTooLong,
+    #[msg("Only the message sender or the moderator may do this")]
+    NotSenderOrModerator,
+    #[msg("The moderator applied this warning")]
+    WarningSetByModerator,
+    #[msg("Regions must be up to 16 distinct uppercase ISO country codes, listed only when restricted")]
+    InvalidRegions,
+    #[msg("Room message is not awaiting approval")]
+    RoomMessageNotPending,
+    #[msg("Community moderation is not enabled for this room")]
+    CommunityModerationDisabled,
+    #[msg("Room message is not published")]
+    RoomMessageNotPublished,
+    #[msg("Only room members may vote")]
+    NotRoomMember,
+    #[msg("Stake account has no active stake for this voter")]
+    NoActiveStake,
+    #[msg("Council needs 1-7 distinct members and a threshold no larger than the council")]
+    InvalidCouncil,
+    #[msg("Account has no moderation action that can be appealed")]
+    NothingToAppeal,
+    #[msg("Only the author of the moderated content may appeal")]
+    NotAppellant,
+    #[msg("Signer is not on the moderation council")]
+    NotCouncilMember,
+    #[msg("Appeal has already been resolved")]
+    AppealResolved,
+    #[msg("Council member has already ruled on this appeal")]
+    AlreadyRuled,
+    #[msg("The warning's payer must be passed to lift it")]
+    WarningPayerRequired,
+    #[msg("Council elections are not enabled")]
+    ElectionsDisabled,
+    #[msg("Election is not open in this epoch")]
+    ElectionClosed,
+    #[msg("Election is still open")]
+    ElectionOpen,
+    #[msg("Election has already been finalized")]
+    ElectionFinalized,
+    #[msg("Already standing in this election")]
+    AlreadyCandidate,
+    #[msg("The ballot is full and the bond does not beat the lowest candidate bond")]
+    TooManyCandidates,
+    #[msg("Not a candidate in this election")]
+    NotCandidate,
+    #[msg("Vote amount must be positive")]
+    InvalidVoteAmount,
+    #[msg("Only the admin or a council member may do this")]
+    NotTreasurySigner,
+    #[msg("Spend amount must be positive")]
+    InvalidSpendAmount,
+    #[msg("Spend proposal is not open")]
+    SpendNotOpen,
+    #[msg("Spend proposal has not been approved")]
+    SpendNotApproved,
+    #[msg("Already approved this spend")]
+    SpendAlreadyApproved,
+    #[msg("Treasury cannot cover this spend")]
+    TreasuryInsufficientFunds,
+    #[msg("Spend memo exceeds maximum length")]
+    SpendMemoTooLong,
+    #[msg("Fee tiers must be at most 4, in ascending order, and all bps values at most 10000")]
+    InvalidFeeSchedule,
+    #[msg("The treasury must be passed to pay the protocol fee")]
+    TreasuryRequired,
+    #[msg("Pass duration must be positive")]
+    InvalidPassOffer,
+    #[msg("App name must be 1-32 bytes")]
+    InvalidAppName,
+    #[msg("App has no revenue the treasury can pay")]
+    NoRevenueToClaim,
+    #[msg("Feature gate lists at most 16 apps")]
+    TooManyGatedApps,
+    #[msg("This feature is limited to allow-listed apps during its rollout")]
+    FeatureNotEnabledForApp,
+    #[msg("Schema needs a 1-32 byte name, a positive version following the previous one, and a URI of at most 128 bytes")]
+    InvalidSchema,
+    #[msg("Only the schema's authority may publish new versions")]
+    NotSchemaAuthority,
+    #[msg("Payment note must directly follow a token transfer from the sender to the recipient")]
+    PaymentTransferNotFound,
+    #[msg("Swap needs a positive minimum output")]
+    InvalidSwapLimits,
+    #[msg("Swap went outside the sender's slippage limits")]
+    SlippageExceeded,
+    #[msg("Tip must go to a token account owned by the recipient")]
+    InvalidTipDestination,
+    #[msg("Tip must be paid out in the recipient's preferred mint")]
+    NotPreferredTipMint,
+    #[msg("Tip amount must be positive")]
+    InvalidTipAmount,
+    #[msg("The config's charity must be passed to receive the round-up")]
+    CharityRequired,
+    #[msg("Tip stream needs a recipient other than the sender, a positive amount and duration, and a non-negative idle timeout")]
+    InvalidTipStream,
+    #[msg("Tip stream is paused")]
+    TipStreamPaused,
+    #[msg("Tip stream is not paused")]
+    TipStreamNotPaused,
+    #[msg("Nothing has vested since the last withdrawal")]
+    NothingVested,
+    #[msg("Booking needs another party, a future start and a positive duration")]
+    InvalidBooking,
+    #[msg("Booking is not awaiting a response")]
+    BookingNotProposed,
+    #[msg("Booking has not been accepted")]
+    BookingNotAccepted,
+    #[msg("Booking slot has already started")]
+    BookingSlotPassed,
+    #[msg("Booking cannot be settled yet")]
+    BookingNotOver,
+    #[msg("Signer is not a party to this booking")]
+    NotBookingParty,
+    #[msg("UTC offset must be between -12:00 and +14:00")]
+    InvalidAvailability,
+    #[msg("Consultant is not available for the whole slot")]
+    SlotUnavailable,
+    #[msg("Profile links need a label of at most 32 bytes and an https URI of at most 128")]
+    InvalidProfileLink,
+    #[msg("Too many profile links")]
+    TooManyProfileLinks,
+    #[msg("Profile link was changed or removed")]
+    ProfileLinkChanged,
+    #[msg("Namespace name must be 1-32 bytes")]
+    InvalidNamespace,
+    #[msg("Branding needs a display name of at most 32 bytes and a logo URI of at most 128")]
+    InvalidBranding,
+    #[msg("Too many federation peers")]
+    TooManyFederationPeers,
+    #[msg("Message must be sent in the sender's namespace")]
+    WrongNamespace,
+    #[msg("Namespaces are not federated with each other")]
+    NotFederated,
+    #[msg("Timelock must be between zero and 30 days")]
+    InvalidTimelock,
+    #[msg("Config change is still timelocked")]
+    TimelockNotElapsed,
+    #[msg("Account the config change applies to was not provided")]
+    ConfigChangeAccountMissing,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Fault injection is only available in chaos builds")]
+    ChaosDisabled,
+    #[msg("Injected fault")]
+    ChaosFaultInjected,
+    #[msg("An earlier instruction in this transaction is signed by the payer")]
+    UnexpectedPrecedingInstruction,
+    #[msg("A private inbox index needs a published encryption key")]
+    EncryptionKeyRequired,
+    #[msg("Private inbox indexes take a salted entry and public ones take none")]
+    InvalidIndexEntry,
+    #[msg("Message is already in the inbox index")]
+    AlreadyIndexed,
+    #[msg("Spam filter window must be positive")]
+    InvalidSpamFilter,
+    #[msg("Content was recently flagged as spam; pass a spam deposit account to send it")]
+    SpamDepositRequired,
+    #[msg("Spam deposit is still held")]
+    SpamDepositHeld,
+    #[msg("Signer is not a party to this deposit")]
+    NotDepositParty,
+    #[msg("Signer is not the configured moderator")]
+    NotModerator,
+    #[msg("Patron receipts need an https URI base of at most 64 bytes")]
+    InvalidPatronConfig,
+    #[msg("Tip does not exceed the patron receipt threshold")]
+    PatronThresholdNotMet,
+    #[msg("Patron receipts need the tipped message and the Bubblegum tree accounts")]
+    PatronAccountsMissing,
+    #[msg("The message was sent before its recipient became a spam trap")]
+    MessagePredatesTrap,
+    #[msg("Standing for the council needs at least the candidate bond")]
+    InsufficientCandidateBond,
+    #[msg("Crank bounty is above the protocol cap")]
+    CrankBountyTooHigh,
+    #[msg("Only the sender may index to a public inbox, and only its owner or delegate to a private one")]
+    NotIndexAuthority,
+    #[msg("The beneficiary already claimed this message")]
+    DeadManMessageClaimed,
+    #[msg("Collection metadata account is not the mint's Metaplex metadata")]
+    InvalidCollectionMetadata,
+    #[msg("Only the collection's update authority can do this")]
+    NotCollectionAuthority,
+    #[msg("The client disputed this booking")]
+    BookingDisputed,
+    #[msg("Booking is not disputed")]
+    BookingNotDisputed,
+    #[msg("The window to dispute this booking has closed")]
+    DisputeWindowClosed,
+    #[msg("The deal can't be refunded by the buyer until it times out")]
+    DealTimeoutNotReached,
+    #[msg("The other player still has time to move")]
+    MoveTimeoutNotReached,
+    #[msg("The poll was not resolved in time; its stakes are refunded")]
+    PollResolutionExpired,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+}
+
+/// Pays out an escrow vault owned by a PDA and closes it, returning rent to `rent_receiver`.
+fn release_escrow<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    destination: &Account<'info, TokenAccount>,
+    rent_receiver: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    authority_seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    let signer = &[authority_seeds];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: destination.to_account_info(),
+                authority: authority.clone(),
+            },
+            signer,
+        ),
+        amount,
+    )?;
+
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: vault.to_account_info(),
+            destination: rent_receiver.clone(),
+            authority: authority.clone(),
+        },
+        signer,
+    ))
+}
+
+/// Aggregate price read from a Pyth price account.
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub slot: u64,
+}
+
+/// Reads the aggregate price from a legacy Pyth v2 price account.
+fn read_pyth_price(account: &AccountInfo) -> Result<OraclePrice> {
+    const MAGIC: u32 = 0xa1b2_c3d4;
+    const PRICE_ACCOUNT_TYPE: u32 = 3;
+    const STATUS_TRADING: u32 = 1;
+
+    require_keys_eq!(*account.owner, PYTH_PROGRAM_ID, ErrorCode::InvalidOracleAccount);
+    let data = account.try_borrow_data()?;
+    require!(data.len() >= 240, ErrorCode::InvalidOracleAccount);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+    require!(u32_at(0) == MAGIC, ErrorCode::InvalidOracleAccount);
+    require!(u32_at(8) == PRICE_ACCOUNT_TYPE, ErrorCode::InvalidOracleAccount);
+    require!(u32_at(224) == STATUS_TRADING, ErrorCode::OraclePriceUnavailable);
+
+    Ok(OraclePrice {
+        price: u64_at(208) as i64,
+        conf: u64_at(216),
+        expo: u32_at(20) as i32,
+        slot: u64_at(232),
+    })
+}
+
+/// Reads the update authority from a Metaplex metadata account.
+fn metadata_update_authority(account: &AccountInfo) -> Result<Pubkey> {
+    const METADATA_V1: u8 = 4;
+
+    let data = account.try_borrow_data()?;
+    require!(data.len() >= 33 && data[0] == METADATA_V1, ErrorCode::InvalidCollectionMetadata);
+
+    Ok(Pubkey::new_from_array(data[1..33].try_into().unwrap()))
+}
+
+/// Reads the governance an SPL Governance proposal (V1 or V2) belongs to.
+fn proposal_governance(account: &AccountInfo) -> Result<Pubkey> {
+    const PROPOSAL_V1: u8 = 5;
+    const PROPOSAL_V2: u8 = 14;
+
+    let data = account.try_borrow_data()?;
+    require!(data.len() >= 33, ErrorCode::InvalidProposalAccount);
+    require!(data[0] == PROPOSAL_V1 || data[0] == PROPOSAL_V2, ErrorCode::InvalidProposalAccount);
+
+    Ok(Pubkey::new_from_array(data[1..33].try_into().unwrap()))
+}
+
+/// Looks for an earlier SPL Token transfer in the current transaction that pays at least
+/// `amount` from `payer` to `destination` and carries the Solana Pay `reference` key.
+fn find_token_payment(
+    instructions: &AccountInfo,
+    payer: &Pubkey,
+    destination: &Pubkey,
+    reference: &Pubkey,
+    amount: u64,
+) -> Result<bool> {
+    const TRANSFER: u8 = 3;
+    const TRANSFER_CHECKED: u8 = 12;
+
+    let current = sysvar_instructions::load_current_index_checked(instructions)?;
+    for index in 0..current {
+        let ix = sysvar_instructions::load_instruction_at_checked(index as usize, instructions)?;
+        if ix.program_id != token::ID || ix.data.len() < 9 {
+            continue;
+        }
+
+        // Transfer: [source, destination, authority]; TransferChecked: [source, mint, destination, authority]
+        let (dest_index, authority_index) = match ix.data[0] {
+            TRANSFER => (1, 2),
+            TRANSFER_CHECKED => (2, 3),
+            _ => continue,
+        };
+        let paid = u64::from_le_bytes(ix.data[1..9].try_into().unwrap());
+        let key_at = |i: usize| ix.accounts.get(i).map(|meta| meta.pubkey);
+
+        if key_at(dest_index) == Some(*destination)
+            && key_at(authority_index) == Some(*payer)
+            && paid >= amount
+            && ix.accounts.iter().any(|meta| meta.pubkey == *reference)
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Applies a reputation event when the caller passed the user's reputation account.
+fn record_reputation(
+    reputation: Option<&mut Account<Reputation>>,
+    config: Option<&Account<Config>>,
+    event: ReputationEvent,
+    now: i64,
+) -> Result<()> {
+    let Some(reputation) = reputation else {
+        return Ok(());
+    };
+    let config = config.ok_or(ErrorCode::MissingConfig)?;
+
+    reputation.record(event, &config.reputation, now);
+
+    Ok(())
+}
+
+/// Applies a reputation event to the user's reputation PDA, if they have one. For
+/// permissionless callers, who could otherwise leave out an account that is about to be
+/// penalised.
+fn record_reputation_at(info: &AccountInfo, params: &ReputationParams, event: ReputationEvent, now: i64) -> Result<()> {
+    if info.data_is_empty() {
+        return Ok(());
+    }
+    let mut data = info.try_borrow_mut_data()?;
+    let mut reputation = Reputation::try_deserialize(&mut &data[..])?;
+    reputation.record(event, params, now);
+    reputation.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
+
+/// Enforces the recipient's sybil gate, if any: the sender passes with either an old enough
+/// active stake account they control or an attestation of enough history.
+fn check_sybil_gate(
+    gate_info: &AccountInfo,
+    sender: &Pubkey,
+    stake_account: Option<&AccountInfo>,
+    attestation: Option<&Attestation>,
+    clock: &Clock,
+) -> Result<()> {
+    if gate_info.data_is_empty() {
+        return Ok(());
+    }
+    let gate = InboxGate::try_deserialize(&mut &gate_info.try_borrow_data()?[..])?;
+
+    if let Some(stake) = stake_account {
+        if stake_age_epochs(stake, sender, clock.epoch)? >= gate.min_stake_age_epochs {
+            return Ok(());
+        }
+    }
+
+    if let Some(attestation) = attestation {
+        let account_age = clock.unix_timestamp.saturating_sub(attestation.first_activity_ts);
+        if attestation.tx_count >= gate.min_tx_count && account_age >= gate.min_account_age_secs {
+            return Ok(());
+        }
+    }
+
+    err!(ErrorCode::InboxGateNotMet)
+}
+
+/// Epochs since activation of an active native stake account whose staker or withdrawer is
+/// `owner`; zero for anything else.
+fn stake_age_epochs(stake: &AccountInfo, owner: &Pubkey, current_epoch: u64) -> Result<u64> {
+    const STAKE_STATE_STAKE: u32 = 2;
+
+    if *stake.owner != anchor_lang::solana_program::stake::program::ID {
+        return Ok(0);
+    }
+    let data = stake.try_borrow_data()?;
+    if data.len() < 180 {
+        return Ok(0);
+    }
+
+    // StakeStateV2::Stake(Meta { rent_exempt_reserve, authorized, lockup }, Stake { delegation, .. })
+    let tag = u32::from_le_bytes(data[0..4].try_into().unwrap());
+    let staker = Pubkey::new_from_array(data[12..44].try_into().unwrap());
+    let withdrawer = Pubkey::new_from_array(data[44..76].try_into().unwrap());
+    let activation_epoch = u64::from_le_bytes(data[164..172].try_into().unwrap());
+    let deactivation_epoch = u64::from_le_bytes(data[172..180].try_into().unwrap());
+
+    if tag != STAKE_STATE_STAKE
+        || (staker != *owner && withdrawer != *owner)
+        || deactivation_epoch != u64::MAX
+    {
+        return Ok(0);
+    }
+
+    Ok(current_epoch.saturating_sub(activation_epoch))
+}
+
+/// Active delegated stake of a stake account the owner is staker or withdrawer of; 0 otherwise.
+fn active_stake(stake: &AccountInfo, owner: &Pubkey, current_epoch: u64) -> Result<u64> {
+    if stake_age_epochs(stake, owner, current_epoch)? == 0 {
+        return Ok(0);
+    }
+    let data = stake.try_borrow_data()?;
+    // Stake { delegation: Delegation { voter_pubkey, stake, .. }, .. }
+    Ok(u64::from_le_bytes(data[156..164].try_into().unwrap()))
+}
+
+fn validate_guardians(owner: &Pubkey, guardians: &[Pubkey], threshold: u8, timelock_secs: i64) -> Result<()> {
+    require!(guardians.len() <= GuardianSet::MAX_GUARDIANS, ErrorCode::InvalidGuardianSet);
+    require!(threshold > 0 && threshold as usize <= guardians.len(), ErrorCode::InvalidGuardianSet);
+    require!(timelock_secs >= GuardianSet::MIN_TIMELOCK_SECS, ErrorCode::InvalidGuardianSet);
+    require!(!guardians.contains(owner), ErrorCode::InvalidGuardianSet);
+
+    for (i, guardian) in guardians.iter().enumerate() {
+        require!(!guardians[..i].contains(guardian), ErrorCode::InvalidGuardianSet);
+    }
+
+    Ok(())
+}
+
+/// Moves up to `bounty` lamports from a program-owned account to the cranker, never taking it
+/// below `floor`. Accounts being closed pass a floor of zero.
+fn pay_crank_bounty(from: &AccountInfo, cranker: &AccountInfo, bounty: u64, floor: u64) -> Result<()> {
+    let amount = bounty.min(from.lamports().saturating_sub(floor));
+    if amount == 0 {
+        return Ok(());
+    }
+
+    **from.try_borrow_mut_lamports()? -= amount;
+    **cranker.try_borrow_mut_lamports()? += amount;
+
+    Ok(())
+}
+
+/// Closes a program account that wasn't deserialized by the accounts struct, zeroing its data
+/// first so nothing recoverable is left in the final account state.
+fn close_and_zero(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    account.try_borrow_mut_data()?.fill(0);
+
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? += lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+
+    account.assign(&system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+fn is_language_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= MessageTranslations::MAX_LANGUAGE_LEN
+        && tag.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+fn is_moderator(config: &Config, key: &Pubkey) -> bool {
+    config.moderator != Pubkey::default() && config.moderator == *key
+}
+
+fn validate_council_elections(vote_mint: &Pubkey, seats: u8, candidate_bond: u64) -> Result<()> {
+    if *vote_mint == Pubkey::default() {
+        require!(seats == 0 && candidate_bond == 0, ErrorCode::InvalidCouncil);
+    } else {
+        require!(seats > 0 && seats as usize <= ModerationCouncil::MAX_MEMBERS, ErrorCode::InvalidCouncil);
+        require!(candidate_bond > 0, ErrorCode::InvalidCouncil);
+    }
+    Ok(())
+}
+
+fn validate_council(members: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !members.is_empty() && members.len() <= ModerationCouncil::MAX_MEMBERS,
+        ErrorCode::InvalidCouncil
+    );
+    require!(threshold > 0 && threshold as usize <= members.len(), ErrorCode::InvalidCouncil);
+    for (i, member) in members.iter().enumerate() {
+        require!(!members[..i].contains(member), ErrorCode::InvalidCouncil);
+    }
+    Ok(())
+}
+
+/// Who may propose and approve treasury spends.
+enum TreasurySigner<'a> {
+    Admin,
+    CouncilMember(&'a ModerationCouncil),
+}
+
+fn treasury_signer<'a>(config: &Config, council: Option<&'a ModerationCouncil>, key: &Pubkey) -> Option<TreasurySigner<'a>> {
+    if config.admin == *key {
+        return Some(TreasurySigner::Admin);
+    }
+    council.filter(|council| council.members.contains(key)).map(TreasurySigner::CouncilMember)
+}
+
+fn validate_fee_schedule(tiers: &[FeeTier], pass_discount_bps: u16, app_share_bps: u16) -> Result<()> {
+    require!(tiers.len() <= FeeSchedule::MAX_TIERS, ErrorCode::InvalidFeeSchedule);
+    require!(pass_discount_bps <= 10_000 && app_share_bps <= 10_000, ErrorCode::InvalidFeeSchedule);
+    for (i, tier) in tiers.iter().enumerate() {
+        require!(tier.discount_bps <= 10_000, ErrorCode::InvalidFeeSchedule);
+        require!(
+            i == 0 || tiers[i - 1].min_messages_sent < tier.min_messages_sent,
+            ErrorCode::InvalidFeeSchedule
+        );
+    }
+    Ok(())
+}
+
+/// Whether `token` holds the NFT `pass` records and the pass hasn't expired.
+fn holds_live_pass(token: Option<&TokenAccount>, pass: Option<&ProtocolPass>, now: i64) -> bool {
+    match (token, pass) {
+        (Some(token), Some(pass)) => token.mint == pass.mint && token.amount > 0 && now < pass.expires_at,
+        _ => false,
+    }
+}
+
+/// Fails if the feature is gated and the instruction didn't come through an allow-listed app.
+/// The app reference is not signed by the app, so this stages a rollout rather than securing it.
+fn check_feature_gate(gate: &AccountInfo, app: Option<&Account<App>>) -> Result<()> {
+    let Some(gate) = FeatureGate::load(gate)? else {
+        return Ok(());
+    };
+    require!(
+        app.is_some_and(|app| gate.apps.contains(&app.key())),
+        ErrorCode::FeatureNotEnabledForApp
+    );
+    Ok(())
+}
+
+/// An SPL Token or Token-2022 transfer read from the instructions sysvar.
+struct TokenTransfer {
+    program_id: Pubkey,
+    source: Pubkey,
+    destination: Pubkey,
+    authority: Pubkey,
+    amount: u64,
+}
+
+/// The `Transfer` or `TransferChecked` instruction directly before the current one.
+fn preceding_token_transfer(instructions: &AccountInfo) -> Result<TokenTransfer> {
+    const TRANSFER: u8 = 3;
+    const TRANSFER_CHECKED: u8 = 12;
+
+    let current = sysvar_instructions::load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::PaymentTransferNotFound);
+    let ix = sysvar_instructions::load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        (ix.program_id == token::ID || ix.program_id == anchor_spl::token_2022::ID) && ix.data.len() >= 9,
+        ErrorCode::PaymentTransferNotFound
+    );
+
+    // Transfer: [source, destination, authority]; TransferChecked: [source, mint, destination, authority]
+    let (dest_index, authority_index) = match ix.data[0] {
+        TRANSFER => (1, 2),
+        TRANSFER_CHECKED => (2, 3),
+        _ => return err!(ErrorCode::PaymentTransferNotFound),
+    };
+    let key_at = |i: usize| ix.accounts.get(i).map(|meta| meta.pubkey).ok_or(ErrorCode::PaymentTransferNotFound);
+
+    Ok(TokenTransfer {
+        program_id: ix.program_id,
+        source: key_at(0)?,
+        destination: key_at(dest_index)?,
+        authority: key_at(authority_index)?,
+        amount: u64::from_le_bytes(ix.data[1..9].try_into().unwrap()),
+    })
+}
+
+/// Fails if an instruction before the current one moves, delegates, hands over or closes what
+/// `signer` holds. Other instructions, such as compute budget requests, associated token account
+/// creation, swap setup or an earlier send, may precede a payment.
+fn require_no_preceding_drain(instructions: &AccountInfo, signer: &Pubkey) -> Result<()> {
+    let current = sysvar_instructions::load_current_index_checked(instructions)?;
+    for index in 0..current {
+        let ix = sysvar_instructions::load_instruction_at_checked(index as usize, instructions)?;
+        require!(!drains(&ix, signer), ErrorCode::UnexpectedPrecedingInstruction);
+    }
+
+    Ok(())
+}
+
+/// Whether `ix` is a System Program, SPL Token or Token-2022 instruction that `owner` authorizes
+/// and that takes lamports or tokens out of their hands: transfers, account creation funded by
+/// them, reassignment, delegation, authority changes, burns and closes. Wrapping SOL into the
+/// owner's own associated token account is a transfer they keep.
+fn drains(ix: &Instruction, owner: &Pubkey) -> bool {
+    let signed_by_owner = |meta: &AccountMeta| meta.is_signer && meta.pubkey == *owner;
+
+    if ix.program_id == system_program::ID {
+        let Some(tag) = ix.data.get(..4) else {
+            return false;
+        };
+        let authority_index = match u32::from_le_bytes(tag.try_into().unwrap()) {
+            // Transfer: [from, to]
+            2 => {
+                let wrapped_sol = anchor_spl::associated_token::get_associated_token_address(
+                    owner,
+                    &anchor_spl::token::spl_token::native_mint::ID,
+                );
+                if ix.accounts.get(1).is_some_and(|meta| meta.pubkey == wrapped_sol) {
+                    return false;
+                }
+                0
+            }
+            // CreateAccount, Assign, CreateAccountWithSeed, Allocate: the funding or target account
+            0 | 1 | 3 | 8 => 0,
+            // AuthorizeNonceAccount, AllocateWithSeed, AssignWithSeed, TransferWithSeed: the base or authority
+            7 | 9 | 10 | 11 => 1,
+            // WithdrawNonceAccount: [nonce, to, recent blockhashes, rent, authority]
+            5 => 4,
+            _ => return false,
+        };
+        return ix.accounts.get(authority_index).is_some_and(signed_by_owner);
+    }
+
+    if ix.program_id == token::ID || ix.program_id == anchor_spl::token_2022::ID {
+        let authority_index = match ix.data.first() {
+            // SetAuthority: [account, current authority]
+            Some(6) => 1,
+            // Transfer, Approve, Burn, CloseAccount, BurnChecked: the owner follows two accounts
+            Some(3 | 4 | 8 | 9 | 15) => 2,
+            // TransferChecked, ApproveChecked: [source, mint, destination or delegate, owner]
+            Some(12 | 13) => 3,
+            // Token-2022 TransferCheckedWithFee
+            Some(26) if ix.data.get(1) == Some(&1) => 3,
+            _ => return false,
+        };
+        // A multisig authority is followed by its signers, so the owner may sign at or after it
+        return ix.accounts.iter().skip(authority_index).any(signed_by_owner);
+    }
+
+    false
+}
+
+/// Appends an admin action to the audit log and emits it.
+fn record_admin_action<O: AnchorSerialize, N: AnchorSerialize>(
+    log: &mut AuditLog,
+    actor: Pubkey,
+    action: AdminAction,
+    target: Pubkey,
+    old: &O,
+    new: &N,
+) -> Result<()> {
+    let entry = log.append(AuditEntry {
+        sequence: 0,
+        actor,
+        action,
+        target,
+        old_hash: hash(&old.try_to_vec()?).to_bytes(),
+        new_hash: hash(&new.try_to_vec()?).to_bytes(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    emit!(AdminActionRecorded { entry });
+
+    Ok(())
+}
+
+/// Fails if the chaos plan among `remaining` is armed for `point` and its skips are used up.
+/// Compiles to nothing without the `chaos` feature.
+#[cfg(feature = "chaos")]
+fn fail_point(remaining: &[AccountInfo], point: ChaosPoint) -> Result<()> {
+    let (address, _) = Pubkey::find_program_address(&[b"chaos_plan"], &crate::ID);
+    let Some(info) = remaining.iter().find(|info| info.key() == address && info.owner == &crate::ID) else {
+        return Ok(());
+    };
+
+    let mut plan = ChaosPlan::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    if plan.point != point {
+        return Ok(());
+    }
+    require!(plan.hits < plan.skip_hits, ErrorCode::ChaosFaultInjected);
+    plan.hits += 1;
+    plan.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+fn fail_point(_remaining: &[AccountInfo], _point: ChaosPoint) -> Result<()> {
+    Ok(())
+}
+
+/// Bubblegum's `MetadataArgs`, with its nested structs as tuples in the same field order
+#[derive(AnchorSerialize)]
+struct BubblegumMetadataArgs {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    /// `TokenStandard`
+    token_standard: Option<u8>,
+    /// `Collection { verified, key }`
+    collection: Option<(bool, Pubkey)>,
+    /// `Uses { use_method, remaining, total }`
+    uses: Option<(u8, u64, u64)>,
+    /// `TokenProgramVersion`
+    token_program_version: u8,
+    /// `Creator { address, verified, share }`
+    creators: Vec<(Pubkey, bool, u8)>,
+}
+
+/// Mints a patron receipt to `tipper` with Bubblegum's `mint_v1`, signed by the patron config
+/// as tree delegate. The tipped creator is listed, unverified, as the receipt's creator.
+fn mint_patron_receipt<'info>(
+    patron: &Account<'info, PatronConfig>,
+    tree_authority: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    tipper: &AccountInfo<'info>,
+    log_wrapper: &AccountInfo<'info>,
+    compression_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    recipient: Pubkey,
+    message: Pubkey,
+    amount_lamports: u64,
+) -> Result<()> {
+    const MINT_V1: [u8; 8] = [145, 98, 192, 118, 184, 147, 118, 104];
+    const NON_FUNGIBLE: u8 = 0;
+    const ORIGINAL: u8 = 0;
+
+    let args = BubblegumMetadataArgs {
+        name: format!("Patron #{}", patron.receipts_minted + 1),
+        symbol: "PATRON".to_string(),
+        uri: format!("{}/{}/{}", patron.uri_base, message, amount_lamports),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(NON_FUNGIBLE),
+        collection: None,
+        uses: None,
+        token_program_version: ORIGINAL,
+        creators: vec![(recipient, false, 100)],
+    };
+    let mut data = MINT_V1.to_vec();
+    args.serialize(&mut data)?;
+
+    let mint = Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(tree_authority.key(), false),
+            AccountMeta::new_readonly(tipper.key(), false),
+            AccountMeta::new_readonly(tipper.key(), false),
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new(tipper.key(), true),
+            AccountMeta::new_readonly(patron.key(), true),
+            AccountMeta::new_readonly(log_wrapper.key(), false),
+            AccountMeta::new_readonly(compression_program.key(), false),
+            AccountMeta::new_readonly(system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &mint,
+        &[
+            tree_authority.clone(),
+            tipper.clone(),
+            merkle_tree.clone(),
+            patron.to_account_info(),
+            log_wrapper.clone(),
+            compression_program.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"patron_config", &[patron.bump]]],
+    )?;
+
+    Ok(())
+}
+
+/// PDA seed for an account's namespace: the namespace address, or nothing on the public network,
+/// so public accounts keep their original addresses.
+fn namespace_seed(namespace: &Pubkey) -> &[u8] {
+    if *namespace == Pubkey::default() {
+        &[]
+    } else {
+        namespace.as_ref()
+    }
+}
+
+/// Namespace an account is being created in, which has to be the creator's own.
+fn namespace_for(namespace: Option<&Account<Namespace>>, membership: &AccountInfo) -> Result<Pubkey> {
+    let namespace = namespace.map_or(Pubkey::default(), |namespace| namespace.key());
+    require_keys_eq!(namespace, NamespaceMember::namespace_of(membership)?, ErrorCode::WrongNamespace);
+    Ok(namespace)
 }
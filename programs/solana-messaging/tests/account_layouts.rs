@@ -0,0 +1,1575 @@
+//! Golden-file tests for account layouts. Every account type is serialized with fixed data
+//! and compared byte-for-byte with its snapshot in `tests/snapshots`, so a change that would
+//! misread accounts already on chain fails here first.
+//!
+//! When a layout change is intended (a new account type, or a migration), regenerate the
+//! snapshots with `UPDATE_SNAPSHOTS=1 cargo test -p solana-messaging --test account_layouts`
+//! and commit them with the change.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use solana_messaging::{
+    AdminAction, AmaInbox, AmaQuestion, App, Appeal, AppealKind, AppealStatus, Attachment,
+    Attendee, Attestation, AuditEntry, AuditLog, Availability, Ballot, BetaFeature, BlobMessage,
+    BookingMessage, BookingStatus, ChaosPlan, ChaosPoint, CollectionFeed, CommittedMessage,
+    ConditionalMessage, Config, ConfigChange, ContentBlob, ContentFilter, ContentSegment,
+    ContentWarning, ContentWarningCategory, ConversationSettings, CouncilCandidacy,
+    CouncilElection, CouncilVote, DealMessage, DealMessageKind, DealRoom, DealStatus,
+    DeliveryState, DeliveryWindow, Disclosure, DmLink, EncryptionKey, EpochStats, EventMessage,
+    FeatureGate, FederationPolicy, FeeSchedule, FeeTier, Forwarding, GameSession, GameStatus,
+    GovernanceNotifier, GuardianSet, InboxGate, InboxIndex, IndexedMessage, InternedMessage,
+    Invoice, InvoiceStatus, KeyTransparencyLog, LinkVerification, MarketEventKind, MarketNotice,
+    MerchantProfile, MerchantReceipt, Message, MessageCost, MessageDelivery, MessageDictionary,
+    MessageMedia, MessagePriority, MessageTranslations, ModerationCouncil, ModerationVote,
+    Namespace, NamespaceBranding, NamespaceMember, Notification, NotificationSeverity, Notifier,
+    ObserverGrant, ObserverKey, PassOffer, PatronConfig, PaymentNote, PendingConfigChange, Poll,
+    PriceQuoteMessage, PriorityPolicy, Profile, ProfileLink, ProposalNotification, ProtocolPass,
+    Receipt, ReceiptReference, RecoveryRequest, RegionMode, Reminder, Reputation, ReputationParams,
+    Room, RoomMessage, RoomMessageStatus, RoomReport, RoundUp, Schema, SchemaFormat, Snooze,
+    SpamDeposit, SpamFilter, SpamFlag, SpamTrap, SpendStatus, StructuredMessage, TaskMessage,
+    TaskStatus, TeamInbox, TeamInboxStats, Ticket, TicketStatus, TipStream, TradeProposal,
+    TradeStatus, Treasury, TreasuryProposal, UnlockCondition, UserStats, Vouch, WarningAction,
+};
+
+fn key(seed: u8) -> Pubkey {
+    Pubkey::new_from_array([seed; 32])
+}
+
+/// Compares the serialized account with `tests/snapshots/<name>.hex`, 32 bytes per line.
+fn check_snapshot<T: AccountSerialize>(name: &str, account: &T) {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).expect("serialize");
+    let mut hex = String::new();
+    for line in data.chunks(32) {
+        for byte in line {
+            write!(hex, "{byte:02x}").unwrap();
+        }
+        hex.push('\n');
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.hex"));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, hex).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("no snapshot for {name}; run with UPDATE_SNAPSHOTS=1 to record it")
+    });
+    assert!(
+        hex == expected,
+        "{name} layout differs from {}",
+        path.display()
+    );
+}
+
+#[test]
+fn message_layout() {
+    check_snapshot(
+        "Message",
+        &Message {
+            sender: key(1),
+            recipient: key(2),
+            content: "content".to_string(),
+            timestamp: 3003,
+            nft_mint: key(4),
+            priority: MessagePriority::Urgent,
+            client_message_id: [5; 16],
+            app: key(6),
+            namespace: key(7),
+            cost: MessageCost {
+                rent_lamports: 8008,
+                protocol_fee_lamports: 9009,
+                urgent_fee_lamports: 10010,
+                deposit_lamports: 11011,
+            },
+            bump: 12,
+        },
+    );
+}
+
+#[test]
+fn event_message_layout() {
+    check_snapshot(
+        "EventMessage",
+        &EventMessage {
+            organizer: key(1),
+            recipient: key(2),
+            event_id: 3003,
+            title: "title".to_string(),
+            location_uri: "location_uri".to_string(),
+            start_ts: 4004,
+            end_ts: 5005,
+            capacity: 6006,
+            attendee_count: 7007,
+            timestamp: 8008,
+            bump: 9,
+        },
+    );
+}
+
+#[test]
+fn attendee_layout() {
+    check_snapshot(
+        "Attendee",
+        &Attendee {
+            event: key(1),
+            attendee: key(2),
+            timestamp: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn task_message_layout() {
+    check_snapshot(
+        "TaskMessage",
+        &TaskMessage {
+            creator: key(1),
+            assignee: key(2),
+            task_id: 3003,
+            description: "description".to_string(),
+            due_ts: 4004,
+            status: TaskStatus::Completed,
+            completed_at: 5005,
+            timestamp: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn trade_proposal_layout() {
+    check_snapshot(
+        "TradeProposal",
+        &TradeProposal {
+            proposer: key(1),
+            recipient: key(2),
+            trade_id: 3003,
+            offer_mint: key(4),
+            offer_amount: 5005,
+            ask_mint: key(6),
+            ask_amount: 7007,
+            status: TradeStatus::Declined,
+            timestamp: 8008,
+            bump: 9,
+            vault_bump: 10,
+        },
+    );
+}
+
+#[test]
+fn invoice_layout() {
+    check_snapshot(
+        "Invoice",
+        &Invoice {
+            issuer: key(1),
+            payer: key(2),
+            invoice_id: 3003,
+            mint: key(4),
+            amount: 5005,
+            memo: "memo".to_string(),
+            due_ts: 6006,
+            status: InvoiceStatus::Cancelled,
+            period_secs: 7007,
+            max_periods: 8008,
+            periods_paid: 9009,
+            payer_token_account: key(10),
+            timestamp: 11011,
+            namespace: key(12),
+            bump: 13,
+        },
+    );
+}
+
+#[test]
+fn receipt_layout() {
+    check_snapshot(
+        "Receipt",
+        &Receipt {
+            invoice: key(1),
+            payer: key(2),
+            mint: key(3),
+            amount: 4004,
+            paid_at: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn conditional_message_layout() {
+    check_snapshot(
+        "ConditionalMessage",
+        &ConditionalMessage {
+            sender: key(1),
+            recipient: key(2),
+            message_id: 3003,
+            encrypted_content: "encrypted_content".to_string(),
+            key_hash: [4; 32],
+            condition: UnlockCondition::AfterTimestamp { unlock_ts: 5005 },
+            content_key: [6; 32],
+            unlocked_at: 7007,
+            last_heartbeat: 8008,
+            claimed_at: 9009,
+            public_countdown: true,
+            timestamp: 10010,
+            bump: 11,
+        },
+    );
+}
+
+#[test]
+fn committed_message_layout() {
+    check_snapshot(
+        "CommittedMessage",
+        &CommittedMessage {
+            sender: key(1),
+            recipient: key(2),
+            commit_id: 3003,
+            commitment: [4; 32],
+            content: "content".to_string(),
+            revealed_at: 5005,
+            timestamp: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn game_session_layout() {
+    check_snapshot(
+        "GameSession",
+        &GameSession {
+            creator: key(1),
+            opponent: key(2),
+            game_id: 3003,
+            board: [4; 9],
+            moves: 5,
+            status: GameStatus::Draw,
+            winner: key(6),
+            wager: 7007,
+            timestamp: 8008,
+            last_move_at: 9009,
+            bump: 10,
+        },
+    );
+}
+
+#[test]
+fn config_layout() {
+    check_snapshot(
+        "Config",
+        &Config {
+            admin: key(1),
+            max_poll_stake: 2002,
+            reputation: ReputationParams {
+                answered_weight: 3003,
+                resolved_weight: 4004,
+                dispute_penalty: 5005,
+                spam_penalty: 6006,
+                vouch_weight: 7007,
+                half_life_secs: 8008,
+            },
+            attestor: key(9),
+            crank_bounty_lamports: 10010,
+            moderator: key(11),
+            charity: key(12),
+            timelock_secs: 13013,
+            paused: true,
+            bump: 14,
+        },
+    );
+}
+
+#[test]
+fn poll_layout() {
+    check_snapshot(
+        "Poll",
+        &Poll {
+            creator: key(1),
+            recipient: key(2),
+            poll_id: 3003,
+            question: "question".to_string(),
+            options: vec!["options".to_string()],
+            votes: [4004; Poll::MAX_OPTIONS],
+            stake_per_vote: 5005,
+            closes_at: 6006,
+            resolver: key(7),
+            resolved: true,
+            winning_option: 8,
+            timestamp: 9009,
+            bump: 10,
+        },
+    );
+}
+
+#[test]
+fn ballot_layout() {
+    check_snapshot(
+        "Ballot",
+        &Ballot {
+            poll: key(1),
+            voter: key(2),
+            option: 3,
+            stake: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn price_quote_message_layout() {
+    check_snapshot(
+        "PriceQuoteMessage",
+        &PriceQuoteMessage {
+            sender: key(1),
+            recipient: key(2),
+            quote_id: 3003,
+            price_feed: key(4),
+            price: 5005,
+            conf: 6006,
+            expo: 7007,
+            slot: 8008,
+            note: "note".to_string(),
+            timestamp: 9009,
+            bump: 10,
+        },
+    );
+}
+
+#[test]
+fn governance_notifier_layout() {
+    check_snapshot(
+        "GovernanceNotifier",
+        &GovernanceNotifier {
+            governance: key(1),
+            poster: key(2),
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn proposal_notification_layout() {
+    check_snapshot(
+        "ProposalNotification",
+        &ProposalNotification {
+            governance: key(1),
+            proposal: key(2),
+            title: "title".to_string(),
+            voting_ends_at: 3003,
+            poster: key(4),
+            timestamp: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn collection_feed_layout() {
+    check_snapshot(
+        "CollectionFeed",
+        &CollectionFeed {
+            collection: key(1),
+            owner: key(2),
+            bot: key(3),
+            notice_count: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn market_notice_layout() {
+    check_snapshot(
+        "MarketNotice",
+        &MarketNotice {
+            feed: key(1),
+            index: 2002,
+            kind: MarketEventKind::Bid,
+            nft_mint: key(3),
+            price: 4004,
+            counterparty: key(5),
+            timestamp: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn notifier_layout() {
+    check_snapshot(
+        "Notifier",
+        &Notifier {
+            authority: key(1),
+            name: "name".to_string(),
+            notification_count: 2002,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn notification_layout() {
+    check_snapshot(
+        "Notification",
+        &Notification {
+            notifier: key(1),
+            recipient: key(2),
+            index: 3003,
+            severity: NotificationSeverity::Critical,
+            content: "content".to_string(),
+            timestamp: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn merchant_profile_layout() {
+    check_snapshot(
+        "MerchantProfile",
+        &MerchantProfile {
+            owner: key(1),
+            name: "name".to_string(),
+            settlement_account: key(2),
+            receipt_count: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn merchant_receipt_layout() {
+    check_snapshot(
+        "MerchantReceipt",
+        &MerchantReceipt {
+            merchant: key(1),
+            customer: key(2),
+            index: 3003,
+            amount: 4004,
+            reference: key(5),
+            order_ref: "order_ref".to_string(),
+            timestamp: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn receipt_reference_layout() {
+    check_snapshot(
+        "ReceiptReference",
+        &ReceiptReference {
+            receipt: key(1),
+            bump: 2,
+        },
+    );
+}
+
+#[test]
+fn team_inbox_layout() {
+    check_snapshot(
+        "TeamInbox",
+        &TeamInbox {
+            owner: key(1),
+            name: "name".to_string(),
+            members: vec![key(2)],
+            ticket_count: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn ticket_layout() {
+    check_snapshot(
+        "Ticket",
+        &Ticket {
+            team_inbox: key(1),
+            opener: key(2),
+            index: 3003,
+            subject: "subject".to_string(),
+            status: TicketStatus::Resolved,
+            opened_at: 4004,
+            first_response_at: 5005,
+            resolved_at: 6006,
+            reopen_count: 7007,
+            rating: 8,
+            rating_comment: "rating_comment".to_string(),
+            bump: 9,
+        },
+    );
+}
+
+#[test]
+fn team_inbox_stats_layout() {
+    check_snapshot(
+        "TeamInboxStats",
+        &TeamInboxStats {
+            team_inbox: key(1),
+            rating_count: 2002,
+            rating_sum: 3003,
+            rating_histogram: [4004; 5],
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn deal_room_layout() {
+    check_snapshot(
+        "DealRoom",
+        &DealRoom {
+            seller: key(1),
+            buyer: key(2),
+            arbiter: key(3),
+            deal_id: 4004,
+            mint: key(5),
+            offer_amount: 6006,
+            offer_by: key(7),
+            price: 8008,
+            status: DealStatus::Ruled,
+            message_count: 9009,
+            funded_at: 10010,
+            bump: 11,
+            vault_bump: 12,
+        },
+    );
+}
+
+#[test]
+fn deal_message_layout() {
+    check_snapshot(
+        "DealMessage",
+        &DealMessage {
+            deal: key(1),
+            index: 2002,
+            author: key(3),
+            kind: DealMessageKind::Refund,
+            amount: 4004,
+            note: "note".to_string(),
+            timestamp: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn reputation_layout() {
+    check_snapshot(
+        "Reputation",
+        &Reputation {
+            user: key(1),
+            score: 2002,
+            messages_answered: 3003,
+            tickets_resolved: 4004,
+            disputes_lost: 5005,
+            spam_flags: 6006,
+            vouches: 7007,
+            last_updated: 8008,
+            bump: 9,
+        },
+    );
+}
+
+#[test]
+fn vouch_layout() {
+    check_snapshot(
+        "Vouch",
+        &Vouch {
+            voucher: key(1),
+            vouchee: key(2),
+            weight: 3,
+            stake: 4004,
+            vouchee_flags_at: 5005,
+            timestamp: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn spam_flag_layout() {
+    check_snapshot(
+        "SpamFlag",
+        &SpamFlag {
+            message: key(1),
+            sender: key(2),
+            flagged_by: key(3),
+            flagged_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn attestation_layout() {
+    check_snapshot(
+        "Attestation",
+        &Attestation {
+            subject: key(1),
+            tx_count: 2002,
+            first_activity_ts: 3003,
+            attested_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn inbox_gate_layout() {
+    check_snapshot(
+        "InboxGate",
+        &InboxGate {
+            owner: key(1),
+            min_stake_age_epochs: 2002,
+            min_tx_count: 3003,
+            min_account_age_secs: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn encryption_key_layout() {
+    check_snapshot(
+        "EncryptionKey",
+        &EncryptionKey {
+            owner: key(1),
+            key: [2; 32],
+            version: 3003,
+            updated_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn guardian_set_layout() {
+    check_snapshot(
+        "GuardianSet",
+        &GuardianSet {
+            owner: key(1),
+            guardians: vec![key(2)],
+            threshold: 3,
+            timelock_secs: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn recovery_request_layout() {
+    check_snapshot(
+        "RecoveryRequest",
+        &RecoveryRequest {
+            owner: key(1),
+            proposer: key(2),
+            new_key: [3; 32],
+            approvals: vec![key(4)],
+            proposed_at: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn forwarding_layout() {
+    check_snapshot(
+        "Forwarding",
+        &Forwarding {
+            old_wallet: key(1),
+            new_wallet: key(2),
+            migrated_at: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn dm_link_layout() {
+    check_snapshot(
+        "DmLink",
+        &DmLink {
+            owner: key(1),
+            slug: "slug".to_string(),
+            max_uses: 2002,
+            uses: 3003,
+            expires_at: 4004,
+            created_at: 5005,
+            namespace: key(6),
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn ama_inbox_layout() {
+    check_snapshot(
+        "AmaInbox",
+        &AmaInbox {
+            owner: key(1),
+            title: "title".to_string(),
+            question_count: 2002,
+            is_open: true,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn ama_question_layout() {
+    check_snapshot(
+        "AmaQuestion",
+        &AmaQuestion {
+            ama_inbox: key(1),
+            index: 2002,
+            commitment: [3; 32],
+            content: "content".to_string(),
+            asked_at: 4004,
+            answer: "answer".to_string(),
+            answered_at: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn room_layout() {
+    check_snapshot(
+        "Room",
+        &Room {
+            creator: key(1),
+            room_id: 2002,
+            name: "name".to_string(),
+            created_at: 3003,
+            expires_at: 4004,
+            message_count: 5005,
+            min_age: 6,
+            region_mode: RegionMode::Deny,
+            regions: vec![[7; 2]],
+            moderated: true,
+            hide_threshold_lamports: 8008,
+            namespace: key(9),
+            bump: 10,
+        },
+    );
+}
+
+#[test]
+fn room_message_layout() {
+    check_snapshot(
+        "RoomMessage",
+        &RoomMessage {
+            room: key(1),
+            author: key(2),
+            room_creator: key(3),
+            index: 4004,
+            content: "content".to_string(),
+            timestamp: 5005,
+            expires_at: 6006,
+            imported: true,
+            status: RoomMessageStatus::Hidden,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn treasury_layout() {
+    check_snapshot("Treasury", &Treasury { bump: 1 });
+}
+
+#[test]
+fn content_blob_layout() {
+    check_snapshot(
+        "ContentBlob",
+        &ContentBlob {
+            payer: key(1),
+            content_hash: [2; 32],
+            content: "content".to_string(),
+            refcount: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn blob_message_layout() {
+    check_snapshot(
+        "BlobMessage",
+        &BlobMessage {
+            sender: key(1),
+            recipient: key(2),
+            nonce: 3003,
+            blob: key(4),
+            timestamp: 5005,
+            namespace: key(6),
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn message_dictionary_layout() {
+    check_snapshot(
+        "MessageDictionary",
+        &MessageDictionary {
+            entries: vec!["entries".to_string()],
+            bump: 1,
+        },
+    );
+}
+
+#[test]
+fn interned_message_layout() {
+    check_snapshot(
+        "InternedMessage",
+        &InternedMessage {
+            sender: key(1),
+            recipient: key(2),
+            nonce: 3003,
+            word_index: 4004,
+            timestamp: 5005,
+            namespace: key(6),
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn epoch_stats_layout() {
+    check_snapshot(
+        "EpochStats",
+        &EpochStats {
+            epoch: 1001,
+            messages: 2002,
+            active_senders: 3003,
+            tips_volume_lamports: 4004,
+            sender_bloom: [5; 256],
+            opened_at: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn user_stats_layout() {
+    check_snapshot(
+        "UserStats",
+        &UserStats {
+            user: key(1),
+            messages_sent: 2002,
+            activity_bitmap: [3; 46],
+            last_active_day: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn key_transparency_log_layout() {
+    check_snapshot(
+        "KeyTransparencyLog",
+        &KeyTransparencyLog {
+            leaf_count: 1001,
+            frontier: [[2; 32]; 20],
+            root: [3; 32],
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn conversation_settings_layout() {
+    check_snapshot(
+        "ConversationSettings",
+        &ConversationSettings {
+            participants: [key(1); 2],
+            confirmed: [true; 2],
+            verified: true,
+            key_versions: [2002; 2],
+            safety_number: [3; 32],
+            namespace: key(4),
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn disclosure_layout() {
+    check_snapshot(
+        "Disclosure",
+        &Disclosure {
+            message: key(1),
+            discloser: key(2),
+            decryption_key: vec![1, 2, 3],
+            disclosed_at: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn observer_grant_layout() {
+    check_snapshot(
+        "ObserverGrant",
+        &ObserverGrant {
+            conversation: key(1),
+            granter: key(2),
+            observer: key(3),
+            label: "label".to_string(),
+            granted_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn observer_key_layout() {
+    check_snapshot(
+        "ObserverKey",
+        &ObserverKey {
+            grant: key(1),
+            message: key(2),
+            wrapped_key: vec![1, 2, 3],
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn delivery_window_layout() {
+    check_snapshot(
+        "DeliveryWindow",
+        &DeliveryWindow {
+            owner: key(1),
+            start_minute: 2002,
+            end_minute: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn message_delivery_layout() {
+    check_snapshot(
+        "MessageDelivery",
+        &MessageDelivery {
+            message: key(1),
+            recipient: key(2),
+            state: DeliveryState::Delivered,
+            deliver_at: 3003,
+            delivered_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn priority_policy_layout() {
+    check_snapshot(
+        "PriorityPolicy",
+        &PriorityPolicy {
+            owner: key(1),
+            urgent_fee_lamports: 2002,
+            urgent_from_contacts: true,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn reminder_layout() {
+    check_snapshot(
+        "Reminder",
+        &Reminder {
+            owner: key(1),
+            message: key(2),
+            remind_at: 3003,
+            created_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn snooze_layout() {
+    check_snapshot(
+        "Snooze",
+        &Snooze {
+            owner: key(1),
+            peer: key(2),
+            until: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn message_translations_layout() {
+    check_snapshot(
+        "MessageTranslations",
+        &MessageTranslations {
+            message: key(1),
+            primary_language: "primary_language".to_string(),
+            segments: vec![ContentSegment {
+                language: "language".to_string(),
+                content: "content".to_string(),
+            }],
+            bump: 2,
+        },
+    );
+}
+
+#[test]
+fn message_media_layout() {
+    check_snapshot(
+        "MessageMedia",
+        &MessageMedia {
+            message: key(1),
+            attachments: vec![Attachment {
+                uri: "uri".to_string(),
+                mime_type: "mime_type".to_string(),
+                content_hash: [2; 32],
+                alt_text: "alt_text".to_string(),
+            }],
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn content_warning_layout() {
+    check_snapshot(
+        "ContentWarning",
+        &ContentWarning {
+            message: key(1),
+            category: ContentWarningCategory::Sensitive,
+            by_moderator: true,
+            payer: key(2),
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn content_filter_layout() {
+    check_snapshot(
+        "ContentFilter",
+        &ContentFilter {
+            owner: key(1),
+            actions: [WarningAction::Show; ContentWarningCategory::COUNT],
+            bump: 2,
+        },
+    );
+}
+
+#[test]
+fn room_report_layout() {
+    check_snapshot(
+        "RoomReport",
+        &RoomReport {
+            room: key(1),
+            message: key(2),
+            reporter: key(3),
+            threshold_lamports: 4004,
+            tally_lamports: 5005,
+            votes: 6006,
+            created_at: 7007,
+            bump: 8,
+        },
+    );
+}
+
+#[test]
+fn moderation_vote_layout() {
+    check_snapshot(
+        "ModerationVote",
+        &ModerationVote {
+            report: key(1),
+            voter: key(2),
+            stake_account: key(3),
+            stake_lamports: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn moderation_council_layout() {
+    check_snapshot(
+        "ModerationCouncil",
+        &ModerationCouncil {
+            members: vec![key(1)],
+            threshold: 2,
+            vote_mint: key(3),
+            seats: 4,
+            candidate_bond: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn appeal_layout() {
+    check_snapshot(
+        "Appeal",
+        &Appeal {
+            target: key(1),
+            appellant: key(2),
+            kind: AppealKind::ContentWarning,
+            status: AppealStatus::Reversed,
+            upholds: vec![key(3)],
+            reversals: vec![key(4)],
+            filed_at: 5005,
+            resolved_at: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn council_election_layout() {
+    check_snapshot(
+        "CouncilElection",
+        &CouncilElection {
+            epoch: 1001,
+            vote_mint: key(2),
+            candidate_bond: 3003,
+            candidates: vec![key(4)],
+            votes: vec![5005],
+            bonds: vec![6006],
+            finalized: true,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn council_candidacy_layout() {
+    check_snapshot(
+        "CouncilCandidacy",
+        &CouncilCandidacy {
+            election: key(1),
+            candidate: key(2),
+            bond: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn council_vote_layout() {
+    check_snapshot(
+        "CouncilVote",
+        &CouncilVote {
+            election: key(1),
+            voter: key(2),
+            candidate: key(3),
+            amount: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn treasury_proposal_layout() {
+    check_snapshot(
+        "TreasuryProposal",
+        &TreasuryProposal {
+            proposer: key(1),
+            proposal_id: 2002,
+            recipient: key(3),
+            amount_lamports: 4004,
+            memo: "memo".to_string(),
+            approvals: vec![key(5)],
+            status: SpendStatus::Cancelled,
+            created_at: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn fee_schedule_layout() {
+    check_snapshot(
+        "FeeSchedule",
+        &FeeSchedule {
+            send_fee_lamports: 1001,
+            tiers: vec![FeeTier {
+                min_messages_sent: 2002,
+                discount_bps: 3003,
+            }],
+            pass_discount_bps: 4004,
+            app_share_bps: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn pass_offer_layout() {
+    check_snapshot(
+        "PassOffer",
+        &PassOffer {
+            price_lamports: 1001,
+            duration_secs: 2002,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn protocol_pass_layout() {
+    check_snapshot(
+        "ProtocolPass",
+        &ProtocolPass {
+            mint: key(1),
+            buyer: key(2),
+            expires_at: 3003,
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn app_layout() {
+    check_snapshot(
+        "App",
+        &App {
+            authority: key(1),
+            name: "name".to_string(),
+            messages_sent: 2002,
+            fees_lamports: 3003,
+            revenue_owed_lamports: 4004,
+            revenue_paid_lamports: 5005,
+            registered_at: 6006,
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn feature_gate_layout() {
+    check_snapshot(
+        "FeatureGate",
+        &FeatureGate {
+            feature: BetaFeature::Games,
+            apps: vec![key(1)],
+            bump: 2,
+        },
+    );
+}
+
+#[test]
+fn schema_layout() {
+    check_snapshot(
+        "Schema",
+        &Schema {
+            authority: key(1),
+            name: "name".to_string(),
+            version: 2002,
+            format: SchemaFormat::Borsh,
+            layout_hash: [3; 32],
+            uri: "uri".to_string(),
+            created_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn structured_message_layout() {
+    check_snapshot(
+        "StructuredMessage",
+        &StructuredMessage {
+            sender: key(1),
+            recipient: key(2),
+            nonce: 3003,
+            schema: key(4),
+            payload: vec![1, 2, 3],
+            timestamp: 5005,
+            namespace: key(6),
+            bump: 7,
+        },
+    );
+}
+
+#[test]
+fn payment_note_layout() {
+    check_snapshot(
+        "PaymentNote",
+        &PaymentNote {
+            sender: key(1),
+            recipient: key(2),
+            nonce: 3003,
+            token_program: key(4),
+            mint: key(5),
+            source: key(6),
+            destination: key(7),
+            amount: 8008,
+            note: "note".to_string(),
+            timestamp: 9009,
+            namespace: key(10),
+            bump: 11,
+        },
+    );
+}
+
+#[test]
+fn profile_layout() {
+    check_snapshot(
+        "Profile",
+        &Profile {
+            owner: key(1),
+            preferred_tip_mint: key(2),
+            links: vec![ProfileLink {
+                label: "label".to_string(),
+                uri: "uri".to_string(),
+                verification: LinkVerification::WellKnownFile,
+                verified_at: 3003,
+            }],
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn round_up_layout() {
+    check_snapshot(
+        "RoundUp",
+        &RoundUp {
+            owner: key(1),
+            unit_lamports: 2002,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn tip_stream_layout() {
+    check_snapshot(
+        "TipStream",
+        &TipStream {
+            sender: key(1),
+            recipient: key(2),
+            stream_id: 3003,
+            total_lamports: 4004,
+            withdrawn_lamports: 5005,
+            duration_secs: 6006,
+            idle_timeout_secs: 7007,
+            accrued_secs: 8008,
+            resumed_at: 9009,
+            paused: true,
+            last_active_at: [10010; 2],
+            namespace: key(11),
+            bump: 12,
+        },
+    );
+}
+
+#[test]
+fn booking_message_layout() {
+    check_snapshot(
+        "BookingMessage",
+        &BookingMessage {
+            client: key(1),
+            consultant: key(2),
+            booking_id: 3003,
+            start_at: 4004,
+            duration_mins: 5005,
+            rate_lamports_per_hour: 6006,
+            escrow_lamports: 7007,
+            note: "note".to_string(),
+            status: BookingStatus::Disputed,
+            timestamp: 8008,
+            namespace: key(9),
+            bump: 10,
+        },
+    );
+}
+
+#[test]
+fn availability_layout() {
+    check_snapshot(
+        "Availability",
+        &Availability {
+            owner: key(1),
+            utc_offset_mins: 2002,
+            slots: [3; 42],
+            bump: 4,
+        },
+    );
+}
+
+#[test]
+fn namespace_layout() {
+    check_snapshot(
+        "Namespace",
+        &Namespace {
+            admin: key(1),
+            name: "name".to_string(),
+            branding: NamespaceBranding {
+                display_name: "display_name".to_string(),
+                logo_uri: "logo_uri".to_string(),
+                primary_color: [2; 3],
+                accent_color: [3; 3],
+            },
+            federation: FederationPolicy::AllowList,
+            peers: vec![key(4)],
+            created_at: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn namespace_member_layout() {
+    check_snapshot(
+        "NamespaceMember",
+        &NamespaceMember {
+            user: key(1),
+            namespace: key(2),
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn audit_log_layout() {
+    check_snapshot(
+        "AuditLog",
+        &AuditLog {
+            entries: vec![AuditEntry {
+                sequence: 1001,
+                actor: key(2),
+                action: AdminAction::CancelConfigChange,
+                target: key(3),
+                old_hash: [4; 32],
+                new_hash: [5; 32],
+                timestamp: 6006,
+            }],
+            next_sequence: 7007,
+            bump: 8,
+        },
+    );
+}
+
+#[test]
+fn pending_config_change_layout() {
+    check_snapshot(
+        "PendingConfigChange",
+        &PendingConfigChange {
+            change_id: 1001,
+            change: ConfigChange::FeeSchedule {
+                send_fee_lamports: 2002,
+                tiers: vec![FeeTier {
+                    min_messages_sent: 3003,
+                    discount_bps: 4004,
+                }],
+                pass_discount_bps: 5005,
+                app_share_bps: 6006,
+            },
+            queued_at: 7007,
+            eta: 8008,
+            bump: 9,
+        },
+    );
+}
+
+#[test]
+fn chaos_plan_layout() {
+    check_snapshot(
+        "ChaosPlan",
+        &ChaosPlan {
+            point: ChaosPoint::AfterMessageInit,
+            skip_hits: 1,
+            hits: 2,
+            bump: 3,
+        },
+    );
+}
+
+#[test]
+fn inbox_index_layout() {
+    check_snapshot(
+        "InboxIndex",
+        &InboxIndex {
+            owner: key(1),
+            private: true,
+            delegate: key(2),
+            entries: vec![IndexedMessage::Public(key(3))],
+            next_sequence: 4004,
+            namespace: key(5),
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn spam_filter_layout() {
+    check_snapshot(
+        "SpamFilter",
+        &SpamFilter {
+            current: [1; SpamFilter::BYTES],
+            previous: [2; SpamFilter::BYTES],
+            rotated_at: 3003,
+            window_secs: 4004,
+            deposit_lamports: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn spam_deposit_layout() {
+    check_snapshot(
+        "SpamDeposit",
+        &SpamDeposit {
+            message: key(1),
+            sender: key(2),
+            recipient: key(3),
+            amount_lamports: 4004,
+            held_until: 5005,
+            bump: 6,
+        },
+    );
+}
+
+#[test]
+fn spam_trap_layout() {
+    check_snapshot(
+        "SpamTrap",
+        &SpamTrap {
+            inbox: key(1),
+            messages_caught: 2002,
+            last_caught_at: 3003,
+            created_at: 4004,
+            bump: 5,
+        },
+    );
+}
+
+#[test]
+fn patron_config_layout() {
+    check_snapshot(
+        "PatronConfig",
+        &PatronConfig {
+            merkle_tree: key(1),
+            threshold_lamports: 2002,
+            uri_base: "uri_base".to_string(),
+            receipts_minted: 3003,
+            bump: 4,
+        },
+    );
+}
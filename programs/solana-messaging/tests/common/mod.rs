@@ -0,0 +1,222 @@
+//! Runs one instruction through the program's entrypoint on the host. Accounts are laid out in
+//! memory the way the runtime serializes them for a program, so closes and reallocs behave as
+//! they do on chain. Cross-program invocations are not executed; they only log.
+
+// Each test file uses its own subset of the helpers
+#![allow(dead_code)]
+
+use std::mem::size_of;
+use std::sync::Once;
+
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::entrypoint::{
+    deserialize, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar::instructions::{
+    construct_instructions_data, store_current_index, BorrowedAccountMeta, BorrowedInstruction,
+};
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program, sysvar};
+use anchor_lang::AccountSerialize;
+use solana_messaging::ErrorCode;
+
+/// Unix time the clock sysvar reports
+pub const NOW: i64 = 1_700_000_000;
+
+pub fn key(seed: u8) -> Pubkey {
+    Pubkey::new_from_array([seed; 32])
+}
+
+#[derive(Debug, Clone)]
+pub struct TestAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub executable: bool,
+}
+
+impl TestAccount {
+    /// A system account that signs the transaction.
+    pub fn wallet(key: Pubkey, lamports: u64) -> Self {
+        Self {
+            key,
+            owner: system_program::ID,
+            lamports,
+            data: Vec::new(),
+            is_signer: true,
+            is_writable: true,
+            executable: false,
+        }
+    }
+
+    /// A writable system account that doesn't sign, such as a payment destination.
+    pub fn destination(key: Pubkey) -> Self {
+        Self {
+            is_signer: false,
+            ..Self::wallet(key, 0)
+        }
+    }
+
+    /// A read-only account, such as a sysvar or an empty PDA.
+    pub fn readonly(key: Pubkey, owner: Pubkey) -> Self {
+        Self {
+            is_signer: false,
+            is_writable: false,
+            ..Self::wallet(key, 0)
+        }
+        .owned_by(owner)
+    }
+
+    /// A deployed program.
+    pub fn executable(key: Pubkey) -> Self {
+        Self {
+            executable: true,
+            ..Self::readonly(key, bpf_loader_upgradeable::ID)
+        }
+    }
+
+    fn owned_by(self, owner: Pubkey) -> Self {
+        Self { owner, ..self }
+    }
+
+    /// A writable program account holding `account`.
+    pub fn program<T: AccountSerialize>(key: Pubkey, account: &T) -> Self {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        Self {
+            lamports: 1_000_000,
+            data,
+            is_signer: false,
+            ..Self::wallet(key, 0)
+        }
+        .owned_by(solana_messaging::ID)
+    }
+
+    /// The instructions sysvar of a transaction made of `instructions`, executing `current`.
+    pub fn instructions(instructions: &[Instruction], current: u16) -> Self {
+        let borrowed: Vec<BorrowedInstruction> = instructions
+            .iter()
+            .map(|ix| BorrowedInstruction {
+                program_id: &ix.program_id,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &ix.data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        store_current_index(&mut data, current);
+        Self {
+            data,
+            ..Self::readonly(sysvar::instructions::ID, sysvar::ID)
+        }
+    }
+}
+
+/// Accounts for `metas` with their state from `known` and their flags from the metas. Keys not
+/// in `known` are taken to be programs, which covers left-out optional accounts: those pass the
+/// program's own ID.
+pub fn accounts_for(metas: &[AccountMeta], known: &[TestAccount]) -> Vec<TestAccount> {
+    metas
+        .iter()
+        .map(|meta| {
+            let state = known.iter().find(|account| account.key == meta.pubkey);
+            TestAccount {
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+                ..state
+                    .cloned()
+                    .unwrap_or_else(|| TestAccount::executable(meta.pubkey))
+            }
+        })
+        .collect()
+}
+
+/// The error `entry` returns for `code`.
+pub fn error(code: ErrorCode) -> ProgramError {
+    anchor_lang::error::Error::from(code).into()
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: 1,
+            epoch: 1,
+            unix_timestamp: NOW,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+}
+
+/// Runs the instruction with `data` over `accounts` and writes the resulting account state back.
+pub fn process(data: &[u8], accounts: &mut [TestAccount]) -> Result<(), ProgramError> {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(Stubs));
+    });
+
+    let mut input = serialize(data, accounts);
+    let (program_id, infos, data) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+    let result = solana_messaging::entry(program_id, &infos, data);
+
+    for account in accounts.iter_mut() {
+        let info = infos.iter().find(|info| *info.key == account.key).unwrap();
+        account.owner = *info.owner;
+        account.lamports = info.lamports();
+        account.data = info.data.borrow().to_vec();
+    }
+    result
+}
+
+/// The runtime's input layout for a program, in a buffer aligned for the u64 reads.
+fn serialize(data: &[u8], accounts: &[TestAccount]) -> Vec<u64> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+    for (index, account) in accounts.iter().enumerate() {
+        if let Some(first) = accounts[..index]
+            .iter()
+            .position(|other| other.key == account.key)
+        {
+            bytes.push(first as u8);
+            bytes.extend_from_slice(&[0; 7]);
+            continue;
+        }
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(account.is_signer as u8);
+        bytes.push(account.is_writable as u8);
+        bytes.push(account.executable as u8);
+        bytes.extend_from_slice(&[0; 4]); // original data length, filled in on deserialize
+        bytes.extend_from_slice(account.key.as_ref());
+        bytes.extend_from_slice(account.owner.as_ref());
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+        bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+    }
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(solana_messaging::ID.as_ref());
+
+    let mut input = vec![0u64; bytes.len().div_ceil(size_of::<u64>())];
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), input.as_mut_ptr() as *mut u8, bytes.len())
+    };
+    input
+}
@@ -0,0 +1,253 @@
+//! The screen paid instructions run over what precedes them in the transaction: setup that
+//! leaves the payer's funds alone passes, while anything that moves or hands them over fails.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::{system_instruction, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use common::{accounts_for, error, key, process, TestAccount};
+use solana_messaging::{
+    accounts, instruction, Config, ErrorCode, ReputationParams, COMPUTE_BUDGET_PROGRAM_ID,
+};
+
+const TIP: u64 = 1_000;
+
+fn sender() -> Pubkey {
+    key(1)
+}
+
+fn recipient() -> Pubkey {
+    key(2)
+}
+
+fn config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &solana_messaging::ID)
+}
+
+fn send_tip() -> Instruction {
+    Instruction {
+        program_id: solana_messaging::ID,
+        accounts: accounts::SendTip {
+            sender: sender(),
+            recipient_wallet: recipient(),
+            config: config_address().0,
+            round_up: None,
+            charity: None,
+            epoch_stats: None,
+            system_program: anchor_lang::system_program::ID,
+            instructions: sysvar::instructions::ID,
+            patron_config: None,
+            tip_message: None,
+            tree_authority: None,
+            merkle_tree: None,
+            log_wrapper: None,
+            compression_program: None,
+            bubblegum_program: None,
+        }
+        .to_account_metas(None),
+        data: instruction::SendTip {
+            recipient: recipient(),
+            amount_lamports: TIP,
+        }
+        .data(),
+    }
+}
+
+/// Runs `send_tip` as the last instruction of a transaction that starts with `preceding`.
+fn tip_after(preceding: Vec<Instruction>) -> Result<(), ProgramError> {
+    let (config, bump) = config_address();
+    let config = TestAccount::program(
+        config,
+        &Config {
+            admin: key(9),
+            max_poll_stake: 0,
+            reputation: ReputationParams {
+                answered_weight: 0,
+                resolved_weight: 0,
+                dispute_penalty: 0,
+                spam_penalty: 0,
+                vouch_weight: 0,
+                half_life_secs: 0,
+            },
+            attestor: Pubkey::default(),
+            crank_bounty_lamports: 0,
+            moderator: Pubkey::default(),
+            charity: Pubkey::default(),
+            timelock_secs: 0,
+            paused: false,
+            bump,
+        },
+    );
+
+    let tip = send_tip();
+    let current = preceding.len() as u16;
+    let transaction: Vec<Instruction> = preceding.into_iter().chain([tip.clone()]).collect();
+    let known = [
+        TestAccount::wallet(sender(), 1_000_000_000),
+        TestAccount::destination(recipient()),
+        config,
+        TestAccount::instructions(&transaction, current),
+    ];
+    process(&tip.data, &mut accounts_for(&tip.accounts, &known))
+}
+
+fn compute_unit_limit() -> Instruction {
+    let mut data = vec![2];
+    data.extend_from_slice(&200_000u32.to_le_bytes());
+    Instruction {
+        program_id: COMPUTE_BUDGET_PROGRAM_ID,
+        accounts: Vec::new(),
+        data,
+    }
+}
+
+/// Associated Token Account `Create`, paid for by the sender.
+fn create_associated_token_account(wallet: &Pubkey, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: anchor_spl::associated_token::ID,
+        accounts: vec![
+            AccountMeta::new(sender(), true),
+            AccountMeta::new(get_associated_token_address(wallet, mint), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ],
+        data: vec![0],
+    }
+}
+
+fn sender_token_account() -> Pubkey {
+    get_associated_token_address(&sender(), &key(5))
+}
+
+fn on_token_2022(mut ix: Instruction) -> Instruction {
+    ix.program_id = anchor_spl::token_2022::ID;
+    ix
+}
+
+#[test]
+fn a_tip_on_its_own_passes() {
+    assert_eq!(tip_after(Vec::new()), Ok(()));
+}
+
+#[test]
+fn setup_that_leaves_the_payers_funds_alone_passes() {
+    let wrapped_sol = get_associated_token_address(&sender(), &spl_token::native_mint::ID);
+    let preceding = vec![
+        compute_unit_limit(),
+        create_associated_token_account(&recipient(), &key(5)),
+        // Wrapping SOL for a swap moves it into the payer's own token account
+        system_instruction::transfer(&sender(), &wrapped_sol, TIP),
+        spl_token::instruction::sync_native(&spl_token::ID, &wrapped_sol).unwrap(),
+        // A transfer to the payer, authorized by someone else
+        spl_token::instruction::transfer(
+            &spl_token::ID,
+            &key(6),
+            &sender_token_account(),
+            &key(7),
+            &[],
+            TIP,
+        )
+        .unwrap(),
+        // An earlier tip or send in the same transaction
+        send_tip(),
+        Instruction {
+            program_id: key(8),
+            accounts: vec![AccountMeta::new(sender(), true)],
+            data: vec![1, 2, 3],
+        },
+    ];
+
+    assert_eq!(tip_after(preceding), Ok(()));
+}
+
+#[test]
+fn moving_the_payers_lamports_first_fails() {
+    let drains = [
+        system_instruction::transfer(&sender(), &key(6), TIP),
+        system_instruction::create_account(&sender(), &key(6), TIP, 0, &key(6)),
+        system_instruction::assign(&sender(), &key(6)),
+        system_instruction::transfer_with_seed(
+            &key(6),
+            &sender(),
+            "seed".to_string(),
+            &key(7),
+            &key(8),
+            TIP,
+        ),
+        system_instruction::withdraw_nonce_account(&key(6), &sender(), &key(7), TIP),
+        system_instruction::authorize_nonce_account(&key(6), &sender(), &key(7)),
+    ];
+
+    for drain in drains {
+        assert_eq!(
+            tip_after(vec![compute_unit_limit(), drain.clone()]),
+            Err(error(ErrorCode::UnexpectedPrecedingInstruction)),
+            "{drain:?}"
+        );
+    }
+}
+
+#[test]
+fn moving_or_handing_over_the_payers_tokens_first_fails() {
+    let source = sender_token_account();
+    let token = [
+        spl_token::instruction::transfer(&spl_token::ID, &source, &key(6), &sender(), &[], TIP)
+            .unwrap(),
+        spl_token::instruction::transfer_checked(
+            &spl_token::ID,
+            &source,
+            &key(5),
+            &key(6),
+            &sender(),
+            &[],
+            TIP,
+            0,
+        )
+        .unwrap(),
+        spl_token::instruction::approve(&spl_token::ID, &source, &key(6), &sender(), &[], TIP)
+            .unwrap(),
+        spl_token::instruction::set_authority(
+            &spl_token::ID,
+            &source,
+            Some(&key(6)),
+            AuthorityType::AccountOwner,
+            &sender(),
+            &[],
+        )
+        .unwrap(),
+        spl_token::instruction::burn(&spl_token::ID, &source, &key(5), &sender(), &[], TIP)
+            .unwrap(),
+        spl_token::instruction::close_account(&spl_token::ID, &source, &key(6), &sender(), &[])
+            .unwrap(),
+        // As one of the signers of a multisig owner
+        spl_token::instruction::transfer(
+            &spl_token::ID,
+            &source,
+            &key(6),
+            &key(7),
+            &[&sender()],
+            TIP,
+        )
+        .unwrap(),
+    ];
+    let drains = token
+        .iter()
+        .cloned()
+        .chain(token.iter().cloned().map(on_token_2022));
+
+    for drain in drains {
+        assert_eq!(
+            tip_after(vec![drain.clone()]),
+            Err(error(ErrorCode::UnexpectedPrecedingInstruction)),
+            "{drain:?}"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! `wipe_my_data` closes every account that belongs to the caller and refunds its rent, leaving
+//! only shared conversation settings, with the caller's side cleared.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use common::{accounts_for, error, key, process, TestAccount, NOW};
+use solana_messaging::{
+    accounts, instruction, Availability, ContentFilter, ContentWarningCategory,
+    ConversationSettings, DeliveryWindow, DmLink, EncryptionKey, ErrorCode, FederationPolicy,
+    GuardianSet, InboxGate, KeyTransparencyLog, MerchantProfile, Namespace, NamespaceBranding,
+    NamespaceMember, PriorityPolicy, Profile, RecoveryRequest, Reminder, RoundUp, Snooze,
+    UserStats, WarningAction,
+};
+
+const RENT: u64 = 1_000_000;
+
+fn owner() -> Pubkey {
+    key(1)
+}
+
+fn peer() -> Pubkey {
+    key(2)
+}
+
+fn admin() -> Pubkey {
+    key(3)
+}
+
+fn namespace() -> Pubkey {
+    key(4)
+}
+
+fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &solana_messaging::ID)
+}
+
+/// `account` at the PDA for `seeds`, with its bump filled in by `with_bump`.
+fn at_pda<T: AccountSerialize>(seeds: &[&[u8]], with_bump: impl FnOnce(u8) -> T) -> TestAccount {
+    let (address, bump) = pda(seeds);
+    TestAccount::program(address, &with_bump(bump))
+}
+
+/// Every kind of account `wipe_my_data` takes that belongs to the owner.
+fn owned_accounts() -> Vec<TestAccount> {
+    let owner = owner();
+    let seed = owner.as_ref();
+    vec![
+        at_pda(&[b"encryption_key", seed], |bump| EncryptionKey {
+            owner,
+            key: [7; 32],
+            version: 1,
+            updated_at: NOW,
+            bump,
+        }),
+        at_pda(&[b"guardians", seed], |bump| GuardianSet {
+            owner,
+            guardians: vec![key(10), key(11)],
+            threshold: 2,
+            timelock_secs: 0,
+            bump,
+        }),
+        at_pda(&[b"recovery", seed], |bump| RecoveryRequest {
+            owner,
+            proposer: key(10),
+            new_key: [8; 32],
+            approvals: Vec::new(),
+            proposed_at: NOW,
+            bump,
+        }),
+        at_pda(&[b"inbox_gate", seed], |bump| InboxGate {
+            owner,
+            min_stake_age_epochs: 0,
+            min_tx_count: 0,
+            min_account_age_secs: 0,
+            bump,
+        }),
+        at_pda(&[b"user_stats", seed], |bump| UserStats {
+            user: owner,
+            messages_sent: 3,
+            activity_bitmap: [0; 46],
+            last_active_day: 0,
+            bump,
+        }),
+        at_pda(&[b"merchant", seed], |bump| MerchantProfile {
+            owner,
+            name: "shop".to_string(),
+            settlement_account: owner,
+            receipt_count: 0,
+            bump,
+        }),
+        at_pda(&[b"profile", seed], |bump| Profile {
+            owner,
+            preferred_tip_mint: Pubkey::default(),
+            links: Vec::new(),
+            bump,
+        }),
+        at_pda(&[b"delivery_window", seed], |bump| DeliveryWindow {
+            owner,
+            start_minute: 0,
+            end_minute: 60,
+            bump,
+        }),
+        at_pda(&[b"priority_policy", seed], |bump| PriorityPolicy {
+            owner,
+            urgent_fee_lamports: 10,
+            urgent_from_contacts: false,
+            bump,
+        }),
+        at_pda(&[b"content_filter", seed], |bump| ContentFilter {
+            owner,
+            actions: [WarningAction::Hide; ContentWarningCategory::COUNT],
+            bump,
+        }),
+        at_pda(&[b"round_up", seed], |bump| RoundUp {
+            owner,
+            unit_lamports: 100,
+            bump,
+        }),
+        at_pda(&[b"availability", seed], |bump| Availability {
+            owner,
+            utc_offset_mins: 0,
+            slots: [0; 42],
+            bump,
+        }),
+        at_pda(&[b"namespace_member", seed], |bump| NamespaceMember {
+            user: owner,
+            namespace: namespace(),
+            bump,
+        }),
+    ]
+}
+
+/// Accounts passed as remaining accounts: one of each kind with a variable count.
+fn listed_accounts(account_owner: Pubkey) -> Vec<TestAccount> {
+    vec![
+        TestAccount::program(
+            key(20),
+            &DmLink {
+                owner: account_owner,
+                slug: "hello".to_string(),
+                max_uses: 0,
+                uses: 0,
+                expires_at: 0,
+                created_at: NOW,
+                namespace: Pubkey::default(),
+                bump: 255,
+            },
+        ),
+        TestAccount::program(
+            key(21),
+            &Reminder {
+                owner: account_owner,
+                message: key(30),
+                remind_at: NOW + 60,
+                created_at: NOW,
+                bump: 255,
+            },
+        ),
+        TestAccount::program(
+            key(22),
+            &Snooze {
+                owner: account_owner,
+                peer: peer(),
+                until: NOW + 60,
+                bump: 255,
+            },
+        ),
+    ]
+}
+
+fn settings() -> TestAccount {
+    TestAccount::program(
+        key(23),
+        &ConversationSettings {
+            participants: [owner(), peer()],
+            confirmed: [true, true],
+            verified: true,
+            key_versions: [1, 1],
+            safety_number: [9; 32],
+            namespace: Pubkey::default(),
+            bump: 255,
+        },
+    )
+}
+
+fn shared_accounts() -> Vec<TestAccount> {
+    let (key_log, bump) = pda(&[b"key_log"]);
+    vec![
+        TestAccount::program(
+            key_log,
+            &KeyTransparencyLog {
+                leaf_count: 0,
+                frontier: [[0; 32]; 20],
+                root: [0; 32],
+                bump,
+            },
+        ),
+        TestAccount::program(
+            namespace(),
+            &Namespace {
+                admin: admin(),
+                name: "acme".to_string(),
+                branding: NamespaceBranding::default(),
+                federation: FederationPolicy::Open,
+                peers: Vec::new(),
+                created_at: NOW,
+                bump: 255,
+            },
+        ),
+        TestAccount::destination(admin()),
+        TestAccount::wallet(owner(), 0),
+    ]
+}
+
+fn wipe(listed: &[TestAccount]) -> Instruction {
+    let owner = owner();
+    let address = |seed: &[u8]| Some(pda(&[seed, owner.as_ref()]).0);
+    let mut metas = accounts::WipeMyData {
+        encryption_key: address(b"encryption_key"),
+        key_log: Some(pda(&[b"key_log"]).0),
+        guardian_set: address(b"guardians"),
+        recovery_request: address(b"recovery"),
+        inbox_gate: address(b"inbox_gate"),
+        user_stats: address(b"user_stats"),
+        merchant_profile: address(b"merchant"),
+        profile: address(b"profile"),
+        delivery_window: address(b"delivery_window"),
+        priority_policy: address(b"priority_policy"),
+        content_filter: address(b"content_filter"),
+        round_up: address(b"round_up"),
+        availability: address(b"availability"),
+        namespace_member: address(b"namespace_member"),
+        member_namespace: Some(namespace()),
+        namespace_admin: Some(admin()),
+        owner,
+    }
+    .to_account_metas(None);
+    metas.extend(
+        listed
+            .iter()
+            .map(|account| AccountMeta::new(account.key, false)),
+    );
+    Instruction {
+        program_id: solana_messaging::ID,
+        accounts: metas,
+        data: instruction::WipeMyData {}.data(),
+    }
+}
+
+fn is_closed(account: &TestAccount) -> bool {
+    account.lamports == 0 && account.owner == system_program::ID && account.data.is_empty()
+}
+
+/// Whether `account` is a program account whose owner or user field is `wallet`. Every
+/// per-user account type starts with that key right after the discriminator.
+fn belongs_to(account: &TestAccount, wallet: &Pubkey) -> bool {
+    account.owner == solana_messaging::ID && account.data.get(8..40) == Some(wallet.as_ref())
+}
+
+#[test]
+fn a_full_wipe_leaves_no_account_belonging_to_the_caller() {
+    let mut listed = listed_accounts(owner());
+    listed.push(settings());
+    let wipe = wipe(&listed);
+    let known: Vec<TestAccount> = owned_accounts()
+        .into_iter()
+        .chain(listed.iter().cloned())
+        .chain(shared_accounts())
+        .collect();
+    let mut accounts = accounts_for(&wipe.accounts, &known);
+
+    assert_eq!(process(&wipe.data, &mut accounts), Ok(()));
+
+    let after = |address: Pubkey| {
+        accounts
+            .iter()
+            .find(|account| account.key == address)
+            .unwrap()
+    };
+    for account in owned_accounts().iter().chain(&listed_accounts(owner())) {
+        assert!(is_closed(after(account.key)), "{:?}", account.key);
+    }
+    // The conversation settings are shared with the peer and stay
+    assert!(!accounts
+        .iter()
+        .filter(|account| account.key != key(23))
+        .any(|account| belongs_to(account, &owner())));
+
+    // Membership rent goes back to the admin who paid it, everything else to the owner
+    assert_eq!(after(admin()).lamports, RENT);
+    assert_eq!(after(owner()).lamports, RENT * 15);
+
+    let settings = after(key(23));
+    let settings = ConversationSettings::try_deserialize(&mut &settings.data[..]).unwrap();
+    assert_eq!(settings.participants, [owner(), peer()]);
+    assert_eq!(settings.confirmed, [false, true]);
+    assert_eq!(settings.key_versions, [0, 1]);
+    assert!(!settings.verified);
+    assert_eq!(settings.safety_number, [0; 32]);
+
+    let (key_log, _) = pda(&[b"key_log"]);
+    let key_log = KeyTransparencyLog::try_deserialize(&mut &after(key_log).data[..]).unwrap();
+    assert_eq!(key_log.leaf_count, 1);
+}
+
+#[test]
+fn someone_elses_listed_account_is_not_wipeable() {
+    for account in listed_accounts(peer()) {
+        let listed = [account];
+        let wipe = wipe(&listed);
+        let known: Vec<TestAccount> = owned_accounts()
+            .into_iter()
+            .chain(listed.iter().cloned())
+            .chain(shared_accounts())
+            .collect();
+        let mut accounts = accounts_for(&wipe.accounts, &known);
+
+        assert_eq!(
+            process(&wipe.data, &mut accounts),
+            Err(error(ErrorCode::NotWipeable)),
+            "{:?}",
+            listed[0].key
+        );
+    }
+}
+
+#[test]
+fn membership_rent_only_goes_to_the_namespace_admin() {
+    let wipe = wipe(&[]);
+    let known: Vec<TestAccount> = owned_accounts()
+        .into_iter()
+        .chain(shared_accounts())
+        .chain([TestAccount::destination(key(5))])
+        .collect();
+    let mut accounts = accounts_for(&wipe.accounts, &known);
+    // Swap the admin for another wallet
+    let admin = accounts
+        .iter_mut()
+        .find(|account| account.key == admin())
+        .unwrap();
+    admin.key = key(5);
+
+    assert_eq!(
+        process(&wipe.data, &mut accounts),
+        Err(error(ErrorCode::NotWipeable))
+    );
+}
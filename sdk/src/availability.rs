@@ -0,0 +1,45 @@
+//! Turning a published availability bitmap into bookable time ranges for a scheduling picker.
+
+use anchor_lang::prelude::Pubkey;
+use solana_messaging::Availability;
+
+use crate::PROGRAM_ID;
+
+pub fn availability_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"availability", owner.as_ref()], &PROGRAM_ID).0
+}
+
+/// Open ranges between `from` and `until` as `(start, end)` Unix timestamps, with adjacent
+/// slots merged. Ranges are clipped to slot boundaries, so each one can be booked as is.
+pub fn open_ranges(availability: &Availability, from: i64, until: i64) -> Vec<(i64, i64)> {
+    let first = from.div_euclid(Availability::SLOT_SECS) + i64::from(from.rem_euclid(Availability::SLOT_SECS) != 0);
+    let last = until.div_euclid(Availability::SLOT_SECS);
+
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for slot in first..last {
+        let start = slot * Availability::SLOT_SECS;
+        if !availability.is_slot_open(availability.slot_index(start)) {
+            continue;
+        }
+        let end = start + Availability::SLOT_SECS;
+        match ranges.last_mut() {
+            Some(range) if range.1 == start => range.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// Builds the bitmap for `set_availability` from `(day, start_minute, end_minute)` entries in
+/// local time, day 0 being Monday. Minutes are rounded outwards to whole slots.
+pub fn weekly_slots(hours: &[(u8, u16, u16)]) -> [u8; 42] {
+    let mut slots = [0u8; 42];
+    for &(day, start_minute, end_minute) in hours {
+        let base = day as usize % 7 * 48;
+        for slot in start_minute as usize / 30..(end_minute as usize).div_ceil(30).min(48) {
+            let index = base + slot;
+            slots[index / 8] |= 1 << (index % 8);
+        }
+    }
+    slots
+}
@@ -0,0 +1,40 @@
+//! Per-namespace branding, so one client can show each white-label deployment's messages
+//! under its own name, logo and colors.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use solana_messaging::{Message, Namespace, NamespaceBranding};
+
+use crate::PROGRAM_ID;
+
+pub fn namespace_address(name: &str) -> Pubkey {
+    Pubkey::find_program_address(&[b"namespace", name.as_bytes()], &PROGRAM_ID).0
+}
+
+/// `#rrggbb` for a branding color.
+pub fn css_color(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Branding of the namespaces a client has fetched, by namespace address.
+#[derive(Debug, Default)]
+pub struct BrandingCache {
+    namespaces: HashMap<Pubkey, NamespaceBranding>,
+}
+
+impl BrandingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, address: Pubkey, namespace: &Namespace) {
+        self.namespaces.insert(address, namespace.branding.clone());
+    }
+
+    /// Branding to show on `message`; `None` for the public network or a namespace that
+    /// hasn't been fetched, which clients render with their own theme.
+    pub fn for_message(&self, message: &Message) -> Option<&NamespaceBranding> {
+        self.namespaces.get(&message.namespace)
+    }
+}
@@ -0,0 +1,133 @@
+//! Compute budget autotuning: simulate first, then request just enough compute units and a
+//! priority fee drawn from recent fees, instead of guessing both.
+//!
+//! Build the simulation copy of the transaction with `ComputeBudget::simulation()` so the
+//! measurement isn't capped by a low limit, then sign the real transaction with the
+//! instructions of the tuned budget.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+
+use crate::{Result, SdkError};
+
+/// `ComputeBudget111111111111111111111111111111`
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231,
+    188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+]);
+/// Most compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// `ComputeBudgetInstruction::SetComputeUnitLimit` discriminant
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+/// `ComputeBudgetInstruction::SetComputeUnitPrice` discriminant
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Result of `simulateTransaction`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    pub units_consumed: u64,
+    pub error: Option<String>,
+}
+
+/// The RPC calls autotuning needs.
+pub trait Simulate {
+    fn simulate(&mut self, wire_transaction: &[u8]) -> std::result::Result<Simulation, String>;
+    /// Recent prioritization fees, in micro-lamports per compute unit, for the written accounts
+    fn recent_priority_fees(&mut self, writable_accounts: &[Pubkey]) -> std::result::Result<Vec<u64>, String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetPolicy {
+    /// Headroom added on top of simulated units, in percent
+    pub margin_percent: u32,
+    pub min_units: u32,
+    /// Percentile of recent fees to pay, 0-100
+    pub fee_percentile: u8,
+    pub max_micro_lamports: u64,
+}
+
+impl Default for BudgetPolicy {
+    fn default() -> Self {
+        Self { margin_percent: 10, min_units: 5_000, fee_percentile: 75, max_micro_lamports: 1_000_000 }
+    }
+}
+
+/// Caller-pinned values that take precedence over tuning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BudgetOverride {
+    pub compute_unit_limit: Option<u32>,
+    pub micro_lamports_per_unit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub compute_unit_limit: u32,
+    pub micro_lamports_per_unit: u64,
+}
+
+impl ComputeBudget {
+    /// Budget for the simulation copy of a transaction.
+    pub fn simulation() -> Self {
+        Self { compute_unit_limit: MAX_COMPUTE_UNIT_LIMIT, micro_lamports_per_unit: 0 }
+    }
+
+    /// Instructions to prepend to the transaction.
+    pub fn instructions(&self) -> [Instruction; 2] {
+        let mut limit = vec![SET_COMPUTE_UNIT_LIMIT];
+        limit.extend_from_slice(&self.compute_unit_limit.to_le_bytes());
+        let mut price = vec![SET_COMPUTE_UNIT_PRICE];
+        price.extend_from_slice(&self.micro_lamports_per_unit.to_le_bytes());
+
+        [
+            Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: vec![], data: limit },
+            Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID, accounts: vec![], data: price },
+        ]
+    }
+
+    /// Priority fee paid on top of the base signature fee, in lamports.
+    pub fn priority_fee_lamports(&self) -> u64 {
+        (self.compute_unit_limit as u64 * self.micro_lamports_per_unit).div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+    }
+}
+
+/// Simulates `simulation_transaction` and derives the budget for the real send.
+pub fn tune_budget<S: Simulate>(
+    rpc: &mut S,
+    simulation_transaction: &[u8],
+    writable_accounts: &[Pubkey],
+    policy: &BudgetPolicy,
+    overrides: BudgetOverride,
+) -> Result<ComputeBudget> {
+    let compute_unit_limit = match overrides.compute_unit_limit {
+        Some(limit) => limit,
+        None => {
+            let simulation = rpc.simulate(simulation_transaction).map_err(SdkError::Transport)?;
+            if let Some(error) = simulation.error {
+                return Err(SdkError::SimulationFailed(error));
+            }
+            let with_margin = simulation.units_consumed * (100 + policy.margin_percent as u64) / 100;
+            with_margin.clamp(policy.min_units as u64, MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+        }
+    };
+
+    let micro_lamports_per_unit = match overrides.micro_lamports_per_unit {
+        Some(price) => price,
+        None => {
+            let mut fees = rpc.recent_priority_fees(writable_accounts).map_err(SdkError::Transport)?;
+            percentile(&mut fees, policy.fee_percentile).min(policy.max_micro_lamports)
+        }
+    };
+
+    Ok(ComputeBudget { compute_unit_limit, micro_lamports_per_unit })
+}
+
+fn percentile(values: &mut [u64], percentile: u8) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (values.len() - 1) * percentile.min(100) as usize / 100;
+    values[rank]
+}
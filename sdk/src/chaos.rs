@@ -0,0 +1,108 @@
+//! Fault injection for integration tests, so a suite can assert that no failure leaves message
+//! state half-initialized.
+//!
+//! On chain, `arm_fail_point` makes an instruction fail partway through a handler; send the
+//! instructions under test through `with_fail_point` so the handler can see the armed plan.
+//! Off chain, `FaultyTransport` cuts the connection after a number of sends to abort a batch
+//! such as an `OfflineQueue::drain` in the middle.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_messaging::{accounts, instruction, ChaosPoint};
+
+use crate::send::{SignatureStatus, Transport};
+use crate::PROGRAM_ID;
+
+pub fn chaos_plan_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"chaos_plan"], &PROGRAM_ID).0
+}
+
+fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &PROGRAM_ID).0
+}
+
+/// Makes the `skip_hits + 1`th instruction reaching `point` fail.
+pub fn arm_fail_point(admin: &Pubkey, point: ChaosPoint, skip_hits: u8) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ArmFailPoint {
+            chaos_plan: chaos_plan_address(),
+            config: config_address(),
+            admin: *admin,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ArmFailPoint { point, skip_hits }.data(),
+    }
+}
+
+pub fn disarm_fail_point(admin: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::DisarmFailPoint { chaos_plan: chaos_plan_address(), config: config_address(), admin: *admin }
+            .to_account_metas(None),
+        data: instruction::DisarmFailPoint {}.data(),
+    }
+}
+
+/// Passes the chaos plan to `instruction` as a trailing writable account.
+pub fn with_fail_point(mut instruction: Instruction) -> Instruction {
+    instruction.accounts.push(AccountMeta::new(chaos_plan_address(), false));
+    instruction
+}
+
+/// A transport that stops accepting transactions after `sends_before_abort` of them.
+pub struct FaultyTransport<T> {
+    inner: T,
+    sends_before_abort: Option<u32>,
+    sends: u32,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, sends_before_abort: None, sends: 0 }
+    }
+
+    /// Fails every send after the next `sends` succeed.
+    pub fn abort_after(&mut self, sends: u32) {
+        self.sends_before_abort = Some(self.sends + sends);
+    }
+
+    /// Lets sends through again.
+    pub fn heal(&mut self) {
+        self.sends_before_abort = None;
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn latest_blockhash(&mut self) -> std::result::Result<(Hash, u64), String> {
+        self.inner.latest_blockhash()
+    }
+
+    fn block_height(&mut self) -> std::result::Result<u64, String> {
+        self.inner.block_height()
+    }
+
+    fn send_transaction(&mut self, wire_transaction: &[u8]) -> std::result::Result<(), String> {
+        if self.sends_before_abort.is_some_and(|limit| self.sends >= limit) {
+            return Err("injected abort".to_string());
+        }
+        self.sends += 1;
+        self.inner.send_transaction(wire_transaction)
+    }
+
+    fn signature_status(&mut self, signature: &[u8; 64]) -> std::result::Result<Option<SignatureStatus>, String> {
+        self.inner.signature_status(signature)
+    }
+
+    fn account_exists(&mut self, address: &Pubkey) -> std::result::Result<bool, String> {
+        self.inner.account_exists(address)
+    }
+}
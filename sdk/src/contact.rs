@@ -0,0 +1,164 @@
+//! Contact cards for in-person exchange over QR codes.
+//!
+//! A card carries the owner's wallet, optional handle, registered encryption key and an
+//! optional one-time DM link slug, signed by the wallet. Scanning a valid card lets the client
+//! pin the encryption key as trusted without going through the on-chain registry first.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+use solana_messaging::{DmLink, EncryptionKey};
+
+use crate::{Result, SdkError};
+
+/// Prefix of the text encoded in the QR code
+pub const QR_PREFIX: &str = "gmchat:contact:";
+
+const CARD_VERSION: u8 = 1;
+const SIGNING_DOMAIN: &[u8] = b"gmchat-contact-card-v1";
+const FLAG_HANDLE: u8 = 1 << 0;
+const FLAG_DM_LINK: u8 = 1 << 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactCard {
+    pub wallet: Pubkey,
+    pub handle: Option<String>,
+    pub encryption_key: [u8; 32],
+    /// `EncryptionKey::version` the card was issued for
+    pub key_version: u32,
+    /// Slug of a `DmLink` owned by `wallet`
+    pub dm_link: Option<String>,
+    pub issued_at: i64,
+}
+
+impl ContactCard {
+    /// Signs the card with the wallet (e.g. a wallet adapter's `signMessage`) and returns the
+    /// QR payload text.
+    pub fn to_qr_payload<S>(&self, sign: S) -> Result<String>
+    where
+        S: FnOnce(&[u8]) -> [u8; 64],
+    {
+        let body = self.encode_body()?;
+        let signature = sign(&signing_message(&body));
+
+        let mut payload = body;
+        payload.extend_from_slice(&signature);
+        Ok(format!("{QR_PREFIX}{}", bs58::encode(payload).into_string()))
+    }
+
+    /// Parses a scanned payload and checks the wallet's signature and the card's age.
+    pub fn from_qr_payload(text: &str, now: i64, max_age_secs: i64) -> Result<Self> {
+        let encoded = text.strip_prefix(QR_PREFIX).ok_or(SdkError::InvalidContactCard("not a gmchat contact"))?;
+        let payload = bs58::decode(encoded)
+            .into_vec()
+            .map_err(|_| SdkError::InvalidContactCard("payload is not base58"))?;
+        if payload.len() < 64 {
+            return Err(SdkError::InvalidContactCard("payload too short"));
+        }
+
+        let (body, signature) = payload.split_at(payload.len() - 64);
+        let card = Self::decode_body(body)?;
+
+        let wallet = PublicKey::from_bytes(card.wallet.as_ref())
+            .map_err(|_| SdkError::InvalidContactCard("wallet is not an ed25519 key"))?;
+        let signature =
+            Signature::from_bytes(signature).map_err(|_| SdkError::InvalidContactCard("malformed signature"))?;
+        wallet
+            .verify(&signing_message(body), &signature)
+            .map_err(|_| SdkError::InvalidContactCard("signature does not match wallet"))?;
+
+        if card.issued_at > now || now - card.issued_at > max_age_secs {
+            return Err(SdkError::InvalidContactCard("card has expired"));
+        }
+
+        Ok(card)
+    }
+
+    /// Cross-checks the card against the wallet's registry entry, and its DM link if it names
+    /// one, using raw account data fetched by the caller.
+    pub fn check_on_chain(&self, encryption_key_data: &[u8], dm_link_data: Option<&[u8]>) -> Result<()> {
+        let entry = EncryptionKey::try_deserialize(&mut &encryption_key_data[..])
+            .map_err(|_| SdkError::InvalidAccountData("encryption key entry"))?;
+        if entry.owner != self.wallet {
+            return Err(SdkError::AccountMismatch("encryption key entry belongs to another wallet"));
+        }
+        if entry.key != self.encryption_key || entry.version != self.key_version {
+            return Err(SdkError::AccountMismatch("encryption key was rotated since the card was issued"));
+        }
+
+        if let (Some(slug), Some(data)) = (&self.dm_link, dm_link_data) {
+            let link = DmLink::try_deserialize(&mut &data[..]).map_err(|_| SdkError::InvalidAccountData("dm link"))?;
+            if link.owner != self.wallet || link.slug != *slug {
+                return Err(SdkError::AccountMismatch("dm link does not belong to the card"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn encode_body(&self) -> Result<Vec<u8>> {
+        let mut flags = 0;
+        if self.handle.is_some() {
+            flags |= FLAG_HANDLE;
+        }
+        if self.dm_link.is_some() {
+            flags |= FLAG_DM_LINK;
+        }
+
+        let mut body = vec![CARD_VERSION, flags];
+        body.extend_from_slice(self.wallet.as_ref());
+        body.extend_from_slice(&self.encryption_key);
+        body.extend_from_slice(&self.key_version.to_le_bytes());
+        body.extend_from_slice(&self.issued_at.to_le_bytes());
+        for field in [&self.handle, &self.dm_link].into_iter().flatten() {
+            let len = u8::try_from(field.len()).map_err(|_| SdkError::InvalidContactCard("field too long"))?;
+            body.push(len);
+            body.extend_from_slice(field.as_bytes());
+        }
+        Ok(body)
+    }
+
+    fn decode_body(body: &[u8]) -> Result<Self> {
+        let mut reader = body;
+        let [version, flags] = take::<2>(&mut reader)?;
+        if version != CARD_VERSION {
+            return Err(SdkError::InvalidContactCard("unsupported card version"));
+        }
+
+        let wallet = Pubkey::new_from_array(take::<32>(&mut reader)?);
+        let encryption_key = take::<32>(&mut reader)?;
+        let key_version = u32::from_le_bytes(take::<4>(&mut reader)?);
+        let issued_at = i64::from_le_bytes(take::<8>(&mut reader)?);
+        let handle = if flags & FLAG_HANDLE != 0 { Some(take_string(&mut reader)?) } else { None };
+        let dm_link = if flags & FLAG_DM_LINK != 0 { Some(take_string(&mut reader)?) } else { None };
+        if !reader.is_empty() {
+            return Err(SdkError::InvalidContactCard("trailing bytes"));
+        }
+
+        Ok(Self { wallet, handle, encryption_key, key_version, dm_link, issued_at })
+    }
+}
+
+fn signing_message(body: &[u8]) -> Vec<u8> {
+    [SIGNING_DOMAIN, body].concat()
+}
+
+fn take<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N]> {
+    if reader.len() < N {
+        return Err(SdkError::InvalidContactCard("payload truncated"));
+    }
+    let (head, rest) = reader.split_at(N);
+    *reader = rest;
+    Ok(head.try_into().unwrap())
+}
+
+fn take_string(reader: &mut &[u8]) -> Result<String> {
+    let [len] = take::<1>(reader)?;
+    let len = len as usize;
+    if reader.len() < len {
+        return Err(SdkError::InvalidContactCard("payload truncated"));
+    }
+    let (head, rest) = reader.split_at(len);
+    *reader = rest;
+    String::from_utf8(head.to_vec()).map_err(|_| SdkError::InvalidContactCard("field is not utf-8"))
+}
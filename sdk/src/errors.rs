@@ -0,0 +1,308 @@
+//! What clients should do about each program error.
+//!
+//! Every `ErrorCode` variant is listed exactly once in the table below, together with a
+//! category and a message that can be shown to the user as-is. The table is matched
+//! exhaustively, so adding an error to the program without classifying it here fails to build.
+
+use solana_messaging::ErrorCode;
+
+use crate::SdkError;
+
+/// How a client should react to a failed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Transient or time-dependent: the same transaction can succeed later
+    Retryable,
+    /// The user can change their input or make a choice that lets it succeed
+    UserFixable,
+    /// Will keep failing for this signer and these accounts
+    Permanent,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorInfo {
+    pub error: ErrorCode,
+    /// Numeric code as reported by the runtime (`6000 +` the variant's position)
+    pub code: u32,
+    /// Variant name, stable across releases, for logs and analytics
+    pub name: &'static str,
+    pub category: ErrorCategory,
+    pub user_message: &'static str,
+}
+
+impl ErrorInfo {
+    pub fn is_retryable(&self) -> bool {
+        self.category == ErrorCategory::Retryable
+    }
+}
+
+/// Looks up a numeric program error code.
+pub fn lookup(code: u32) -> Option<ErrorInfo> {
+    ALL_ERRORS.iter().map(|&error| describe(error)).find(|info| info.code == code)
+}
+
+/// Finds the program error in a transaction or simulation error string, as formatted by the
+/// RPC (`custom program error: 0x1770`) or by Anchor in the logs (`Error Number: 6000.`).
+pub fn from_error_message(message: &str) -> Option<ErrorInfo> {
+    let code = if let Some(index) = message.find("custom program error: 0x") {
+        let hex = &message[index + "custom program error: 0x".len()..];
+        let end = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+        u32::from_str_radix(&hex[..end], 16).ok()?
+    } else {
+        let index = message.find("Error Number: ")?;
+        let digits = &message[index + "Error Number: ".len()..];
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        digits[..end].parse().ok()?
+    };
+    lookup(code)
+}
+
+impl SdkError {
+    /// The program error behind a failed transaction or simulation, if it was one of ours.
+    pub fn program_error(&self) -> Option<ErrorInfo> {
+        match self {
+            SdkError::TransactionFailed(err) | SdkError::SimulationFailed(err) => from_error_message(err),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! error_table {
+    ($($variant:ident => $category:ident, $message:literal;)*) => {
+        /// Every program error, in code order.
+        pub const ALL_ERRORS: &[ErrorCode] = &[$(ErrorCode::$variant),*];
+
+        pub fn describe(error: ErrorCode) -> ErrorInfo {
+            let (name, category, user_message) = match error {
+                $(ErrorCode::$variant => (stringify!($variant), ErrorCategory::$category, $message),)*
+            };
+            ErrorInfo { error, code: error.into(), name, category, user_message }
+        }
+    };
+}
+
+error_table! {
+    MessageTooLong => UserFixable, "Your message is too long. Shorten it and try again.";
+    EmptyMessage => UserFixable, "Your message is empty.";
+    UnauthorizedRecipient => Permanent, "This message was sent to someone else.";
+    EmptyEventTitle => UserFixable, "Give the event a title.";
+    EventTitleTooLong => UserFixable, "The event title is too long.";
+    LocationUriTooLong => UserFixable, "The event location link is too long.";
+    InvalidEventTimes => UserFixable, "The event must start in the future and end after it starts.";
+    InvalidEventCapacity => UserFixable, "The event needs room for at least one guest.";
+    EventEnded => Permanent, "This event has already ended.";
+    EventFull => Permanent, "This event is full.";
+    EmptyTaskDescription => UserFixable, "Describe the task.";
+    TaskDescriptionTooLong => UserFixable, "The task description is too long.";
+    InvalidDueDate => UserFixable, "Pick a due date in the future.";
+    UnauthorizedAssignee => Permanent, "Only the person this task is assigned to can complete it.";
+    TaskNotOpen => Permanent, "This task is no longer open.";
+    InvalidTradeAmount => UserFixable, "Enter an amount greater than zero.";
+    InvalidTradeParty => Permanent, "You are not part of this trade.";
+    TradeNotPending => Permanent, "This trade has already been settled or cancelled.";
+    InvalidInvoiceAmount => UserFixable, "Enter an invoice amount greater than zero.";
+    InvoiceMemoTooLong => UserFixable, "The invoice memo is too long.";
+    InvalidInvoicePayer => UserFixable, "Choose someone else to bill.";
+    InvoiceAlreadyPaid => Permanent, "This invoice has already been paid.";
+    InvalidInvoicePeriod => UserFixable, "Choose a valid billing period.";
+    RecurringInvoice => Permanent, "This invoice is collected automatically on its schedule.";
+    NotRecurringInvoice => Permanent, "This invoice is not on a schedule.";
+    InvoiceNotActive => Permanent, "This invoice is no longer active.";
+    InvoicePeriodNotDue => Retryable, "The next payment is not due yet.";
+    UnauthorizedInvoiceParty => Permanent, "You are not part of this invoice.";
+    InvalidUnlockCondition => UserFixable, "Check the unlock condition and try again.";
+    UnlockConditionNotMet => Retryable, "This message can't be unlocked yet.";
+    InvalidContentKey => Permanent, "The key doesn't unlock this message.";
+    MessageAlreadyUnlocked => Permanent, "This message is already unlocked.";
+    MissingConditionAccount => Permanent, "Something went wrong building the unlock. Update the app and try again.";
+    InvalidOracleAccount => Permanent, "The price feed for this condition is invalid.";
+    OraclePriceUnavailable => Retryable, "The price feed is unavailable right now. Try again shortly.";
+    HeartbeatExpired => Permanent, "The check-in window has passed.";
+    CommitmentMismatch => Permanent, "The revealed content doesn't match what was committed.";
+    AlreadyRevealed => Permanent, "This has already been revealed.";
+    InvalidOpponent => UserFixable, "Choose someone else to play against.";
+    GameNotActive => Permanent, "This game is over.";
+    NotYourTurn => Retryable, "Wait for your opponent to move.";
+    InvalidMove => UserFixable, "That move isn't allowed.";
+    EmptyPollQuestion => UserFixable, "Enter a poll question.";
+    PollQuestionTooLong => UserFixable, "The poll question is too long.";
+    InvalidPollOptions => UserFixable, "Check the poll options and try again.";
+    InvalidPollClose => UserFixable, "The poll must close in the future.";
+    PollStakeTooHigh => UserFixable, "The stake per vote is above the allowed limit.";
+    PollClosed => Permanent, "This poll is closed.";
+    PollStillOpen => Retryable, "This poll hasn't closed yet.";
+    UnauthorizedResolver => Permanent, "Only the poll's resolver can resolve it.";
+    UnauthorizedNotifier => Permanent, "You can't post notifications for this DAO.";
+    InvalidProposalAccount => Permanent, "That governance proposal couldn't be found.";
+    PaymentNotFound => UserFixable, "No payment to the merchant was found. Include the payment and try again.";
+    UnauthorizedTeamMember => Permanent, "You are not a member of this team inbox.";
+    AlreadyTeamMember => Permanent, "Already a member of this team inbox.";
+    TeamInboxFull => Permanent, "This team inbox is full.";
+    InvalidTicketTransition => Permanent, "The ticket can't move to that status.";
+    TicketNotResolved => Retryable, "This ticket hasn't been resolved yet.";
+    TicketAlreadyRated => Permanent, "You already rated this ticket.";
+    InvalidRating => UserFixable, "Pick a rating from 1 to 5.";
+    InvalidArbiter => UserFixable, "Choose a different arbiter.";
+    InvalidDealState => Permanent, "This deal can't do that right now.";
+    InvalidRuling => UserFixable, "The ruling share can't exceed 100%.";
+    InvalidReplyTarget => UserFixable, "You can only reply to messages from this person.";
+    InvalidReputationAccount => Permanent, "Something went wrong with reputation. Update the app and try again.";
+    MissingConfig => Permanent, "Something went wrong with reputation. Update the app and try again.";
+    InvalidReputationParams => UserFixable, "Check the reputation parameters and try again.";
+    InvalidVouch => UserFixable, "You can't vouch for this account.";
+    VouchUnderReview => Permanent, "This person was flagged after your vouch, so it can't be withdrawn now.";
+    VouchNotSlashable => Permanent, "This vouch can't be slashed.";
+    UnauthorizedAttestor => Permanent, "Only the attestor can do this.";
+    InboxGateNotMet => UserFixable, "You don't meet this person's inbox requirements.";
+    InvalidGuardianSet => UserFixable, "Check your guardians and the number of approvals required.";
+    NotGuardian => Permanent, "You are not a guardian of this account.";
+    AlreadyApproved => Permanent, "You already approved this recovery.";
+    RecoveryThresholdNotMet => Retryable, "More guardians need to approve this recovery.";
+    RecoveryTimelockActive => Retryable, "Recovery is waiting out its safety delay.";
+    InvalidMigration => UserFixable, "Choose a different wallet to migrate to.";
+    OutstandingVouches => UserFixable, "Withdraw your vouches before migrating.";
+    InvalidDmLink => UserFixable, "This DM link is invalid.";
+    DmLinkExpired => Permanent, "This DM link has expired.";
+    DmLinkExhausted => Permanent, "This DM link has been used up.";
+    AmaInboxClosed => Permanent, "This AMA isn't taking questions.";
+    AlreadyAnswered => Permanent, "This question has already been answered.";
+    InvalidRoomTtl => UserFixable, "Rooms can last up to 30 days.";
+    RoomExpired => Permanent, "This room has expired.";
+    RoomNotExpired => Retryable, "This room hasn't expired yet.";
+    BlobRefcountOverflow => Permanent, "This attachment can't be shared any further.";
+    BlobStillReferenced => Retryable, "This attachment is still used by other messages.";
+    ContentHashMismatch => Permanent, "The content was corrupted. Upload it again.";
+    UnauthorizedBlobAccess => Permanent, "You can't use this attachment.";
+    DictionaryFull => Permanent, "The message dictionary is full.";
+    DuplicateDictionaryEntry => UserFixable, "That word is already in the dictionary.";
+    UnknownDictionaryEntry => Permanent, "This message uses an unknown dictionary word. Update the app and try again.";
+    InvalidEpoch => Retryable, "The epoch just changed. Try again.";
+    KeyLogFull => Permanent, "Your key history is full.";
+    ParticipantsNotOrdered => Permanent, "Something went wrong with this conversation. Update the app and try again.";
+    NotConversationParticipant => Permanent, "You are not part of this conversation.";
+    SafetyNumberMismatch => UserFixable, "Safety numbers changed. Verify them with your contact again.";
+    KeyLogRequired => Permanent, "Your key history is needed to revoke this key. Update the app and try again.";
+    NotWipeable => Permanent, "This can't be deleted.";
+    DecryptionKeyTooLong => UserFixable, "The decryption key must be 1-64 bytes.";
+    ObserverIsParticipant => UserFixable, "Conversation members can't be added as observers.";
+    ObserverLabelTooLong => UserFixable, "The observer label is too long.";
+    MessageBeforeObserverGrant => Permanent, "This observer can't see messages sent before they were added.";
+    WrappedKeyTooLong => UserFixable, "The wrapped key must be 1-128 bytes.";
+    InvalidDeliveryWindow => UserFixable, "Choose different start and end times within a day.";
+    DeliveryRecordRequired => Permanent, "This person's quiet hours are on. Update the app to queue the message.";
+    MessageNotQueued => Permanent, "This message isn't waiting for delivery.";
+    DeliveryWindowClosed => Retryable, "This person's quiet hours haven't ended yet.";
+    UrgentNotAllowed => UserFixable, "This person doesn't accept urgent messages from you. Send it normally.";
+    ReminderInPast => UserFixable, "Pick a reminder time in the future.";
+    ReminderNotDue => Retryable, "This reminder isn't due yet.";
+    SnoozeInPast => UserFixable, "Pick a snooze time in the future.";
+    SnoozeNotExpired => Retryable, "This snooze hasn't ended yet.";
+    InvalidClientMessageId => Permanent, "Something went wrong sending this message. Update the app and try again.";
+    ImportNotBackdated => UserFixable, "Imported messages must be dated in the past.";
+    InvalidLanguageTag => UserFixable, "Check the language codes and try again.";
+    TooManySegments => UserFixable, "A message can have 1 to 4 translations.";
+    DuplicateLanguage => UserFixable, "Each language can only be used once.";
+    TranslationsTooLong => UserFixable, "The translations are too long.";
+    TooManyAttachments => UserFixable, "A message can have 1 to 4 attachments.";
+    InvalidAttachment => UserFixable, "Check the attachment and try again.";
+    AltTextRequired => UserFixable, "Add a description to every attachment.";
+    AltTextTooLong => UserFixable, "An attachment description is too long.";
+    NotSenderOrModerator => Permanent, "Only the sender or a moderator can do this.";
+    WarningSetByModerator => Permanent, "A moderator added this warning, so you can't remove it.";
+    InvalidRegions => UserFixable, "Check the region list and try again.";
+    RoomMessageNotPending => Permanent, "This post isn't waiting for approval.";
+    CommunityModerationDisabled => Permanent, "Community moderation is off in this room.";
+    RoomMessageNotPublished => Permanent, "This post isn't published.";
+    NotRoomMember => Permanent, "Only room members can vote.";
+    NoActiveStake => UserFixable, "You need active stake to vote.";
+    InvalidCouncil => UserFixable, "Councils need 1-7 different members and a reachable threshold.";
+    NothingToAppeal => Permanent, "There is nothing to appeal.";
+    NotAppellant => Permanent, "Only the author can appeal this decision.";
+    NotCouncilMember => Permanent, "You are not on the moderation council.";
+    AppealResolved => Permanent, "This appeal has already been decided.";
+    AlreadyRuled => Permanent, "You already ruled on this appeal.";
+    WarningPayerRequired => Permanent, "Something went wrong lifting this warning. Update the app and try again.";
+    ElectionsDisabled => Permanent, "Council elections are turned off.";
+    ElectionClosed => Retryable, "There is no election open right now.";
+    ElectionOpen => Retryable, "The election is still running.";
+    ElectionFinalized => Permanent, "This election has already been finalized.";
+    AlreadyCandidate => Permanent, "You are already standing in this election.";
+    TooManyCandidates => UserFixable, "The ballot is full. Lock more than the lowest candidate's bond to take their place.";
+    NotCandidate => UserFixable, "That person isn't standing in this election.";
+    InvalidVoteAmount => UserFixable, "Vote with an amount greater than zero.";
+    NotTreasurySigner => Permanent, "Only the admin or a council member can do this.";
+    InvalidSpendAmount => UserFixable, "Enter a spend amount greater than zero.";
+    SpendNotOpen => Permanent, "This spend proposal is closed.";
+    SpendNotApproved => Retryable, "This spend hasn't been approved yet.";
+    SpendAlreadyApproved => Permanent, "You already approved this spend.";
+    TreasuryInsufficientFunds => Retryable, "The treasury doesn't have enough funds yet.";
+    SpendMemoTooLong => UserFixable, "The spend memo is too long.";
+    InvalidFeeSchedule => UserFixable, "Check the fee tiers and try again.";
+    TreasuryRequired => Permanent, "Something went wrong paying the protocol fee. Update the app and try again.";
+    InvalidPassOffer => UserFixable, "The pass must last longer than zero.";
+    InvalidAppName => UserFixable, "App names must be 1-32 bytes.";
+    NoRevenueToClaim => Retryable, "There is no revenue to claim yet.";
+    TooManyGatedApps => UserFixable, "A feature can be limited to at most 16 apps.";
+    FeatureNotEnabledForApp => Permanent, "This feature isn't available in this app yet.";
+    InvalidSchema => UserFixable, "Check the schema name, version and link.";
+    NotSchemaAuthority => Permanent, "Only the schema's owner can publish new versions.";
+    PaymentTransferNotFound => Permanent, "Something went wrong attaching this payment note. Update the app and try again.";
+    InvalidSwapLimits => UserFixable, "Set a minimum amount to receive.";
+    SlippageExceeded => Retryable, "The price moved too much. Try again or allow more slippage.";
+    InvalidTipDestination => Permanent, "This tip can't be sent to that account.";
+    NotPreferredTipMint => UserFixable, "This person prefers tips in a different token.";
+    InvalidTipAmount => UserFixable, "Enter a tip amount greater than zero.";
+    CharityRequired => Permanent, "Something went wrong with the round-up. Update the app and try again.";
+    InvalidTipStream => UserFixable, "Check the tip stream's recipient, amount and duration.";
+    TipStreamPaused => Permanent, "This tip stream is paused.";
+    TipStreamNotPaused => Permanent, "This tip stream isn't paused.";
+    NothingVested => Retryable, "Nothing to withdraw yet.";
+    InvalidBooking => UserFixable, "Bookings need a future start time and a length.";
+    BookingNotProposed => Permanent, "This booking has already been answered.";
+    BookingNotAccepted => Permanent, "This booking hasn't been accepted.";
+    BookingSlotPassed => Permanent, "This booking slot has already started.";
+    BookingNotOver => Retryable, "This booking can't be settled yet.";
+    NotBookingParty => Permanent, "You are not part of this booking.";
+    InvalidAvailability => UserFixable, "Choose a time zone between UTC-12 and UTC+14.";
+    SlotUnavailable => UserFixable, "That time isn't available. Pick another slot.";
+    InvalidProfileLink => UserFixable, "Profile links need a short label and an https link.";
+    TooManyProfileLinks => UserFixable, "Remove a profile link before adding another.";
+    ProfileLinkChanged => Retryable, "This profile link changed. Refresh and try again.";
+    InvalidNamespace => UserFixable, "Namespace names must be 1-32 bytes.";
+    InvalidBranding => UserFixable, "Check the display name and logo link.";
+    TooManyFederationPeers => UserFixable, "Remove a federation peer before adding another.";
+    WrongNamespace => Permanent, "You can only send from your own community.";
+    NotFederated => Permanent, "Your community can't message this person's community.";
+    InvalidTimelock => UserFixable, "The delay must be between zero and 30 days.";
+    TimelockNotElapsed => Retryable, "This change is still in its waiting period.";
+    ConfigChangeAccountMissing => Permanent, "Something went wrong applying this change. Update the app and try again.";
+    ProtocolPaused => Retryable, "Messaging is paused for maintenance. Try again later.";
+    ChaosDisabled => Permanent, "Fault injection isn't available in this build.";
+    ChaosFaultInjected => Retryable, "A test fault was injected. Try again.";
+    UnexpectedPrecedingInstruction => Permanent, "This transaction moves or hands over your funds in an earlier step before the payment, so it was blocked. Don't sign it again.";
+    EncryptionKeyRequired => UserFixable, "Set up an encryption key before making your inbox private.";
+    InvalidIndexEntry => Permanent, "Something went wrong adding this message to the inbox. Update the app and try again.";
+    AlreadyIndexed => Permanent, "This message is already in the inbox.";
+    InvalidSpamFilter => UserFixable, "The spam filter window must be longer than zero.";
+    SpamDepositRequired => UserFixable, "This message matches recent spam, so sending it needs a refundable deposit.";
+    SpamDepositHeld => Retryable, "Your deposit is still on hold.";
+    NotDepositParty => Permanent, "You are not part of this deposit.";
+    NotModerator => Permanent, "Only the moderator can do this.";
+    InvalidPatronConfig => UserFixable, "Patron receipts need an https link of at most 64 bytes.";
+    PatronThresholdNotMet => UserFixable, "Tip more than the patron threshold to get a receipt, or tip without one.";
+    PatronAccountsMissing => Permanent, "Something went wrong minting your patron receipt. Update the app and try again.";
+    MessagePredatesTrap => Permanent, "This message arrived before the address became a spam trap.";
+    InsufficientCandidateBond => UserFixable, "Lock at least the candidate bond to stand.";
+    CrankBountyTooHigh => Permanent, "The crank bounty can't be set that high.";
+    NotIndexAuthority => Permanent, "You can't add messages to this inbox.";
+    DeadManMessageClaimed => Permanent, "This message was already claimed.";
+    InvalidCollectionMetadata => Permanent, "Couldn't read this collection's metadata.";
+    NotCollectionAuthority => Permanent, "Only the collection's update authority can open its feed.";
+    BookingDisputed => Permanent, "The client disputed this booking, so it's settled by the moderator.";
+    BookingNotDisputed => Permanent, "This booking isn't disputed.";
+    DisputeWindowClosed => Permanent, "It's too late to dispute this booking.";
+    DealTimeoutNotReached => Retryable, "You can take the escrow back once the deal times out.";
+    MoveTimeoutNotReached => Retryable, "Your opponent still has time to move.";
+    PollResolutionExpired => Permanent, "It's too late to resolve this poll; voters get their stakes back.";
+    Overflow => Permanent, "A total got too large to record, so this was blocked.";
+}
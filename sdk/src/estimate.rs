@@ -0,0 +1,164 @@
+//! Cost of a send before the user signs it: rent for every account the send creates, the
+//! signature fee, the priority fee and any lamports transferred along the way.
+//!
+//! Rent and signature fees are exact, since account sizes are fixed by each account's `LEN`
+//! rather than by the content. Compute units default to a limit that covers the instruction;
+//! pass a `ComputeBudget` from `budget::tune_budget` to price the simulated figure instead.
+
+use anchor_lang::solana_program::rent::Rent;
+use serde::Serialize;
+use solana_messaging::{BlobMessage, ContentBlob, InternedMessage, Message, MessageDelivery};
+
+use crate::budget::ComputeBudget;
+use crate::{Result, SdkError};
+
+/// Lamports per signature at the base fee rate
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// `spl_token::state::Mint::LEN`
+const MINT_LEN: usize = 82;
+/// `spl_token::state::Account::LEN`
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Which send instruction the estimate is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendKind {
+    /// `send_message`: a `Message` plus its NFT mint and both token accounts. `queued` when the
+    /// recipient's delivery window is closed, which adds a `MessageDelivery` record.
+    Nft { queued: bool },
+    /// `send_blob_message`, plus `create_content_blob` when no blob holds the content yet
+    Blob { new_blob: bool },
+    /// `send_interned_message`: a one-word message from the dictionary
+    Interned,
+}
+
+impl SendKind {
+    /// Compute unit limit that covers the send, used when no tuned budget is given.
+    pub fn default_compute_units(&self) -> u32 {
+        match self {
+            SendKind::Nft { .. } => 200_000,
+            SendKind::Blob { new_blob: true } => 60_000,
+            SendKind::Blob { new_blob: false } => 30_000,
+            SendKind::Interned => 25_000,
+        }
+    }
+
+    /// Transaction signers: the sender, plus the fresh mint keypair for NFT sends.
+    pub fn signatures(&self) -> u64 {
+        match self {
+            SendKind::Nft { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendConfig {
+    pub kind: SendKind,
+    /// Content length in bytes; ignored for interned sends
+    pub content_len: usize,
+    /// Tip sent to the recipient in the same transaction
+    pub tip_lamports: u64,
+    /// Urgent fee the recipient's priority policy charges, if sending urgent
+    pub urgent_fee_lamports: u64,
+    /// Protocol fee after discounts (`FeeSchedule::fee_for`); only NFT sends pay it
+    pub protocol_fee_lamports: u64,
+    pub budget: Option<ComputeBudget>,
+}
+
+/// One account the send creates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountRent {
+    pub account: &'static str,
+    pub space: usize,
+    pub lamports: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CostEstimate {
+    pub accounts: Vec<AccountRent>,
+    /// Rent deposited; recoverable when the accounts are closed
+    pub rent_lamports: u64,
+    pub signature_fee_lamports: u64,
+    pub priority_fee_lamports: u64,
+    pub compute_units: u32,
+    /// Tips and fees paid to the recipient
+    pub transfer_lamports: u64,
+    pub protocol_fee_lamports: u64,
+    /// Everything leaving the sender's wallet
+    pub total_lamports: u64,
+}
+
+/// Estimates the cost of a send under the default rent parameters.
+pub fn estimate(config: &SendConfig) -> Result<CostEstimate> {
+    estimate_with_rent(config, &Rent::default())
+}
+
+/// Estimates the cost of a send under the cluster's `Rent` sysvar.
+pub fn estimate_with_rent(config: &SendConfig, rent: &Rent) -> Result<CostEstimate> {
+    // Validate the content fits the instruction
+    let max_content_len = match config.kind {
+        // Past `Message::MAX_CONTENT_LEN` the sender needs a protocol pass
+        SendKind::Nft { .. } => Message::MAX_PASS_CONTENT_LEN,
+        SendKind::Blob { .. } => ContentBlob::MAX_CONTENT_LEN,
+        SendKind::Interned => usize::MAX,
+    };
+    if config.kind != SendKind::Interned && config.content_len == 0 {
+        return Err(SdkError::InvalidSendConfig("content is empty"));
+    }
+    if config.content_len > max_content_len {
+        return Err(SdkError::InvalidSendConfig("content is too long"));
+    }
+
+    let mut created = Vec::new();
+    match config.kind {
+        SendKind::Nft { queued } => {
+            created.push(("message", Message::space_for(config.content_len)));
+            created.push(("nft_mint", MINT_LEN));
+            created.push(("recipient_token_account", TOKEN_ACCOUNT_LEN));
+            created.push(("sender_token_account", TOKEN_ACCOUNT_LEN));
+            if queued {
+                created.push(("delivery", MessageDelivery::LEN));
+            }
+        }
+        SendKind::Blob { new_blob } => {
+            if new_blob {
+                created.push(("content_blob", ContentBlob::LEN));
+            }
+            created.push(("blob_message", BlobMessage::LEN));
+        }
+        SendKind::Interned => created.push(("interned_message", InternedMessage::LEN)),
+    }
+
+    let accounts: Vec<AccountRent> = created
+        .into_iter()
+        .map(|(account, space)| AccountRent { account, space, lamports: rent.minimum_balance(space) })
+        .collect();
+    let rent_lamports = accounts.iter().map(|account| account.lamports).sum();
+
+    let budget = config.budget.unwrap_or(ComputeBudget {
+        compute_unit_limit: config.kind.default_compute_units(),
+        micro_lamports_per_unit: 0,
+    });
+    let signature_fee_lamports = config.kind.signatures() * LAMPORTS_PER_SIGNATURE;
+    let priority_fee_lamports = budget.priority_fee_lamports();
+    let transfer_lamports = config.tip_lamports + config.urgent_fee_lamports;
+    let protocol_fee_lamports = match config.kind {
+        SendKind::Nft { .. } => config.protocol_fee_lamports,
+        _ => 0,
+    };
+
+    Ok(CostEstimate {
+        accounts,
+        rent_lamports,
+        signature_fee_lamports,
+        priority_fee_lamports,
+        compute_units: budget.compute_unit_limit,
+        transfer_lamports,
+        protocol_fee_lamports,
+        total_lamports: rent_lamports
+            + signature_fee_lamports
+            + priority_fee_lamports
+            + transfer_lamports
+            + protocol_fee_lamports,
+    })
+}
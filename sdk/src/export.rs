@@ -0,0 +1,434 @@
+//! Conversation export: packages fetched message accounts, emitted events and the signed
+//! transactions that created them, with their logs, into a bundle that can be checked offline by
+//! `gmchat-verify`.
+//!
+//! Every account snapshot, event and transaction becomes a leaf of a SHA-256 Merkle tree. The
+//! exporter signs the root together with the export time and the slot hashes captured at export,
+//! so a third party can tell the bundle was not altered after it was produced.
+
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Result, SdkError};
+
+/// Bundle format version written by this SDK; 2 added transaction logs
+pub const BUNDLE_VERSION: u8 = 2;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const SIGNING_DOMAIN: &[u8] = b"gmchat-conversation-export-v1";
+
+/// Raw state of a program account as fetched from RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    /// Base58 account address
+    pub address: String,
+    /// Base58 owning program
+    pub owner: String,
+    /// Slot the snapshot was read at
+    pub slot: u64,
+    /// Base64 account data
+    pub data: String,
+}
+
+/// An Anchor event taken from a transaction's `Program data:` log line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Base58 signature of the transaction that emitted the event
+    pub transaction: String,
+    pub slot: u64,
+    /// Base64 event payload, discriminator included
+    pub data: String,
+}
+
+/// A transaction as signed by its fee payer and other signers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub slot: u64,
+    /// Base64 serialized legacy transaction message
+    pub message: String,
+    /// Base58 signatures, in the order of the message's signer keys
+    pub signatures: Vec<String>,
+    /// Log messages from the transaction's metadata, where its events were emitted
+    pub logs: Vec<String>,
+}
+
+/// One entry of the `SlotHashes` sysvar captured at export time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotHashEntry {
+    pub slot: u64,
+    /// Base58 bank hash
+    pub hash: String,
+}
+
+/// Sibling path from a leaf to the Merkle root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub leaf_index: u32,
+    /// Base58 sibling hashes, leaf level first
+    pub siblings: Vec<String>,
+}
+
+/// A signed, self-contained export of one conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationBundle {
+    pub version: u8,
+    /// Base58 messaging program id the accounts belong to
+    pub program_id: String,
+    /// Unix timestamp of the export
+    pub exported_at: i64,
+    pub accounts: Vec<AccountSnapshot>,
+    pub events: Vec<EventRecord>,
+    pub transactions: Vec<TransactionRecord>,
+    pub slot_hashes: Vec<SlotHashEntry>,
+    /// Base58 Merkle root over accounts, then events, then transactions
+    pub merkle_root: String,
+    /// One proof per leaf, in leaf order
+    pub proofs: Vec<MerkleProof>,
+    /// Base58 ed25519 public key of the exporter
+    pub exporter: String,
+    /// Base58 exporter signature over `signing_digest`
+    pub signature: String,
+}
+
+/// Collects conversation data before it is sealed into a `ConversationBundle`.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationExport {
+    accounts: Vec<AccountSnapshot>,
+    events: Vec<EventRecord>,
+    transactions: Vec<TransactionRecord>,
+    slot_hashes: Vec<SlotHashEntry>,
+}
+
+impl ConversationExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_account(&mut self, address: &[u8; 32], owner: &[u8; 32], slot: u64, data: &[u8]) -> &mut Self {
+        self.accounts.push(AccountSnapshot {
+            address: bs58::encode(address).into_string(),
+            owner: bs58::encode(owner).into_string(),
+            slot,
+            data: base64::encode(data),
+        });
+        self
+    }
+
+    pub fn add_event(&mut self, transaction: &[u8; 64], slot: u64, data: &[u8]) -> &mut Self {
+        self.events.push(EventRecord {
+            transaction: bs58::encode(transaction).into_string(),
+            slot,
+            data: base64::encode(data),
+        });
+        self
+    }
+
+    pub fn add_transaction(
+        &mut self,
+        slot: u64,
+        message: &[u8],
+        signatures: &[[u8; 64]],
+        logs: &[String],
+    ) -> &mut Self {
+        self.transactions.push(TransactionRecord {
+            slot,
+            message: base64::encode(message),
+            signatures: signatures.iter().map(|s| bs58::encode(s).into_string()).collect(),
+            logs: logs.to_vec(),
+        });
+        self
+    }
+
+    pub fn add_slot_hash(&mut self, slot: u64, hash: &[u8; 32]) -> &mut Self {
+        self.slot_hashes.push(SlotHashEntry { slot, hash: bs58::encode(hash).into_string() });
+        self
+    }
+
+    /// Builds the Merkle tree and signs the bundle with the exporter's key.
+    pub fn finish(self, exporter: &Keypair, exported_at: i64) -> Result<ConversationBundle> {
+        let mut bundle = ConversationBundle {
+            version: BUNDLE_VERSION,
+            program_id: crate::PROGRAM_ID.to_string(),
+            exported_at,
+            accounts: self.accounts,
+            events: self.events,
+            transactions: self.transactions,
+            slot_hashes: self.slot_hashes,
+            merkle_root: String::new(),
+            proofs: Vec::new(),
+            exporter: bs58::encode(exporter.public.as_bytes()).into_string(),
+            signature: String::new(),
+        };
+
+        let leaves = bundle.leaves()?;
+        let root = merkle_root(&leaves);
+        bundle.proofs = (0..leaves.len())
+            .map(|index| MerkleProof {
+                leaf_index: index as u32,
+                siblings: merkle_proof(&leaves, index).iter().map(|h| bs58::encode(h).into_string()).collect(),
+            })
+            .collect();
+        bundle.merkle_root = bs58::encode(root).into_string();
+
+        let digest = bundle.signing_digest(&root, leaves.len());
+        bundle.signature = bs58::encode(exporter.sign(&digest).to_bytes()).into_string();
+
+        Ok(bundle)
+    }
+}
+
+impl ConversationBundle {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("bundle serializes")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|_| SdkError::InvalidAccountData("malformed bundle json"))
+    }
+
+    /// Leaf hashes in tree order: accounts, then events, then transactions.
+    pub fn leaves(&self) -> Result<Vec<[u8; 32]>> {
+        let mut leaves = Vec::with_capacity(self.accounts.len() + self.events.len() + self.transactions.len());
+        for account in &self.accounts {
+            leaves.push(hash_leaf(&[
+                b"account",
+                &decode_base58::<32>(&account.address, "account address")?,
+                &decode_base58::<32>(&account.owner, "account owner")?,
+                &account.slot.to_le_bytes(),
+                &decode_base64(&account.data, "account data")?,
+            ]));
+        }
+        for event in &self.events {
+            leaves.push(hash_leaf(&[
+                b"event",
+                &decode_base58::<64>(&event.transaction, "event transaction")?,
+                &event.slot.to_le_bytes(),
+                &decode_base64(&event.data, "event data")?,
+            ]));
+        }
+        for transaction in &self.transactions {
+            // The signature count separates the signatures from the log lines that follow them
+            let mut parts: Vec<Vec<u8>> = vec![
+                b"transaction".to_vec(),
+                transaction.slot.to_le_bytes().to_vec(),
+                decode_base64(&transaction.message, "transaction message")?,
+                (transaction.signatures.len() as u32).to_le_bytes().to_vec(),
+            ];
+            for signature in &transaction.signatures {
+                parts.push(decode_base58::<64>(signature, "transaction signature")?.to_vec());
+            }
+            parts.extend(transaction.logs.iter().map(|line| line.as_bytes().to_vec()));
+            leaves.push(hash_leaf(&parts.iter().map(Vec::as_slice).collect::<Vec<_>>()));
+        }
+        Ok(leaves)
+    }
+
+    /// Message the exporter signs: the root bound to the export time, leaf count and slot hashes.
+    pub fn signing_digest(&self, root: &[u8; 32], leaf_count: usize) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SIGNING_DOMAIN);
+        hasher.update([self.version]);
+        hasher.update(self.program_id.as_bytes());
+        hasher.update(self.exported_at.to_le_bytes());
+        hasher.update((leaf_count as u64).to_le_bytes());
+        hasher.update(root);
+        for entry in &self.slot_hashes {
+            hasher.update(entry.slot.to_le_bytes());
+            hasher.update(entry.hash.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// Checks the exporter signature against the recomputed root.
+    pub fn verify_signature(&self) -> Result<()> {
+        let leaves = self.leaves()?;
+        let root = merkle_root(&leaves);
+        if bs58::encode(root).into_string() != self.merkle_root {
+            return Err(SdkError::AccountMismatch("merkle root does not match bundle contents"));
+        }
+
+        let exporter = PublicKey::from_bytes(&decode_base58::<32>(&self.exporter, "exporter key")?)
+            .map_err(|_| SdkError::InvalidAccountData("exporter key"))?;
+        let signature = Signature::from_bytes(&decode_base58::<64>(&self.signature, "exporter signature")?)
+            .map_err(|_| SdkError::InvalidAccountData("exporter signature"))?;
+
+        exporter
+            .verify(&self.signing_digest(&root, leaves.len()), &signature)
+            .map_err(|_| SdkError::AccountMismatch("exporter signature does not verify"))
+    }
+}
+
+/// Hashes the concatenated parts of one leaf under the leaf domain prefix.
+pub fn hash_leaf(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    for part in parts {
+        hasher.update((part.len() as u32).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Root of the tree over `leaves`; an odd node at any level is paired with itself.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return Sha256::digest([NODE_PREFIX]).into();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| hash_node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+    }
+    level[0]
+}
+
+/// Sibling hashes from `leaves[index]` up to the root.
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        proof.push(*level.get(sibling).unwrap_or(&level[index]));
+        level = level
+            .chunks(2)
+            .map(|pair| hash_node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+        index /= 2;
+    }
+    proof
+}
+
+/// Recomputes the root from a leaf and its sibling path.
+pub fn verify_merkle_proof(leaf: &[u8; 32], mut index: usize, siblings: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    let mut node = *leaf;
+    for sibling in siblings {
+        node = if index % 2 == 0 { hash_node(&node, sibling) } else { hash_node(sibling, &node) };
+        index /= 2;
+    }
+    node == *root
+}
+
+pub(crate) fn decode_base58<const N: usize>(value: &str, what: &'static str) -> Result<[u8; N]> {
+    let bytes = bs58::decode(value).into_vec().map_err(|_| SdkError::InvalidAccountData(what))?;
+    bytes.try_into().map_err(|_| SdkError::InvalidAccountData(what))
+}
+
+pub(crate) fn decode_base64(value: &str, what: &'static str) -> Result<Vec<u8>> {
+    base64::decode(value).map_err(|_| SdkError::InvalidAccountData(what))
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SecretKey;
+
+    use super::*;
+
+    fn keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        Keypair { public: PublicKey::from(&secret), secret }
+    }
+
+    fn bundle() -> ConversationBundle {
+        let mut export = ConversationExport::new();
+        export
+            .add_account(&[1; 32], &[2; 32], 10, b"message account")
+            .add_account(&[3; 32], &[2; 32], 11, b"delivery account")
+            .add_event(&[4; 64], 10, b"event")
+            .add_transaction(10, b"transaction message", &[[5; 64]], &["Program data: ZXZlbnQ=".to_string()])
+            .add_slot_hash(12, &[6; 32]);
+        export.finish(&keypair(7), 1_700_000_000).unwrap()
+    }
+
+    #[test]
+    fn every_leaf_proves_against_the_root() {
+        let bundle = bundle();
+        let leaves = bundle.leaves().unwrap();
+        let root = decode_base58::<32>(&bundle.merkle_root, "root").unwrap();
+
+        assert_eq!(leaves.len(), 4);
+        assert_eq!(bundle.proofs.len(), 4);
+        for (index, proof) in bundle.proofs.iter().enumerate() {
+            let siblings: Vec<[u8; 32]> =
+                proof.siblings.iter().map(|sibling| decode_base58::<32>(sibling, "sibling").unwrap()).collect();
+            assert_eq!(proof.leaf_index as usize, index);
+            assert!(verify_merkle_proof(&leaves[index], index, &siblings, &root));
+            assert!(!verify_merkle_proof(&leaves[index], index ^ 1, &siblings, &root));
+        }
+    }
+
+    #[test]
+    fn an_odd_leaf_is_paired_with_itself() {
+        let leaves = [[1; 32], [2; 32], [3; 32]];
+        let root = merkle_root(&leaves);
+
+        assert_eq!(root, hash_node(&hash_node(&leaves[0], &leaves[1]), &hash_node(&leaves[2], &leaves[2])));
+        assert!(verify_merkle_proof(&leaves[2], 2, &merkle_proof(&leaves, 2), &root));
+    }
+
+    #[test]
+    fn a_fresh_bundle_verifies_after_a_json_round_trip() {
+        let bundle = bundle();
+        let decoded = ConversationBundle::from_json(&bundle.to_json()).unwrap();
+
+        assert_eq!(decoded, bundle);
+        assert!(decoded.verify_signature().is_ok());
+    }
+
+    #[test]
+    fn changed_contents_no_longer_match_the_root() {
+        let mut bundle = bundle();
+        bundle.accounts[0].data = base64::encode(b"edited account");
+
+        assert!(matches!(bundle.verify_signature(), Err(SdkError::AccountMismatch(_))));
+    }
+
+    #[test]
+    fn a_changed_export_time_breaks_the_signature() {
+        let mut bundle = bundle();
+        bundle.exported_at += 1;
+
+        assert!(matches!(bundle.verify_signature(), Err(SdkError::AccountMismatch(_))));
+    }
+
+    #[test]
+    fn a_resealed_bundle_needs_the_exporters_key() {
+        let mut bundle = bundle();
+        bundle.events.clear();
+        let leaves = bundle.leaves().unwrap();
+        let root = merkle_root(&leaves);
+        bundle.merkle_root = bs58::encode(root).into_string();
+
+        // A consistent root is not enough without a signature over it
+        let digest = bundle.signing_digest(&root, leaves.len());
+        bundle.signature = bs58::encode(keypair(8).sign(&digest).to_bytes()).into_string();
+        assert!(matches!(bundle.verify_signature(), Err(SdkError::AccountMismatch(_))));
+    }
+
+    #[test]
+    fn changed_logs_no_longer_match_the_root() {
+        let mut bundle = bundle();
+        bundle.transactions[0].logs.push("Program data: Zm9yZ2Vk".to_string());
+
+        assert!(matches!(bundle.verify_signature(), Err(SdkError::AccountMismatch(_))));
+    }
+
+    #[test]
+    fn malformed_fields_are_reported() {
+        let mut bundle = bundle();
+        bundle.transactions[0].signatures[0] = "not base58!".to_string();
+
+        assert!(matches!(bundle.leaves(), Err(SdkError::InvalidAccountData("transaction signature"))));
+    }
+}
@@ -0,0 +1,72 @@
+//! Recipient resolution across `migrate_inbox` forwarding pointers, so a sender is told when a
+//! wallet was abandoned and the message goes to its replacement instead.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use solana_messaging::Forwarding;
+
+use crate::{Result, SdkError, PROGRAM_ID};
+
+/// Hops followed by `resolve_recipient` when the caller has no preference
+pub const DEFAULT_MAX_HOPS: usize = 4;
+
+/// Where a message to the requested wallet should actually go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRecipient {
+    /// Wallet the sender asked for
+    pub requested: Pubkey,
+    /// Wallet the message should be sent to
+    pub address: Pubkey,
+    /// Abandoned wallets passed through, starting with `requested`
+    pub hops: Vec<Pubkey>,
+    /// When the last wallet in the chain migrated
+    pub migrated_at: Option<i64>,
+}
+
+impl ResolvedRecipient {
+    pub fn was_forwarded(&self) -> bool {
+        !self.hops.is_empty()
+    }
+
+    /// Human-readable notice for the sender, if the recipient migrated.
+    pub fn warning(&self) -> Option<String> {
+        let migrated_at = self.migrated_at?;
+        Some(format!(
+            "{} moved their inbox to {} (migrated at unix time {migrated_at}); the message will be sent there",
+            self.requested, self.address
+        ))
+    }
+}
+
+pub fn forwarding_address(wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"forward", wallet.as_ref()], &PROGRAM_ID).0
+}
+
+/// Follows forwarding pointers from `recipient`, fetching each `Forwarding` PDA through
+/// `fetch_account`, which returns `None` for accounts that don't exist.
+pub fn resolve_recipient<F>(recipient: Pubkey, max_hops: usize, mut fetch_account: F) -> Result<ResolvedRecipient>
+where
+    F: FnMut(&Pubkey) -> Option<Vec<u8>>,
+{
+    let mut address = recipient;
+    let mut hops = Vec::new();
+    let mut migrated_at = None;
+
+    while let Some(data) = fetch_account(&forwarding_address(&address)) {
+        if hops.len() == max_hops {
+            return Err(SdkError::ForwardingHopLimit(max_hops));
+        }
+
+        let forwarding = Forwarding::try_deserialize(&mut data.as_slice())
+            .map_err(|_| SdkError::InvalidAccountData("forwarding record"))?;
+        if forwarding.old_wallet != address {
+            return Err(SdkError::AccountMismatch("forwarding record is for another wallet"));
+        }
+
+        hops.push(address);
+        address = forwarding.new_wallet;
+        migrated_at = Some(forwarding.migrated_at);
+    }
+
+    Ok(ResolvedRecipient { requested: recipient, address, hops, migrated_at })
+}
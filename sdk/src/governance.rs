@@ -0,0 +1,133 @@
+//! Vote-from-chat: turns a proposal notification into an SPL Governance `CastVote`
+//! instruction so users can vote without leaving their messaging client.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::system_program;
+use solana_messaging::{ProposalNotification, SPL_GOVERNANCE_PROGRAM_ID};
+
+use crate::{Result, SdkError};
+
+/// `GovernanceInstruction::CastVote` discriminant in SPL Governance v3
+const CAST_VOTE_INSTRUCTION: u8 = 13;
+
+/// How the voter wants to vote on a single-choice proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteChoice {
+    Approve,
+    Deny,
+    Abstain,
+}
+
+/// Accounts of an SPL Governance proposal that a vote has to reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposalRef {
+    pub realm: Pubkey,
+    pub governance: Pubkey,
+    pub proposal: Pubkey,
+    pub governing_token_mint: Pubkey,
+    pub proposal_owner_record: Pubkey,
+}
+
+impl ProposalRef {
+    /// Resolves the proposal embedded in a notification using the raw proposal and governance
+    /// account data fetched by the caller.
+    pub fn from_notification(
+        notification: &ProposalNotification,
+        proposal_data: &[u8],
+        governance_data: &[u8],
+    ) -> Result<Self> {
+        // ProposalV2: account_type, governance, governing_token_mint, state, token_owner_record
+        let governance = read_pubkey(proposal_data, 1, "proposal governance")?;
+        let governing_token_mint = read_pubkey(proposal_data, 33, "proposal governing mint")?;
+        let proposal_owner_record = read_pubkey(proposal_data, 66, "proposal owner record")?;
+        // GovernanceV2: account_type, realm
+        let realm = read_pubkey(governance_data, 1, "governance realm")?;
+
+        if governance != notification.governance {
+            return Err(SdkError::AccountMismatch("proposal does not belong to the notifying governance"));
+        }
+
+        Ok(Self {
+            realm,
+            governance,
+            proposal: notification.proposal,
+            governing_token_mint,
+            proposal_owner_record,
+        })
+    }
+}
+
+/// Whether the notification's voting window is still open at `now`.
+pub fn is_voting_open(notification: &ProposalNotification, now: i64) -> bool {
+    now < notification.voting_ends_at
+}
+
+/// Builds the `CastVote` instruction for `voter`, paying for the vote record with `payer`.
+pub fn cast_vote_instruction(
+    proposal: &ProposalRef,
+    voter: &Pubkey,
+    payer: &Pubkey,
+    choice: VoteChoice,
+) -> Instruction {
+    let voter_token_owner_record = Pubkey::find_program_address(
+        &[
+            b"governance",
+            proposal.realm.as_ref(),
+            proposal.governing_token_mint.as_ref(),
+            voter.as_ref(),
+        ],
+        &SPL_GOVERNANCE_PROGRAM_ID,
+    )
+    .0;
+    let vote_record = Pubkey::find_program_address(
+        &[
+            b"governance",
+            proposal.proposal.as_ref(),
+            voter_token_owner_record.as_ref(),
+        ],
+        &SPL_GOVERNANCE_PROGRAM_ID,
+    )
+    .0;
+    let realm_config = Pubkey::find_program_address(
+        &[b"realm-config", proposal.realm.as_ref()],
+        &SPL_GOVERNANCE_PROGRAM_ID,
+    )
+    .0;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(proposal.realm, false),
+        AccountMeta::new(proposal.governance, false),
+        AccountMeta::new(proposal.proposal, false),
+        AccountMeta::new(proposal.proposal_owner_record, false),
+        AccountMeta::new(voter_token_owner_record, false),
+        AccountMeta::new_readonly(*voter, true),
+        AccountMeta::new(vote_record, false),
+        AccountMeta::new_readonly(proposal.governing_token_mint, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(realm_config, false),
+    ];
+
+    Instruction {
+        program_id: SPL_GOVERNANCE_PROGRAM_ID,
+        accounts,
+        data: encode_vote(choice),
+    }
+}
+
+/// Borsh encoding of `CastVote { vote }` with a single full-weight approve choice.
+fn encode_vote(choice: VoteChoice) -> Vec<u8> {
+    match choice {
+        // Vote::Approve(vec![VoteChoice { rank: 0, weight_percentage: 100 }])
+        VoteChoice::Approve => vec![CAST_VOTE_INSTRUCTION, 0, 1, 0, 0, 0, 0, 100],
+        VoteChoice::Deny => vec![CAST_VOTE_INSTRUCTION, 1],
+        VoteChoice::Abstain => vec![CAST_VOTE_INSTRUCTION, 2],
+    }
+}
+
+fn read_pubkey(data: &[u8], offset: usize, what: &'static str) -> Result<Pubkey> {
+    data.get(offset..offset + 32)
+        .map(|bytes| Pubkey::new_from_array(bytes.try_into().unwrap()))
+        .ok_or(SdkError::InvalidAccountData(what))
+}
@@ -0,0 +1,73 @@
+//! Inbox ordering and visibility shared by all clients, so every client shows the same inbox.
+
+use std::cmp::Reverse;
+
+use solana_messaging::{
+    ContentFilter, ContentWarning, Message, MessagePriority, MessageTranslations, Snooze, WarningAction,
+};
+
+/// Urgent first, then normal, then low.
+pub fn priority_rank(priority: MessagePriority) -> u8 {
+    match priority {
+        MessagePriority::Urgent => 0,
+        MessagePriority::Normal => 1,
+        MessagePriority::Low => 2,
+    }
+}
+
+/// Sorts by priority, newest first within a priority.
+pub fn sort_inbox(messages: &mut [Message]) {
+    messages.sort_by_key(|message| (priority_rank(message.priority), Reverse(message.timestamp)));
+}
+
+/// Whether the snoozed thread is hidden at `now`. A lapsed snooze no longer hides the thread,
+/// even before the crank has closed it.
+pub fn is_snoozed(snooze: &Snooze, now: i64) -> bool {
+    now < snooze.until
+}
+
+/// How to render a message given its warning and the reader's filter. Unwarned messages are
+/// shown; warned ones are blurred unless the reader's filter says otherwise.
+pub fn warning_action(warning: Option<&ContentWarning>, filter: Option<&ContentFilter>) -> WarningAction {
+    match (warning, filter) {
+        (None, _) => WarningAction::Show,
+        (Some(_), None) => WarningAction::Blur,
+        (Some(warning), Some(filter)) => filter.actions[warning.category as usize],
+    }
+}
+
+/// Content to show a reader whose languages are `preferred`, most preferred first. An exact tag
+/// match wins, then a match on the primary subtag ("pt" for "pt-BR"); otherwise the message's
+/// own content is shown.
+pub fn localized_content<'a>(
+    message: &'a Message,
+    translations: Option<&'a MessageTranslations>,
+    preferred: &[&str],
+) -> &'a str {
+    let Some(translations) = translations else {
+        return &message.content;
+    };
+    let primary_subtag = |tag: &str| tag.split('-').next().unwrap_or_default().to_ascii_lowercase();
+    let matches = |exact: bool, a: &str, b: &str| {
+        if exact {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            primary_subtag(a) == primary_subtag(b)
+        }
+    };
+
+    for exact in [true, false] {
+        for language in preferred {
+            if matches(exact, language, &translations.primary_language) {
+                return &message.content;
+            }
+            if let Some(segment) =
+                translations.segments.iter().find(|segment| matches(exact, language, &segment.language))
+            {
+                return &segment.content;
+            }
+        }
+    }
+
+    &message.content
+}
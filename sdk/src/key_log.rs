@@ -0,0 +1,175 @@
+//! Client side of the key transparency log.
+//!
+//! `KeyLog` replays `KeyLogAppended` events into the same depth-20 Merkle tree the program
+//! maintains, so a client can check the on-chain root, prove a registry entry was logged, and
+//! audit the full key history of a wallet. A key served to one user but never logged, or a
+//! history entry the owner didn't make, shows up as a mismatch here.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use sha2::{Digest, Sha256};
+use solana_messaging::{EncryptionKey, KeyLogAppended, KeyTransparencyLog};
+
+use crate::{Result, SdkError};
+
+const DEPTH: usize = KeyTransparencyLog::DEPTH;
+
+/// One logged registry change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyLogEntry {
+    pub index: u64,
+    pub owner: Pubkey,
+    pub key: [u8; 32],
+    pub version: u32,
+    pub updated_at: i64,
+}
+
+impl From<&KeyLogAppended> for KeyLogEntry {
+    fn from(event: &KeyLogAppended) -> Self {
+        Self {
+            index: event.index,
+            owner: event.owner,
+            key: event.key,
+            version: event.version,
+            updated_at: event.updated_at,
+        }
+    }
+}
+
+impl KeyLogEntry {
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        hash(&[
+            b"key_log_leaf",
+            &self.index.to_le_bytes(),
+            self.owner.as_ref(),
+            &self.key,
+            &self.version.to_le_bytes(),
+            &self.updated_at.to_le_bytes(),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyLog {
+    entries: Vec<KeyLogEntry>,
+}
+
+impl KeyLog {
+    /// Builds the log from entries, which must be complete and in index order.
+    pub fn from_entries(entries: Vec<KeyLogEntry>) -> Result<Self> {
+        if entries.iter().enumerate().any(|(i, entry)| entry.index != i as u64) {
+            return Err(SdkError::AccountMismatch("key log entries are missing or out of order"));
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let leaves: Vec<[u8; 32]> = self.entries.iter().map(KeyLogEntry::leaf_hash).collect();
+        subtree_root(&leaves, DEPTH)
+    }
+
+    /// Checks the replayed log against the raw `KeyTransparencyLog` account.
+    pub fn verify_against(&self, log_account_data: &[u8]) -> Result<()> {
+        let log = KeyTransparencyLog::try_deserialize(&mut &log_account_data[..])
+            .map_err(|_| SdkError::InvalidAccountData("key transparency log"))?;
+        if log.leaf_count != self.entries.len() as u64 {
+            return Err(SdkError::AccountMismatch("key log length differs from the on-chain log"));
+        }
+        if log.root != self.root() {
+            return Err(SdkError::AccountMismatch("key log root differs from the on-chain log"));
+        }
+        Ok(())
+    }
+
+    /// Every logged key for `owner`, oldest first.
+    pub fn history(&self, owner: &Pubkey) -> Vec<&KeyLogEntry> {
+        self.entries.iter().filter(|entry| entry.owner == *owner).collect()
+    }
+
+    /// Checks that a registry entry served by RPC is the latest logged key for its owner.
+    pub fn check_registry_entry(&self, encryption_key_data: &[u8]) -> Result<&KeyLogEntry> {
+        let entry = EncryptionKey::try_deserialize(&mut &encryption_key_data[..])
+            .map_err(|_| SdkError::InvalidAccountData("encryption key entry"))?;
+        let latest = self
+            .history(&entry.owner)
+            .pop()
+            .ok_or(SdkError::AccountMismatch("encryption key was never logged"))?;
+        if latest.key != entry.key || latest.version != entry.version {
+            return Err(SdkError::AccountMismatch("encryption key differs from the latest logged key"));
+        }
+        Ok(latest)
+    }
+
+    /// Sibling path for the entry at `index`, leaf level first.
+    pub fn inclusion_proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        let mut level: Vec<[u8; 32]> = self.entries.iter().map(KeyLogEntry::leaf_hash).collect();
+        let mut zero = [0u8; 32];
+        let mut position = index;
+        let mut proof = Vec::with_capacity(DEPTH);
+
+        for _ in 0..DEPTH {
+            proof.push(*level.get(position ^ 1).unwrap_or(&zero));
+            level = level
+                .chunks(2)
+                .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&zero)))
+                .collect();
+            zero = node_hash(&zero, &zero);
+            position >>= 1;
+        }
+        Some(proof)
+    }
+}
+
+/// Recomputes the root from an entry and its inclusion proof.
+pub fn verify_inclusion(entry: &KeyLogEntry, proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    if proof.len() != DEPTH {
+        return false;
+    }
+    let mut node = entry.leaf_hash();
+    let mut position = entry.index;
+    for sibling in proof {
+        node = if position & 1 == 0 { node_hash(&node, sibling) } else { node_hash(sibling, &node) };
+        position >>= 1;
+    }
+    node == *root
+}
+
+fn subtree_root(leaves: &[[u8; 32]], depth: usize) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    let mut zero = [0u8; 32];
+    for _ in 0..depth {
+        if level.is_empty() {
+            level.push(zero);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&zero)))
+            .collect();
+        zero = node_hash(&zero, &zero);
+    }
+    level[0]
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hash(&[b"key_log_node", left, right])
+}
+
+/// Matches the program's `hashv`: SHA-256 over the concatenated parts.
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
@@ -0,0 +1,77 @@
+//! Client helpers for building and interpreting Solana NFT Messaging transactions.
+
+use std::fmt;
+
+pub mod availability;
+pub mod branding;
+pub mod budget;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod contact;
+pub mod errors;
+pub mod estimate;
+pub mod export;
+pub mod forwarding;
+pub mod governance;
+pub mod inbox;
+pub mod key_log;
+pub mod links;
+pub mod offline;
+pub mod optimistic;
+pub mod payment_note;
+pub mod preview;
+pub mod private_inbox;
+pub mod safety;
+pub mod send;
+pub mod structured;
+pub mod tip;
+
+pub use solana_messaging::ID as PROGRAM_ID;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SdkError {
+    /// Raw account data did not match the expected layout
+    InvalidAccountData(&'static str),
+    /// The referenced accounts do not belong together
+    AccountMismatch(&'static str),
+    /// Forwarding pointers went on for more hops than allowed
+    ForwardingHopLimit(usize),
+    /// The RPC transport reported an error
+    Transport(String),
+    /// The transaction landed but its execution failed
+    TransactionFailed(String),
+    /// No attempt landed within the retry policy
+    RetriesExhausted(u32),
+    /// Simulation reported an execution error
+    SimulationFailed(String),
+    /// A `gmchat://` link could not be parsed or resolved
+    InvalidLink(&'static str),
+    /// A scanned contact card is malformed, unsigned or stale
+    InvalidContactCard(&'static str),
+    /// The offline queue could not be read or written
+    Storage(String),
+    /// A send configuration the program would reject
+    InvalidSendConfig(&'static str),
+}
+
+impl fmt::Display for SdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdkError::InvalidAccountData(what) => write!(f, "invalid account data: {what}"),
+            SdkError::AccountMismatch(what) => write!(f, "account mismatch: {what}"),
+            SdkError::ForwardingHopLimit(hops) => write!(f, "recipient forwarding exceeds {hops} hops"),
+            SdkError::Transport(err) => write!(f, "rpc error: {err}"),
+            SdkError::TransactionFailed(err) => write!(f, "transaction failed: {err}"),
+            SdkError::RetriesExhausted(attempts) => write!(f, "transaction did not land after {attempts} attempts"),
+            SdkError::SimulationFailed(err) => write!(f, "simulation failed: {err}"),
+            SdkError::InvalidLink(what) => write!(f, "invalid link: {what}"),
+            SdkError::InvalidContactCard(what) => write!(f, "invalid contact card: {what}"),
+            SdkError::Storage(err) => write!(f, "storage error: {err}"),
+            SdkError::InvalidSendConfig(what) => write!(f, "invalid send configuration: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for SdkError {}
+
+pub type Result<T> = std::result::Result<T, SdkError>;
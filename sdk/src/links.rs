@@ -0,0 +1,122 @@
+//! `gmchat://` deep links, so clients and bots can reference chat objects interchangeably.
+//!
+//! - `gmchat://conversation/<pda>` points at a message-like program account
+//! - `gmchat://user/<handle>` points at a user, by handle or by base58 wallet address
+
+use std::fmt;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use solana_messaging::{AmaQuestion, BlobMessage, DealMessage, InternedMessage, Message, RoomMessage};
+
+use crate::forwarding::{resolve_recipient, ResolvedRecipient, DEFAULT_MAX_HOPS};
+use crate::{Result, SdkError, PROGRAM_ID};
+
+pub const SCHEME: &str = "gmchat://";
+
+/// Long enough for a base58 wallet address used in place of a handle
+const MAX_HANDLE_LEN: usize = 44;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    Conversation(Pubkey),
+    User(String),
+}
+
+impl DeepLink {
+    pub fn conversation(address: Pubkey) -> Self {
+        DeepLink::Conversation(address)
+    }
+
+    pub fn user(handle: &str) -> Result<Self> {
+        validate_handle(handle)?;
+        Ok(DeepLink::User(handle.to_string()))
+    }
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLink::Conversation(address) => write!(f, "{SCHEME}conversation/{address}"),
+            DeepLink::User(handle) => write!(f, "{SCHEME}user/{handle}"),
+        }
+    }
+}
+
+impl FromStr for DeepLink {
+    type Err = SdkError;
+
+    fn from_str(link: &str) -> Result<Self> {
+        let path = link.strip_prefix(SCHEME).ok_or(SdkError::InvalidLink("missing gmchat:// scheme"))?;
+        // Tolerate a trailing slash or query string added by link shorteners
+        let path = path.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/');
+
+        match path.split_once('/') {
+            Some(("conversation", address)) => Pubkey::from_str(address)
+                .map(DeepLink::Conversation)
+                .map_err(|_| SdkError::InvalidLink("conversation address is not a pubkey")),
+            Some(("user", handle)) => DeepLink::user(handle),
+            _ => Err(SdkError::InvalidLink("unknown link kind")),
+        }
+    }
+}
+
+/// What a deep link points at on-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedLink {
+    Conversation { address: Pubkey, kind: &'static str },
+    User(ResolvedRecipient),
+}
+
+/// Resolves `link` using account data fetched by the caller. Handles that aren't wallet
+/// addresses go through `lookup_handle`; user links then follow inbox forwarding pointers.
+pub fn resolve_link<F, H>(link: &DeepLink, mut fetch_account: F, lookup_handle: H) -> Result<ResolvedLink>
+where
+    F: FnMut(&Pubkey) -> Option<(Pubkey, Vec<u8>)>,
+    H: FnOnce(&str) -> Option<Pubkey>,
+{
+    match link {
+        DeepLink::Conversation(address) => {
+            let (owner, data) = fetch_account(address).ok_or(SdkError::AccountMismatch("conversation account not found"))?;
+            if owner != PROGRAM_ID {
+                return Err(SdkError::AccountMismatch("conversation account is not owned by the program"));
+            }
+            let kind = conversation_kind(&data).ok_or(SdkError::InvalidAccountData("not a conversation account"))?;
+            Ok(ResolvedLink::Conversation { address: *address, kind })
+        }
+        DeepLink::User(handle) => {
+            let wallet = match Pubkey::from_str(handle) {
+                Ok(wallet) => wallet,
+                Err(_) => lookup_handle(handle).ok_or(SdkError::InvalidLink("unknown handle"))?,
+            };
+            let resolved = resolve_recipient(wallet, DEFAULT_MAX_HOPS, |key| fetch_account(key).map(|(_, data)| data))?;
+            Ok(ResolvedLink::User(resolved))
+        }
+    }
+}
+
+fn conversation_kind(data: &[u8]) -> Option<&'static str> {
+    let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+    [
+        (Message::DISCRIMINATOR, "message"),
+        (BlobMessage::DISCRIMINATOR, "blob_message"),
+        (InternedMessage::DISCRIMINATOR, "interned_message"),
+        (RoomMessage::DISCRIMINATOR, "room_message"),
+        (DealMessage::DISCRIMINATOR, "deal_message"),
+        (AmaQuestion::DISCRIMINATOR, "ama_question"),
+    ]
+    .into_iter()
+    .find_map(|(known, kind)| (known == discriminator).then_some(kind))
+}
+
+fn validate_handle(handle: &str) -> Result<()> {
+    let valid = !handle.is_empty()
+        && handle.len() <= MAX_HANDLE_LEN
+        && handle.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(SdkError::InvalidLink("invalid handle"))
+    }
+}
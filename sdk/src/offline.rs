@@ -0,0 +1,418 @@
+//! Persisted outgoing queue for sends made while offline.
+//!
+//! `OfflineQueue::enqueue` writes the message to a `QueueStore` before anything touches the
+//! network and assigns it the next client nonce, which is persisted too so nonces are never
+//! reused across restarts. `drain` sends queued messages oldest first through a `send::Sender`
+//! and stops at the first transport failure, so later messages never overtake earlier ones.
+//!
+//! A message's client message ID is derived from the device ID and its nonce
+//! (`optimistic::client_message_id`) and is part of the message PDA, so a resend after a restart
+//! cannot land a duplicate: if an earlier attempt already landed, the resend fails on the
+//! existing account instead, and `drain` reports it as delivered. Nonces are per device, so
+//! each of a user's devices keeps its own queue and they never collide.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use serde::{Deserialize, Serialize};
+use solana_messaging::MessagePriority;
+
+use crate::optimistic::{client_message_id, message_address, DeviceId};
+use crate::send::{SendOutcome, SignedTransaction, Sender, Transport};
+use crate::{Result, SdkError};
+
+/// A send waiting for connectivity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedMessage {
+    pub client_nonce: u64,
+    pub client_message_id: [u8; 16],
+    /// Base58 sender wallet
+    pub sender: String,
+    /// Base58 recipient wallet
+    pub recipient: String,
+    pub content: String,
+    /// 0 normal, 1 low, 2 urgent
+    pub priority: u8,
+    /// Local clock when the message was queued
+    pub queued_at: i64,
+}
+
+impl QueuedMessage {
+    pub fn sender(&self) -> Result<Pubkey> {
+        parse_pubkey(&self.sender, "queued sender")
+    }
+
+    pub fn recipient(&self) -> Result<Pubkey> {
+        parse_pubkey(&self.recipient, "queued recipient")
+    }
+
+    pub fn priority(&self) -> Result<MessagePriority> {
+        match self.priority {
+            0 => Ok(MessagePriority::Normal),
+            1 => Ok(MessagePriority::Low),
+            2 => Ok(MessagePriority::Urgent),
+            _ => Err(SdkError::InvalidAccountData("queued priority")),
+        }
+    }
+
+    /// Address the message account will have once sent.
+    pub fn address(&self) -> Result<Pubkey> {
+        Ok(message_address(&self.sender()?, &self.recipient()?, &self.client_message_id))
+    }
+}
+
+/// Durable storage behind the queue. `FileStore` is the built-in implementation; mobile
+/// clients can back this with their platform database instead.
+pub trait QueueStore {
+    /// Queued messages in any order, and the next unused client nonce.
+    fn load(&mut self) -> Result<(Vec<QueuedMessage>, u64)>;
+    /// Must be durable before returning.
+    fn push(&mut self, message: &QueuedMessage) -> Result<()>;
+    fn remove(&mut self, client_nonce: u64) -> Result<()>;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record {
+    Push { message: QueuedMessage },
+    Remove { client_nonce: u64 },
+    /// Written by compaction so the nonce survives removed messages
+    NextNonce { next: u64 },
+}
+
+/// Append-only JSON-lines log. Each change is one line, synced before returning.
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+}
+
+impl FileStore {
+    /// Opens the log, cutting off a torn final line left by a crash mid-write so the next
+    /// record starts on a line of its own. The torn record was never acknowledged.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&path).map_err(storage)?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).map_err(storage)?;
+        if contents.last().is_some_and(|&byte| byte != b'\n') {
+            let intact = contents.iter().rposition(|&byte| byte == b'\n').map_or(0, |newline| newline + 1);
+            file.set_len(intact as u64).map_err(storage)?;
+            file.sync_data().map_err(storage)?;
+        }
+
+        Ok(Self { path, file })
+    }
+
+    /// Rewrites the log with only the live messages. The new log is written beside the old
+    /// one and renamed over it, so a crash leaves one or the other intact.
+    pub fn compact(&mut self) -> Result<()> {
+        let (messages, next_nonce) = self.load()?;
+        let temp = self.path.with_extension("compact");
+
+        let mut file = File::create(&temp).map_err(storage)?;
+        write_record(&mut file, &Record::NextNonce { next: next_nonce })?;
+        for message in messages {
+            write_record(&mut file, &Record::Push { message })?;
+        }
+        drop(file);
+
+        fs::rename(&temp, &self.path).map_err(storage)?;
+        self.file = OpenOptions::new().append(true).open(&self.path).map_err(storage)?;
+        Ok(())
+    }
+}
+
+impl QueueStore for FileStore {
+    fn load(&mut self) -> Result<(Vec<QueuedMessage>, u64)> {
+        let mut messages: Vec<QueuedMessage> = Vec::new();
+        let mut next_nonce = 0;
+
+        for line in BufReader::new(File::open(&self.path).map_err(storage)?).lines() {
+            let line = line.map_err(storage)?;
+            // A torn final line from a crash since `open` is dropped; it was never acknowledged
+            let Ok(record) = serde_json::from_str::<Record>(&line) else { continue };
+            match record {
+                Record::Push { message } => {
+                    next_nonce = next_nonce.max(message.client_nonce + 1);
+                    messages.retain(|queued| queued.client_nonce != message.client_nonce);
+                    messages.push(message);
+                }
+                Record::Remove { client_nonce } => messages.retain(|queued| queued.client_nonce != client_nonce),
+                Record::NextNonce { next } => next_nonce = next_nonce.max(next),
+            }
+        }
+
+        Ok((messages, next_nonce))
+    }
+
+    fn push(&mut self, message: &QueuedMessage) -> Result<()> {
+        write_record(&mut self.file, &Record::Push { message: message.clone() })
+    }
+
+    fn remove(&mut self, client_nonce: u64) -> Result<()> {
+        write_record(&mut self.file, &Record::Remove { client_nonce })
+    }
+}
+
+/// How a queued message reached the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    Sent(SendOutcome),
+    /// The message account already existed: an attempt from before a restart had landed
+    AlreadyLanded,
+}
+
+/// Result of sending one queued message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drained {
+    pub message: QueuedMessage,
+    pub result: Result<Delivery>,
+}
+
+pub struct OfflineQueue<S: QueueStore> {
+    store: S,
+    device: DeviceId,
+    messages: Vec<QueuedMessage>,
+    next_nonce: u64,
+}
+
+impl<S: QueueStore> OfflineQueue<S> {
+    pub fn open(mut store: S, device: DeviceId) -> Result<Self> {
+        let (mut messages, next_nonce) = store.load()?;
+        messages.sort_by_key(|message| message.client_nonce);
+        Ok(Self { store, device, messages, next_nonce })
+    }
+
+    /// Queued messages, oldest first.
+    pub fn messages(&self) -> &[QueuedMessage] {
+        &self.messages
+    }
+
+    /// Persists a send and returns it with its assigned nonce and client message ID.
+    pub fn enqueue(
+        &mut self,
+        sender: &Pubkey,
+        recipient: &Pubkey,
+        content: String,
+        priority: MessagePriority,
+        now: i64,
+    ) -> Result<&QueuedMessage> {
+        let message = QueuedMessage {
+            client_nonce: self.next_nonce,
+            client_message_id: client_message_id(sender, &self.device, self.next_nonce),
+            sender: sender.to_string(),
+            recipient: recipient.to_string(),
+            content,
+            priority: priority as u8,
+            queued_at: now,
+        };
+        self.store.push(&message)?;
+        self.next_nonce += 1;
+        self.messages.push(message);
+        Ok(self.messages.last().expect("just pushed"))
+    }
+
+    /// Drops a queued message without sending it.
+    pub fn discard(&mut self, client_nonce: u64) -> Result<()> {
+        self.store.remove(client_nonce)?;
+        self.messages.retain(|message| message.client_nonce != client_nonce);
+        Ok(())
+    }
+
+    /// Sends queued messages oldest first. Landed messages and ones whose transaction failed
+    /// on chain leave the queue; a failure because the message account already exists counts
+    /// as delivered. A transport failure or exhausted retries stops the drain with that message
+    /// and everything after it still queued. `sign` builds the send for a queued message against
+    /// a blockhash, as for `Sender::send`.
+    pub fn drain<T, F>(&mut self, sender: &mut Sender<T>, mut sign: F) -> Result<Vec<Drained>>
+    where
+        T: Transport,
+        F: FnMut(&QueuedMessage, Hash) -> SignedTransaction,
+    {
+        let mut drained = Vec::new();
+
+        while let Some(message) = self.messages.first().cloned() {
+            let result = message.address().and_then(|address| {
+                match sender.send(message.client_nonce, &address, |blockhash| sign(&message, blockhash)) {
+                    Ok(outcome) => Ok(Delivery::Sent(outcome)),
+                    Err(SdkError::TransactionFailed(error)) => match sender.transport_mut().account_exists(&address) {
+                        Ok(true) => Ok(Delivery::AlreadyLanded),
+                        Ok(false) => Err(SdkError::TransactionFailed(error)),
+                        Err(error) => Err(SdkError::Transport(error)),
+                    },
+                    Err(error) => Err(error),
+                }
+            });
+            let offline = matches!(result, Err(SdkError::Transport(_) | SdkError::RetriesExhausted(_)));
+            if !offline {
+                self.discard(message.client_nonce)?;
+                sender.forget(message.client_nonce);
+            }
+            drained.push(Drained { message, result });
+            if offline {
+                break;
+            }
+        }
+
+        Ok(drained)
+    }
+}
+
+fn write_record(file: &mut File, record: &Record) -> Result<()> {
+    let mut line = serde_json::to_string(record).map_err(|e| SdkError::Storage(e.to_string()))?;
+    line.push('\n');
+    file.write_all(line.as_bytes()).map_err(storage)?;
+    file.sync_data().map_err(storage)
+}
+
+fn storage(error: std::io::Error) -> SdkError {
+    SdkError::Storage(error.to_string())
+}
+
+fn parse_pubkey(value: &str, what: &'static str) -> Result<Pubkey> {
+    value.parse().map_err(|_| SdkError::InvalidAccountData(what))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::send::tests::{instant_policy, signer, MockTransport};
+    use crate::send::SignatureStatus;
+
+    const DEVICE: DeviceId = [7; 8];
+
+    /// A log path of its own per test, cleared of any earlier run.
+    fn log_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gmchat-offline-{}-{name}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn enqueue(queue: &mut OfflineQueue<FileStore>, content: &str) -> QueuedMessage {
+        let (sender, recipient) = (Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([2; 32]));
+        queue.enqueue(&sender, &recipient, content.to_string(), MessagePriority::Normal, 0).unwrap().clone()
+    }
+
+    #[test]
+    fn survives_a_restart() {
+        let path = log_path("restart");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+        enqueue(&mut queue, "second");
+        queue.discard(0).unwrap();
+
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        let contents: Vec<_> = queue.messages().iter().map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, ["second"]);
+        assert_eq!(enqueue(&mut queue, "third").client_nonce, 2);
+    }
+
+    #[test]
+    fn compaction_keeps_the_next_nonce() {
+        let path = log_path("compact");
+        let mut store = FileStore::open(&path).unwrap();
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+        queue.discard(0).unwrap();
+        store.compact().unwrap();
+
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        assert!(queue.messages().is_empty());
+        assert_eq!(enqueue(&mut queue, "second").client_nonce, 1);
+    }
+
+    #[test]
+    fn a_torn_tail_does_not_swallow_the_next_record() {
+        let path = log_path("torn");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(br#"{"op":"push","mess"#).unwrap();
+
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "second");
+
+        let queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        let contents: Vec<_> = queue.messages().iter().map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, ["first", "second"]);
+    }
+
+    #[test]
+    fn drains_oldest_first() {
+        let path = log_path("drain");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+        enqueue(&mut queue, "second");
+
+        let mut transport = MockTransport::default();
+        for signature in [[0; 64], [1; 64]] {
+            transport.statuses.insert(signature, vec![SignatureStatus::Landed(crate::send::Commitment::Confirmed)]);
+        }
+        let mut sender = Sender::new(transport, instant_policy(3));
+        let signed = Cell::new(0);
+        let mut sign = signer(&signed);
+        let drained = queue.drain(&mut sender, |_, blockhash| sign(blockhash)).unwrap();
+
+        let contents: Vec<_> = drained.iter().map(|drained| drained.message.content.as_str()).collect();
+        assert_eq!(contents, ["first", "second"]);
+        assert!(drained.iter().all(|drained| matches!(drained.result, Ok(Delivery::Sent(_)))));
+        assert!(queue.messages().is_empty());
+    }
+
+    #[test]
+    fn an_existing_message_account_counts_as_delivered() {
+        let path = log_path("landed");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        let message = enqueue(&mut queue, "first");
+
+        // The resend executes and fails because the account an earlier attempt created exists
+        let mut transport = MockTransport { created_on_send: Some(message.address().unwrap()), ..Default::default() };
+        let in_use = SignatureStatus::Failed("account already in use".to_string());
+        transport.statuses.insert([0; 64], vec![in_use]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+        let signed = Cell::new(0);
+        let mut sign = signer(&signed);
+        let drained = queue.drain(&mut sender, |_, blockhash| sign(blockhash)).unwrap();
+
+        assert_eq!(drained[0].result, Ok(Delivery::AlreadyLanded));
+        assert!(queue.messages().is_empty());
+    }
+
+    #[test]
+    fn an_on_chain_failure_leaves_the_queue() {
+        let path = log_path("failed");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+
+        let mut transport = MockTransport::default();
+        transport.statuses.insert([0; 64], vec![SignatureStatus::Failed("custom program error: 0x1".to_string())]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+        let signed = Cell::new(0);
+        let mut sign = signer(&signed);
+        let drained = queue.drain(&mut sender, |_, blockhash| sign(blockhash)).unwrap();
+
+        assert!(matches!(drained[0].result, Err(SdkError::TransactionFailed(_))));
+        assert!(queue.messages().is_empty());
+    }
+
+    #[test]
+    fn stops_at_a_transport_failure() {
+        let path = log_path("offline");
+        let mut queue = OfflineQueue::open(FileStore::open(&path).unwrap(), DEVICE).unwrap();
+        enqueue(&mut queue, "first");
+        enqueue(&mut queue, "second");
+
+        let transport = MockTransport { offline: true, ..Default::default() };
+        let mut sender = Sender::new(transport, instant_policy(3));
+        let drained = queue.drain(&mut sender, |_, _| panic!("nothing is signed while offline")).unwrap();
+
+        assert_eq!(drained.len(), 1);
+        assert!(matches!(drained[0].result, Err(SdkError::Transport(_))));
+        assert_eq!(queue.messages().len(), 2);
+    }
+}
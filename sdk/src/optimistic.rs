@@ -0,0 +1,216 @@
+//! Optimistic local echo for chat UIs.
+//!
+//! `LocalEcho::echo` returns the message account a send will create, at its final address,
+//! before the transaction is even signed, so the UI can render it immediately. Once the send
+//! settles, the pending entry is reconciled against the confirmed account or `MessageSent`
+//! event, or rolled back if the send failed. The client message ID in the PDA seeds is what
+//! ties the optimistic entry to the chain state.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use sha2::{Digest, Sha256};
+use solana_messaging::{Message, MessageCost, MessagePriority, MessageSent};
+
+use crate::send::SendOutcome;
+use crate::{Result, SdkError, PROGRAM_ID};
+
+/// Where a pending message is in its lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EchoState {
+    /// Shown optimistically; nothing has landed yet
+    Pending,
+    /// The transaction landed; waiting to see the account or event
+    Landed { signature: Option<[u8; 64]> },
+    /// Matched against the confirmed account or event
+    Confirmed { timestamp: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingMessage {
+    pub client_message_id: [u8; 16],
+    pub address: Pubkey,
+    /// Local order of sends, for stable rendering while timestamps are still local
+    pub seq: u64,
+    /// Local clock when the message was echoed
+    pub created_at: i64,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub content: String,
+    pub priority: MessagePriority,
+    /// Local clock until confirmed, then the on-chain timestamp
+    pub timestamp: i64,
+    pub state: EchoState,
+}
+
+impl PendingMessage {
+    /// The message account as it will look once created.
+    pub fn to_account(&self) -> Message {
+        Message {
+            sender: self.sender,
+            recipient: self.recipient,
+            content: self.content.clone(),
+            timestamp: self.timestamp,
+            nft_mint: Pubkey::default(),
+            priority: self.priority,
+            client_message_id: self.client_message_id,
+            app: Pubkey::default(),
+            namespace: Pubkey::default(),
+            cost: MessageCost::default(),
+            bump: 0,
+        }
+    }
+}
+
+/// Callbacks for UI updates. Both default to doing nothing.
+pub trait EchoListener {
+    fn on_confirmed(&mut self, _pending: &PendingMessage) {}
+    /// The optimistic message must be removed from the UI (or marked as failed).
+    fn on_rollback(&mut self, _pending: &PendingMessage, _error: &SdkError) {}
+}
+
+/// Identifies one of a user's devices. Each device draws one from the platform RNG on first
+/// run and keeps it, so devices counting client nonces independently never derive the same
+/// client message ID and never race each other onto the same message PDA.
+pub type DeviceId = [u8; 8];
+
+/// Client message ID for a device's `send::Sender` client nonce, so retries of one send share
+/// an ID.
+pub fn client_message_id(sender: &Pubkey, device: &DeviceId, client_nonce: u64) -> [u8; 16] {
+    let digest = Sha256::new()
+        .chain_update(b"gmchat-client-message-id")
+        .chain_update(sender.as_ref())
+        .chain_update(device)
+        .chain_update(client_nonce.to_le_bytes())
+        .finalize();
+    let mut id = [0; 16];
+    id.copy_from_slice(&digest[..16]);
+    // All zeros is reserved for messages sent without an ID
+    if id == [0; 16] {
+        id[0] = 1;
+    }
+    id
+}
+
+/// Address of the message `sender` creates for `recipient` with this client message ID.
+pub fn message_address(sender: &Pubkey, recipient: &Pubkey, client_message_id: &[u8; 16]) -> Pubkey {
+    Pubkey::find_program_address(&[b"message", sender.as_ref(), recipient.as_ref(), client_message_id], &PROGRAM_ID).0
+}
+
+/// Like `message_address`, for a message sent within a white-label namespace.
+pub fn namespaced_message_address(
+    namespace: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    client_message_id: &[u8; 16],
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"message", namespace.as_ref(), sender.as_ref(), recipient.as_ref(), client_message_id],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub struct LocalEcho<L: EchoListener> {
+    listener: L,
+    next_seq: u64,
+    pending: BTreeMap<[u8; 16], PendingMessage>,
+}
+
+impl<L: EchoListener> LocalEcho<L> {
+    pub fn new(listener: L) -> Self {
+        Self { listener, next_seq: 0, pending: BTreeMap::new() }
+    }
+
+    /// Records a send and returns the would-be account to render.
+    pub fn echo(
+        &mut self,
+        sender: Pubkey,
+        recipient: Pubkey,
+        content: String,
+        priority: MessagePriority,
+        client_message_id: [u8; 16],
+        now: i64,
+    ) -> &PendingMessage {
+        let pending = PendingMessage {
+            client_message_id,
+            address: message_address(&sender, &recipient, &client_message_id),
+            seq: self.next_seq,
+            created_at: now,
+            sender,
+            recipient,
+            content,
+            priority,
+            timestamp: now,
+            state: EchoState::Pending,
+        };
+        self.next_seq += 1;
+        self.pending.entry(client_message_id).or_insert(pending)
+    }
+
+    /// Pending messages in send order.
+    pub fn pending(&self) -> Vec<&PendingMessage> {
+        let mut pending: Vec<_> = self.pending.values().collect();
+        pending.sort_by_key(|pending| pending.seq);
+        pending
+    }
+
+    /// Feeds the result of `Sender::send` for a pending message. Failures roll it back.
+    pub fn settle(&mut self, client_message_id: &[u8; 16], outcome: &Result<SendOutcome>) {
+        match outcome {
+            Ok(outcome) => {
+                if let Some(pending) = self.pending.get_mut(client_message_id) {
+                    pending.state = EchoState::Landed { signature: outcome.signature };
+                }
+            }
+            Err(error) => self.rollback(client_message_id, error.clone()),
+        }
+    }
+
+    /// Drops a pending message and notifies the listener.
+    pub fn rollback(&mut self, client_message_id: &[u8; 16], error: SdkError) {
+        if let Some(pending) = self.pending.remove(client_message_id) {
+            self.listener.on_rollback(&pending, &error);
+        }
+    }
+
+    /// Reconciles against a fetched message account, returning the confirmed entry. Accounts
+    /// that don't belong to a pending send are ignored.
+    pub fn reconcile_account(&mut self, address: &Pubkey, data: &[u8]) -> Result<Option<PendingMessage>> {
+        let message =
+            Message::try_deserialize(&mut &data[..]).map_err(|_| SdkError::InvalidAccountData("message"))?;
+        let Some(pending) = self.pending.get(&message.client_message_id) else {
+            return Ok(None);
+        };
+
+        if pending.address != *address
+            || pending.sender != message.sender
+            || pending.recipient != message.recipient
+            || pending.content != message.content
+        {
+            let error = SdkError::AccountMismatch("confirmed message differs from the optimistic echo");
+            self.rollback(&message.client_message_id, error.clone());
+            return Err(error);
+        }
+
+        Ok(self.confirm(&message.client_message_id, message.timestamp))
+    }
+
+    /// Reconciles against a `MessageSent` event from a log subscription.
+    pub fn reconcile_event(&mut self, event: &MessageSent) -> Option<PendingMessage> {
+        let pending = self.pending.get(&event.client_message_id)?;
+        if pending.address != event.message {
+            return None;
+        }
+        self.confirm(&event.client_message_id, event.timestamp)
+    }
+
+    fn confirm(&mut self, client_message_id: &[u8; 16], timestamp: i64) -> Option<PendingMessage> {
+        let mut pending = self.pending.remove(client_message_id)?;
+        pending.timestamp = timestamp;
+        pending.state = EchoState::Confirmed { timestamp };
+        self.listener.on_confirmed(&pending);
+        Some(pending)
+    }
+}
@@ -0,0 +1,88 @@
+//! Payment notes: a token transfer and the gmchat note describing it, sent as consecutive
+//! instructions of one transaction. The program checks the transfer on-chain and copies its
+//! details onto the note, so the transaction signature is the reference tying the two together.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_messaging::{accounts, instruction, PaymentNote};
+
+use crate::{Result, SdkError, PROGRAM_ID};
+
+/// `TokenInstruction::TransferChecked`, shared by SPL Token and Token-2022
+const TRANSFER_CHECKED: u8 = 12;
+
+/// A transfer out of the sender's token account into one owned by the recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenPayment {
+    /// SPL Token or Token-2022
+    pub token_program: Pubkey,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+pub fn note_address(sender: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"payment_note", sender.as_ref(), &nonce.to_le_bytes()], &PROGRAM_ID).0
+}
+
+/// Like `note_address`, for a sender within a white-label namespace.
+pub fn namespaced_note_address(namespace: &Pubkey, sender: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"payment_note", namespace.as_ref(), sender.as_ref(), &nonce.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+/// The transfer followed by `attach_payment_note`; they must stay adjacent in the transaction.
+/// `namespace` is the sender's white-label namespace, `None` on the public network.
+pub fn transfer_with_note(
+    namespace: Option<&Pubkey>,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    payment: &TokenPayment,
+    nonce: u64,
+    note: &str,
+) -> Result<[Instruction; 2]> {
+    if note.is_empty() || note.len() > PaymentNote::MAX_NOTE_LEN {
+        return Err(SdkError::InvalidSendConfig("payment note must be 1-280 bytes"));
+    }
+
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&payment.amount.to_le_bytes());
+    data.push(payment.decimals);
+    let transfer = Instruction {
+        program_id: payment.token_program,
+        accounts: vec![
+            AccountMeta::new(payment.source, false),
+            AccountMeta::new_readonly(payment.mint, false),
+            AccountMeta::new(payment.destination, false),
+            AccountMeta::new_readonly(*sender, true),
+        ],
+        data,
+    };
+
+    let attach = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::AttachPaymentNote {
+            payment_note: namespace.map_or_else(
+                || note_address(sender, nonce),
+                |namespace| namespaced_note_address(namespace, sender, nonce),
+            ),
+            destination: payment.destination,
+            sender: *sender,
+            instructions: sysvar::instructions::ID,
+            system_program: system_program::ID,
+            namespace: namespace.copied(),
+            sender_membership: Pubkey::find_program_address(&[b"namespace_member", sender.as_ref()], &PROGRAM_ID).0,
+        }
+        .to_account_metas(None),
+        data: instruction::AttachPaymentNote { nonce, recipient: *recipient, note: note.to_string() }.data(),
+    };
+
+    Ok([transfer, attach])
+}
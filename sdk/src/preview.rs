@@ -0,0 +1,183 @@
+//! Dry-run previews: simulate a transaction and decode what it would change, so a UI can show
+//! "this will extend your streak to 31 days" before the user signs.
+//!
+//! The caller passes the accounts to watch with their current data; the simulation returns
+//! their post-transaction data, and every watched account that changed is decoded into a typed
+//! preview. Events logged by the program fill in what the accounts alone don't show, such as
+//! when a queued message will be delivered.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use solana_messaging::{Message, MessagePriority, MessageQueued, UserStats};
+
+use crate::{Result, SdkError, PROGRAM_ID};
+
+const PROGRAM_DATA: &str = "Program data: ";
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Result of `simulateTransaction` with the `accounts` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedEffects {
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: u64,
+    /// Post-simulation data of the requested accounts, in request order; `None` if absent
+    pub accounts: Vec<Option<Vec<u8>>>,
+}
+
+/// The RPC call previews need.
+pub trait SimulateEffects {
+    fn simulate_effects(
+        &mut self,
+        wire_transaction: &[u8],
+        addresses: &[Pubkey],
+    ) -> std::result::Result<SimulatedEffects, String>;
+}
+
+/// A message account the transaction creates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessagePreview {
+    pub address: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub content: String,
+    pub priority: MessagePriority,
+    pub client_message_id: [u8; 16],
+    /// Set when the recipient's delivery window is closed
+    pub deliver_at: Option<i64>,
+}
+
+/// How the transaction moves a user's daily activity streak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreakPreview {
+    pub user: Pubkey,
+    pub days_before: u32,
+    pub days_after: u32,
+    pub messages_sent: u64,
+}
+
+impl StreakPreview {
+    pub fn extends(&self) -> bool {
+        self.days_after > self.days_before
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionPreview {
+    pub units_consumed: u64,
+    pub messages: Vec<MessagePreview>,
+    pub streaks: Vec<StreakPreview>,
+    /// Watched accounts that changed but have no typed preview
+    pub other_changes: Vec<Pubkey>,
+}
+
+/// A watched account and its data before the transaction, `None` if it doesn't exist yet.
+pub struct Watched<'a> {
+    pub address: Pubkey,
+    pub before: Option<&'a [u8]>,
+}
+
+/// Simulates `wire_transaction` and decodes its effects on the watched accounts.
+pub fn preview<S: SimulateEffects>(
+    rpc: &mut S,
+    wire_transaction: &[u8],
+    watched: &[Watched],
+) -> Result<TransactionPreview> {
+    let addresses: Vec<Pubkey> = watched.iter().map(|account| account.address).collect();
+    let effects = rpc.simulate_effects(wire_transaction, &addresses).map_err(SdkError::Transport)?;
+    if let Some(error) = effects.error {
+        return Err(SdkError::SimulationFailed(error));
+    }
+    if effects.accounts.len() != watched.len() {
+        return Err(SdkError::AccountMismatch("simulation returned a different number of accounts"));
+    }
+
+    let queued = queued_messages(&effects.logs);
+    let mut preview = TransactionPreview {
+        units_consumed: effects.units_consumed,
+        messages: Vec::new(),
+        streaks: Vec::new(),
+        other_changes: Vec::new(),
+    };
+
+    for (account, after) in watched.iter().zip(&effects.accounts) {
+        let Some(after) = after.as_deref() else { continue };
+        if account.before == Some(after) {
+            continue;
+        }
+
+        if after.starts_with(&Message::DISCRIMINATOR) && account.before.is_none() {
+            let message = Message::try_deserialize(&mut &after[..])
+                .map_err(|_| SdkError::InvalidAccountData("simulated message"))?;
+            preview.messages.push(MessagePreview {
+                address: account.address,
+                sender: message.sender,
+                recipient: message.recipient,
+                content: message.content,
+                priority: message.priority,
+                client_message_id: message.client_message_id,
+                deliver_at: queued.iter().find(|event| event.message == account.address).map(|event| event.deliver_at),
+            });
+        } else if after.starts_with(&UserStats::DISCRIMINATOR) {
+            let stats_after = UserStats::try_deserialize(&mut &after[..])
+                .map_err(|_| SdkError::InvalidAccountData("simulated user stats"))?;
+            let today = stats_after.last_active_day * SECS_PER_DAY;
+            let days_before = match account.before {
+                Some(mut before) => UserStats::try_deserialize(&mut before)
+                    .map(|stats| streak(&stats, today))
+                    .map_err(|_| SdkError::InvalidAccountData("user stats"))?,
+                None => 0,
+            };
+            preview.streaks.push(StreakPreview {
+                user: stats_after.user,
+                days_before,
+                days_after: streak(&stats_after, today),
+                messages_sent: stats_after.messages_sent,
+            });
+        } else {
+            preview.other_changes.push(account.address);
+        }
+    }
+
+    Ok(preview)
+}
+
+/// Consecutive active days ending today, or yesterday if the user hasn't been active yet today.
+pub fn streak(stats: &UserStats, now: i64) -> u32 {
+    let today = now.div_euclid(SECS_PER_DAY);
+    let last = if stats.was_active(today) { today } else { today - 1 };
+    (0..UserStats::HEATMAP_DAYS).take_while(|offset| stats.was_active(last - offset)).count() as u32
+}
+
+/// `MessageQueued` events logged by the program itself, not by programs it invoked.
+fn queued_messages(logs: &[String]) -> Vec<MessageQueued> {
+    let program_id = PROGRAM_ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        if let Some(rest) = line.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => stack.push(program),
+                (Some(program), Some("success" | "failed:")) if stack.last() == Some(&program) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+
+        let Some(encoded) = line.strip_prefix(PROGRAM_DATA) else { continue };
+        if stack.last() != Some(&program_id.as_str()) {
+            continue;
+        }
+        let Ok(data) = base64::decode(encoded) else { continue };
+        if data.starts_with(&MessageQueued::DISCRIMINATOR) {
+            if let Ok(event) = MessageQueued::deserialize(&mut &data[8..]) {
+                events.push(event);
+            }
+        }
+    }
+
+    events
+}
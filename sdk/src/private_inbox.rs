@@ -0,0 +1,79 @@
+//! Salted inbox index entries, so a private inbox index doesn't reveal who messaged its owner.
+//!
+//! The sender picks a fresh X25519 key for every entry and salts the message address with the
+//! secret it shares with the recipient's published encryption key. Only the recipient can
+//! recompute that secret, so only they can tell which message an entry stands for.
+//!
+//! Only the index owner or their delegate may write private entries, so senders hand the entry
+//! to the recipient's relay rather than submitting it themselves.
+
+use anchor_lang::prelude::Pubkey;
+use curve25519_dalek::constants::X25519_BASEPOINT;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha256};
+use solana_messaging::{InboxIndex, IndexedMessage, SaltedIndexEntry};
+
+use crate::PROGRAM_ID;
+
+const DIGEST_TAG: &[u8] = b"gmchat-inbox-index";
+
+pub fn inbox_index_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"inbox_index", owner.as_ref()], &PROGRAM_ID).0
+}
+
+/// Like `inbox_index_address`, for an owner within a white-label namespace.
+pub fn namespaced_inbox_index_address(namespace: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"inbox_index", namespace.as_ref(), owner.as_ref()], &PROGRAM_ID).0
+}
+
+/// Entry for `message` in `recipient_key`'s private index. `ephemeral_secret` must be random
+/// and used for this entry only.
+pub fn salted_entry(ephemeral_secret: &[u8; 32], recipient_key: &[u8; 32], message: &Pubkey) -> SaltedIndexEntry {
+    let scalar = clamp(ephemeral_secret);
+    let shared = MontgomeryPoint(*recipient_key) * scalar;
+    SaltedIndexEntry {
+        ephemeral_key: (X25519_BASEPOINT * scalar).to_bytes(),
+        digest: digest(&shared.to_bytes(), message),
+    }
+}
+
+/// The messages in `index`, oldest first. Entries of a private index are matched against
+/// `candidates` using the owner's encryption secret; entries matching none of them, such as
+/// junk relayed on a sender's behalf, are skipped.
+pub fn indexed_messages(index: &InboxIndex, owner_secret: &[u8; 32], candidates: &[Pubkey]) -> Vec<Pubkey> {
+    let scalar = clamp(owner_secret);
+    let oldest = if index.entries.len() < InboxIndex::CAPACITY {
+        0
+    } else {
+        (index.next_sequence % InboxIndex::CAPACITY as u64) as usize
+    };
+
+    (0..index.entries.len())
+        .map(|offset| &index.entries[(oldest + offset) % index.entries.len()])
+        .filter_map(|entry| match entry {
+            IndexedMessage::Public(message) => Some(*message),
+            IndexedMessage::Private(salted) => {
+                let shared = (MontgomeryPoint(salted.ephemeral_key) * scalar).to_bytes();
+                candidates.iter().find(|message| digest(&shared, message) == salted.digest).copied()
+            }
+        })
+        .collect()
+}
+
+fn digest(shared_secret: &[u8; 32], message: &Pubkey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(DIGEST_TAG);
+    hasher.update(shared_secret);
+    hasher.update(message.as_ref());
+    hasher.finalize().into()
+}
+
+/// X25519 secret key clamping (RFC 7748)
+fn clamp(secret: &[u8; 32]) -> Scalar {
+    let mut bits = *secret;
+    bits[0] &= 248;
+    bits[31] &= 127;
+    bits[31] |= 64;
+    Scalar::from_bits(bits)
+}
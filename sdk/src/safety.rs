@@ -0,0 +1,74 @@
+//! Safety numbers for out-of-band key verification.
+//!
+//! Both parties see the same number for a conversation as long as neither registered key
+//! changes. Once both confirm it with `confirm_safety_number`, the conversation settings are
+//! marked verified; `check_safety_number` clears that and emits `SafetyNumberChanged` after a
+//! rotation, which clients show as a system message.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use solana_messaging::{ConversationSettings, EncryptionKey};
+
+use crate::{Result, SdkError, PROGRAM_ID};
+
+/// Groups of five digits shown to the user
+const DISPLAY_GROUPS: usize = 12;
+
+/// Settings account for the conversation between `a` and `b`, in either order.
+pub fn conversation_settings_address(a: &Pubkey, b: &Pubkey) -> (Pubkey, u8) {
+    let (first, second) = ordered(a, b);
+    Pubkey::find_program_address(&[b"conversation_settings", first.as_ref(), second.as_ref()], &PROGRAM_ID)
+}
+
+/// Like `conversation_settings_address`, for a conversation within a white-label namespace.
+pub fn namespaced_conversation_settings_address(namespace: &Pubkey, a: &Pubkey, b: &Pubkey) -> (Pubkey, u8) {
+    let (first, second) = ordered(a, b);
+    Pubkey::find_program_address(
+        &[b"conversation_settings", namespace.as_ref(), first.as_ref(), second.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Participants in the order `open_conversation_settings` expects.
+pub fn ordered<'a>(a: &'a Pubkey, b: &'a Pubkey) -> (&'a Pubkey, &'a Pubkey) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Safety number for two registry entries, given as raw account data in either order.
+pub fn safety_number(key_data: &[u8], peer_key_data: &[u8]) -> Result<[u8; 32]> {
+    let key = decode_key(key_data)?;
+    let peer_key = decode_key(peer_key_data)?;
+    if key.owner == peer_key.owner {
+        return Err(SdkError::AccountMismatch("both encryption keys belong to the same wallet"));
+    }
+    Ok(if key.owner < peer_key.owner {
+        ConversationSettings::safety_number(&key, &peer_key)
+    } else {
+        ConversationSettings::safety_number(&peer_key, &key)
+    })
+}
+
+/// Renders a safety number as twelve groups of five digits, e.g. `01234 56789 ...`.
+pub fn display(safety_number: &[u8; 32]) -> String {
+    safety_number
+        .chunks(2)
+        .take(DISPLAY_GROUPS)
+        .map(|chunk| format!("{:05}", u16::from_le_bytes([chunk[0], chunk[1]])))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the settings account is verified for the keys currently registered.
+pub fn is_verified(settings_data: &[u8], key_data: &[u8], peer_key_data: &[u8]) -> Result<bool> {
+    let settings = ConversationSettings::try_deserialize(&mut &settings_data[..])
+        .map_err(|_| SdkError::InvalidAccountData("conversation settings"))?;
+    Ok(settings.verified && settings.safety_number == safety_number(key_data, peer_key_data)?)
+}
+
+fn decode_key(data: &[u8]) -> Result<EncryptionKey> {
+    EncryptionKey::try_deserialize(&mut &data[..]).map_err(|_| SdkError::InvalidAccountData("encryption key entry"))
+}
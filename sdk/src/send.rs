@@ -0,0 +1,404 @@
+//! Reliable transaction landing for message sends.
+//!
+//! `Sender` drives any RPC client through the `Transport` trait: it signs against a fresh
+//! blockhash, rebroadcasts while polling signature status, re-signs once the blockhash expires
+//! and every earlier attempt has dropped, and backs off between attempts. Every send is keyed by
+//! a caller-chosen client nonce, and all signatures produced for a nonce are remembered, so a
+//! retry never lands a second copy after an earlier attempt that only looked lost.
+//!
+//! Those signatures only live as long as the `Sender`. Each send also names the account it
+//! creates, which is derived from its client message ID, so an attempt made before a restart
+//! is still found by that account existing.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+
+use crate::{Result, SdkError};
+
+/// How far a transaction must have progressed for a send to count as landed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+/// Status of a signature as reported by the cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Landed(Commitment),
+    Failed(String),
+}
+
+/// The RPC calls the send pipeline needs, implemented over whichever client the integrator uses.
+pub trait Transport {
+    /// Latest blockhash and the last block height at which it is valid.
+    fn latest_blockhash(&mut self) -> std::result::Result<(Hash, u64), String>;
+    fn block_height(&mut self) -> std::result::Result<u64, String>;
+    /// Submits a serialized transaction without preflight.
+    fn send_transaction(&mut self, wire_transaction: &[u8]) -> std::result::Result<(), String>;
+    /// `None` while the cluster has not seen the signature.
+    fn signature_status(&mut self, signature: &[u8; 64]) -> std::result::Result<Option<SignatureStatus>, String>;
+    /// Whether an account exists at `address` at the transport's commitment.
+    fn account_exists(&mut self, address: &Pubkey) -> std::result::Result<bool, String>;
+}
+
+/// A transaction signed against a specific blockhash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub signature: [u8; 64],
+    pub wire_transaction: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Blockhashes to try before giving up
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Delay between status polls; the transaction is rebroadcast on every poll
+    pub poll_interval: Duration,
+    pub commitment: Commitment,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            poll_interval: Duration::from_millis(500),
+            commitment: Commitment::Confirmed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendOutcome {
+    /// `None` when only the created account shows the send landed, as after a restart
+    pub signature: Option<[u8; 64]>,
+    pub commitment: Commitment,
+    /// Blockhashes signed against for this client nonce, including earlier calls
+    pub attempts: u32,
+}
+
+pub struct Sender<T: Transport> {
+    transport: T,
+    policy: RetryPolicy,
+    /// Every signature produced per client nonce
+    sent: HashMap<u64, Vec<[u8; 64]>>,
+}
+
+impl<T: Transport> Sender<T> {
+    pub fn new(transport: T, policy: RetryPolicy) -> Self {
+        Self { transport, policy, sent: HashMap::new() }
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Lands the transaction produced by `sign` for `client_nonce`. `creates` is the account the
+    /// transaction creates. `sign` is called once per blockhash and must build the same
+    /// instructions every time.
+    pub fn send<F>(&mut self, client_nonce: u64, creates: &Pubkey, mut sign: F) -> Result<SendOutcome>
+    where
+        F: FnMut(Hash) -> SignedTransaction,
+    {
+        // A previous call for this nonce may have landed after the caller gave up on it
+        if let Some(outcome) = self.settle(client_nonce, creates)? {
+            return Ok(outcome);
+        }
+
+        let mut backoff = self.policy.initial_backoff;
+        for _ in 0..self.policy.max_attempts {
+            let (blockhash, last_valid_height) = self.transport.latest_blockhash().map_err(SdkError::Transport)?;
+            let transaction = sign(blockhash);
+            self.sent.entry(client_nonce).or_default().push(transaction.signature);
+
+            loop {
+                // Send errors are transient more often than not; the status poll decides
+                let _ = self.transport.send_transaction(&transaction.wire_transaction);
+                thread::sleep(self.policy.poll_interval);
+
+                if let Progress::Landed(outcome) = self.progress(client_nonce, creates)? {
+                    return Ok(outcome);
+                }
+                if self.transport.block_height().map_err(SdkError::Transport)? > last_valid_height {
+                    break;
+                }
+            }
+
+            // An expired attempt the cluster has already seen can still land, so only sign again
+            // once it has dropped
+            if let Some(outcome) = self.settle(client_nonce, creates)? {
+                return Ok(outcome);
+            }
+
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+        }
+
+        Err(SdkError::RetriesExhausted(self.policy.max_attempts))
+    }
+
+    /// Forgets the signatures kept for a nonce once the caller no longer needs idempotency.
+    pub fn forget(&mut self, client_nonce: u64) {
+        self.sent.remove(&client_nonce);
+    }
+
+    /// Polls until no attempt for the nonce is in flight, returning the one that landed if any.
+    fn settle(&mut self, client_nonce: u64, creates: &Pubkey) -> Result<Option<SendOutcome>> {
+        loop {
+            match self.progress(client_nonce, creates)? {
+                Progress::Landed(outcome) => return Ok(Some(outcome)),
+                Progress::InFlight => thread::sleep(self.policy.poll_interval),
+                Progress::Dropped => return Ok(None),
+            }
+        }
+    }
+
+    fn progress(&mut self, client_nonce: u64, creates: &Pubkey) -> Result<Progress> {
+        let signatures = self.sent.get(&client_nonce).map_or(&[][..], Vec::as_slice);
+        let attempts = signatures.len() as u32;
+
+        let mut in_flight = false;
+        for signature in signatures {
+            match self.transport.signature_status(signature).map_err(SdkError::Transport)? {
+                Some(SignatureStatus::Landed(commitment)) if commitment >= self.policy.commitment => {
+                    return Ok(Progress::Landed(SendOutcome { signature: Some(*signature), commitment, attempts }));
+                }
+                Some(SignatureStatus::Landed(_)) => in_flight = true,
+                Some(SignatureStatus::Failed(error)) => return Err(SdkError::TransactionFailed(error)),
+                None => {}
+            }
+        }
+
+        if self.transport.account_exists(creates).map_err(SdkError::Transport)? {
+            return Ok(Progress::Landed(SendOutcome { signature: None, commitment: self.policy.commitment, attempts }));
+        }
+        Ok(if in_flight { Progress::InFlight } else { Progress::Dropped })
+    }
+}
+
+enum Progress {
+    Landed(SendOutcome),
+    /// Seen by the cluster below the required commitment
+    InFlight,
+    /// Nothing the cluster has seen, so a new attempt can't double-send
+    Dropped,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Scripted cluster. Blockhashes stay valid for `VALID_FOR` blocks and every status poll
+    /// advances the height by `height_step`.
+    #[derive(Default)]
+    pub(crate) struct MockTransport {
+        pub height: u64,
+        pub height_step: u64,
+        /// Statuses a signature reports, one per poll; the last one repeats
+        pub statuses: HashMap<[u8; 64], Vec<SignatureStatus>>,
+        pub accounts: Vec<Pubkey>,
+        /// Created by the first broadcast, as when an attempt executes but fails afterwards
+        pub created_on_send: Option<Pubkey>,
+        /// Every RPC call fails
+        pub offline: bool,
+        pub broadcasts: u32,
+    }
+
+    const VALID_FOR: u64 = 10;
+
+    impl MockTransport {
+        fn online(&self) -> std::result::Result<(), String> {
+            if self.offline {
+                Err("connection refused".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn latest_blockhash(&mut self) -> std::result::Result<(Hash, u64), String> {
+            self.online()?;
+            Ok((Hash::new_from_array([self.height as u8; 32]), self.height + VALID_FOR))
+        }
+
+        fn block_height(&mut self) -> std::result::Result<u64, String> {
+            self.online()?;
+            self.height += self.height_step;
+            Ok(self.height)
+        }
+
+        fn send_transaction(&mut self, _wire_transaction: &[u8]) -> std::result::Result<(), String> {
+            self.online()?;
+            self.broadcasts += 1;
+            self.accounts.extend(self.created_on_send.take());
+            Ok(())
+        }
+
+        fn signature_status(&mut self, signature: &[u8; 64]) -> std::result::Result<Option<SignatureStatus>, String> {
+            self.online()?;
+            Ok(self.statuses.get_mut(signature).map(|statuses| {
+                if statuses.len() > 1 {
+                    statuses.remove(0)
+                } else {
+                    statuses[0].clone()
+                }
+            }))
+        }
+
+        fn account_exists(&mut self, address: &Pubkey) -> std::result::Result<bool, String> {
+            self.online()?;
+            Ok(self.accounts.contains(address))
+        }
+    }
+
+    pub(crate) fn instant_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            poll_interval: Duration::ZERO,
+            commitment: Commitment::Confirmed,
+        }
+    }
+
+    /// The n-th transaction signed by a test carries signature `[n; 64]`.
+    pub(crate) fn signer(count: &Cell<u8>) -> impl FnMut(Hash) -> SignedTransaction + '_ {
+        move |_| {
+            let signature = [count.get(); 64];
+            count.set(count.get() + 1);
+            SignedTransaction { signature, wire_transaction: signature.to_vec() }
+        }
+    }
+
+    fn confirmed() -> SignatureStatus {
+        SignatureStatus::Landed(Commitment::Confirmed)
+    }
+
+    #[test]
+    fn lands_on_the_first_attempt() {
+        let mut transport = MockTransport::default();
+        transport.statuses.insert([0; 64], vec![confirmed()]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let outcome = sender.send(1, &Pubkey::new_unique(), signer(&signed)).unwrap();
+
+        assert_eq!(outcome, SendOutcome { signature: Some([0; 64]), commitment: Commitment::Confirmed, attempts: 1 });
+        assert_eq!(signed.get(), 1);
+    }
+
+    #[test]
+    fn signs_again_once_an_expired_attempt_has_dropped() {
+        let mut transport = MockTransport { height_step: VALID_FOR + 1, ..Default::default() };
+        transport.statuses.insert([1; 64], vec![confirmed()]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let outcome = sender.send(1, &Pubkey::new_unique(), signer(&signed)).unwrap();
+
+        assert_eq!(outcome.signature, Some([1; 64]));
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(signed.get(), 2);
+    }
+
+    #[test]
+    fn waits_for_an_expired_attempt_still_in_flight() {
+        let mut transport = MockTransport { height_step: VALID_FOR + 1, ..Default::default() };
+        let processed = SignatureStatus::Landed(Commitment::Processed);
+        transport.statuses.insert([0; 64], vec![processed.clone(), processed, confirmed()]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let outcome = sender.send(1, &Pubkey::new_unique(), signer(&signed)).unwrap();
+
+        assert_eq!(outcome.signature, Some([0; 64]));
+        assert_eq!(signed.get(), 1, "re-signing while the first attempt could land risks a double send");
+    }
+
+    #[test]
+    fn gives_up_after_the_attempt_limit() {
+        let transport = MockTransport { height_step: VALID_FOR + 1, ..Default::default() };
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let result = sender.send(1, &Pubkey::new_unique(), signer(&signed));
+
+        assert_eq!(result, Err(SdkError::RetriesExhausted(3)));
+        assert_eq!(signed.get(), 3);
+        assert_eq!(sender.transport_mut().broadcasts, 3);
+    }
+
+    #[test]
+    fn rebroadcasts_until_the_blockhash_expires() {
+        let transport = MockTransport { height_step: 3, ..Default::default() };
+        let mut sender = Sender::new(transport, instant_policy(1));
+
+        let signed = Cell::new(0);
+        let result = sender.send(1, &Pubkey::new_unique(), signer(&signed));
+
+        assert_eq!(result, Err(SdkError::RetriesExhausted(1)));
+        // Heights 3, 6, 9 are within the blockhash's validity and 12 is past it
+        assert_eq!(sender.transport_mut().broadcasts, 4);
+    }
+
+    #[test]
+    fn a_repeated_nonce_returns_the_earlier_landing() {
+        let mut transport = MockTransport::default();
+        transport.statuses.insert([0; 64], vec![confirmed()]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+        let creates = Pubkey::new_unique();
+
+        let signed = Cell::new(0);
+        sender.send(1, &creates, signer(&signed)).unwrap();
+        let outcome = sender.send(1, &creates, signer(&signed)).unwrap();
+
+        assert_eq!(outcome.signature, Some([0; 64]));
+        assert_eq!(signed.get(), 1);
+    }
+
+    #[test]
+    fn an_existing_account_counts_as_landed_without_signing() {
+        let creates = Pubkey::new_unique();
+        let transport = MockTransport { accounts: vec![creates], ..Default::default() };
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let outcome = sender.send(1, &creates, |_| panic!("already landed before a restart")).unwrap();
+
+        assert_eq!(outcome, SendOutcome { signature: None, commitment: Commitment::Confirmed, attempts: 0 });
+    }
+
+    #[test]
+    fn reports_a_failed_transaction() {
+        let mut transport = MockTransport::default();
+        transport.statuses.insert([0; 64], vec![SignatureStatus::Failed("custom program error: 0x1".to_string())]);
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let result = sender.send(1, &Pubkey::new_unique(), signer(&signed));
+
+        assert_eq!(result, Err(SdkError::TransactionFailed("custom program error: 0x1".to_string())));
+    }
+
+    #[test]
+    fn transport_errors_surface() {
+        let transport = MockTransport { offline: true, ..Default::default() };
+        let mut sender = Sender::new(transport, instant_policy(3));
+
+        let signed = Cell::new(0);
+        let result = sender.send(1, &Pubkey::new_unique(), signer(&signed));
+
+        assert_eq!(result, Err(SdkError::Transport("connection refused".to_string())));
+    }
+}
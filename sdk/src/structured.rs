@@ -0,0 +1,61 @@
+//! Decoding structured messages against the schemas a client has chosen to support.
+//!
+//! A client registers each schema it can render together with the layout document it was
+//! built against; the document must hash to the schema's on-chain `layout_hash`, so a schema
+//! can't be swapped out from under the renderer. Messages whose schema isn't registered are
+//! ignored rather than shown as raw bytes.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use sha2::{Digest, Sha256};
+use solana_messaging::{Schema, SchemaFormat, StructuredMessage};
+
+use crate::{Result, SdkError};
+
+/// What a client should do with a structured message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded<'a> {
+    Json(serde_json::Value),
+    /// Borsh payload of a known schema, left to the renderer registered for it
+    Borsh { schema: Pubkey, data: &'a [u8] },
+    /// Unknown schema, or a payload that doesn't parse; skip the message
+    Ignore,
+}
+
+/// SHA-256 of a layout document, as stored in `Schema::layout_hash`.
+pub fn layout_hash(layout: &[u8]) -> [u8; 32] {
+    Sha256::digest(layout).into()
+}
+
+/// Schemas this client knows how to render, by schema account address.
+#[derive(Debug, Default)]
+pub struct KnownSchemas {
+    formats: HashMap<Pubkey, SchemaFormat>,
+}
+
+impl KnownSchemas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a schema after checking `layout` is the document it was registered with.
+    pub fn register(&mut self, address: Pubkey, schema: &Schema, layout: &[u8]) -> Result<()> {
+        if layout_hash(layout) != schema.layout_hash {
+            return Err(SdkError::AccountMismatch("layout does not match the schema's hash"));
+        }
+        self.formats.insert(address, schema.format);
+        Ok(())
+    }
+
+    pub fn decode<'a>(&self, message: &'a StructuredMessage) -> Decoded<'a> {
+        match self.formats.get(&message.schema) {
+            Some(SchemaFormat::Json) => match serde_json::from_slice(&message.payload) {
+                Ok(value) => Decoded::Json(value),
+                Err(_) => Decoded::Ignore,
+            },
+            Some(SchemaFormat::Borsh) => Decoded::Borsh { schema: message.schema, data: &message.payload },
+            None => Decoded::Ignore,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Picking how to pay a tip so it arrives in the mint the recipient asked for on their profile,
+//! and the accounts for a patron receipt on large tips.
+
+use anchor_lang::prelude::Pubkey;
+use solana_messaging::{PatronConfig, Profile, BUBBLEGUM_PROGRAM_ID};
+
+use crate::PROGRAM_ID;
+
+/// How a tip paid in one mint reaches the recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipRoute {
+    /// Plain transfer; the recipient takes the paying mint
+    Direct { mint: Pubkey },
+    /// `swap_and_tip` from the paying mint into the recipient's preferred one
+    Swap { from: Pubkey, to: Pubkey },
+}
+
+pub fn profile_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"profile", owner.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn patron_config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"patron_config"], &PROGRAM_ID).0
+}
+
+/// Bubblegum's tree config PDA for `merkle_tree`, passed as `tree_authority` with a patron receipt.
+pub fn patron_tree_authority(merkle_tree: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[merkle_tree.as_ref()], &BUBBLEGUM_PROGRAM_ID).0
+}
+
+/// Whether a tip of `amount_lamports` qualifies for a patron receipt under `patron`.
+pub fn earns_patron_receipt(patron: &PatronConfig, amount_lamports: u64) -> bool {
+    patron.threshold_lamports > 0 && amount_lamports > patron.threshold_lamports
+}
+
+/// Route for a tip paid in `paying_mint` to a recipient with `profile`, `None` if they have
+/// no profile account.
+pub fn tip_route(paying_mint: Pubkey, profile: Option<&Profile>) -> TipRoute {
+    match profile.map(|profile| profile.preferred_tip_mint) {
+        Some(preferred) if preferred != Pubkey::default() && preferred != paying_mint => {
+            TipRoute::Swap { from: paying_mint, to: preferred }
+        }
+        _ => TipRoute::Direct { mint: paying_mint },
+    }
+}
@@ -17,6 +17,7 @@ export interface EncryptedMessage {
 class EncryptionService {
   private static readonly STORAGE_KEY = 'gmchat_encryption_keys';
   private static readonly KEY_EXPIRY = 24 * 60 * 60 * 1000; // 24 hours
+  private static readonly MESSAGE_KEY_SALT = 'gmchat-message-key';
 
   /**
    * Generate a shared encryption key for two wallet addresses
@@ -27,6 +28,42 @@ class EncryptionService {
     return CryptoJS.SHA256(combined).toString();
   }
 
+  /**
+   * Derive the key for a single message from the conversation's shared key, with HKDF-SHA256
+   * over the message's account address. Disclosing this key reveals that message only.
+   */
+  static deriveMessageKey(sharedKey: string, messageId: string): string {
+    const prk = CryptoJS.HmacSHA256(CryptoJS.enc.Hex.parse(sharedKey), this.MESSAGE_KEY_SALT);
+    const info = CryptoJS.enc.Utf8.parse(messageId).concat(CryptoJS.enc.Hex.parse('01'));
+    return CryptoJS.HmacSHA256(info, prk).toString();
+  }
+
+  /**
+   * Encrypt on-chain message content under its per-message key. The output is the CryptoJS
+   * passphrase format the preview route decrypts once the key is disclosed.
+   */
+  static encryptWithMessageKey(content: string, wallet1: string, wallet2: string, messageId: string): string {
+    const messageKey = this.deriveMessageKey(this.generateSharedKey(wallet1, wallet2), messageId);
+    return CryptoJS.AES.encrypt(content, messageKey).toString();
+  }
+
+  /**
+   * Decrypt on-chain message content encrypted with `encryptWithMessageKey`
+   */
+  static decryptWithMessageKey(encryptedContent: string, wallet1: string, wallet2: string, messageId: string): string {
+    const messageKey = this.deriveMessageKey(this.generateSharedKey(wallet1, wallet2), messageId);
+    return CryptoJS.AES.decrypt(encryptedContent, messageKey).toString(CryptoJS.enc.Utf8);
+  }
+
+  /**
+   * The key to publish with `disclose_message`: the per-message key, never the shared one,
+   * which would open every other message in the conversation
+   */
+  static disclosureKey(wallet1: string, wallet2: string, messageId: string): Uint8Array {
+    const messageKey = this.deriveMessageKey(this.generateSharedKey(wallet1, wallet2), messageId);
+    return new TextEncoder().encode(messageKey);
+  }
+
   /**
    * Generate a random encryption key and IV
    */
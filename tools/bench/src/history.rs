@@ -0,0 +1,147 @@
+//! Benchmark history: one `Report` per line, oldest first.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::Report;
+
+pub fn append(path: &Path, report: &Report) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(report).map_err(io::Error::other)?;
+    writeln!(file, "{line}")
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Report>> {
+    let mut reports = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        reports.push(serde_json::from_str(&line).map_err(io::Error::other)?);
+    }
+    Ok(reports)
+}
+
+/// A scenario whose mean compute units grew by more than the allowed tolerance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regression {
+    pub scenario: String,
+    pub previous_units: u64,
+    pub latest_units: u64,
+}
+
+impl Regression {
+    pub fn percent(&self) -> f64 {
+        (self.latest_units as f64 - self.previous_units as f64) * 100.0
+            / self.previous_units.max(1) as f64
+    }
+}
+
+/// Compares the compute units of scenarios present in both reports. Wall time is too noisy
+/// between machines to gate on, so it is recorded but never flagged.
+pub fn compare(previous: &Report, latest: &Report, tolerance_percent: f64) -> Vec<Regression> {
+    latest
+        .measurements
+        .iter()
+        .filter_map(|measurement| {
+            let before = previous
+                .measurements
+                .iter()
+                .find(|m| m.scenario == measurement.scenario)?;
+            let regression = Regression {
+                scenario: measurement.scenario.clone(),
+                previous_units: before.units_mean,
+                latest_units: measurement.units_mean,
+            };
+            (regression.percent() > tolerance_percent).then_some(regression)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Measurement;
+
+    fn report(label: &str, units: &[(&str, u64)]) -> Report {
+        Report {
+            label: label.to_string(),
+            timestamp: 0,
+            measurements: units
+                .iter()
+                .map(|&(scenario, units_mean)| Measurement {
+                    scenario: scenario.to_string(),
+                    units_mean,
+                    units_max: units_mean,
+                    wall_micros_median: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn flags_growth_beyond_the_tolerance() {
+        let previous = report(
+            "a",
+            &[("send_message/1", 10_000), ("receive_message/1", 2_000)],
+        );
+        let latest = report(
+            "b",
+            &[("send_message/1", 10_300), ("receive_message/1", 2_020)],
+        );
+
+        let regressions = compare(&previous, &latest, 2.0);
+
+        assert_eq!(
+            regressions,
+            [Regression {
+                scenario: "send_message/1".to_string(),
+                previous_units: 10_000,
+                latest_units: 10_300,
+            }]
+        );
+        assert_eq!(regressions[0].percent(), 3.0);
+    }
+
+    #[test]
+    fn growth_at_the_tolerance_passes() {
+        let previous = report("a", &[("send_message/1", 10_000)]);
+        let latest = report("b", &[("send_message/1", 10_200)]);
+
+        assert!(compare(&previous, &latest, 2.0).is_empty());
+    }
+
+    #[test]
+    fn ignores_improvements_and_new_scenarios() {
+        let previous = report("a", &[("send_message/1", 10_000)]);
+        let latest = report(
+            "b",
+            &[("send_message/1", 9_000), ("post_room_message/1", 50_000)],
+        );
+
+        assert!(compare(&previous, &latest, 0.0).is_empty());
+    }
+
+    #[test]
+    fn growth_from_zero_is_flagged() {
+        let previous = report("a", &[("send_message/1", 0)]);
+        let latest = report("b", &[("send_message/1", 1)]);
+
+        assert_eq!(compare(&previous, &latest, 2.0).len(), 1);
+    }
+
+    #[test]
+    fn round_trips_through_the_history_file() {
+        let path = std::env::temp_dir().join(format!("gmchat-bench-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let reports = [report("a", &[("send_message/1", 10_000)]), report("b", &[])];
+        for report in &reports {
+            append(&path, report).unwrap();
+        }
+
+        assert_eq!(load(&path).unwrap(), reports);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,585 @@
+//! Benchmarks for the instructions every conversation hits: message sends in each mode, marking
+//! a message read (`receive_message`), reactions and group sends, each across content sizes.
+//!
+//! Sends always pass the treasury, so with the fee switch on they measure the paid path. Urgent
+//! sends pay the recipient's urgent fee, and pass holders are measured up to the longer content
+//! their pass allows. Reactions are structured messages under a reaction schema the suite
+//! registers, and group sends are room posts, both by the room's creator and by other members.
+//!
+//! The suite is driven through `Runner`, which executes transactions against a deployed copy
+//! of the program and reports the compute units each one consumed; `rpc::RpcRunner` does so
+//! through a validator's JSON-RPC endpoint. Wall time is measured around `Runner::execute`.
+//! Reports are appended to a JSON lines history, and `history::compare` flags scenarios whose
+//! compute units grew between two runs.
+
+pub mod history;
+pub mod rpc;
+
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use gmchat_sdk::optimistic::message_address;
+use serde::{Deserialize, Serialize};
+use solana_messaging::{
+    accounts, instruction, MessagePriority, PriorityPolicy, RoomMessage, SchemaFormat,
+    ID as PROGRAM_ID,
+};
+
+/// Metaplex Token Metadata (metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s)
+const TOKEN_METADATA_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    11, 112, 101, 177, 227, 209, 124, 69, 56, 157, 82, 127, 107, 4, 195, 205, 88, 184, 108, 115,
+    26, 160, 253, 181, 73, 182, 209, 188, 3, 248, 41, 70,
+]);
+
+/// Content sizes each scenario is measured at, clipped to what the instruction accepts
+pub const CONTENT_SIZES: [usize; 5] = [1, 140, 280, 500, 1000];
+
+/// What the recipients of urgent sends charge for them
+const URGENT_FEE_LAMPORTS: u64 = 1_000_000;
+
+/// Emoji the reaction scenario sends
+const REACTION: &str = "\u{1f44d}";
+
+/// Executes transactions against the program under test. The program's config, treasury and
+/// pass offer must already be initialized, and the payer must be able to afford a pass.
+pub trait Runner {
+    /// Fee payer and signer of every transaction; it sends the messages and creates the rooms
+    fn payer(&self) -> Pubkey;
+    /// A fresh keypair the runner signs with whenever its key is marked as a signer.
+    fn new_signer(&mut self) -> Pubkey;
+    /// Executes `instructions` in one transaction and returns the compute units it consumed.
+    fn execute(&mut self, instructions: &[Instruction]) -> Result<u64, String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScenarioKind {
+    /// `send_message` without a client message ID, at its original address
+    Send,
+    /// `send_message` keyed by a client message ID
+    SendWithClientId,
+    /// Urgent `send_message` to a recipient who charges an urgent fee
+    SendUrgent,
+    /// `send_message` from the holder of a pass minted in setup
+    SendWithPass,
+    /// `receive_message` on a message sent in setup
+    MarkRead,
+    /// `send_structured_message` reacting to a message sent in setup
+    Reaction,
+    /// `post_room_message` by the creator of a room created in setup
+    RoomPost,
+    /// `post_room_message` to the same room by another member
+    GroupSend,
+}
+
+impl ScenarioKind {
+    pub const ALL: [ScenarioKind; 8] = [
+        Self::Send,
+        Self::SendWithClientId,
+        Self::SendUrgent,
+        Self::SendWithPass,
+        Self::MarkRead,
+        Self::Reaction,
+        Self::RoomPost,
+        Self::GroupSend,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Send => "send_message",
+            Self::SendWithClientId => "send_message/client_id",
+            Self::SendUrgent => "send_message/urgent",
+            Self::SendWithPass => "send_message/pass",
+            Self::MarkRead => "receive_message",
+            Self::Reaction => "send_structured_message/reaction",
+            Self::RoomPost => "post_room_message",
+            Self::GroupSend => "post_room_message/member",
+        }
+    }
+
+    /// `CONTENT_SIZES` clipped to what the instruction accepts
+    fn content_sizes(self) -> Vec<usize> {
+        let max = match self {
+            Self::Reaction => return vec![REACTION.len()],
+            Self::RoomPost | Self::GroupSend => RoomMessage::MAX_CONTENT_LEN,
+            Self::SendWithPass => solana_messaging::Message::MAX_PASS_CONTENT_LEN,
+            _ => solana_messaging::Message::MAX_CONTENT_LEN,
+        };
+        let mut sizes: Vec<usize> = CONTENT_SIZES.iter().map(|&size| size.min(max)).collect();
+        sizes.dedup();
+        sizes
+    }
+}
+
+/// Accounts the scenarios share, created once per run.
+struct Setup {
+    room_id: u64,
+    /// Token account holding the payer's pass, and the pass's record
+    pass: (Pubkey, Pubkey),
+    reaction_schema: Pubkey,
+    /// Message the reactions are to, and its recipient
+    reacted_to: (Pubkey, Pubkey),
+}
+
+/// Averages for one scenario at one content size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// `<instruction>/<content bytes>`
+    pub scenario: String,
+    pub units_mean: u64,
+    pub units_max: u64,
+    pub wall_micros_median: u64,
+}
+
+/// One run of the suite, as stored in the history file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// Commit or build the run measured
+    pub label: String,
+    pub timestamp: i64,
+    pub measurements: Vec<Measurement>,
+}
+
+/// Runs every scenario at every content size `iterations` times.
+pub fn run_suite<R: Runner>(
+    runner: &mut R,
+    iterations: u32,
+    label: &str,
+    timestamp: i64,
+) -> Result<Report, String> {
+    let setup = set_up(runner, timestamp)?;
+
+    let mut measurements = Vec::new();
+    let mut room_posts = 0u64;
+    let mut reactions = 0u64;
+    for kind in ScenarioKind::ALL {
+        for size in kind.content_sizes() {
+            let content = "m".repeat(size);
+            let mut units = Vec::new();
+            let mut walls = Vec::new();
+            for iteration in 0..iterations {
+                let payer = runner.payer();
+                let measured = match kind {
+                    ScenarioKind::Send => send(runner, &content, None),
+                    ScenarioKind::SendWithClientId => send(
+                        runner,
+                        &content,
+                        Some(client_id(timestamp, size, iteration)),
+                    ),
+                    ScenarioKind::SendUrgent => {
+                        let recipient = runner.new_signer();
+                        let rent = Rent::default().minimum_balance(PriorityPolicy::LEN);
+                        runner.execute(&[
+                            system_instruction::transfer(&payer, &recipient, rent),
+                            set_priority_policy(&recipient, URGENT_FEE_LAMPORTS),
+                        ])?;
+                        let options = SendOptions {
+                            urgent: true,
+                            ..SendOptions::default()
+                        };
+                        vec![send_message(runner, recipient, &content, None, options)]
+                    }
+                    ScenarioKind::SendWithPass => {
+                        let recipient = runner.new_signer();
+                        let options = SendOptions {
+                            pass: Some(setup.pass),
+                            ..SendOptions::default()
+                        };
+                        vec![send_message(runner, recipient, &content, None, options)]
+                    }
+                    ScenarioKind::MarkRead => {
+                        let id = client_id(timestamp, size, iteration);
+                        let recipient = runner.new_signer();
+                        let setup = send_message(
+                            runner,
+                            recipient,
+                            &content,
+                            Some(id),
+                            SendOptions::default(),
+                        );
+                        runner.execute(&[setup])?;
+                        vec![receive_message(&payer, &recipient, &id)]
+                    }
+                    ScenarioKind::Reaction => {
+                        reactions += 1;
+                        let (message, recipient) = setup.reacted_to;
+                        let payload = (message, REACTION.to_string())
+                            .try_to_vec()
+                            .map_err(|e| e.to_string())?;
+                        vec![send_structured_message(
+                            &payer,
+                            &recipient,
+                            &setup.reaction_schema,
+                            reactions,
+                            payload,
+                        )]
+                    }
+                    ScenarioKind::RoomPost => {
+                        room_posts += 1;
+                        vec![post_room_message(
+                            &payer,
+                            &payer,
+                            setup.room_id,
+                            room_posts - 1,
+                            &content,
+                        )]
+                    }
+                    ScenarioKind::GroupSend => {
+                        let member = runner.new_signer();
+                        let rent = Rent::default().minimum_balance(RoomMessage::LEN);
+                        runner.execute(&[system_instruction::transfer(&payer, &member, rent)])?;
+                        room_posts += 1;
+                        vec![post_room_message(
+                            &member,
+                            &payer,
+                            setup.room_id,
+                            room_posts - 1,
+                            &content,
+                        )]
+                    }
+                };
+                let started = Instant::now();
+                units.push(runner.execute(&measured)?);
+                walls.push(started.elapsed());
+            }
+            measurements.push(summarize(
+                format!("{}/{size}", kind.name()),
+                &units,
+                &mut walls,
+            ));
+        }
+    }
+
+    Ok(Report {
+        label: label.to_string(),
+        timestamp,
+        measurements,
+    })
+}
+
+/// Creates the room, pass, reaction schema and reacted-to message the scenarios use.
+fn set_up<R: Runner>(runner: &mut R, timestamp: i64) -> Result<Setup, String> {
+    let payer = runner.payer();
+    let room_id = timestamp as u64;
+    runner.execute(&[create_room(&payer, room_id)])?;
+
+    let pass_mint = runner.new_signer();
+    runner.execute(&[mint_pass(&payer, &pass_mint)])?;
+    let pass = (
+        pda(&[b"pass_token", pass_mint.as_ref()]),
+        pda(&[b"pass", pass_mint.as_ref()]),
+    );
+
+    // Schema names are global, so each run registers its own
+    let schema_name = format!("bench-reaction-{timestamp}");
+    runner.execute(&[register_schema(&payer, &schema_name)])?;
+    let reaction_schema = pda(&[b"schema", schema_name.as_bytes(), &1u16.to_le_bytes()]);
+
+    let recipient = runner.new_signer();
+    let id = client_id(timestamp, 0, u32::MAX);
+    let send = send_message(
+        runner,
+        recipient,
+        REACTION,
+        Some(id),
+        SendOptions::default(),
+    );
+    runner.execute(&[send])?;
+    let reacted_to = (message_address(&payer, &recipient, &id), recipient);
+
+    Ok(Setup {
+        room_id,
+        pass,
+        reaction_schema,
+        reacted_to,
+    })
+}
+
+fn summarize(scenario: String, units: &[u64], walls: &mut [Duration]) -> Measurement {
+    walls.sort();
+    Measurement {
+        scenario,
+        units_mean: units.iter().sum::<u64>() / units.len().max(1) as u64,
+        units_max: units.iter().copied().max().unwrap_or(0),
+        wall_micros_median: walls
+            .get(walls.len() / 2)
+            .map_or(0, |wall| wall.as_micros() as u64),
+    }
+}
+
+/// Distinct per measurement, so repeated runs never collide with earlier messages.
+fn client_id(timestamp: i64, size: usize, iteration: u32) -> [u8; 16] {
+    let mut id = [0; 16];
+    id[..8].copy_from_slice(&timestamp.to_le_bytes());
+    id[8..12].copy_from_slice(&(size as u32).to_le_bytes());
+    id[12..].copy_from_slice(&iteration.to_le_bytes());
+    id[15] |= 0x80;
+    id
+}
+
+fn send<R: Runner>(
+    runner: &mut R,
+    content: &str,
+    client_message_id: Option<[u8; 16]>,
+) -> Vec<Instruction> {
+    // Without a client ID the address only depends on the pair, so each send needs a new recipient
+    let recipient = runner.new_signer();
+    vec![send_message(
+        runner,
+        recipient,
+        content,
+        client_message_id,
+        SendOptions::default(),
+    )]
+}
+
+fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+/// How a send differs from a plain one
+#[derive(Debug, Clone, Copy, Default)]
+struct SendOptions {
+    /// Urgent, paying the recipient's urgent fee
+    urgent: bool,
+    /// Token account holding the sender's pass, and the pass's record
+    pass: Option<(Pubkey, Pubkey)>,
+}
+
+fn send_message<R: Runner>(
+    runner: &mut R,
+    recipient: Pubkey,
+    content: &str,
+    client_message_id: Option<[u8; 16]>,
+    options: SendOptions,
+) -> Instruction {
+    let sender = runner.payer();
+    let nft_mint = runner.new_signer();
+    let message = match client_message_id {
+        Some(id) => message_address(&sender, &recipient, &id),
+        None => pda(&[b"message", sender.as_ref(), recipient.as_ref()]),
+    };
+    let metadata_account = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            nft_mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0;
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SendMessage {
+            message,
+            nft_mint,
+            recipient_token_account: get_associated_token_address(&recipient, &nft_mint),
+            sender_token_account: get_associated_token_address(&sender, &nft_mint),
+            metadata_account,
+            sender,
+            system_program: system_program::ID,
+            token_program: anchor_spl::token::ID,
+            rent: sysvar::rent::ID,
+            recipient_gate: pda(&[b"inbox_gate", recipient.as_ref()]),
+            sender_stake_account: None,
+            sender_attestation: None,
+            dm_link: None,
+            epoch_stats: None,
+            sender_stats: None,
+            reply_to: None,
+            sender_reputation: None,
+            config: pda(&[b"config"]),
+            recipient_window: pda(&[b"delivery_window", recipient.as_ref()]),
+            delivery: None,
+            recipient_priority_policy: pda(&[b"priority_policy", recipient.as_ref()]),
+            contact_settings: None,
+            recipient_wallet: options.urgent.then_some(recipient),
+            fee_schedule: pda(&[b"fee_schedule"]),
+            treasury: Some(pda(&[b"treasury"])),
+            sender_pass: options.pass.map(|(token, _)| token),
+            sender_pass_record: options.pass.map(|(_, record)| record),
+            app: None,
+            app_authority: None,
+            namespace: None,
+            sender_membership: pda(&[b"namespace_member", sender.as_ref()]),
+            recipient_membership: pda(&[b"namespace_member", recipient.as_ref()]),
+            recipient_namespace: None,
+            instructions: sysvar::instructions::ID,
+            spam_filter: pda(&[b"spam_filter"]),
+            spam_deposit: None,
+        }
+        .to_account_metas(None),
+        data: instruction::SendMessage {
+            message_content: content.to_string(),
+            recipient,
+            priority: if options.urgent {
+                MessagePriority::Urgent
+            } else {
+                MessagePriority::Normal
+            },
+            client_message_id,
+        }
+        .data(),
+    }
+}
+
+fn receive_message(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    client_message_id: &[u8; 16],
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ReceiveMessage {
+            message: message_address(sender, recipient, client_message_id),
+            recipient: *recipient,
+        }
+        .to_account_metas(None),
+        data: instruction::ReceiveMessage {}.data(),
+    }
+}
+
+fn room_address(creator: &Pubkey, room_id: u64) -> Pubkey {
+    pda(&[b"room", creator.as_ref(), &room_id.to_le_bytes()])
+}
+
+fn create_room(creator: &Pubkey, room_id: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::CreateRoom {
+            room: room_address(creator, room_id),
+            creator: *creator,
+            system_program: system_program::ID,
+            namespace: None,
+            creator_membership: pda(&[b"namespace_member", creator.as_ref()]),
+        }
+        .to_account_metas(None),
+        data: instruction::CreateRoom {
+            room_id,
+            name: "bench".to_string(),
+            ttl_secs: 24 * 60 * 60,
+        }
+        .data(),
+    }
+}
+
+fn post_room_message(
+    author: &Pubkey,
+    creator: &Pubkey,
+    room_id: u64,
+    index: u64,
+    content: &str,
+) -> Instruction {
+    let room = room_address(creator, room_id);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::PostRoomMessage {
+            room,
+            message: pda(&[b"room_msg", room.as_ref(), &index.to_le_bytes()]),
+            author: *author,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::PostRoomMessage {
+            content: content.to_string(),
+        }
+        .data(),
+    }
+}
+
+fn set_priority_policy(owner: &Pubkey, urgent_fee_lamports: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SetPriorityPolicy {
+            policy: pda(&[b"priority_policy", owner.as_ref()]),
+            owner: *owner,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::SetPriorityPolicy {
+            urgent_fee_lamports,
+            urgent_from_contacts: false,
+        }
+        .data(),
+    }
+}
+
+fn mint_pass(buyer: &Pubkey, pass_mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::MintPass {
+            pass_offer: pda(&[b"pass_offer"]),
+            pass_mint: *pass_mint,
+            pass_token: pda(&[b"pass_token", pass_mint.as_ref()]),
+            pass: pda(&[b"pass", pass_mint.as_ref()]),
+            treasury: pda(&[b"treasury"]),
+            buyer: *buyer,
+            system_program: system_program::ID,
+            token_program: anchor_spl::token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::MintPass {}.data(),
+    }
+}
+
+/// A Borsh schema for reactions: the reacted-to message's address, then the emoji as a string.
+fn register_schema(authority: &Pubkey, name: &str) -> Instruction {
+    let layout = "struct Reaction { message: [u8; 32], emoji: String }";
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::RegisterSchema {
+            schema: pda(&[b"schema", name.as_bytes(), &1u16.to_le_bytes()]),
+            previous: None,
+            authority: *authority,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::RegisterSchema {
+            name: name.to_string(),
+            version: 1,
+            format: SchemaFormat::Borsh,
+            layout_hash: hash(layout.as_bytes()).to_bytes(),
+            uri: String::new(),
+        }
+        .data(),
+    }
+}
+
+fn send_structured_message(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    schema: &Pubkey,
+    nonce: u64,
+    payload: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SendStructuredMessage {
+            message: pda(&[
+                b"structured",
+                sender.as_ref(),
+                recipient.as_ref(),
+                &nonce.to_le_bytes(),
+            ]),
+            schema: *schema,
+            sender: *sender,
+            system_program: system_program::ID,
+            namespace: None,
+            sender_membership: pda(&[b"namespace_member", sender.as_ref()]),
+        }
+        .to_account_metas(None),
+        data: instruction::SendStructuredMessage {
+            recipient: *recipient,
+            nonce,
+            payload,
+        }
+        .data(),
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gmchat_bench::rpc::{read_keypair, RpcRunner};
+use gmchat_bench::{history, run_suite};
+
+const DEFAULT_TOLERANCE_PERCENT: f64 = 2.0;
+const DEFAULT_ITERATIONS: u32 = 10;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("run") => run(&args[1..]),
+        Some("compare") => compare(&args[1..]),
+        _ => usage(),
+    }
+}
+
+fn run(args: &[String]) -> ExitCode {
+    let (url, keypair, path, label, iterations) = match args {
+        [url, keypair, path, label] => (url, keypair, path, label, DEFAULT_ITERATIONS),
+        [url, keypair, path, label, iterations] => match iterations.parse() {
+            Ok(iterations) => (url, keypair, path, label, iterations),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let report = read_keypair(Path::new(keypair))
+        .and_then(|payer| RpcRunner::new(url, payer))
+        .and_then(|mut runner| run_suite(&mut runner, iterations, label, timestamp));
+    let report = match report {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("benchmark failed: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for measurement in &report.measurements {
+        println!(
+            "{}: {} CU mean, {} CU max, {} us median",
+            measurement.scenario,
+            measurement.units_mean,
+            measurement.units_max,
+            measurement.wall_micros_median
+        );
+    }
+    if let Err(err) = history::append(Path::new(path), &report) {
+        eprintln!("failed to write {path}: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn compare(args: &[String]) -> ExitCode {
+    let (path, tolerance) = match args {
+        [path] => (PathBuf::from(path), DEFAULT_TOLERANCE_PERCENT),
+        [path, tolerance] => match tolerance.parse() {
+            Ok(tolerance) => (PathBuf::from(path), tolerance),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    let reports = match history::load(&path) {
+        Ok(reports) => reports,
+        Err(err) => {
+            eprintln!("failed to read {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let [.., previous, latest] = reports.as_slice() else {
+        println!("fewer than two runs recorded, nothing to compare");
+        return ExitCode::SUCCESS;
+    };
+
+    let regressions = history::compare(previous, latest, tolerance);
+    for regression in &regressions {
+        println!(
+            "{}: {} -> {} CU (+{:.1}%)",
+            regression.scenario,
+            regression.previous_units,
+            regression.latest_units,
+            regression.percent()
+        );
+    }
+    if regressions.is_empty() {
+        println!("{} within {tolerance}% of {}", latest.label, previous.label);
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!(
+        "usage: gmchat-bench run <http://rpc-url> <payer-keypair.json> <history.jsonl> <label> \
+         [iterations]"
+    );
+    eprintln!("       gmchat-bench compare <history.jsonl> [tolerance-percent]");
+    ExitCode::FAILURE
+}
@@ -0,0 +1,297 @@
+//! `Runner` backed by a validator's JSON-RPC endpoint, e.g. `solana-test-validator` with the
+//! program deployed and its config initialized.
+//!
+//! Each `execute` signs a legacy transaction, sends it, waits until it is confirmed and reads
+//! the compute units from its metadata, so the wall time measured around it includes
+//! confirmation. Only plain `http://` endpoints are supported; a local validator is the
+//! intended target.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::Message;
+use ed25519_dalek::{Keypair, Signer};
+use serde_json::{json, Value};
+
+use crate::Runner;
+
+/// How long a transaction may take to reach `confirmed` before `execute` gives up
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub struct RpcRunner {
+    /// `host:port` of the endpoint
+    address: String,
+    /// Request path, `/` for a validator
+    path: String,
+    payer: Keypair,
+    signers: HashMap<Pubkey, Keypair>,
+    next_id: u64,
+}
+
+impl RpcRunner {
+    /// `url` is an `http://host:port` endpoint; `payer` signs and pays for every transaction.
+    pub fn new(url: &str, payer: Keypair) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{url}: only http:// endpoints are supported"))?;
+        let (address, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        };
+        Ok(Self {
+            address: address.to_string(),
+            path: path.to_string(),
+            payer,
+            signers: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        self.next_id += 1;
+        let body =
+            json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params })
+                .to_string();
+        let response = http_post(&self.address, &self.path, &body)?;
+        let mut response: Value =
+            serde_json::from_slice(&response).map_err(|e| format!("{method}: {e}"))?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("{method}: {error}"));
+        }
+        Ok(response["result"].take())
+    }
+
+    fn latest_blockhash(&mut self) -> Result<Hash, String> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or("getLatestBlockhash: missing blockhash")?;
+        let bytes: [u8; 32] = bs58::decode(blockhash)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("invalid blockhash {blockhash}"))?;
+        Ok(Hash::new_from_array(bytes))
+    }
+
+    /// Signs `message` with the payer or a signer from `new_signer` for each required key.
+    fn sign(&self, message: &Message) -> Result<Vec<u8>, String> {
+        let data = message.serialize();
+        let required = message.header.num_required_signatures as usize;
+
+        let mut transaction = Vec::with_capacity(1 + required * 64 + data.len());
+        push_short_u16(&mut transaction, required);
+        for key in &message.account_keys[..required] {
+            let keypair = if key.to_bytes() == self.payer.public.to_bytes() {
+                &self.payer
+            } else {
+                self.signers
+                    .get(key)
+                    .ok_or_else(|| format!("no keypair for signer {key}"))?
+            };
+            transaction.extend_from_slice(&keypair.sign(&data).to_bytes());
+        }
+        transaction.extend_from_slice(&data);
+        Ok(transaction)
+    }
+
+    fn wait_for_confirmation(&mut self, signature: &str) -> Result<(), String> {
+        let deadline = Instant::now() + CONFIRM_TIMEOUT;
+        loop {
+            let result = self.call("getSignatureStatuses", json!([[signature]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    return Err(format!("transaction {signature} failed: {}", status["err"]));
+                }
+                if matches!(
+                    status["confirmationStatus"].as_str(),
+                    Some("confirmed" | "finalized")
+                ) {
+                    return Ok(());
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("transaction {signature} not confirmed in time"));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Runner for RpcRunner {
+    fn payer(&self) -> Pubkey {
+        Pubkey::new_from_array(self.payer.public.to_bytes())
+    }
+
+    fn new_signer(&mut self) -> Pubkey {
+        let keypair = Keypair::generate(&mut rand::rngs::OsRng);
+        let key = Pubkey::new_from_array(keypair.public.to_bytes());
+        self.signers.insert(key, keypair);
+        key
+    }
+
+    fn execute(&mut self, instructions: &[Instruction]) -> Result<u64, String> {
+        let mut message = Message::new(instructions, Some(&self.payer()));
+        message.recent_blockhash = self.latest_blockhash()?;
+        let transaction = self.sign(&message)?;
+
+        let options = json!({ "encoding": "base64", "preflightCommitment": "confirmed" });
+        let signature = self.call(
+            "sendTransaction",
+            json!([base64::encode(transaction), options]),
+        )?;
+        let signature = signature
+            .as_str()
+            .ok_or("sendTransaction: missing signature")?
+            .to_string();
+        self.wait_for_confirmation(&signature)?;
+
+        let options = json!({
+            "encoding": "json",
+            "commitment": "confirmed",
+            "maxSupportedTransactionVersion": 0,
+        });
+        let transaction = self.call("getTransaction", json!([signature, options]))?;
+        transaction["meta"]["computeUnitsConsumed"]
+            .as_u64()
+            .ok_or_else(|| format!("transaction {signature}: no compute units in its metadata"))
+    }
+}
+
+/// Reads a keypair file as written by `solana-keygen`: a JSON array of 64 bytes.
+pub fn read_keypair(path: &Path) -> Result<Keypair, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let bytes: Vec<u8> =
+        serde_json::from_str(&raw).map_err(|e| format!("{}: {e}", path.display()))?;
+    Keypair::from_bytes(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Solana's compact-u16 length prefix.
+fn push_short_u16(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn http_post(address: &str, path: &str, body: &str) -> Result<Vec<u8>, String> {
+    let mut stream = TcpStream::connect(address).map_err(|e| format!("{address}: {e}"))?;
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("{address}: {e}"))?;
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("{address}: {e}"))?;
+
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or("malformed HTTP response")?;
+    let head = String::from_utf8_lossy(&response[..split]).to_ascii_lowercase();
+    let body = &response[split + 4..];
+
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("{address}: HTTP {status}"));
+    }
+    if head.contains("transfer-encoding: chunked") {
+        decode_chunked(body)
+    } else {
+        Ok(body.to_vec())
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or("truncated chunked body")?;
+        let size = std::str::from_utf8(&body[..line_end])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?.trim(), 16).ok())
+            .ok_or("invalid chunk size")?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        let chunk = body
+            .get(line_end + 2..line_end + 2 + size)
+            .ok_or("truncated chunked body")?;
+        decoded.extend_from_slice(chunk);
+        body = body
+            .get(line_end + 4 + size..)
+            .ok_or("truncated chunked body")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_compact_u16() {
+        for (value, encoded) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (0xffff, &[0xff, 0xff, 0x03]),
+        ] {
+            let mut out = Vec::new();
+            push_short_u16(&mut out, value);
+            assert_eq!(out, encoded, "{value}");
+        }
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let body = b"4\r\n{\"a\"\r\n3;ext=1\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked(body).unwrap(), b"{\"a\":1}");
+        assert!(decode_chunked(b"4\r\n{\"a").is_err());
+        assert!(decode_chunked(b"zz\r\n").is_err());
+    }
+
+    fn keypair() -> Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        Keypair { secret, public }
+    }
+
+    #[test]
+    fn splits_the_endpoint_url() {
+        let runner = RpcRunner::new("http://127.0.0.1:8899", keypair()).unwrap();
+        assert_eq!(
+            (runner.address.as_str(), runner.path.as_str()),
+            ("127.0.0.1:8899", "/")
+        );
+
+        let runner = RpcRunner::new("http://localhost:8899/rpc", keypair()).unwrap();
+        assert_eq!(
+            (runner.address.as_str(), runner.path.as_str()),
+            ("localhost:8899", "/rpc")
+        );
+
+        assert!(RpcRunner::new("https://api.devnet.solana.com", keypair()).is_err());
+    }
+}
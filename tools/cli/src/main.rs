@@ -0,0 +1,74 @@
+//! Command-line client for gmchat.
+//!
+//! ```text
+//! gmchat estimate <nft|blob|interned> [--content-len N] [--queued] [--new-blob]
+//!                 [--tip LAMPORTS] [--urgent-fee LAMPORTS] [--protocol-fee LAMPORTS]
+//!                 [--compute-units N] [--priority-fee MICRO_LAMPORTS]
+//! ```
+//!
+//! `estimate` prints the cost breakdown of a send as JSON (see `gmchat_sdk::estimate`).
+
+use std::{env, process};
+
+use gmchat_sdk::budget::ComputeBudget;
+use gmchat_sdk::estimate::{estimate, SendConfig, SendKind};
+
+const USAGE: &str = "usage: gmchat estimate <nft|blob|interned> [--content-len N] [--queued] [--new-blob] \
+                     [--tip LAMPORTS] [--urgent-fee LAMPORTS] [--protocol-fee LAMPORTS] [--compute-units N] [--priority-fee MICRO_LAMPORTS]";
+
+fn run_estimate(args: &[String]) -> Result<(), String> {
+    let (kind, flags) = args.split_first().ok_or(USAGE)?;
+
+    let mut content_len = 0;
+    let (mut queued, mut new_blob) = (false, false);
+    let (mut tip_lamports, mut urgent_fee_lamports, mut protocol_fee_lamports) = (0, 0, 0);
+    let (mut compute_units, mut priority_fee) = (None, None);
+
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let mut value = || flags.next().ok_or_else(|| format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--queued" => queued = true,
+            "--new-blob" => new_blob = true,
+            "--content-len" => content_len = parse(flag, value()?)?,
+            "--tip" => tip_lamports = parse(flag, value()?)?,
+            "--urgent-fee" => urgent_fee_lamports = parse(flag, value()?)?,
+            "--protocol-fee" => protocol_fee_lamports = parse(flag, value()?)?,
+            "--compute-units" => compute_units = Some(parse(flag, value()?)?),
+            "--priority-fee" => priority_fee = Some(parse(flag, value()?)?),
+            _ => return Err(format!("unknown flag {flag}\n{USAGE}")),
+        }
+    }
+
+    let kind = match kind.as_str() {
+        "nft" => SendKind::Nft { queued },
+        "blob" => SendKind::Blob { new_blob },
+        "interned" => SendKind::Interned,
+        _ => return Err(format!("unknown send kind {kind}\n{USAGE}")),
+    };
+    let budget = (compute_units.is_some() || priority_fee.is_some()).then(|| ComputeBudget {
+        compute_unit_limit: compute_units.unwrap_or_else(|| kind.default_compute_units()),
+        micro_lamports_per_unit: priority_fee.unwrap_or(0),
+    });
+
+    let config = SendConfig { kind, content_len, tip_lamports, urgent_fee_lamports, protocol_fee_lamports, budget };
+    let estimate = estimate(&config).map_err(|e| e.to_string())?;
+    println!("{}", serde_json::to_string_pretty(&estimate).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{flag}: invalid value {value}"))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "estimate" => run_estimate(rest),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Imports a community's message history into a gmchat room.
+//!
+//! Parses a Discord or Telegram export (see `source`), turns each message into one or more
+//! `import_room_message` instructions that keep the original time and are flagged as imported,
+//! and hands them to a submitter one transaction at a time over stdin/stdout:
+//!
+//! - each planned transaction is printed as a JSON line with the delay to wait before sending
+//!   it, its instructions, and the room message indices it creates;
+//! - the submitter answers with one line per transaction, `{"batch": n, "ok": true}` once it has
+//!   confirmed, or `{"batch": n, "ok": false, "error": "..."}` if it failed or was rate limited.
+//!
+//! Room messages are addressed by sequential index, so a transaction can only land after the
+//! previous one and only one is ever in flight. Successes shrink the delay back towards
+//! `min_interval_ms`; failures double it up to `max_backoff_ms` and resend the same batch.
+//! Progress is written to the checkpoint file after every confirmed batch, so an interrupted
+//! import resumes where it stopped.
+//!
+//! ```toml
+//! format = "discord-json"  # or "telegram-json", "csv"
+//! room = "..."
+//! creator = "..."          # room creator wallet; signs and pays rent
+//! start_index = 0          # room.message_count when the import begins
+//! messages_per_tx = 4
+//! min_interval_ms = 400
+//! max_backoff_ms = 30000
+//! max_retries = 8
+//! ```
+//!
+//! Usage: gmchat-import <config.toml> <export> <checkpoint.json> < acks.jsonl
+
+mod source;
+
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use std::{env, fs, process};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use serde::{Deserialize, Serialize};
+use solana_messaging::{accounts, instruction, RoomMessage, ID as PROGRAM_ID};
+
+use crate::source::Record;
+
+#[derive(Deserialize)]
+struct Config {
+    format: String,
+    room: String,
+    creator: String,
+    start_index: u64,
+    messages_per_tx: usize,
+    min_interval_ms: u64,
+    max_backoff_ms: u64,
+    max_retries: u32,
+}
+
+/// Import progress; both counters only move after a batch is confirmed.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    /// Next entry of the flattened import to send
+    next_entry: usize,
+    /// Room message index that entry will get
+    next_index: u64,
+}
+
+/// A room message to create: one chunk of one exported record.
+struct Entry {
+    content: String,
+    timestamp: i64,
+}
+
+#[derive(Serialize)]
+struct PlannedBatch {
+    batch: u64,
+    delay_ms: u64,
+    /// Room message indices created, first and last
+    indices: (u64, u64),
+    instructions: Vec<SerializedInstruction>,
+}
+
+#[derive(Serialize)]
+struct SerializedInstruction {
+    program_id: String,
+    accounts: Vec<SerializedAccountMeta>,
+    /// Base64 instruction data
+    data: String,
+}
+
+#[derive(Serialize)]
+struct SerializedAccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl From<&Instruction> for SerializedInstruction {
+    fn from(ix: &Instruction) -> Self {
+        Self {
+            program_id: ix.program_id.to_string(),
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| SerializedAccountMeta {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: base64::encode(&ix.data),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Ack {
+    batch: u64,
+    ok: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Splits a record into room messages of at most `RoomMessage::MAX_CONTENT_LEN` bytes, each
+/// attributed to the original author.
+fn entries(record: &Record) -> Vec<Entry> {
+    let prefix = format!("{}: ", record.author);
+    let room = RoomMessage::MAX_CONTENT_LEN.saturating_sub(prefix.len()).max(1);
+
+    let mut entries = Vec::new();
+    let mut chunk = String::new();
+    for c in record.text.chars() {
+        if chunk.len() + c.len_utf8() > room {
+            entries.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    entries.push(chunk);
+
+    entries
+        .into_iter()
+        .map(|chunk| {
+            let mut content = format!("{prefix}{chunk}");
+            // Author names are not length-limited upstream
+            while content.len() > RoomMessage::MAX_CONTENT_LEN {
+                content.pop();
+            }
+            Entry { content, timestamp: record.timestamp }
+        })
+        .collect()
+}
+
+fn import_instruction(room: Pubkey, creator: Pubkey, index: u64, entry: &Entry) -> Instruction {
+    let message = Pubkey::find_program_address(&[b"room_msg", room.as_ref(), &index.to_le_bytes()], &PROGRAM_ID).0;
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ImportRoomMessage { room, message, creator, system_program: system_program::ID }
+            .to_account_metas(None),
+        data: instruction::ImportRoomMessage { content: entry.content.clone(), original_timestamp: entry.timestamp }.data(),
+    }
+}
+
+fn save_checkpoint(path: &str, checkpoint: &Checkpoint) -> Result<(), String> {
+    let temp = format!("{path}.tmp");
+    fs::write(&temp, serde_json::to_string(checkpoint).map_err(|e| e.to_string())?).map_err(|e| format!("{temp}: {e}"))?;
+    fs::rename(&temp, path).map_err(|e| format!("{path}: {e}"))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let [config_path, export_path, checkpoint_path] = args else {
+        return Err("usage: gmchat-import <config.toml> <export> <checkpoint.json> < acks.jsonl".into());
+    };
+
+    let raw = fs::read_to_string(config_path).map_err(|e| format!("{config_path}: {e}"))?;
+    let config: Config = toml::from_str(&raw).map_err(|e| format!("{config_path}: {e}"))?;
+    if config.messages_per_tx == 0 {
+        return Err(format!("{config_path}: messages_per_tx must be positive"));
+    }
+    let room = Pubkey::from_str(&config.room).map_err(|e| format!("{config_path}: room: {e}"))?;
+    let creator = Pubkey::from_str(&config.creator).map_err(|e| format!("{config_path}: creator: {e}"))?;
+
+    let raw = fs::read_to_string(export_path).map_err(|e| format!("{export_path}: {e}"))?;
+    let records = source::parse(&config.format, &raw).map_err(|e| format!("{export_path}: {e}"))?;
+    let entries: Vec<Entry> = records.iter().flat_map(entries).collect();
+
+    let mut checkpoint = match fs::read_to_string(checkpoint_path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(|e| format!("{checkpoint_path}: {e}"))?,
+        Err(_) => Checkpoint { next_entry: 0, next_index: config.start_index },
+    };
+
+    let mut acks = io::stdin().lock().lines();
+    let mut delay_ms = 0;
+    let mut batch = 0;
+    let mut retries = 0;
+
+    while checkpoint.next_entry < entries.len() {
+        let end = (checkpoint.next_entry + config.messages_per_tx).min(entries.len());
+        let pending = &entries[checkpoint.next_entry..end];
+        let instructions: Vec<Instruction> = pending
+            .iter()
+            .zip(checkpoint.next_index..)
+            .map(|(entry, index)| import_instruction(room, creator, index, entry))
+            .collect();
+
+        let planned = PlannedBatch {
+            batch,
+            delay_ms,
+            indices: (checkpoint.next_index, checkpoint.next_index + pending.len() as u64 - 1),
+            instructions: instructions.iter().map(SerializedInstruction::from).collect(),
+        };
+        println!("{}", serde_json::to_string(&planned).map_err(|e| e.to_string())?);
+
+        // The submitter hanging up pauses the import; the checkpoint already covers every ack
+        let Some(line) = acks.next() else { return Ok(()) };
+        let ack: Ack = serde_json::from_str(&line.map_err(|e| e.to_string())?).map_err(|e| format!("invalid ack: {e}"))?;
+        if ack.batch != batch {
+            return Err(format!("ack for batch {} while waiting on batch {batch}", ack.batch));
+        }
+        batch += 1;
+
+        if ack.ok {
+            checkpoint.next_entry = end;
+            checkpoint.next_index += pending.len() as u64;
+            save_checkpoint(checkpoint_path, &checkpoint)?;
+            retries = 0;
+            delay_ms = (delay_ms / 2).max(config.min_interval_ms);
+        } else {
+            retries += 1;
+            if retries > config.max_retries {
+                return Err(format!(
+                    "giving up on entries {}..{end} after {retries} attempts: {}",
+                    checkpoint.next_entry,
+                    ack.error.unwrap_or_default()
+                ));
+            }
+            delay_ms = (delay_ms.max(config.min_interval_ms) * 2).min(config.max_backoff_ms);
+        }
+    }
+
+    eprintln!("imported {} messages into {room}", entries.len());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
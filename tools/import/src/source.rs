@@ -0,0 +1,202 @@
+//! Parses community history exports into a flat, oldest-first list of records.
+//!
+//! Supported formats:
+//! - `discord-json`: DiscordChatExporter JSON (`messages[].timestamp/author.name/content`)
+//! - `telegram-json`: Telegram Desktop `result.json` (`messages[].date_unixtime/from/text`)
+//! - `csv`: any CSV with a header naming author, date and content columns, which covers
+//!   DiscordChatExporter CSV (`Author,Date,Content`)
+//!
+//! Dates are either Unix seconds or RFC 3339.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// One message from the export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub timestamp: i64,
+    pub author: String,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordExport {
+    messages: Vec<DiscordMessage>,
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    timestamp: String,
+    author: DiscordAuthor,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordAuthor {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TelegramExport {
+    messages: Vec<TelegramMessage>,
+}
+
+#[derive(Deserialize)]
+struct TelegramMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    date_unixtime: String,
+    #[serde(default)]
+    from: Option<String>,
+    /// A string, or an array of strings and `{ "type", "text" }` entities
+    text: Value,
+}
+
+/// Parses an export. Messages without text (attachments only, service messages) are skipped,
+/// and the result is sorted by time, keeping export order for equal timestamps.
+pub fn parse(format: &str, raw: &str) -> Result<Vec<Record>, String> {
+    let mut records = match format {
+        "discord-json" => {
+            let export: DiscordExport = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+            export
+                .messages
+                .into_iter()
+                .map(|message| {
+                    Ok(Record { timestamp: parse_date(&message.timestamp)?, author: message.author.name, text: message.content })
+                })
+                .collect::<Result<Vec<_>, String>>()?
+        }
+        "telegram-json" => {
+            let export: TelegramExport = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+            export
+                .messages
+                .into_iter()
+                .filter(|message| message.kind == "message")
+                .map(|message| {
+                    Ok(Record {
+                        timestamp: parse_date(&message.date_unixtime)?,
+                        author: message.from.unwrap_or_else(|| "Deleted Account".to_string()),
+                        text: telegram_text(&message.text),
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?
+        }
+        "csv" => parse_csv(raw)?,
+        _ => return Err(format!("unknown format {format}")),
+    };
+
+    records.retain(|record| !record.text.trim().is_empty());
+    records.sort_by_key(|record| record.timestamp);
+    Ok(records)
+}
+
+fn telegram_text(text: &Value) -> String {
+    match text {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.as_str()),
+                entity => entity.get("text").and_then(Value::as_str),
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+fn parse_csv(raw: &str) -> Result<Vec<Record>, String> {
+    let mut rows = csv_rows(raw)?.into_iter();
+    let header = rows.next().ok_or("empty csv")?;
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|name| names.contains(&name.trim().to_ascii_lowercase().as_str()))
+            .ok_or_else(|| format!("csv header has no {} column", names[0]))
+    };
+    let (author, date, content) =
+        (column(&["author", "from"])?, column(&["date", "timestamp"])?, column(&["content", "text"])?);
+
+    rows.enumerate()
+        .map(|(line, row)| {
+            let field = |index: usize| row.get(index).ok_or_else(|| format!("csv row {}: missing column", line + 2));
+            Ok(Record { timestamp: parse_date(field(date)?)?, author: field(author)?.clone(), text: field(content)?.clone() })
+        })
+        .collect()
+}
+
+/// RFC 4180 rows: quoted fields may contain commas, newlines and doubled quotes.
+fn csv_rows(raw: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted csv field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Unix seconds, or RFC 3339 such as `2021-03-04T05:06:07.890+02:00`.
+fn parse_date(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+
+    let invalid = || format!("invalid date {value}");
+    let number = |range: std::ops::Range<usize>| value.get(range).and_then(|s| s.parse::<i64>().ok()).ok_or_else(invalid);
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Skip fractional seconds, then read the offset
+    let rest = value.get(19..).ok_or_else(invalid)?;
+    let offset = rest.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset_seconds = match offset {
+        "Z" | "z" => 0,
+        _ if offset.len() == 6 && (offset.starts_with('+') || offset.starts_with('-')) => {
+            let hours: i64 = offset[1..3].parse().map_err(|_| invalid())?;
+            let minutes: i64 = offset[4..6].parse().map_err(|_| invalid())?;
+            let seconds = hours * 3600 + minutes * 60;
+            if offset.starts_with('-') {
+                -seconds
+            } else {
+                seconds
+            }
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,692 @@
+//! Offline checks for conversation bundles produced by `gmchat_sdk::export`.
+//!
+//! Nothing here talks to an RPC node: every claim in the bundle is checked against the
+//! program's own account layouts, PDA seeds, the Merkle proofs and the ed25519 signatures
+//! carried inside the bundle.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+use gmchat_sdk::export::{merkle_root, verify_merkle_proof, ConversationBundle, BUNDLE_VERSION};
+use solana_messaging::{
+    AmaInbox, AmaQuestion, App, Appeal, Attendee, Attestation, AuditLog, Availability, Ballot, BlobMessage,
+    BookingMessage, ChaosPlan, CollectionFeed, CommittedMessage, ConditionalMessage, Config, ContentBlob, ContentFilter,
+    ContentWarning, ConversationSettings, CouncilCandidacy, CouncilElection, CouncilVote, DealMessage, DealRoom,
+    DeliveryWindow, Disclosure, DmLink, EncryptionKey, EpochStats, EventMessage, FeatureGate, FeeSchedule, Forwarding,
+    GameSession, GovernanceNotifier, GuardianSet, InboxGate, InboxIndex, InternedMessage, Invoice, KeyTransparencyLog,
+    MarketNotice, MerchantProfile, MerchantReceipt, Message, MessageDelivery, MessageDictionary, MessageMedia,
+    MessageTranslations, ModerationCouncil, ModerationVote, Namespace, NamespaceMember, Notification, Notifier,
+    ObserverGrant, ObserverKey, PassOffer, PatronConfig, PaymentNote, PendingConfigChange, Poll, PriceQuoteMessage,
+    PriorityPolicy, Profile, ProposalNotification, ProtocolPass, Receipt, ReceiptReference, RecoveryRequest, Reminder,
+    Reputation, Room, RoomMessage, RoomReport, RoundUp, Schema, Snooze, SpamDeposit, SpamFilter, SpamFlag, SpamTrap,
+    StructuredMessage, TaskMessage, TeamInbox, TeamInboxStats, Ticket, TipStream, TradeProposal, Treasury,
+    TreasuryProposal, UserStats, Vouch, ID as PROGRAM_ID,
+};
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub check: &'static str,
+    pub subject: String,
+    pub error: Option<String>,
+}
+
+impl Finding {
+    fn pass(check: &'static str, subject: impl Into<String>) -> Self {
+        Self { check, subject: subject.into(), error: None }
+    }
+
+    fn fail(check: &'static str, subject: impl Into<String>, error: impl Into<String>) -> Self {
+        Self { check, subject: subject.into(), error: Some(error.into()) }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Every finding for one bundle, in the order the checks ran.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.findings.iter().all(Finding::passed)
+    }
+
+    fn push(&mut self, check: &'static str, subject: impl Into<String>, result: Result<(), String>) {
+        self.findings.push(match result {
+            Ok(()) => Finding::pass(check, subject),
+            Err(error) => Finding::fail(check, subject, error),
+        });
+    }
+}
+
+/// Runs every check on `bundle`.
+pub fn verify_bundle(bundle: &ConversationBundle) -> Report {
+    let mut report = Report::default();
+
+    report.push("header", "bundle", check_header(bundle));
+    report.push("exporter signature", bundle.exporter.clone(), bundle.verify_signature().map_err(|e| e.to_string()));
+    check_proofs(bundle, &mut report);
+
+    for account in &bundle.accounts {
+        report.push("account", account.address.clone(), check_account(account, bundle));
+    }
+    for event in &bundle.events {
+        report.push("event", event.transaction.clone(), check_event(event, bundle));
+    }
+    for (index, transaction) in bundle.transactions.iter().enumerate() {
+        report.push("transaction signatures", format!("transaction #{index}"), check_transaction(transaction));
+    }
+
+    report
+}
+
+fn check_header(bundle: &ConversationBundle) -> Result<(), String> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(format!("unsupported bundle version {}", bundle.version));
+    }
+    if bundle.program_id != PROGRAM_ID.to_string() {
+        return Err(format!("bundle is for program {}, expected {PROGRAM_ID}", bundle.program_id));
+    }
+    if bundle.slot_hashes.is_empty() {
+        return Err("bundle carries no slot hashes".to_string());
+    }
+    Ok(())
+}
+
+fn check_proofs(bundle: &ConversationBundle, report: &mut Report) {
+    let leaves = match bundle.leaves() {
+        Ok(leaves) => leaves,
+        Err(e) => return report.push("merkle proofs", "bundle", Err(e.to_string())),
+    };
+    if bundle.proofs.len() != leaves.len() {
+        let error = format!("{} proofs for {} leaves", bundle.proofs.len(), leaves.len());
+        return report.push("merkle proofs", "bundle", Err(error));
+    }
+
+    // Proofs are checked against the signed root, so a leaf changed after signing fails its own
+    let root = match decode_base58::<32>(&bundle.merkle_root) {
+        Ok(root) => root,
+        Err(e) => return report.push("merkle proofs", "bundle", Err(format!("merkle root: {e}"))),
+    };
+    if merkle_root(&leaves) != root {
+        report.push("merkle proofs", "bundle", Err("bundle contents do not match the signed root".to_string()));
+    }
+    for (leaf, proof) in leaves.iter().zip(&bundle.proofs) {
+        let siblings: Result<Vec<[u8; 32]>, String> = proof.siblings.iter().map(|s| decode_base58::<32>(s)).collect();
+        let result = siblings.and_then(|siblings| {
+            if verify_merkle_proof(leaf, proof.leaf_index as usize, &siblings, &root) {
+                Ok(())
+            } else {
+                Err("inclusion proof does not reach the root".to_string())
+            }
+        });
+        report.push("merkle proof", format!("leaf #{}", proof.leaf_index), result);
+    }
+}
+
+fn check_account(account: &gmchat_sdk::export::AccountSnapshot, bundle: &ConversationBundle) -> Result<(), String> {
+    let address = Pubkey::new_from_array(decode_base58::<32>(&account.address)?);
+    let owner = Pubkey::new_from_array(decode_base58::<32>(&account.owner)?);
+    let data = base64::decode(&account.data).map_err(|e| e.to_string())?;
+
+    if owner != PROGRAM_ID {
+        return Err(format!("owned by {owner}, not the messaging program"));
+    }
+    if !bundle.slot_hashes.iter().any(|entry| entry.slot == account.slot) {
+        return Err(format!("snapshot slot {} is not covered by the bundle's slot hashes", account.slot));
+    }
+
+    let expected = expected_address(&data, bundle)?;
+    if expected != address {
+        return Err(format!("address does not match PDA derivation {expected}"));
+    }
+    Ok(())
+}
+
+/// Re-derives the PDA of any program account from its own fields. Every `#[account]` type is
+/// listed; an unrecognised discriminator is an error rather than a skipped check.
+fn expected_address(data: &[u8], bundle: &ConversationBundle) -> Result<Pubkey, String> {
+    let discriminator: [u8; 8] = data.get(..8).and_then(|d| d.try_into().ok()).ok_or("account data too short")?;
+    let seeds_of = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &PROGRAM_ID).0;
+
+    // The marker only stores the receipt it points at; the seed is that receipt's reference
+    if discriminator == ReceiptReference::DISCRIMINATOR {
+        let marker = ReceiptReference::try_deserialize(&mut &data[..]).map_err(|e| e.to_string())?;
+        let receipt = bundle
+            .accounts
+            .iter()
+            .find(|account| account.address == marker.receipt.to_string())
+            .ok_or_else(|| format!("receipt {} it marks is not in the bundle", marker.receipt))?;
+        let receipt_data = base64::decode(&receipt.data).map_err(|e| e.to_string())?;
+        let receipt = MerchantReceipt::try_deserialize(&mut &receipt_data[..]).map_err(|e| e.to_string())?;
+        return Ok(seeds_of(&[b"receipt", receipt.reference.as_ref()]));
+    }
+
+    macro_rules! derive {
+        ($($ty:ident => |$account:ident| [$($seed:expr),* $(,)?]),* $(,)?) => {
+            $(
+                if discriminator == $ty::DISCRIMINATOR {
+                    let $account = $ty::try_deserialize(&mut &data[..]).map_err(|e| e.to_string())?;
+                    return Ok(seeds_of(&[$($seed),*]));
+                }
+            )*
+        };
+    }
+
+    derive! {
+        Message => |a| [b"message", a.namespace_seed(), a.sender.as_ref(), a.recipient.as_ref(), a.client_id_seed()],
+        EventMessage => |a| [b"event", a.organizer.as_ref(), &a.event_id.to_le_bytes()],
+        Attendee => |a| [b"attendee", a.event.as_ref(), a.attendee.as_ref()],
+        TaskMessage => |a| [b"task", a.creator.as_ref(), &a.task_id.to_le_bytes()],
+        TradeProposal => |a| [b"trade", a.proposer.as_ref(), &a.trade_id.to_le_bytes()],
+        Invoice => |a| [b"invoice", a.namespace_seed(), a.issuer.as_ref(), &a.invoice_id.to_le_bytes()],
+        Receipt => |a| [b"receipt", a.invoice.as_ref()],
+        ConditionalMessage => |a| [b"conditional", a.sender.as_ref(), &a.message_id.to_le_bytes()],
+        CommittedMessage => |a| [b"commit", a.sender.as_ref(), &a.commit_id.to_le_bytes()],
+        GameSession => |a| [b"game", a.creator.as_ref(), &a.game_id.to_le_bytes()],
+        Config => |_a| [b"config"],
+        Poll => |a| [b"poll", a.creator.as_ref(), &a.poll_id.to_le_bytes()],
+        Ballot => |a| [b"ballot", a.poll.as_ref(), a.voter.as_ref()],
+        PriceQuoteMessage => |a| [b"quote", a.sender.as_ref(), &a.quote_id.to_le_bytes()],
+        GovernanceNotifier => |a| [b"gov_notifier", a.governance.as_ref()],
+        ProposalNotification => |a| [b"proposal_notice", a.governance.as_ref(), a.proposal.as_ref()],
+        CollectionFeed => |a| [b"collection_feed", a.collection.as_ref()],
+        MarketNotice => |a| [b"market_notice", a.feed.as_ref(), &a.index.to_le_bytes()],
+        Notifier => |a| [b"notifier", a.authority.as_ref()],
+        Notification => |a| [b"notification", a.notifier.as_ref(), &a.index.to_le_bytes()],
+        MerchantProfile => |a| [b"merchant", a.owner.as_ref()],
+        MerchantReceipt => |a| [b"merchant_receipt", a.merchant.as_ref(), &a.index.to_le_bytes()],
+        TeamInbox => |a| [b"team_inbox", a.owner.as_ref()],
+        Ticket => |a| [b"ticket", a.team_inbox.as_ref(), &a.index.to_le_bytes()],
+        TeamInboxStats => |a| [b"team_stats", a.team_inbox.as_ref()],
+        DealRoom => |a| [b"deal", a.seller.as_ref(), &a.deal_id.to_le_bytes()],
+        DealMessage => |a| [b"deal_msg", a.deal.as_ref(), &a.index.to_le_bytes()],
+        Reputation => |a| [b"reputation", a.user.as_ref()],
+        Vouch => |a| [b"vouch", a.voucher.as_ref(), a.vouchee.as_ref()],
+        SpamFlag => |a| [b"spam_flag", a.message.as_ref()],
+        Attestation => |a| [b"attestation", a.subject.as_ref()],
+        InboxGate => |a| [b"inbox_gate", a.owner.as_ref()],
+        EncryptionKey => |a| [b"encryption_key", a.owner.as_ref()],
+        GuardianSet => |a| [b"guardians", a.owner.as_ref()],
+        RecoveryRequest => |a| [b"recovery", a.owner.as_ref()],
+        Forwarding => |a| [b"forward", a.old_wallet.as_ref()],
+        DmLink => |a| [b"dm_link", a.namespace_seed(), a.owner.as_ref(), a.slug.as_bytes()],
+        AmaInbox => |a| [b"ama", a.owner.as_ref()],
+        AmaQuestion => |a| [b"ama_question", a.ama_inbox.as_ref(), a.commitment.as_ref()],
+        Room => |a| [b"room", a.namespace_seed(), a.creator.as_ref(), &a.room_id.to_le_bytes()],
+        RoomMessage => |a| [b"room_msg", a.room.as_ref(), &a.index.to_le_bytes()],
+        Treasury => |_a| [b"treasury"],
+        ContentBlob => |a| [b"content_blob", a.content_hash.as_ref()],
+        BlobMessage => |a| [
+            b"blob_message",
+            a.namespace_seed(),
+            a.blob.as_ref(),
+            a.sender.as_ref(),
+            a.recipient.as_ref(),
+            &a.nonce.to_le_bytes(),
+        ],
+        MessageDictionary => |_a| [b"dictionary"],
+        InternedMessage => |a| [
+            b"interned",
+            a.namespace_seed(),
+            a.sender.as_ref(),
+            a.recipient.as_ref(),
+            &a.nonce.to_le_bytes(),
+        ],
+        EpochStats => |a| [b"epoch_stats", &a.epoch.to_le_bytes()],
+        UserStats => |a| [b"user_stats", a.user.as_ref()],
+        KeyTransparencyLog => |_a| [b"key_log"],
+        ConversationSettings => |a| [
+            b"conversation_settings",
+            a.namespace_seed(),
+            a.participants[0].as_ref(),
+            a.participants[1].as_ref(),
+        ],
+        Disclosure => |a| [b"disclosure", a.message.as_ref(), a.discloser.as_ref()],
+        ObserverGrant => |a| [b"observer", a.conversation.as_ref(), a.observer.as_ref()],
+        ObserverKey => |a| [b"observer_key", a.grant.as_ref(), a.message.as_ref()],
+        DeliveryWindow => |a| [b"delivery_window", a.owner.as_ref()],
+        MessageDelivery => |a| [b"delivery", a.message.as_ref()],
+        PriorityPolicy => |a| [b"priority_policy", a.owner.as_ref()],
+        Reminder => |a| [b"reminder", a.owner.as_ref(), a.message.as_ref()],
+        Snooze => |a| [b"snooze", a.owner.as_ref(), a.peer.as_ref()],
+        MessageTranslations => |a| [b"translations", a.message.as_ref()],
+        MessageMedia => |a| [b"media", a.message.as_ref()],
+        ContentWarning => |a| [b"content_warning", a.message.as_ref()],
+        ContentFilter => |a| [b"content_filter", a.owner.as_ref()],
+        RoomReport => |a| [b"room_report", a.message.as_ref()],
+        ModerationVote => |a| [b"moderation_vote", a.report.as_ref(), a.voter.as_ref()],
+        ModerationCouncil => |_a| [b"moderation_council"],
+        Appeal => |a| [b"appeal", a.target.as_ref()],
+        CouncilElection => |a| [b"council_election", &a.epoch.to_le_bytes()],
+        CouncilCandidacy => |a| [b"council_candidacy", a.election.as_ref(), a.candidate.as_ref()],
+        CouncilVote => |a| [b"council_vote", a.election.as_ref(), a.voter.as_ref()],
+        TreasuryProposal => |a| [b"treasury_proposal", a.proposer.as_ref(), &a.proposal_id.to_le_bytes()],
+        FeeSchedule => |_a| [b"fee_schedule"],
+        PassOffer => |_a| [b"pass_offer"],
+        ProtocolPass => |a| [b"pass", a.mint.as_ref()],
+        App => |a| [b"app", a.name.as_bytes()],
+        FeatureGate => |a| [b"feature_gate", &[a.feature as u8]],
+        Schema => |a| [b"schema", a.name.as_bytes(), &a.version.to_le_bytes()],
+        StructuredMessage => |a| [
+            b"structured",
+            a.namespace_seed(),
+            a.sender.as_ref(),
+            a.recipient.as_ref(),
+            &a.nonce.to_le_bytes(),
+        ],
+        PaymentNote => |a| [b"payment_note", a.namespace_seed(), a.sender.as_ref(), &a.nonce.to_le_bytes()],
+        Profile => |a| [b"profile", a.owner.as_ref()],
+        RoundUp => |a| [b"round_up", a.owner.as_ref()],
+        TipStream => |a| [
+            b"tip_stream",
+            a.namespace_seed(),
+            a.sender.as_ref(),
+            a.recipient.as_ref(),
+            &a.stream_id.to_le_bytes(),
+        ],
+        BookingMessage => |a| [
+            b"booking",
+            a.namespace_seed(),
+            a.client.as_ref(),
+            a.consultant.as_ref(),
+            &a.booking_id.to_le_bytes(),
+        ],
+        Availability => |a| [b"availability", a.owner.as_ref()],
+        Namespace => |a| [b"namespace", a.name.as_bytes()],
+        NamespaceMember => |a| [b"namespace_member", a.user.as_ref()],
+        AuditLog => |_a| [b"audit_log"],
+        PendingConfigChange => |a| [b"config_change", &a.change_id.to_le_bytes()],
+        ChaosPlan => |_a| [b"chaos_plan"],
+        InboxIndex => |a| [b"inbox_index", a.namespace_seed(), a.owner.as_ref()],
+        SpamFilter => |_a| [b"spam_filter"],
+        SpamDeposit => |a| [b"spam_deposit", a.message.as_ref()],
+        SpamTrap => |a| [b"spam_trap", a.inbox.as_ref()],
+        PatronConfig => |_a| [b"patron_config"],
+    }
+
+    Err(format!("unknown account discriminator {}", bs58::encode(discriminator).into_string()))
+}
+
+fn check_event(event: &gmchat_sdk::export::EventRecord, bundle: &ConversationBundle) -> Result<(), String> {
+    use solana_messaging::{
+        AmaAnswered, GameMovePlayed, InvoicePaid, KeyLogAppended, MessageSent, MessageUnlocked,
+        SafetyNumberChanged, SafetyNumberConfirmed, TaskStatusChanged, TicketStatusChanged, TradeStatusChanged,
+    };
+    const KNOWN_EVENTS: [[u8; 8]; 11] = [
+        TaskStatusChanged::DISCRIMINATOR,
+        TradeStatusChanged::DISCRIMINATOR,
+        InvoicePaid::DISCRIMINATOR,
+        MessageUnlocked::DISCRIMINATOR,
+        GameMovePlayed::DISCRIMINATOR,
+        TicketStatusChanged::DISCRIMINATOR,
+        AmaAnswered::DISCRIMINATOR,
+        KeyLogAppended::DISCRIMINATOR,
+        SafetyNumberConfirmed::DISCRIMINATOR,
+        SafetyNumberChanged::DISCRIMINATOR,
+        MessageSent::DISCRIMINATOR,
+    ];
+
+    let data = base64::decode(&event.data).map_err(|e| e.to_string())?;
+    let discriminator: [u8; 8] = data.get(..8).and_then(|d| d.try_into().ok()).ok_or("event data too short")?;
+    if !KNOWN_EVENTS.contains(&discriminator) {
+        return Err("unknown event discriminator".to_string());
+    }
+
+    // The emitting transaction must be in the bundle so its signers can be checked, and the
+    // event must be in its logs, logged by the messaging program itself
+    let transaction = bundle
+        .transactions
+        .iter()
+        .find(|transaction| {
+            transaction.signatures.first() == Some(&event.transaction) && transaction.slot == event.slot
+        })
+        .ok_or("emitting transaction is not part of the bundle")?;
+    if !program_data(&transaction.logs).contains(&data) {
+        return Err("event was not emitted by the messaging program in that transaction".to_string());
+    }
+    Ok(())
+}
+
+/// Payloads of the `Program data:` log lines written while the messaging program was the one
+/// executing, not a program it invoked.
+fn program_data(logs: &[String]) -> Vec<Vec<u8>> {
+    let program = PROGRAM_ID.to_string();
+    let mut invoked: Vec<&str> = Vec::new();
+    let mut payloads = Vec::new();
+
+    for line in logs {
+        if let Some(fields) = line.strip_prefix("Program data: ") {
+            if invoked.last() == Some(&program.as_str()) {
+                let payload: Result<Vec<Vec<u8>>, _> = fields.split(' ').map(base64::decode).collect();
+                payloads.extend(payload.ok().map(|fields| fields.concat()));
+            }
+        } else if let Some(rest) = line.strip_prefix("Program ") {
+            // `Program <id> invoke [n]`, `Program <id> success` and `Program <id> failed: ..`;
+            // `Program log:` and other prefixed lines have no id
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(id), _) if id.ends_with(':') => {}
+                (Some(id), Some("invoke")) => invoked.push(id),
+                (Some(_), Some("success" | "failed:")) => {
+                    invoked.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    payloads
+}
+
+fn check_transaction(transaction: &gmchat_sdk::export::TransactionRecord) -> Result<(), String> {
+    let message = base64::decode(&transaction.message).map_err(|e| e.to_string())?;
+    let signers = message_signers(&message)?;
+    if signers.len() != transaction.signatures.len() {
+        return Err(format!("{} signatures for {} required signers", transaction.signatures.len(), signers.len()));
+    }
+
+    for (signer, signature) in signers.iter().zip(&transaction.signatures) {
+        let key = PublicKey::from_bytes(signer).map_err(|e| e.to_string())?;
+        let signature = Signature::from_bytes(&decode_base58::<64>(signature)?).map_err(|e| e.to_string())?;
+        key.verify(&message, &signature)
+            .map_err(|_| format!("signature by {} does not verify", bs58::encode(signer).into_string()))?;
+    }
+    Ok(())
+}
+
+/// Required signer keys of a serialized legacy or v0 transaction message.
+fn message_signers(message: &[u8]) -> Result<Vec<[u8; 32]>, String> {
+    const VERSION_PREFIX: u8 = 0x80;
+
+    let mut offset = usize::from(message.first().is_some_and(|b| b & VERSION_PREFIX != 0));
+    let num_required_signatures = *message.get(offset).ok_or("truncated message header")? as usize;
+    offset += 3;
+
+    let (num_keys, len) = decode_short_u16(message.get(offset..).ok_or("truncated message")?)?;
+    offset += len;
+    if num_required_signatures > num_keys {
+        return Err("more signers than account keys".to_string());
+    }
+
+    (0..num_required_signatures)
+        .map(|i| {
+            let start = offset + i * 32;
+            message
+                .get(start..start + 32)
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| "truncated account keys".to_string())
+        })
+        .collect()
+}
+
+/// Solana's compact-u16 length prefix.
+fn decode_short_u16(bytes: &[u8]) -> Result<(usize, usize), String> {
+    let mut value = 0usize;
+    for (i, byte) in bytes.iter().take(3).enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err("invalid compact-u16".to_string())
+}
+
+fn decode_base58<const N: usize>(value: &str) -> Result<[u8; N], String> {
+    let bytes = bs58::decode(value).into_vec().map_err(|e| e.to_string())?;
+    bytes.try_into().map_err(|_| format!("{value} is not {N} bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AccountSerialize;
+    use ed25519_dalek::{Keypair, SecretKey};
+    use gmchat_sdk::export::ConversationExport;
+
+    use super::*;
+
+    const SLOT: u64 = 42;
+
+    fn exporter() -> Keypair {
+        let secret = SecretKey::from_bytes(&[9; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        Keypair { secret, public }
+    }
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn pda(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+    }
+
+    fn reputation(user: Pubkey) -> Vec<u8> {
+        serialize(&Reputation {
+            user,
+            score: 0,
+            messages_answered: 0,
+            tickets_resolved: 0,
+            disputes_lost: 0,
+            spam_flags: 0,
+            vouches: 0,
+            last_updated: 0,
+            bump: 255,
+        })
+    }
+
+    fn bundle(accounts: &[(Pubkey, Vec<u8>)]) -> ConversationBundle {
+        let mut export = ConversationExport::new();
+        export.add_slot_hash(SLOT, &[1; 32]);
+        for (address, data) in accounts {
+            export.add_account(&address.to_bytes(), &PROGRAM_ID.to_bytes(), SLOT, data);
+        }
+        export.finish(&exporter(), 1_700_000_000).unwrap()
+    }
+
+    fn errors(report: &Report, check: &str) -> Vec<Option<String>> {
+        report
+            .findings
+            .iter()
+            .filter(|finding| finding.check == check)
+            .map(|finding| finding.error.clone())
+            .collect()
+    }
+
+    #[test]
+    fn accepts_an_account_at_its_pda() {
+        let user = Pubkey::new_unique();
+        let report = verify_bundle(&bundle(&[(pda(&[b"reputation", user.as_ref()]), reputation(user))]));
+
+        assert!(report.passed(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn rejects_an_account_away_from_its_pda() {
+        let user = Pubkey::new_unique();
+        let report = verify_bundle(&bundle(&[(Pubkey::new_unique(), reputation(user))]));
+
+        let errors = errors(&report, "account");
+        assert!(errors[0].as_deref().is_some_and(|error| error.starts_with("address does not match")));
+    }
+
+    #[test]
+    fn rejects_an_unknown_discriminator() {
+        let report = verify_bundle(&bundle(&[(Pubkey::new_unique(), vec![0xff; 64])]));
+
+        let errors = errors(&report, "account");
+        assert!(errors[0].as_deref().is_some_and(|error| error.starts_with("unknown account discriminator")));
+    }
+
+    #[test]
+    fn derives_singleton_and_namespaced_accounts() {
+        let treasury = serialize(&Treasury { bump: 255 });
+        let room = Room {
+            creator: Pubkey::new_unique(),
+            room_id: 3,
+            name: "war room".to_string(),
+            created_at: 0,
+            expires_at: 0,
+            message_count: 0,
+            min_age: 0,
+            region_mode: solana_messaging::RegionMode::Unrestricted,
+            regions: Vec::new(),
+            moderated: false,
+            hide_threshold_lamports: 0,
+            namespace: Pubkey::new_unique(),
+            bump: 255,
+        };
+        let room_address =
+            pda(&[b"room", room.namespace.as_ref(), room.creator.as_ref(), &room.room_id.to_le_bytes()]);
+
+        let report = verify_bundle(&bundle(&[(pda(&[b"treasury"]), treasury), (room_address, serialize(&room))]));
+
+        assert!(report.passed(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn a_namespaced_structured_message_is_not_at_its_public_address() {
+        let message = StructuredMessage {
+            sender: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            nonce: 7,
+            schema: Pubkey::new_unique(),
+            payload: vec![1, 2, 3],
+            timestamp: 0,
+            namespace: Pubkey::new_unique(),
+            bump: 255,
+        };
+        let seeds: [&[u8]; 4] =
+            [b"structured", message.sender.as_ref(), message.recipient.as_ref(), &message.nonce.to_le_bytes()];
+        let namespaced = pda(&[seeds[0], message.namespace.as_ref(), seeds[1], seeds[2], seeds[3]]);
+
+        let report = verify_bundle(&bundle(&[(namespaced, serialize(&message))]));
+        assert!(report.passed(), "{:?}", report.findings);
+
+        let report = verify_bundle(&bundle(&[(pda(&seeds), serialize(&message))]));
+        let errors = errors(&report, "account");
+        assert!(errors[0].as_deref().is_some_and(|error| error.starts_with("address does not match")));
+    }
+
+    #[test]
+    fn a_receipt_reference_needs_its_receipt_in_the_bundle() {
+        let merchant = Pubkey::new_unique();
+        let receipt = MerchantReceipt {
+            merchant,
+            customer: Pubkey::new_unique(),
+            index: 0,
+            amount: 1_000,
+            reference: Pubkey::new_unique(),
+            order_ref: "order-1".to_string(),
+            timestamp: 0,
+            bump: 255,
+        };
+        let receipt_address = pda(&[b"merchant_receipt", merchant.as_ref(), &0u64.to_le_bytes()]);
+        let marker = (
+            pda(&[b"receipt", receipt.reference.as_ref()]),
+            serialize(&ReceiptReference { receipt: receipt_address, bump: 255 }),
+        );
+
+        let report = verify_bundle(&bundle(std::slice::from_ref(&marker)));
+        assert!(errors(&report, "account")[0].as_deref().is_some_and(|error| error.ends_with("is not in the bundle")));
+
+        let report = verify_bundle(&bundle(&[marker, (receipt_address, serialize(&receipt))]));
+        assert!(report.passed(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn rejects_a_tampered_bundle() {
+        let user = Pubkey::new_unique();
+        let mut bundle = bundle(&[(pda(&[b"reputation", user.as_ref()]), reputation(user))]);
+        bundle.accounts[0].slot += 1;
+
+        let report = verify_bundle(&bundle);
+        let failed: Vec<_> = report.findings.iter().filter(|finding| !finding.passed()).map(|f| f.check).collect();
+        // The slot is signed over, so the leaf no longer proves against the signed root, and it is
+        // also no longer one the bundle has a hash for
+        assert_eq!(failed, ["exporter signature", "merkle proofs", "merkle proof", "account"]);
+    }
+
+    fn event_data() -> Vec<u8> {
+        let mut data = solana_messaging::MessageSent::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        data
+    }
+
+    /// A bundle with one transaction carrying `logs` and `event` claimed to be emitted by it.
+    fn event_bundle(logs: &[String], event: &[u8]) -> ConversationBundle {
+        let mut export = ConversationExport::new();
+        export
+            .add_slot_hash(SLOT, &[1; 32])
+            .add_event(&[5; 64], SLOT, event)
+            .add_transaction(SLOT, b"transaction message", &[[5; 64]], logs);
+        export.finish(&exporter(), 1_700_000_000).unwrap()
+    }
+
+    fn invoked(program: &Pubkey, depth: u8, lines: &[String]) -> Vec<String> {
+        let mut logs = vec![format!("Program {program} invoke [{depth}]")];
+        logs.extend_from_slice(lines);
+        logs.push(format!("Program {program} consumed 1000 of 200000 compute units"));
+        logs.push(format!("Program {program} success"));
+        logs
+    }
+
+    #[test]
+    fn accepts_an_event_the_program_logged() {
+        let emitted = format!("Program data: {}", base64::encode(event_data()));
+        let logs = invoked(&PROGRAM_ID, 1, &["Program log: Instruction: SendMessage".to_string(), emitted]);
+
+        let report = verify_bundle(&event_bundle(&logs, &event_data()));
+        assert_eq!(errors(&report, "event"), [None]);
+    }
+
+    #[test]
+    fn rejects_an_event_missing_from_the_transaction_logs() {
+        let emitted = format!("Program data: {}", base64::encode(event_data()));
+        let logs = invoked(&PROGRAM_ID, 1, &[emitted]);
+        let mut forged = event_data();
+        forged[8] = 9;
+
+        let report = verify_bundle(&event_bundle(&logs, &forged));
+        assert!(errors(&report, "event")[0].as_deref().is_some_and(|error| error.starts_with("event was not emitted")));
+    }
+
+    #[test]
+    fn rejects_an_event_logged_by_another_program() {
+        let emitted = format!("Program data: {}", base64::encode(event_data()));
+        let other = Pubkey::new_unique();
+        // Logged at the top level by another program, and by one the messaging program invoked
+        let nested = invoked(&PROGRAM_ID, 1, &invoked(&other, 2, std::slice::from_ref(&emitted)));
+        for logs in [invoked(&other, 1, &[emitted]), nested] {
+            let report = verify_bundle(&event_bundle(&logs, &event_data()));
+            assert!(errors(&report, "event")[0].is_some(), "{logs:?}");
+        }
+    }
+
+    #[test]
+    fn proofs_are_checked_against_the_signed_root() {
+        let user = Pubkey::new_unique();
+        let mut bundle = bundle(&[(pda(&[b"reputation", user.as_ref()]), reputation(user))]);
+        // Swapping in a root of the bundle's own leaves doesn't make them match the signature
+        bundle.merkle_root = bs58::encode([7; 32]).into_string();
+
+        let report = verify_bundle(&bundle);
+        let mismatch = Some("bundle contents do not match the signed root".to_string());
+        assert_eq!(errors(&report, "merkle proofs"), [mismatch]);
+        assert!(errors(&report, "merkle proof")[0].is_some());
+    }
+
+    #[test]
+    fn reads_compact_u16() {
+        assert_eq!(decode_short_u16(&[0x05]), Ok((5, 1)));
+        assert_eq!(decode_short_u16(&[0x80, 0x01]), Ok((128, 2)));
+        assert_eq!(decode_short_u16(&[0xff, 0xff, 0x03]), Ok((0xffff, 3)));
+        assert!(decode_short_u16(&[0x80, 0x80, 0x80]).is_err());
+    }
+}
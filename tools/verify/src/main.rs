@@ -0,0 +1,38 @@
+//! Verifies an exported conversation bundle without RPC access.
+//!
+//! Usage: gmchat-verify <bundle.json>
+//!
+//! Prints one line per check and exits non-zero if any check fails.
+
+use std::{env, fs, process};
+
+use gmchat_sdk::export::ConversationBundle;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: {} <bundle.json>", args[0]);
+        process::exit(2);
+    }
+
+    let raw = fs::read_to_string(&args[1]).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", args[1]);
+        process::exit(2);
+    });
+    let bundle = ConversationBundle::from_json(&raw).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", args[1]);
+        process::exit(2);
+    });
+
+    let report = gmchat_verify::verify_bundle(&bundle);
+    for finding in &report.findings {
+        match &finding.error {
+            None => println!("ok    {}: {}", finding.check, finding.subject),
+            Some(error) => println!("FAIL  {}: {} ({error})", finding.check, finding.subject),
+        }
+    }
+
+    if !report.passed() {
+        process::exit(1);
+    }
+}